@@ -0,0 +1,82 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::error;
+use std::{
+    env,
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    result,
+};
+
+type Error = Box<dyn error::Error>;
+type Result<T> = result::Result<T, Error>;
+
+/// The published lists built in: each file, and the name of the constant holding its words.
+const LISTS: &[(&str, &str)] = &[
+    ("eff_short_wordlist_1.txt", "EFF_SHORT_WORDLIST"),
+    ("eff_short_wordlist_2_0.txt", "EFF_SHORT_2_WORDLIST"),
+    ("diceware.wordlist.asc", "DICEWARE_WORDLIST"),
+];
+
+fn main() -> Result<()> {
+    println!("cargo:rerun-if-changed=eff_large_wordlist.txt");
+    for (file, _) in LISTS {
+        println!("cargo:rerun-if-changed={file}");
+    }
+    let out_dir = env::var("OUT_DIR")?;
+    let mut output = File::create(Path::new(&out_dir).join("wordlist.rs"))?;
+    if env::var_os("CARGO_FEATURE_EFF_WORDLIST").is_some() {
+        write_list(&mut output, "eff_large_wordlist.txt", "EFF_WORDLIST")?;
+    }
+    for (file, name) in LISTS {
+        write_list(&mut output, file, name)?;
+    }
+    Ok(())
+}
+
+/// Writes the words of `file`, a published list with a dice roll and a tab before each word, as
+/// the constant `name`. Other lines, like the PGP signature around Reinhold's list, are skipped.
+fn write_list(output: &mut File, file: &str, name: &str) -> Result<()> {
+    let reader = BufReader::new(File::open(file)?);
+
+    let mut words = Vec::new();
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some((roll, word)) = line.split_once('\t') else {
+            if file.ends_with(".asc") {
+                continue;
+            }
+            return Err("parse failure".into());
+        };
+        if !roll.bytes().all(|b| (b'1'..=b'6').contains(&b)) {
+            return Err("parse failure".into());
+        }
+        words.push(word.to_string());
+    }
+
+    writeln!(output, "// Generated at build time from {file}")?;
+    writeln!(output, "pub const {name}: &[&str] = &[")?;
+    for word in words {
+        writeln!(output, "    {word:?},")?;
+    }
+    writeln!(output, "];")?;
+    Ok(())
+}
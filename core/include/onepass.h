@@ -0,0 +1,91 @@
+/*
+ * Copyright 2025 Steven Dee
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/*
+ * The C interface of libonepass, which derives the same passwords as the onepass CLI for sites
+ * using the default KDF and word list and no second factors.
+ *
+ * Strings are UTF-8 and NUL-terminated. Functions returning strings return NULL on failure, and
+ * onepass_entropy and onepass_load_wordlist return -1; onepass_last_error then describes the failure. Returned strings
+ * must be freed with onepass_free, which also wipes them.
+ */
+
+#ifndef ONEPASS_H
+#define ONEPASS_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * Derives the password of site (a URL or host name, e.g. "example.com") for the master password
+ * master, matching schema (e.g. "[A-Za-z0-9]{18}"; aliases from the config are not available),
+ * with derivation version 2, which stays the default when later versions are added; use
+ * onepass_generate_ex for another. site is canonicalized under canonicalization v1, as in
+ * configs without a canonicalization version.
+ */
+char *onepass_generate(const char *master, const char *site, const char *schema,
+                       uint32_t increment);
+
+/* Like onepass_generate, for the account username (or none if NULL) and derivation version. */
+char *onepass_generate_ex(const char *master, const char *site, const char *username,
+                          const char *schema, uint32_t increment, uint32_t version);
+
+/*
+ * Like onepass_generate_ex, canonicalizing site under the canonicalization version
+ * canonicalization, the config's canonicalization.version; new configs are written with the
+ * latest.
+ */
+char *onepass_generate_with(const char *master, const char *site, const char *username,
+                            const char *schema, uint32_t increment, uint32_t version,
+                            uint32_t canonicalization);
+
+/*
+ * Returns the canonical URL that site is derived from under canonicalization v1, with username
+ * if not NULL.
+ */
+char *onepass_canonicalize(const char *site, const char *username);
+
+/* Like onepass_canonicalize, under the canonicalization version canonicalization. */
+char *onepass_canonicalize_with(const char *site, const char *username,
+                                uint32_t canonicalization);
+
+/* Returns the bits of entropy of passwords matching schema, rounded up, or -1 on failure. */
+int onepass_entropy(const char *schema);
+
+/*
+ * Loads the EFF's large word list from contents, its published file eff_large_wordlist.txt, for
+ * libraries built without it (the eff-wordlist feature). Returns 0, or -1 if contents is not that
+ * file. Libraries built with it only check contents.
+ */
+int onepass_load_wordlist(const char *contents);
+
+/* Wipes and frees a string returned by this library. NULL is ignored. */
+void onepass_free(char *s);
+
+/*
+ * Describes the last failure on this thread, or returns NULL if there was none. The string is
+ * owned by the library and valid until the next call into it on the same thread.
+ */
+const char *onepass_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* ONEPASS_H */
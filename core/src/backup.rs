@@ -0,0 +1,144 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Snapshots of the config file, taken before onepass writes it, in a `backups` directory next to
+//! it, so that increments and schemas lost to a bad write or edit can be restored.
+
+use std::{
+    fs::{self, create_dir_all, read_to_string, rename},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{config::Config, date::Date};
+
+/// The number of snapshots kept; older ones are removed as new ones are taken.
+pub const KEPT: usize = 50;
+
+/// Returns the directory of the snapshots of the config file at `path`.
+pub fn dir(path: &Path) -> Result<PathBuf> {
+    Ok(path
+        .parent()
+        .context("invalid config path")?
+        .join("backups"))
+}
+
+/// Copies the config file at `path` into a new snapshot named by the time, like
+/// `config-2025-06-01T12-34-56.789Z.yaml`, and removes all but the newest [`KEPT`].
+pub fn snapshot(path: &Path) -> Result<PathBuf> {
+    let dir = dir(path)?;
+    create_dir_all(&dir)?;
+    let mut since = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let name = |since: Duration| {
+        let secs = since.as_secs();
+        format!(
+            "config-{}T{:02}-{:02}-{:02}.{:03}Z.yaml",
+            Date::from_unix(secs),
+            secs / 3600 % 24,
+            secs / 60 % 60,
+            secs % 60,
+            since.subsec_millis()
+        )
+    };
+    // Snapshots taken within a millisecond are told apart by the next ones free.
+    while dir.join(name(since)).exists() {
+        since += Duration::from_millis(1);
+    }
+    let backup = dir.join(name(since));
+    // Copying keeps the file's permissions, as it may hold secrets like `hotp_secret`s.
+    fs::copy(path, &backup).with_context(|| format!("failed backing up {}", path.display()))?;
+    for old in list(path)?.into_iter().skip(KEPT) {
+        fs::remove_file(old)?;
+    }
+    Ok(backup)
+}
+
+/// Returns the snapshots of the config file at `path`, newest first.
+pub fn list(path: &Path) -> Result<Vec<PathBuf>> {
+    let dir = dir(path)?;
+    let entries = match fs::read_dir(&dir) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        entries => entries?,
+    };
+    let mut backups = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|name| name.to_str());
+        if name.is_some_and(|name| name.starts_with("config-") && name.ends_with(".yaml")) {
+            backups.push(path);
+        }
+    }
+    // The names sort by time.
+    backups.sort_by(|a, b| b.cmp(a));
+    Ok(backups)
+}
+
+/// Replaces the config file at `path` with its `n`th newest snapshot, counting from 1, after
+/// taking a snapshot of it as it is, so that restoring the newest snapshot again undoes this.
+/// Returns the snapshot restored.
+pub fn restore(path: &Path, n: usize) -> Result<PathBuf> {
+    let backups = list(path)?;
+    let backup = n
+        .checked_sub(1)
+        .and_then(|i| backups.get(i))
+        .with_context(|| format!("no backup {n}; there are {}", backups.len()))?
+        .clone();
+    let text = read_to_string(&backup)?;
+    Config::from_str(&text).with_context(|| format!("invalid backup {}", backup.display()))?;
+    if path.exists() {
+        snapshot(path)?;
+    }
+    let tmp = path.with_extension("yaml.tmp");
+    fs::copy(&backup, &tmp)?;
+    rename(&tmp, path)?;
+    Ok(backup)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs::write};
+
+    use super::*;
+
+    #[test]
+    fn snapshots() -> Result<()> {
+        let dir = env::temp_dir().join(format!("onepass-backup-test-{}", std::process::id()));
+        let path = dir.join("config.yaml");
+        create_dir_all(&dir)?;
+        write(&path, "sites: {a.com: A}\n")?;
+        assert!(list(&path)?.is_empty());
+        snapshot(&path)?;
+        write(&path, "sites: {a.com: B}\n")?;
+        assert!(restore(&path, 2).is_err());
+        let restored = restore(&path, 1)?;
+        assert_eq!("sites: {a.com: A}\n", read_to_string(&path)?);
+        // Restoring saved the state before it, so restoring again undoes it.
+        let backups = list(&path)?;
+        assert_eq!(2, backups.len());
+        assert_eq!(restored, backups[1]);
+        restore(&path, 1)?;
+        assert_eq!("sites: {a.com: B}\n", read_to_string(&path)?);
+        for _ in 0..KEPT {
+            snapshot(&path)?;
+        }
+        assert_eq!(KEPT, list(&path)?.len());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
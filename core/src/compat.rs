@@ -0,0 +1,213 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The password algorithms of other deterministic password managers, so that sites whose
+//! passwords were made by one of them can keep their passwords.
+//!
+//! These replace the whole derivation pipeline: their passwords depend only on the master
+//! password and their own inputs, never on onepass's KDF settings or second factors.
+//!
+//! ```
+//! use onepass::compat::{Algorithm, Compat, Site};
+//!
+//! # #[cfg(feature = "compat")] {
+//! let site = Site {
+//!     name: "example.org",
+//!     login: Some("contact@example.org"),
+//!     user: None,
+//!     counter: 1,
+//! };
+//! let password = Compat::new(Algorithm::Lesspass).password("password", &site)?;
+//! assert_eq!("WHLpUL)e00[iHR+w", *password);
+//! # }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod lesspass;
+pub mod passwordmaker;
+pub mod spectre;
+
+use std::fmt;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use url::Url;
+#[cfg(feature = "compat")]
+use zeroize::Zeroizing;
+
+/// The algorithms, without their options.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    /// LessPass (version 2)
+    Lesspass,
+    /// Spectre, formerly Master Password (algorithm version 3)
+    Spectre,
+    /// PasswordMaker Pro
+    Passwordmaker,
+}
+
+/// A site's compatibility settings: the algorithm deriving its password, with its options.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Compat {
+    #[serde(flatten)]
+    pub options: Options,
+    /// The site name the algorithm derives from, if not the site's host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "algorithm", rename_all = "lowercase")]
+pub enum Options {
+    Lesspass(lesspass::Options),
+    Spectre(spectre::Options),
+    Passwordmaker(passwordmaker::Options),
+}
+
+/// What the algorithms derive a site's password from, besides the master password.
+#[derive(Clone, Debug)]
+pub struct Site<'a> {
+    /// The site's name, e.g. `example.org`
+    pub name: &'a str,
+    /// The username or email address the password is for, if any
+    pub login: Option<&'a str>,
+    /// The full name of the user, for algorithms that derive from it
+    pub user: Option<&'a str>,
+    /// The counter of the password, which is 1 for the site's first password
+    pub counter: u32,
+}
+
+impl Compat {
+    /// Returns the settings of `algorithm` with its default options.
+    pub fn new(algorithm: Algorithm) -> Self {
+        let options = match algorithm {
+            Algorithm::Lesspass => Options::Lesspass(Default::default()),
+            Algorithm::Spectre => Options::Spectre(Default::default()),
+            Algorithm::Passwordmaker => Options::Passwordmaker(Default::default()),
+        };
+        Compat {
+            options,
+            site: None,
+        }
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        match self.options {
+            Options::Lesspass(_) => Algorithm::Lesspass,
+            Options::Spectre(_) => Algorithm::Spectre,
+            Options::Passwordmaker(_) => Algorithm::Passwordmaker,
+        }
+    }
+
+    /// Returns the name the algorithm knows the site with URL `url` by: `site` if set, else the
+    /// URL's host.
+    pub fn site_name(&self, url: &str) -> Result<String> {
+        if let Some(site) = &self.site {
+            return Ok(site.clone());
+        }
+        let url = Url::parse(url).context("invalid url")?;
+        Ok(url.host_str().context("site has no host")?.to_owned())
+    }
+
+    /// Derives the password of `site` from the master password `password`.
+    #[cfg(feature = "compat")]
+    pub fn password(&self, password: &str, site: &Site) -> Result<Zeroizing<String>> {
+        match &self.options {
+            Options::Lesspass(options) => options.password(password, site),
+            Options::Spectre(options) => options.password(password, site),
+            Options::Passwordmaker(options) => options.password(password, site),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().ok_or(fmt::Error)?;
+        f.write_str(value.get_name())
+    }
+}
+
+impl fmt::Display for Compat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let options: &dyn fmt::Display = match &self.options {
+            Options::Lesspass(options) => options,
+            Options::Spectre(options) => options,
+            Options::Passwordmaker(options) => options,
+        };
+        write!(f, "{} ({options})", self.algorithm())?;
+        if let Some(site) = &self.site {
+            write!(f, " as {site}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Divides the big-endian number `n` by `divisor` in place, returning the remainder.
+#[cfg(feature = "compat")]
+fn div_rem(n: &mut [u8], divisor: u32) -> u32 {
+    let mut rem = 0u64;
+    for byte in n {
+        let x = rem << 8 | u64::from(*byte);
+        *byte = (x / u64::from(divisor)) as u8;
+        rem = x % u64::from(divisor);
+    }
+    rem as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "compat")]
+    fn division() {
+        let mut n = 1000u32.to_be_bytes();
+        assert_eq!(div_rem(&mut n, 7), 6);
+        assert_eq!(u32::from_be_bytes(n), 142);
+        let mut n = [0xff; 32];
+        assert_eq!(div_rem(&mut n, 256), 0xff);
+        assert_eq!(n[0], 0);
+        assert_eq!(n[1..], [0xff; 31]);
+    }
+
+    #[test]
+    fn config() -> Result<()> {
+        let compat: Compat = serde_yaml::from_str("algorithm: lesspass\nlength: 20\nsite: a.org")?;
+        assert_eq!(compat.algorithm(), Algorithm::Lesspass);
+        assert_eq!(compat.site_name("https://b.org/")?, "a.org");
+        assert_eq!(
+            Compat::new(Algorithm::Lesspass).site_name("https://bob@b.org:8443/x")?,
+            "b.org"
+        );
+        assert_eq!(
+            compat.to_string(),
+            "lesspass (length 20, lowercase, uppercase, digits, symbols) as a.org"
+        );
+        assert_eq!(
+            serde_yaml::from_str::<Compat>("algorithm: lesspass")?,
+            Compat::new(Algorithm::Lesspass)
+        );
+        let compat: Compat = serde_yaml::from_str("algorithm: spectre\ntemplate: pin")?;
+        assert_eq!(compat.to_string(), "spectre (pin template)");
+        let compat: Compat =
+            serde_yaml::from_str("algorithm: passwordmaker\nhash: hmac-sha256\nmodifier: '2'")?;
+        assert_eq!(
+            compat.to_string(),
+            "passwordmaker (hmac-sha256, length 8, modifier \"2\")"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! LessPass: PBKDF2-HMAC-SHA256 of the master password, salted with the site, login, and
+//! counter, rendered as a password with at least one character of each enabled class.
+
+use std::fmt;
+
+#[cfg(feature = "compat")]
+use anyhow::{Result, ensure};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "compat")]
+use sha2::Sha256;
+#[cfg(feature = "compat")]
+use zeroize::Zeroizing;
+
+#[cfg(feature = "compat")]
+use super::{Site, div_rem};
+
+#[cfg(feature = "compat")]
+const ITERATIONS: u32 = 100_000;
+
+/// The character classes, in the order LessPass draws from them.
+const CLASSES: [(&str, &str); 4] = [
+    ("lowercase", "abcdefghijklmnopqrstuvwxyz"),
+    ("uppercase", "ABCDEFGHIJKLMNOPQRSTUVWXYZ"),
+    ("digits", "0123456789"),
+    ("symbols", "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~"),
+];
+
+/// A LessPass profile's password settings; the defaults are LessPass's.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Options {
+    pub length: usize,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            length: 16,
+            lowercase: true,
+            uppercase: true,
+            digits: true,
+            symbols: true,
+        }
+    }
+}
+
+impl Options {
+    fn classes(&self) -> impl Iterator<Item = (&'static str, &'static str)> {
+        let enabled = [self.lowercase, self.uppercase, self.digits, self.symbols];
+        CLASSES
+            .into_iter()
+            .zip(enabled)
+            .filter_map(|(class, enabled)| enabled.then_some(class))
+    }
+
+    #[cfg(feature = "compat")]
+    pub(super) fn password(&self, password: &str, site: &Site) -> Result<Zeroizing<String>> {
+        let classes: Vec<_> = self.classes().map(|(_, chars)| chars.as_bytes()).collect();
+        ensure!(!classes.is_empty(), "no character classes enabled");
+        ensure!(
+            self.length > classes.len(),
+            "length must be more than the number of character classes"
+        );
+        let salt = Zeroizing::new(format!(
+            "{}{}{:x}",
+            site.name,
+            site.login.unwrap_or(""),
+            site.counter
+        ));
+        let mut entropy = Zeroizing::new([0u8; 32]);
+        pbkdf2::pbkdf2_hmac::<Sha256>(
+            password.as_bytes(),
+            salt.as_bytes(),
+            ITERATIONS,
+            &mut *entropy,
+        );
+
+        let chars = Zeroizing::new(classes.concat());
+        let mut res = Zeroizing::new(Vec::with_capacity(self.length));
+        for _ in classes.len()..self.length {
+            res.push(chars[div_rem(&mut *entropy, chars.len() as u32) as usize]);
+        }
+        // One character of each class goes in at a pseudorandom position.
+        let mut required = Zeroizing::new(Vec::with_capacity(classes.len()));
+        for class in &classes {
+            required.push(class[div_rem(&mut *entropy, class.len() as u32) as usize]);
+        }
+        for &c in required.iter() {
+            let i = div_rem(&mut *entropy, res.len() as u32) as usize;
+            res.insert(i, c);
+        }
+        Ok(Zeroizing::new(String::from_utf8(res.to_vec())?))
+    }
+}
+
+impl fmt::Display for Options {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "length {}", self.length)?;
+        for (name, _) in self.classes() {
+            write!(f, ", {name}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "compat"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passwords() -> Result<()> {
+        // From LessPass's own tests.
+        let site = Site {
+            name: "example.org",
+            login: Some("contact@example.org"),
+            user: None,
+            counter: 1,
+        };
+        let options = Options::default();
+        assert_eq!(*options.password("password", &site)?, "WHLpUL)e00[iHR+w");
+        let options = Options {
+            length: 14,
+            symbols: false,
+            ..Default::default()
+        };
+        assert_eq!(*options.password("password", &site)?, "y5Im77Ctww2695");
+        let options = Options {
+            length: 2,
+            ..Default::default()
+        };
+        assert!(options.password("password", &site).is_err());
+        Ok(())
+    }
+}
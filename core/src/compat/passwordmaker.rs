@@ -0,0 +1,243 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! PasswordMaker Pro: a hash of the master password and the site, written in the digits of the
+//! charset, and hashed again with a numbered key until the password is long enough.
+
+use std::fmt;
+
+#[cfg(feature = "compat")]
+use anyhow::{Result, ensure};
+use clap::ValueEnum;
+#[cfg(feature = "compat")]
+use hmac::{Hmac, Mac, digest::KeyInit};
+#[cfg(feature = "compat")]
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "compat")]
+use sha1::Sha1;
+#[cfg(feature = "compat")]
+use sha2::{Digest, Sha256};
+#[cfg(feature = "compat")]
+use zeroize::Zeroizing;
+
+#[cfg(feature = "compat")]
+use super::{Site, div_rem};
+
+/// PasswordMaker's default charset.
+const CHARSET: &str = concat!(
+    "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+    "abcdefghijklmnopqrstuvwxyz",
+    "0123456789",
+    "`~!@#$%^&*()_-+={}|[]\\:\";'<>?,./",
+);
+
+/// The hash algorithms, of which PasswordMaker's default is MD5.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Hash {
+    #[default]
+    Md5,
+    Sha1,
+    Sha256,
+    HmacMd5,
+    HmacSha1,
+    HmacSha256,
+}
+
+#[cfg(feature = "compat")]
+impl Hash {
+    /// Hashes `data` with the key `key`, which is either the HMAC key or prepended to `data`.
+    fn hash(self, key: &[u8], data: &[u8]) -> Zeroizing<Vec<u8>> {
+        let prefixed = || Zeroizing::new([key, data].concat());
+        Zeroizing::new(match self {
+            Hash::Md5 => Md5::digest(prefixed()).to_vec(),
+            Hash::Sha1 => Sha1::digest(prefixed()).to_vec(),
+            Hash::Sha256 => Sha256::digest(prefixed()).to_vec(),
+            Hash::HmacMd5 => hmac::<Hmac<Md5>>(key, data),
+            Hash::HmacSha1 => hmac::<Hmac<Sha1>>(key, data),
+            Hash::HmacSha256 => hmac::<Hmac<Sha256>>(key, data),
+        })
+    }
+}
+
+/// Returns the HMAC of `data` with the key `key`.
+#[cfg(feature = "compat")]
+fn hmac<M: KeyInit + Mac>(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = <M as KeyInit>::new_from_slice(key).expect("HMAC takes any key");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// A PasswordMaker profile's password settings; the defaults are PasswordMaker's.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Options {
+    pub hash: Hash,
+    pub length: usize,
+    pub charset: String,
+    /// Extra text hashed with the site, which PasswordMaker users change to rotate passwords
+    pub modifier: String,
+    pub prefix: String,
+    pub suffix: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            hash: Hash::default(),
+            length: 8,
+            charset: CHARSET.into(),
+            modifier: String::new(),
+            prefix: String::new(),
+            suffix: String::new(),
+        }
+    }
+}
+
+#[cfg(feature = "compat")]
+impl Options {
+    pub(super) fn password(&self, password: &str, site: &Site) -> Result<Zeroizing<String>> {
+        ensure!(
+            site.counter == 1,
+            "passwordmaker has no counter; change the site's modifier instead"
+        );
+        let charset: Vec<char> = self.charset.chars().collect();
+        ensure!(
+            charset.len() >= 2,
+            "charset must have at least 2 characters"
+        );
+        let data = Zeroizing::new(format!(
+            "{}{}{}",
+            site.name,
+            site.login.unwrap_or(""),
+            self.modifier
+        ));
+
+        let mut res = Zeroizing::new(self.prefix.chars().collect::<Vec<_>>());
+        let mut count = 0;
+        while res.len() < self.prefix.chars().count() + self.length && count < 1000 {
+            let key = match count {
+                0 => Zeroizing::new(password.to_owned()),
+                _ => Zeroizing::new(format!("{password}\n{count}")),
+            };
+            let mut hash = self.hash.hash(key.as_bytes(), data.as_bytes());
+            res.extend(encode(&mut hash, &charset).iter());
+            count += 1;
+        }
+        if !self.suffix.is_empty() {
+            res.truncate(self.length.saturating_sub(self.suffix.chars().count()));
+            res.extend(self.suffix.chars());
+        }
+        res.truncate(self.length);
+        Ok(Zeroizing::new(res.iter().collect()))
+    }
+}
+
+/// Writes the big-endian number `n` in base `charset`, most significant digit first and without
+/// leading zeros, as PasswordMaker's `rstr2any` does.
+#[cfg(feature = "compat")]
+fn encode(n: &mut [u8], charset: &[char]) -> Zeroizing<Vec<char>> {
+    let mut res = Zeroizing::new(Vec::new());
+    loop {
+        res.push(charset[div_rem(n, charset.len() as u32) as usize]);
+        if n.iter().all(|&byte| byte == 0) {
+            break;
+        }
+    }
+    res.reverse();
+    res
+}
+
+impl fmt::Display for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().ok_or(fmt::Error)?;
+        f.write_str(value.get_name())
+    }
+}
+
+impl fmt::Display for Options {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, length {}", self.hash, self.length)?;
+        if self.charset != CHARSET {
+            write!(f, ", charset {:?}", self.charset)?;
+        }
+        for (name, value) in [
+            ("modifier", &self.modifier),
+            ("prefix", &self.prefix),
+            ("suffix", &self.suffix),
+        ] {
+            if !value.is_empty() {
+                write!(f, ", {name} {value:?}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "compat"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passwords() -> Result<()> {
+        // From a port of PasswordMaker's JavaScript.
+        let site = Site {
+            name: "example.org",
+            login: Some("bob"),
+            user: None,
+            counter: 1,
+        };
+        let passwords = [
+            (Hash::Md5, "CI^rl+H{"),
+            (Hash::Sha1, "\\+>m<Vam"),
+            (Hash::Sha256, "DMC#6^8S"),
+            (Hash::HmacMd5, "DRzA+!9)"),
+            (Hash::HmacSha1, "EY?bXU5j"),
+            (Hash::HmacSha256, ">krWk3xR"),
+        ];
+        for (hash, want) in passwords {
+            let options = Options {
+                hash,
+                ..Default::default()
+            };
+            assert_eq!(*options.password("password", &site)?, want, "{hash}");
+        }
+
+        let site = Site {
+            login: None,
+            ..site
+        };
+        let options = Options {
+            length: 40,
+            ..Default::default()
+        };
+        assert_eq!(
+            *options.password("password", &site)?,
+            "HjcLo4dWCN\"h{j!3/uJ5BrR{gLT)P=w'I0N}uyIl"
+        );
+        let options = Options {
+            hash: Hash::Sha256,
+            length: 12,
+            charset: "0123456789abcdef".into(),
+            prefix: "x-".into(),
+            suffix: "!".into(),
+            ..Default::default()
+        };
+        assert_eq!(*options.password("password", &site)?, "x-dc97e970a!");
+        let site = Site { counter: 2, ..site };
+        assert!(options.password("password", &site).is_err());
+        Ok(())
+    }
+}
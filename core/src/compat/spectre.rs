@@ -0,0 +1,227 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Spectre, formerly Master Password (algorithm version 3): a scrypt master key from the master
+//! password and the user's full name, an HMAC-SHA256 site key from it, and a password rendered
+//! from a pattern of character classes, which the site key picks from its template and fills in.
+
+use std::fmt;
+
+#[cfg(feature = "compat")]
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+#[cfg(feature = "compat")]
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "compat")]
+use sha2::Sha256;
+#[cfg(feature = "compat")]
+use zeroize::Zeroizing;
+
+#[cfg(feature = "compat")]
+use super::Site;
+
+#[cfg(feature = "compat")]
+const SCOPE: &[u8] = b"com.lyndir.masterpassword";
+
+/// The password templates, which Spectre calls result types.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Template {
+    /// 20 characters, e.g. `W6@692^B1#&@gVdSdLZ@`
+    Maximum,
+    /// 14 characters, e.g. `Jejr5[RepuSosp`
+    #[default]
+    Long,
+    /// 8 characters, e.g. `Jej2$Quv`
+    Medium,
+    /// 4 characters, e.g. `Jej2`
+    Short,
+    /// 8 letters and digits, e.g. `WAo2xIg6`
+    Basic,
+    /// 4 digits, e.g. `7662`
+    Pin,
+    /// A pronounceable 9-letter name, e.g. `jejraquvo`
+    Name,
+    /// A phrase of pronounceable words, e.g. `jejr quv cabsibu tam`
+    Phrase,
+}
+
+#[cfg(feature = "compat")]
+impl Template {
+    /// Returns the patterns of the template, one of which the site key picks.
+    fn patterns(self) -> &'static [&'static str] {
+        match self {
+            Template::Maximum => &["anoxxxxxxxxxxxxxxxxx", "axxxxxxxxxxxxxxxxxno"],
+            Template::Long => &[
+                "CvcvnoCvcvCvcv",
+                "CvcvCvcvnoCvcv",
+                "CvcvCvcvCvcvno",
+                "CvccnoCvcvCvcv",
+                "CvccCvcvnoCvcv",
+                "CvccCvcvCvcvno",
+                "CvcvnoCvccCvcv",
+                "CvcvCvccnoCvcv",
+                "CvcvCvccCvcvno",
+                "CvcvnoCvcvCvcc",
+                "CvcvCvcvnoCvcc",
+                "CvcvCvcvCvccno",
+                "CvccnoCvccCvcv",
+                "CvccCvccnoCvcv",
+                "CvccCvccCvcvno",
+                "CvcvnoCvccCvcc",
+                "CvcvCvccnoCvcc",
+                "CvcvCvccCvccno",
+                "CvccnoCvcvCvcc",
+                "CvccCvcvnoCvcc",
+                "CvccCvcvCvccno",
+            ],
+            Template::Medium => &["CvcnoCvc", "CvcCvcno"],
+            Template::Short => &["Cvcn"],
+            Template::Basic => &["aaanaaan", "aannaaan", "aaannaaa"],
+            Template::Pin => &["nnnn"],
+            Template::Name => &["cvccvcvcv"],
+            Template::Phrase => &[
+                "cvcc cvc cvccvcv cvc",
+                "cvc cvccvcvcv cvcv",
+                "cv cvccv cvc cvcvccv",
+            ],
+        }
+    }
+}
+
+/// Returns the characters of the pattern character `class`.
+#[cfg(feature = "compat")]
+fn class(class: u8) -> &'static [u8] {
+    match class {
+        b'V' => b"AEIOU",
+        b'C' => b"BCDFGHJKLMNPQRSTVWXYZ",
+        b'v' => b"aeiou",
+        b'c' => b"bcdfghjklmnpqrstvwxyz",
+        b'A' => b"AEIOUBCDFGHJKLMNPQRSTVWXYZ",
+        b'a' => b"AEIOUaeiouBCDFGHJKLMNPQRSTVWXYZbcdfghjklmnpqrstvwxyz",
+        b'n' => b"0123456789",
+        b'o' => b"@&%?,=[]_:-+*$#!'^~;()/.",
+        b'x' => b"AEIOUaeiouBCDFGHJKLMNPQRSTVWXYZbcdfghjklmnpqrstvwxyz0123456789!@#$%^&*()",
+        _ => b" ",
+    }
+}
+
+/// A Spectre site's password settings; the defaults are Spectre's.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Options {
+    pub template: Template,
+}
+
+#[cfg(feature = "compat")]
+impl Options {
+    pub(super) fn password(&self, password: &str, site: &Site) -> Result<Zeroizing<String>> {
+        Ok(self.template.render(&*site_key(password, site)?))
+    }
+}
+
+#[cfg(feature = "compat")]
+impl Template {
+    /// Renders the password of the site with key `site_key` in this template.
+    fn render(self, site_key: &[u8; 32]) -> Zeroizing<String> {
+        let patterns = self.patterns();
+        let pattern = patterns[usize::from(site_key[0]) % patterns.len()].as_bytes();
+        let res = pattern
+            .iter()
+            .zip(&site_key[1..])
+            .map(|(&c, &byte)| {
+                let chars = class(c);
+                char::from(chars[usize::from(byte) % chars.len()])
+            })
+            .collect();
+        Zeroizing::new(res)
+    }
+}
+
+/// Derives the key of `site` from the master password `password`.
+#[cfg(feature = "compat")]
+fn site_key(password: &str, site: &Site) -> Result<Zeroizing<[u8; 32]>> {
+    let user = site
+        .user
+        .context("spectre needs the user's full name (`full_name` in the config)")?;
+    let mut master_key = Zeroizing::new([0u8; 64]);
+    let params = scrypt::Params::new(15, 8, 2, master_key.len())
+        .map_err(|e| anyhow::anyhow!("scrypt::Params::new: {e}"))?;
+    scrypt::scrypt(
+        password.as_bytes(),
+        &scoped(user.as_bytes()),
+        &params,
+        &mut *master_key,
+    )
+    .map_err(|e| anyhow::anyhow!("scrypt: {e}"))?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(&*master_key)?;
+    mac.update(&scoped(site.name.as_bytes()));
+    mac.update(&site.counter.to_be_bytes());
+    Ok(Zeroizing::new(mac.finalize().into_bytes().into()))
+}
+
+/// Returns `SCOPE` followed by the big-endian length of `s` and `s`, as Spectre salts its hashes.
+#[cfg(feature = "compat")]
+fn scoped(s: &[u8]) -> Vec<u8> {
+    let len = u32::try_from(s.len()).unwrap();
+    [SCOPE, &len.to_be_bytes(), s].concat()
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().ok_or(fmt::Error)?;
+        f.write_str(value.get_name())
+    }
+}
+
+impl fmt::Display for Options {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} template", self.template)
+    }
+}
+
+#[cfg(all(test, feature = "compat"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passwords() -> Result<()> {
+        // From Spectre's own tests.
+        let site = Site {
+            name: "masterpasswordapp.com",
+            login: None,
+            user: Some("Robert Lee Mitchell"),
+            counter: 1,
+        };
+        let key = site_key("banana colored duckling", &site)?;
+        let passwords = [
+            (Template::Maximum, "W6@692^B1#&@gVdSdLZ@"),
+            (Template::Long, "Jejr5[RepuSosp"),
+            (Template::Medium, "Jej2$Quv"),
+            (Template::Short, "Jej2"),
+            (Template::Basic, "WAo2xIg6"),
+            (Template::Pin, "7662"),
+            (Template::Name, "jejraquvo"),
+            (Template::Phrase, "jejr quv cabsibu tam"),
+        ];
+        for (template, password) in passwords {
+            assert_eq!(*template.render(&key), password, "{template}");
+        }
+        let site = Site { user: None, ..site };
+        assert!(Options::default().password("x", &site).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,1984 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The config file, `~/.config/onepass/config.yaml` by default: settings, schema aliases, and the
+//! sites with their schemas, increments, and usernames.
+
+use std::{
+    borrow::Cow,
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, hash_map::Entry},
+    env, fmt,
+    fs::{self, OpenOptions, create_dir_all, read_to_string, rename, write},
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use home_dir::HomeDirExt;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+
+use crate::{
+    backup,
+    compat::Compat,
+    date::Date,
+    derive::{DerivationVersion, KdfConfig},
+    policy::Policy,
+    rules,
+    url::{self, Subdomains, canonicalize_with},
+    wordlist::{WordFilter, Wordlist},
+};
+
+/// The config, compiled: with aliases resolved and site names canonicalized. It is serialized in
+/// this form only for the cache of [`Config::from_file_cached`]; config files are read as YAML.
+#[derive(Deserialize, Serialize)]
+pub struct Config {
+    /// The read-only config this one is overlaid on, as written in the config file
+    pub base: Option<Box<Path>>,
+    /// Words files, whose words are combined into one list
+    pub words_path: Vec<Box<Path>>,
+    /// The built-in word list, used unless there is a `words_path`
+    pub wordlist: Wordlist,
+    pub keyfile: Option<Box<Path>>,
+    pub yubikey_slot: Option<u8>,
+    pub fido2: Option<Fido2Config>,
+    pub ssh_agent_key: Option<String>,
+    pub cache_ttl: Option<u32>,
+    pub clipboard: Option<ClipboardBackend>,
+    pub pepper: Option<PepperSource>,
+    /// A duress password, which derives decoy passwords like any other master password
+    pub duress: Option<DuressConfig>,
+    /// A git remote that `onepass sync` keeps the config in step with
+    pub sync: Option<SyncConfig>,
+    pub default_schema: String,
+    pub use_keyring: Option<bool>,
+    pub kdf: KdfConfig,
+    pub default_version: DerivationVersion,
+    pub salt_template: Option<String>,
+    /// The user's full name, for compatibility algorithms that derive from it, like Spectre's
+    pub full_name: Option<String>,
+    /// The rules site names are canonicalized under
+    pub canonicalization: url::Rules,
+    /// The version of the built-in site rules that choose the schemas of sites without one, or 0
+    /// for none
+    pub site_rules: u32,
+    /// The days after which sites' passwords are due for rotation, if not 365, or 0 for never
+    pub rotate_after: Option<u32>,
+    /// The fewest bits of entropy `audit` lets a schema have, if not its default
+    pub min_bits: Option<u32>,
+    pub aliases: HashMap<String, String>,
+    pub sites: HashMap<String, SiteConfig>,
+    /// Sites named in the config file that canonicalize to the same site as another, each with
+    /// the canonical URL; the first by name is the one that takes effect.
+    #[serde(default)]
+    pub shadowed: Vec<(String, String)>,
+
+    #[serde(skip)]
+    config_path: Option<Box<Path>>,
+    #[serde(skip)]
+    cache_dir: Option<Box<Path>>,
+}
+
+/// The settings a base config may have. The rest, like `sync`, `duress`, `pepper`, and `kdf`, are
+/// the user's own, and a base config that sets one is refused.
+pub const BASE_SETTINGS: &[&str] = &[
+    "aliases",
+    "canonicalization",
+    "default_schema",
+    "min_bits",
+    "policy",
+    "rotate_after",
+    "site_rules",
+    "sites",
+    "wordlist",
+    "words_path",
+];
+
+/// The days after which passwords are due for rotation, unless configured otherwise.
+pub const DEFAULT_ROTATE_AFTER: u32 = 365;
+
+/// A compiled config in the cache, with the config file it was compiled from.
+#[derive(Deserialize, Serialize)]
+struct Cached<C> {
+    /// The version of onepass-core that compiled it, since canonicalization may change between
+    /// versions
+    version: String,
+    modified: Option<SystemTime>,
+    /// The BLAKE3 hash of the config file
+    hash: String,
+    /// The BLAKE3 hash of its base config, if it has one
+    #[serde(default)]
+    base_hash: Option<String>,
+    config: C,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SiteConfig {
+    /// The schema of the site's password, or if left out, one following `passwordrules`, or
+    /// else `default_schema`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub schema: String,
+    /// The site's password rules in the `passwordrules` format Safari reads, e.g.
+    /// `minlength: 12; required: upper, lower, digit;`
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_passwordrules"
+    )]
+    pub passwordrules: Option<String>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub increment: u32,
+    /// The day the site's password was last set or changed, e.g. by bumping its increment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotated: Option<Date>,
+    /// The days after which the site's password is due for rotation, if not `rotate_after`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotate_after: Option<u32>,
+    /// The times the site's increment was bumped, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bumps: Vec<Bump>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<DerivationVersion>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt_template: Option<String>,
+    /// Additional secrets for the site, e.g. a PIN or security question answers, mapped to their
+    /// schemas
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+    /// A TOTP secret issued by the site, in base32, for `onepass totp` to use instead of a
+    /// derived one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp_secret: Option<String>,
+    /// A HOTP secret issued by the site, in base32, for `onepass hotp` to use instead of a
+    /// derived one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hotp_secret: Option<String>,
+    /// The counter of the site's next HOTP code, advanced by `onepass hotp`
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub hotp_counter: u64,
+    /// Another password manager's algorithm to derive the site's password with instead, for
+    /// keeping passwords made by it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compat: Option<Compat>,
+    /// Whether the site's name is an identifier that is not a URL, like `laptop-luks`, salted
+    /// verbatim instead of canonicalized
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub raw: bool,
+    /// What becomes of the site's subdomains, if not what `canonicalization` says
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subdomains: Option<Subdomains>,
+    /// Whether the site's port is part of it, if not what `canonicalization` says
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<bool>,
+    /// The path prefix that the site's URLs are cut to, e.g. `/gitea` to tell apart services
+    /// sharing a host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Restrictions on the words the site's passwords draw from, e.g. `max_len: 6`
+    #[serde(default, skip_serializing_if = "WordFilter::is_empty")]
+    pub word_filter: WordFilter,
+}
+
+/// A bump of a site's increment, rotating its password.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Bump {
+    /// The increment the site was bumped from
+    pub increment: u32,
+    /// The day it was bumped
+    pub date: Date,
+    /// Why, e.g. `breach 2025-06`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl FromStr for Config {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let config: SerConfig = serde_yaml::from_str(s)?;
+        Ok(Self::from_ser_config(config))
+    }
+}
+
+impl Config {
+    pub fn from_file(path: Option<&Path>) -> Result<Self> {
+        let path = path.map_or_else(Self::default_path, |p| Ok(p.into()))?;
+        if !path.exists() {
+            create_dir_all(path.parent().context("invalid config path")?)?;
+            write(&path, serde_yaml::to_string(&SerConfig::example())?)?;
+        }
+        let text = read_to_string(&path)?;
+        Ok(Self::parse(path, &text)?.0)
+    }
+
+    /// Parses the config file at `path`, whose text is `text`, overlaid on its base config if it
+    /// names one, and returns it with the base config's text.
+    ///
+    /// Sites and aliases are merged by name, and a site in both configs field by field; every
+    /// other setting in the config file replaces the base config's. The base config can't have a
+    /// base of its own, and may only have the settings in [`BASE_SETTINGS`], since it needn't be
+    /// trusted with the user's second factors, sync remote, or duress command. Its relative paths
+    /// are relative to its own directory.
+    fn parse(path: Box<Path>, text: &str) -> Result<(Self, Option<String>)> {
+        let doc: Value = serde_yaml::from_str(text)?;
+        let Some(base) = doc.get("base") else {
+            let mut config = Self::from_str(text)?;
+            config.config_path = Some(path);
+            return Ok((config, None));
+        };
+        let base: PathBuf = serde_yaml::from_value(base.clone()).context("invalid base")?;
+        let base = base.expand_home().ok().context("invalid base")?;
+        let base = path.parent().context("invalid config path")?.join(base);
+        let base_text = read_to_string(&base)
+            .with_context(|| format!("failed reading base config {}", base.display()))?;
+        let mut base_doc: Value = serde_yaml::from_str(&base_text)
+            .with_context(|| format!("invalid base config {}", base.display()))?;
+        anyhow::ensure!(
+            base_doc.get("base").is_none(),
+            "base config {} has a base of its own",
+            base.display()
+        );
+        if let Some(settings) = base_doc.as_mapping_mut() {
+            let setting = settings.keys().find(|setting| {
+                !setting
+                    .as_str()
+                    .is_some_and(|setting| BASE_SETTINGS.contains(&setting))
+            });
+            if let Some(setting) = setting {
+                anyhow::bail!(
+                    "base config {} sets {}, which only the config file may",
+                    base.display(),
+                    serde_yaml::to_string(setting)?.trim_end()
+                );
+            }
+            let base_dir = base.parent().context("invalid base")?;
+            if let Some(words_path) = settings.get_mut("words_path") {
+                let paths = deserialize_paths(words_path.clone())
+                    .with_context(|| format!("invalid words_path in {}", base.display()))?;
+                let paths = paths
+                    .into_iter()
+                    .map(|path| Ok(base_dir.join(path.expand_home().ok().context("invalid path")?)))
+                    .collect::<Result<Vec<_>>>()?;
+                *words_path = serde_yaml::to_value(paths)?;
+            }
+        }
+        let config = serde_yaml::from_value(overlay(base_doc, doc))?;
+        let mut config = Self::from_ser_config(config);
+        config.config_path = Some(path);
+        Ok((config, Some(base_text)))
+    }
+
+    /// Returns the path of the base config, relative to the config file's directory.
+    pub fn base_path(&self) -> Option<Box<Path>> {
+        self.resolve_path(self.base.as_deref()?)
+    }
+
+    /// Reads the config file at `path` like [`Config::from_file`], but keeps it compiled in
+    /// `cache_dir` too, and reads it from there instead while the file's modification time and
+    /// hash are unchanged. This skips parsing YAML and canonicalizing every site, which adds up
+    /// for launchers and browser integrations that run onepass on every keystroke.
+    ///
+    /// The cache is best-effort: if it can't be read or written, the file is parsed as usual.
+    pub fn from_file_cached(path: Option<&Path>, cache_dir: &Path) -> Result<Self> {
+        let path = path.map_or_else(Self::default_path, |p| Ok(p.into()))?;
+        if !path.exists() {
+            return Self::from_file(Some(&path)).map(|config| config.with_cache_dir(cache_dir));
+        }
+        let text = read_to_string(&path)?;
+        let modified = fs::metadata(&path)?.modified().ok();
+        let hash = hash_text(&text);
+        let cache = cache_dir.join(format!(
+            "config-{}.json",
+            &blake3::hash(path.as_os_str().as_encoded_bytes()).to_hex()[..16]
+        ));
+        let version = env!("CARGO_PKG_VERSION");
+        let cached = fs::read(&cache)
+            .ok()
+            .and_then(|json| serde_json::from_slice::<Cached<Config>>(&json).ok())
+            .filter(|cached| {
+                (cached.version.as_str(), cached.modified, &cached.hash)
+                    == (version, modified, &hash)
+            })
+            .map(|mut cached| {
+                cached.config.config_path = Some(path.clone());
+                cached
+            })
+            // The base config may have changed without the config file changing.
+            .filter(|cached| {
+                let text = cached.config.base_path().map(read_to_string).transpose();
+                text.is_ok_and(|text| text.as_deref().map(hash_text) == cached.base_hash)
+            });
+        let config = match cached {
+            Some(cached) => cached.config,
+            None => {
+                let (config, base_text) = Self::parse(path, &text)?;
+                let cached = Cached {
+                    version: version.into(),
+                    modified,
+                    hash,
+                    base_hash: base_text.as_deref().map(hash_text),
+                    config: &config,
+                };
+                write_cache(cache_dir, &cache, &serde_json::to_vec(&cached)?);
+                config
+            }
+        };
+        Ok(config.with_cache_dir(cache_dir))
+    }
+
+    fn with_cache_dir(mut self, cache_dir: &Path) -> Self {
+        self.cache_dir = Some(cache_dir.into());
+        self
+    }
+
+    /// The directory that compiled state, like this config, is cached in, if it was read with
+    /// [`Config::from_file_cached`].
+    pub fn cache_dir(&self) -> Option<&Path> {
+        self.cache_dir.as_deref()
+    }
+
+    /// Returns the default cache directory, `${XDG_CACHE_HOME:-$HOME/.cache}/onepass`.
+    pub fn default_cache_dir() -> Result<Box<Path>> {
+        let cache_dir = match env::var("XDG_CACHE_HOME") {
+            Err(env::VarError::NotPresent) => {
+                env::var("HOME").map(|home| PathBuf::from(home).join(".cache"))
+            }
+            r => r.map(|cache| cache.into()),
+        }
+        .context("failed finding cache dir")?;
+        Ok(cache_dir.join("onepass").into_boxed_path())
+    }
+
+    /// Returns the built-in rules of the site `url`, if it is a website with any, as of version
+    /// `version` of them.
+    pub fn known_rules(&self, url: &str, version: u32) -> Option<&'static rules::Known> {
+        known_rules(&self.canonicalize(url, None, None).ok()?, version)
+    }
+
+    /// Returns the schema of the site `url` when neither its config nor the command line gives
+    /// one: that of its built-in rules under `site_rules`, if it has any, or else
+    /// `default_schema`.
+    pub fn default_schema_for(&self, url: &str) -> &str {
+        self.known_rules(url, self.site_rules)
+            .map_or(&self.default_schema, rules::Known::schema)
+    }
+
+    /// Returns the canonical URL of the site `url` under the canonicalization rules of `site`.
+    pub fn canonicalize(
+        &self,
+        url: &str,
+        username: Option<&str>,
+        site: Option<&SiteConfig>,
+    ) -> Result<String> {
+        canonicalize_with(url, username, &self.rules(site))
+    }
+
+    /// Returns the canonicalization rules of `site`: the config's, with the site's own subdomain
+    /// policy, port, and path prefix.
+    pub fn rules(&self, site: Option<&SiteConfig>) -> Cow<'_, url::Rules> {
+        site_rules(&self.canonicalization, site)
+    }
+
+    /// Returns the key in `sites` of the site `url`, which each site matches under its own
+    /// canonicalization rules, or that a raw site has verbatim. The most specific rules are tried
+    /// first: path prefixes, longest
+    /// first, then kept ports, then subdomains kept, mapped, or reduced to registrable domains.
+    pub fn site_key(&self, url: &str) -> Result<Option<String>> {
+        if let Some((key, _)) = self.find_raw_site(url) {
+            return Ok(Some(key));
+        }
+        let mut candidates = vec![Cow::Borrowed(&self.canonicalization)];
+        for site in self.sites.values() {
+            let rules = site_rules(&self.canonicalization, Some(site));
+            if !candidates.contains(&rules) {
+                candidates.push(rules);
+            }
+        }
+        candidates.sort_by_key(|rules| {
+            let subdomains = match rules.subdomains {
+                Subdomains::Keep => 2,
+                Subdomains::Map => 1,
+                Subdomains::Registrable => 0,
+            };
+            let path = rules
+                .path
+                .as_deref()
+                .map(|path| path.trim_end_matches('/').len());
+            Reverse((path, rules.port, subdomains))
+        });
+        for rules in candidates {
+            let key = canonicalize_with(url, None, &rules)?;
+            if let Some(site) = self.sites.get(&key)
+                && site_rules(&self.canonicalization, Some(site)) == rules
+            {
+                return Ok(Some(key));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the key in `sites` of a configured site that the site `url` looks like but is not,
+    /// as a phishing domain would; see [`url::lookalike`].
+    pub fn lookalike_site(&self, url: &str) -> Result<Option<&str>> {
+        let url = self.canonicalize(url, None, None)?;
+        Ok(self
+            .sites
+            .iter()
+            .filter(|(_, site)| !site.raw)
+            .map(|(key, _)| key.as_str())
+            .filter(|key| url::lookalike(&url, key))
+            .min())
+    }
+
+    /// Returns the raw site named `name`, if there is one.
+    pub fn find_raw_site(&self, name: &str) -> Option<(String, &SiteConfig)> {
+        self.sites
+            .get_key_value(name)
+            .filter(|(_, site)| site.raw)
+            .map(|(name, site)| (name.clone(), site))
+    }
+
+    pub fn find_site(&self, url: &str) -> Result<Option<(String, &SiteConfig)>> {
+        if let Some(site) = self.find_raw_site(url) {
+            return Ok(Some(site));
+        }
+        let Some(key) = self.site_key(url)? else {
+            return Ok(None);
+        };
+        let site = &self.sites[&key];
+        let url = self.canonicalize(&key, site.username.as_deref(), Some(site))?;
+        Ok(Some((url, site)))
+    }
+
+    /// Updates the config file's entry for the site `url` in place, converting it to the long form
+    /// if needed. A site only in the base config gets an entry of its own in the config file,
+    /// overlaid on the base config's. Where only scalar settings of a site already in the long
+    /// form change, like its `hotp_counter`, just their lines are rewritten, keeping the rest of
+    /// the file as it was; otherwise comments in the file are not preserved.
+    pub fn update_site(&self, url: &str, update: impl FnOnce(&mut Mapping)) -> Result<()> {
+        let key = self
+            .site_key(url)?
+            .with_context(|| format!("{url} is not in the config"))?;
+        let base_name = match self.base_path() {
+            Some(base) => {
+                let base: Value = serde_yaml::from_str(&read_to_string(base)?)?;
+                base.get("sites")
+                    .and_then(Value::as_mapping)
+                    .and_then(|sites| self.entry_name(sites, &key))
+            }
+            None => None,
+        };
+        self.update_with(|doc| {
+            if !doc.contains_key("sites") {
+                doc.insert("sites".into(), Value::Mapping(Mapping::new()));
+            }
+            let sites = doc
+                .get_mut("sites")
+                .and_then(Value::as_mapping_mut)
+                .context("invalid sites")?;
+            let name = self.entry_name(sites, &key).or(base_name);
+            let name = name.with_context(|| format!("{key} is not in the config"))?;
+            let entry = sites
+                .entry(name.clone())
+                .or_insert(Value::Mapping(Mapping::new()));
+            let before = entry.clone();
+            if let Value::String(schema) = entry {
+                let mut site = Mapping::new();
+                site.insert("schema".into(), Value::String(schema.clone()));
+                *entry = Value::Mapping(site);
+            }
+            let entry = entry.as_mapping_mut().context("invalid site config")?;
+            update(entry);
+            Ok(Some(SiteEdit {
+                name,
+                before,
+                after: entry.clone(),
+            }))
+        })
+    }
+
+    /// Returns the name of the entry in `sites`, a config file's sites, that is the site `key`.
+    fn entry_name(&self, sites: &Mapping, key: &str) -> Option<Value> {
+        sites
+            .iter()
+            .find(|(site, entry)| {
+                let entry = serde_yaml::from_value::<SchemaOrSiteConfig>((*entry).clone());
+                let entry = entry.map(SiteConfig::from).ok();
+                site.as_str().is_some_and(|site| match &entry {
+                    Some(entry) if entry.raw => site == key,
+                    entry => self
+                        .canonicalize(site, None, entry.as_ref())
+                        .is_ok_and(|site| site == key),
+                })
+            })
+            .map(|(site, _)| site.clone())
+    }
+
+    /// Updates the config file in place, as a YAML mapping, after taking a snapshot of it with
+    /// [`backup::snapshot`]. Comments in the file are not preserved.
+    pub fn update(&self, update: impl FnOnce(&mut Mapping) -> Result<()>) -> Result<()> {
+        self.update_with(|doc| update(doc).map(|()| None))
+    }
+
+    /// Updates the config file like [`Config::update`], but where `update` returns the edit it
+    /// made to a site, rewrites only the lines of the site's settings that it changed if it can.
+    fn update_with(
+        &self,
+        update: impl FnOnce(&mut Mapping) -> Result<Option<SiteEdit>>,
+    ) -> Result<()> {
+        let path = self.config_path.as_deref().context("no config file")?;
+        let text = read_to_string(path)?;
+        let mut doc: Value = serde_yaml::from_str(&text)?;
+        if doc.is_null() {
+            doc = Value::Mapping(Mapping::new());
+        }
+        let edit = update(doc.as_mapping_mut().context("invalid config")?)?;
+        // Only if the edited text reads back as what would otherwise be written.
+        let edited = edit
+            .and_then(|edit| edit.apply(&text))
+            .filter(|edited| serde_yaml::from_str::<Value>(edited).is_ok_and(|e| e == doc));
+        match edited {
+            Some(edited) => rewrite(path, &edited),
+            None => rewrite(path, &serde_yaml::to_string(&doc)?),
+        }
+    }
+
+    pub fn version(&self, site: Option<&SiteConfig>) -> DerivationVersion {
+        site.and_then(|site| site.version)
+            .unwrap_or(self.default_version)
+    }
+
+    /// Returns the days after which `site`'s password is due for rotation, unless it never is.
+    pub fn rotate_after(&self, site: &SiteConfig) -> Option<u32> {
+        let days = site.rotate_after.or(self.rotate_after);
+        Some(days.unwrap_or(DEFAULT_ROTATE_AFTER)).filter(|days| *days != 0)
+    }
+
+    pub fn salt_template<'a>(&'a self, site: Option<&'a SiteConfig>) -> Option<&'a str> {
+        site.and_then(|site| site.salt_template.as_deref())
+            .or(self.salt_template.as_deref())
+    }
+
+    /// Returns the paths of the words files, relative to the config file's directory.
+    pub fn words_paths(&self) -> Vec<Box<Path>> {
+        self.words_path
+            .iter()
+            .filter_map(|path| self.resolve_path(path))
+            .collect()
+    }
+
+    /// The directory containing the config file, where other onepass state is kept.
+    pub fn config_dir(&self) -> Result<&Path> {
+        self.config_path
+            .as_deref()
+            .and_then(Path::parent)
+            .context("no config directory")
+    }
+
+    pub fn keyfile_path(&self) -> Option<Box<Path>> {
+        self.resolve_path(self.keyfile.as_deref()?)
+    }
+
+    fn resolve_path(&self, path: &Path) -> Option<Box<Path>> {
+        let path = path.expand_home().ok()?;
+        if path.is_relative() {
+            let config_path = self.config_path.as_deref()?.parent()?;
+            Some(config_path.join(path).into())
+        } else {
+            Some(path.into())
+        }
+    }
+
+    fn from_ser_config(config: SerConfig) -> Self {
+        let base = config.base;
+        let words_path = config.words_path;
+        let wordlist = config.wordlist;
+        let keyfile = config.keyfile;
+        let yubikey_slot = config.yubikey_slot;
+        let fido2 = config.fido2;
+        let ssh_agent_key = config.ssh_agent_key;
+        let cache_ttl = config.cache_ttl;
+        let clipboard = config.clipboard;
+        let pepper = config.pepper;
+        let duress = config.duress;
+        let sync = config.sync;
+        let aliases = config.aliases;
+        let policy = config.policy;
+        let default_schema = config
+            .default_schema
+            .or_else(|| policy.map(|policy| policy.default_schema().into()))
+            .unwrap_or_else(default_schema);
+        let default_schema = aliases
+            .get(&default_schema)
+            .map_or(default_schema, Clone::clone);
+        let use_keyring = config.use_keyring;
+        let kdf = config
+            .kdf
+            .or_else(|| policy.map(Policy::kdf))
+            .unwrap_or_default();
+        let default_version = config.default_version;
+        let salt_template = config.salt_template;
+        let full_name = config.full_name;
+        let canonicalization = config.canonicalization;
+        let site_rules_version = config.site_rules;
+        let rotate_after = config
+            .rotate_after
+            .or_else(|| policy.map(Policy::rotate_after));
+        let min_bits = config.min_bits.or_else(|| policy.map(Policy::min_bits));
+        // Sorted, so that which of two sites that canonicalize alike takes effect doesn't depend
+        // on the order of a hash map.
+        let mut named: Vec<_> = config.sites.into_iter().collect();
+        named.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut sites = HashMap::new();
+        let mut shadowed = Vec::new();
+        for (name, mut config) in named {
+            for schema in config.fields.values_mut() {
+                if let Some(alias) = aliases.get(schema) {
+                    *schema = alias.clone();
+                }
+            }
+            // TODO: print warnings on parse errors here
+            let rules = site_rules(&canonicalization, Some(&config));
+            // Raw sites are named verbatim.
+            let site = match canonicalize_with(&name, None, &rules) {
+                Ok(url) if !config.raw => url,
+                _ => name.clone(),
+            };
+            if config.schema.is_empty() {
+                let declared = config.passwordrules.as_deref();
+                // Checked when deserialized.
+                let declared = declared.and_then(|rules| rules.parse().ok());
+                config.schema = match declared {
+                    Some(rules) => rules::PasswordRules::schema(&rules),
+                    None => known_rules(&site, site_rules_version)
+                        .filter(|_| !config.raw)
+                        .map_or_else(|| default_schema.clone(), |known| known.schema().into()),
+                };
+            } else if let Some(schema) = aliases.get(&config.schema) {
+                config.schema = schema.clone();
+            }
+            match sites.entry(site) {
+                Entry::Occupied(entry) => shadowed.push((name, entry.key().clone())),
+                Entry::Vacant(entry) => {
+                    entry.insert(config);
+                }
+            }
+        }
+        Config {
+            base,
+            words_path,
+            wordlist,
+            keyfile,
+            yubikey_slot,
+            fido2,
+            duress,
+            sync,
+            ssh_agent_key,
+            cache_ttl,
+            clipboard,
+            pepper,
+            default_schema,
+            use_keyring,
+            kdf,
+            default_version,
+            salt_template,
+            full_name,
+            canonicalization,
+            site_rules: site_rules_version,
+            rotate_after,
+            min_bits,
+            aliases,
+            sites,
+            shadowed,
+
+            config_path: None,
+            cache_dir: None,
+        }
+    }
+
+    /// Returns the path of the config file when none is given, `~/.config/onepass/config.yaml`.
+    pub fn default_path() -> Result<Box<Path>> {
+        let mut config_dir = match env::var("XDG_CONFIG_DIR") {
+            Err(env::VarError::NotPresent) => {
+                env::var("HOME").map(|home| PathBuf::from(home).join(".config"))
+            }
+            r => r.map(|config| config.into()),
+        }
+        .context("failed finding config dir")?;
+        config_dir.push("onepass");
+        config_dir.push("config.yaml");
+        Ok(config_dir.into_boxed_path())
+    }
+}
+
+/// Where to find the pepper, a random secret that is not memorized but kept on the machine.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum PepperSource {
+    /// Sealed in the TPM with `onepass tpm init`
+    Tpm,
+    /// Stored in the system keyring with `onepass keyring init`
+    Keyring,
+}
+
+impl fmt::Display for PepperSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().ok_or(fmt::Error)?;
+        f.write_str(value.get_name())
+    }
+}
+
+/// A FIDO2 credential created with the hmac-secret extension, e.g. by `fido2-cred -M -h`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Fido2Config {
+    /// The base64-encoded credential id
+    pub credential_id: String,
+    #[serde(default = "default_fido2_rp_id")]
+    pub rp_id: String,
+    /// The authenticator device path; by default, the first device listed by `fido2-token -L`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+}
+
+impl Fido2Config {
+    pub fn new(credential_id: String) -> Self {
+        Fido2Config {
+            credential_id,
+            rp_id: default_fido2_rp_id(),
+            device: None,
+        }
+    }
+}
+
+/// A duress password, known only by its fingerprint from `onepass duress-fingerprint`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DuressConfig {
+    /// The salt and hash of the duress password, in hex
+    pub fingerprint: String,
+    /// A shell command run in the background whenever the duress password is entered, e.g. to
+    /// send an alert
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+}
+
+/// A git remote that the config directory, as a git repository, is synced with.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SyncConfig {
+    /// The remote, by URL or by the name of a remote of the repository
+    pub remote: String,
+    /// The branch to pull and push
+    #[serde(default = "default_sync_branch")]
+    pub branch: String,
+}
+
+fn default_sync_branch() -> String {
+    "main".into()
+}
+
+fn default_fido2_rp_id() -> String {
+    "onepass".into()
+}
+
+/// A way of copying secrets to somewhere they can be pasted from; the CLI implements them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClipboardBackend {
+    /// wl-copy, on Wayland
+    WlCopy,
+    /// xclip, on X11
+    Xclip,
+    /// xsel, on X11
+    Xsel,
+    /// pbcopy, on macOS
+    Pbcopy,
+    /// clip.exe, on Windows
+    Windows,
+    /// termux-clipboard-set, on Android under Termux
+    Termux,
+    /// An OSC 52 escape sequence to the terminal, which works over ssh
+    Osc52,
+    /// The tmux paste buffer "onepass"
+    Tmux,
+}
+
+impl fmt::Display for ClipboardBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().ok_or(fmt::Error)?;
+        f.write_str(value.get_name())
+    }
+}
+
+/// Replaces the config file at `path` with `text`, after taking a snapshot of it with
+/// [`backup::snapshot`]. The file may hold secrets like `totp_secret`s, so it keeps its
+/// permissions rather than taking the umask's, and is readable only by the user until then.
+pub fn rewrite(path: &Path, text: &str) -> Result<()> {
+    backup::snapshot(path)?;
+    let tmp = path.with_extension("yaml.tmp");
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&tmp)?;
+    file.set_permissions(fs::metadata(path)?.permissions())?;
+    file.write_all(text.as_bytes())?;
+    drop(file);
+    rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Writes the cache file `path` in the cache directory `dir`, if it can. A cache only ever saves
+/// work, so failing to write one only makes the next run slower.
+pub fn write_cache(dir: &Path, path: &Path, contents: &[u8]) {
+    let _ = write_private(dir, path, contents);
+}
+
+/// Writes `contents` to `path` in `dir`, creating `dir` if need be, both readable only by the
+/// user, since a cached config holds the config's secrets, like its `totp_secret`s.
+fn write_private(dir: &Path, path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    create_dir_all(dir)?;
+    #[cfg(unix)]
+    fs::set_permissions(dir, std::os::unix::fs::PermissionsExt::from_mode(0o700))?;
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // Created private, rather than chmodded after, when it could already have been opened.
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&tmp)?;
+    file.write_all(contents)?;
+    drop(file);
+    rename(&tmp, path)
+}
+
+#[derive(Debug, Deserialize)]
+struct SerConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<Box<Path>>,
+    #[serde(default, deserialize_with = "deserialize_paths")]
+    pub words_path: Vec<Box<Path>>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub wordlist: Wordlist,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyfile: Option<Box<Path>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub yubikey_slot: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fido2: Option<Fido2Config>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_agent_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_ttl: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clipboard: Option<ClipboardBackend>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pepper: Option<PepperSource>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duress: Option<DuressConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync: Option<SyncConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<Policy>,
+    #[serde(default)]
+    pub default_schema: Option<String>,
+    #[serde(default)]
+    pub use_keyring: Option<bool>,
+    #[serde(default)]
+    pub kdf: Option<KdfConfig>,
+    #[serde(default)]
+    pub default_version: DerivationVersion,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt_template: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_name: Option<String>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub canonicalization: url::Rules,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub site_rules: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotate_after: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_bits: Option<u32>,
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+    #[serde(deserialize_with = "deserialize_sites")]
+    pub sites: HashMap<String, SiteConfig>,
+}
+
+impl SerConfig {
+    fn example() -> Self {
+        let aliases: HashMap<String, String> = [
+            ("alnum", "[A-Za-z0-9]{18}"),
+            ("apple", "[:Word:](-[:word:]){3}[0-9]"),
+            ("login", "[!-~]{12}"),
+            ("mobile", "[a-z0-9]{24}"),
+            ("phrase", "[:word:](-[:word:]){4}"),
+            ("pin", "[0-9]{8}"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.into(), v.into()))
+        .collect();
+        let sites: HashMap<String, SiteConfig> = [
+            ("apple.com", ("apple", 0)),
+            ("google.com", ("mobile", 0)),
+            ("iphone.local", ("pin", 1)),
+        ]
+        .into_iter()
+        .map(|(k, (schema, increment))| {
+            (
+                k.into(),
+                SiteConfig {
+                    schema: schema.into(),
+                    increment,
+                    ..Default::default()
+                },
+            )
+        })
+        .collect();
+        let default_schema = Some("login".to_string());
+        SerConfig {
+            base: None,
+            words_path: Vec::new(),
+            wordlist: Wordlist::default(),
+            keyfile: None,
+            yubikey_slot: None,
+            fido2: None,
+            ssh_agent_key: None,
+            cache_ttl: None,
+            clipboard: None,
+            pepper: None,
+            duress: None,
+            sync: None,
+            policy: None,
+            default_schema,
+            use_keyring: None,
+            kdf: Some(KdfConfig::default()),
+            default_version: DerivationVersion::LATEST,
+            salt_template: None,
+            full_name: None,
+            canonicalization: url::Rules {
+                version: url::Version::LATEST,
+                ..Default::default()
+            },
+            site_rules: rules::LATEST,
+            rotate_after: None,
+            min_bits: None,
+            aliases,
+            sites,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum SchemaOrSiteConfig {
+    Schema(String),
+    Config(Box<SiteConfig>),
+}
+
+impl From<SchemaOrSiteConfig> for SiteConfig {
+    fn from(value: SchemaOrSiteConfig) -> Self {
+        match value {
+            SchemaOrSiteConfig::Schema(schema) => SiteConfig {
+                schema,
+                ..Default::default()
+            },
+            SchemaOrSiteConfig::Config(config) => *config,
+        }
+    }
+}
+
+impl From<&SiteConfig> for SchemaOrSiteConfig {
+    fn from(config: &SiteConfig) -> Self {
+        if is_zero(&config.increment)
+            && config.rotated.is_none()
+            && config.rotate_after.is_none()
+            && config.bumps.is_empty()
+            && config.passwordrules.is_none()
+            && config.username.is_none()
+            && config.version.is_none()
+            && config.salt_template.is_none()
+            && config.fields.is_empty()
+            && config.totp_secret.is_none()
+            && config.hotp_secret.is_none()
+            && is_zero(&config.hotp_counter)
+            && config.compat.is_none()
+            && !config.raw
+            && config.subdomains.is_none()
+            && config.port.is_none()
+            && config.path.is_none()
+            && config.word_filter.is_empty()
+        {
+            SchemaOrSiteConfig::Schema(config.schema.clone())
+        } else {
+            SchemaOrSiteConfig::Config(Box::new(config.clone()))
+        }
+    }
+}
+
+impl Serialize for SerConfig {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("SerConfig", 6)?;
+        state.serialize_field("default_schema", &self.default_schema)?;
+        state.serialize_field("default_version", &self.default_version)?;
+        state.serialize_field("kdf", &self.kdf)?;
+        if is_zero(&self.canonicalization) {
+            state.skip_field("canonicalization")?;
+        } else {
+            state.serialize_field("canonicalization", &self.canonicalization)?;
+        }
+        if is_zero(&self.site_rules) {
+            state.skip_field("site_rules")?;
+        } else {
+            state.serialize_field("site_rules", &self.site_rules)?;
+        }
+        state.serialize_field("aliases", &self.aliases)?;
+
+        let sites_for_serialization: HashMap<String, SchemaOrSiteConfig> = self
+            .sites
+            .iter()
+            .map(|(k, v)| (k.clone(), v.into()))
+            .collect();
+
+        state.serialize_field("sites", &sites_for_serialization)?;
+        state.end()
+    }
+}
+
+fn deserialize_sites<'de, D>(deserializer: D) -> Result<HashMap<String, SiteConfig>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let sites: HashMap<String, SchemaOrSiteConfig> = HashMap::deserialize(deserializer)?;
+    Ok(sites.into_iter().map(|(k, v)| (k, v.into())).collect())
+}
+
+/// Deserializes one path, or a list of them.
+fn deserialize_paths<'de, D>(deserializer: D) -> Result<Vec<Box<Path>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Paths {
+        One(Box<Path>),
+        Many(Vec<Box<Path>>),
+    }
+    Ok(match Paths::deserialize(deserializer)? {
+        Paths::One(path) => vec![path],
+        Paths::Many(paths) => paths,
+    })
+}
+
+/// Deserializes `passwordrules`, failing unless they parse.
+fn deserialize_passwordrules<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let rules = String::deserialize(deserializer)?;
+    rules
+        .parse::<rules::PasswordRules>()
+        .map_err(|err| serde::de::Error::custom(format!("invalid passwordrules: {err:#}")))?;
+    Ok(Some(rules))
+}
+
+/// Returns the built-in rules of the site with the canonical URL `url`, as of version `version`.
+fn known_rules(url: &str, version: u32) -> Option<&'static rules::Known> {
+    let url = ::url::Url::parse(url).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    rules::known(url.host_str()?, version)
+}
+
+fn default_schema() -> String {
+    "[A-Za-z0-9]{16}".into()
+}
+
+/// Returns the canonicalization rules `rules`, with the subdomain policy, port, and path prefix of
+/// `site` if it has them.
+fn site_rules<'a>(rules: &'a url::Rules, site: Option<&SiteConfig>) -> Cow<'a, url::Rules> {
+    let Some(site) = site else {
+        return Cow::Borrowed(rules);
+    };
+    if site.subdomains.is_none() && site.port.is_none() && site.path.is_none() {
+        return Cow::Borrowed(rules);
+    }
+    Cow::Owned(url::Rules {
+        subdomains: site.subdomains.unwrap_or(rules.subdomains),
+        port: site.port.unwrap_or(rules.port),
+        path: site.path.clone(),
+        ..rules.clone()
+    })
+}
+
+/// An edit to a site's entry in the config file: its name, and its settings before and after.
+struct SiteEdit {
+    name: Value,
+    before: Value,
+    after: Mapping,
+}
+
+impl SiteEdit {
+    /// Returns the config file's text `text` with the edit made to only the lines of the settings
+    /// it changed, if the entry is in the long form in a block mapping of `sites` and only scalar
+    /// settings were changed or added.
+    fn apply(&self, text: &str) -> Option<String> {
+        let before = self.before.as_mapping()?;
+        if before.keys().any(|key| !self.after.contains_key(key)) {
+            return None;
+        }
+        let changed: Vec<_> = self
+            .after
+            .iter()
+            .filter(|&(key, value)| before.get(key) != Some(value))
+            .collect();
+        let scalar =
+            |value: &Value| matches!(value, Value::Bool(_) | Value::Number(_) | Value::String(_));
+        if !changed.iter().all(|(_, value)| scalar(value)) {
+            return None;
+        }
+
+        let mut lines: Vec<String> = text.lines().map(Into::into).collect();
+        let top = lines.iter().find_map(|line| indent(line))?;
+        let sites = lines.iter().position(|line| {
+            indent(line) == Some(top) && block_key(line).as_ref() == Some(&"sites".into())
+        })?;
+        let sites = block(&lines, sites, top);
+        let site_indent = sites.clone().find_map(|i| indent(&lines[i]))?;
+        let site = sites.clone().find(|&i| {
+            indent(&lines[i]) == Some(site_indent)
+                && block_key(&lines[i]).as_ref() == Some(&self.name)
+        })?;
+        for (key, value) in changed {
+            let setting = format!(
+                "{}: {}",
+                serde_yaml::to_string(key).ok()?.trim_end(),
+                serde_yaml::to_string(value).ok()?.trim_end()
+            );
+            let children = block(&lines, site, site_indent);
+            let setting_indent = children.clone().find_map(|i| indent(&lines[i]))?;
+            let setting = format!("{}{setting}", " ".repeat(setting_indent));
+            let existing = children.clone().find(|&i| {
+                indent(&lines[i]) == Some(setting_indent)
+                    && line_entry(&lines[i]).is_some_and(|(k, _)| &k == key)
+            });
+            match existing {
+                Some(i) => lines[i] = setting,
+                None => {
+                    let last = children.rev().find(|&i| indent(&lines[i]).is_some())?;
+                    lines.insert(last + 1, setting);
+                }
+            }
+        }
+        let mut edited = lines.join("\n");
+        if text.ends_with('\n') {
+            edited.push('\n');
+        }
+        Some(edited)
+    }
+}
+
+/// Returns the indentation of `line`, or `None` if it is blank or a comment.
+fn indent(line: &str) -> Option<usize> {
+    let content = line.trim_start_matches(' ');
+    (!content.is_empty() && !content.starts_with('#')).then(|| line.len() - content.len())
+}
+
+/// Returns the key and value of `line` if it is one entry of a mapping, like `key: value`.
+fn line_entry(line: &str) -> Option<(Value, Value)> {
+    let entry: Mapping = serde_yaml::from_str(line.trim()).ok()?;
+    let mut entry = entry.into_iter();
+    match (entry.next(), entry.next()) {
+        (Some(entry), None) => Some(entry),
+        _ => None,
+    }
+}
+
+/// Returns the key of `line` if it starts a block mapping or sequence, like `key:`.
+fn block_key(line: &str) -> Option<Value> {
+    line_entry(line)
+        .filter(|(_, value)| value.is_null())
+        .map(|(key, _)| key)
+}
+
+/// Returns the indices of the lines of the block under line `start`, which is indented by
+/// `start_indent`: those up to the next line indented as little, including blanks and comments.
+fn block(lines: &[String], start: usize, start_indent: usize) -> std::ops::Range<usize> {
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| indent(line).is_some_and(|indent| indent <= start_indent))
+        .map_or(lines.len(), |end| start + 1 + end);
+    start + 1..end
+}
+
+/// Returns the config `doc` overlaid on the config `base`, as described in [`Config::parse`].
+fn overlay(base: Value, doc: Value) -> Value {
+    let (Value::Mapping(mut merged), Value::Mapping(doc)) = (base, doc) else {
+        return Value::Null;
+    };
+    for (key, value) in doc {
+        let entries = merged.get_mut(&key).and_then(Value::as_mapping_mut);
+        match (key.as_str(), entries, value) {
+            (Some(setting @ ("aliases" | "sites")), Some(entries), Value::Mapping(overrides)) => {
+                for (name, value) in overrides {
+                    let value = match entries.remove(&name) {
+                        Some(base) if setting == "sites" => overlay_site(base, value),
+                        _ => value,
+                    };
+                    entries.insert(name, value);
+                }
+            }
+            (_, _, value) => {
+                merged.insert(key, value);
+            }
+        }
+    }
+    Value::Mapping(merged)
+}
+
+/// Returns the site config `site` overlaid field by field on the site config `base`, either of
+/// which may be just a schema.
+fn overlay_site(base: Value, site: Value) -> Value {
+    let long = |site: Value| match site {
+        Value::String(schema) => Mapping::from_iter([("schema".into(), Value::String(schema))]),
+        Value::Mapping(site) => site,
+        _ => Mapping::new(),
+    };
+    let mut merged = long(base);
+    merged.extend(long(site));
+    Value::Mapping(merged)
+}
+
+fn hash_text(text: &str) -> String {
+    blake3::hash(text.as_bytes()).to_hex().to_string()
+}
+
+fn is_zero<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn basic() -> Result<()> {
+        let config: SerConfig = serde_yaml::from_str("sites:\n google.com: \"[A-Z]\"")?;
+        let goog = &config.sites["google.com"];
+        assert_eq!("[A-Z]", goog.schema);
+        Ok(())
+    }
+
+    #[test]
+    fn words_paths() -> Result<()> {
+        let config: SerConfig = serde_yaml::from_str("words_path: words.txt\nsites: {}")?;
+        assert_eq!(config.words_path, [Path::new("words.txt").into()]);
+        let config: SerConfig = serde_yaml::from_str("words_path: [/a.txt, /b.txt]\nsites: {}")?;
+        assert_eq!(config.words_path.len(), 2);
+        let config = Config::from_ser_config(config);
+        assert_eq!(config.words_paths()[1], Path::new("/b.txt").into());
+        Ok(())
+    }
+
+    #[test]
+    fn object() -> Result<()> {
+        let config: SerConfig =
+            serde_yaml::from_str("sites:\n abcd:\n  schema: \"A\"\n  increment: 1\n")?;
+        let abcd = &config.sites["abcd"];
+        assert_eq!(1, abcd.increment);
+        assert_eq!("A", abcd.schema);
+        Ok(())
+    }
+
+    #[test]
+    fn bumps() -> Result<()> {
+        let config: SerConfig = serde_yaml::from_str(
+            "sites:\n a.com:\n  increment: 1\n  bumps:\n  - {increment: 0, date: 2025-06-01, \
+             reason: breach}\n",
+        )?;
+        let bump = Bump {
+            increment: 0,
+            date: "2025-06-01".parse()?,
+            reason: Some("breach".into()),
+        };
+        assert_eq!([bump], config.sites["a.com"].bumps[..]);
+        let site = SiteConfig {
+            bumps: config.sites["a.com"].bumps.clone(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            SchemaOrSiteConfig::from(&site),
+            SchemaOrSiteConfig::Config(_)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn find_site_ok() -> Result<()> {
+        let config = Config::from_str(
+            r#"
+            sites:
+                google.com:
+                    schema: A
+                    username: "test@gmail.com"
+                apple.com:
+                    schema: B
+                "http://localhost":
+                    schema: C
+        "#,
+        )?;
+        let tests = [
+            (
+                Some(("https://test%40gmail.com@google.com/", "A")),
+                "google.com",
+            ),
+            (Some(("https://apple.com/", "B")), "https://apple.com"),
+            (Some(("http://localhost/", "C")), "http://localhost/"),
+            (None, "localhost"),
+        ];
+        for (want, input) in tests {
+            let got = config.find_site(input)?;
+            match (want, got) {
+                (Some((want_url, want_schema)), Some((got_url, got_config))) => {
+                    assert_eq!(want_url, &got_url);
+                    assert_eq!(want_schema, got_config.schema);
+                }
+                (None, None) => (),
+                (want, got) => panic!("mismatch: {want:?} / {got:?}"),
+            };
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn registrable_domains() -> Result<()> {
+        let config = Config::from_str(
+            r#"
+            canonicalization:
+                subdomains: registrable
+            sites:
+                accounts.example.co.uk: A
+        "#,
+        )?;
+        let (url, _) = config.find_site("login.example.co.uk")?.unwrap();
+        assert_eq!("https://example.co.uk/", url);
+        assert!(config.find_site("example.com")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn bindings() -> Result<()> {
+        use std::ffi::{CStr, c_char};
+
+        let canonicalize = |site: &CStr, version: Option<u32>| {
+            let url = unsafe {
+                match version {
+                    Some(version) => crate::ffi::onepass_canonicalize_with(
+                        site.as_ptr(),
+                        std::ptr::null(),
+                        version,
+                    ),
+                    None => crate::ffi::onepass_canonicalize(site.as_ptr(), std::ptr::null()),
+                }
+            };
+            let owned = unsafe { CStr::from_ptr(url) }.to_str().map(str::to_owned);
+            unsafe { crate::ffi::onepass_free(url as *mut c_char) };
+            owned
+        };
+        // The bindings' default is that of configs without a version, and passing a config's
+        // version gives its canonical URLs, a new config's included.
+        let example = Config::from_str(&serde_yaml::to_string(&SerConfig::example())?)?;
+        let unversioned = Config::from_str("sites: {}")?;
+        let latest = u32::from(example.canonicalization.version);
+        for site in [c"www.example.com", c"Example.COM.", c"wifi:Home"] {
+            let name = site.to_str()?;
+            assert_eq!(
+                unversioned.canonicalize(name, None, None)?,
+                canonicalize(site, None)?,
+                "{name}"
+            );
+            assert_eq!(
+                example.canonicalize(name, None, None)?,
+                canonicalize(site, Some(latest))?,
+                "{name}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn canonicalization_version() -> Result<()> {
+        let config = Config::from_str("sites:\n a.com: A\n")?;
+        assert_eq!(url::Version::V1, config.canonicalization.version);
+        assert!(config.find_site("www.a.com")?.is_none());
+        let config = Config::from_str("canonicalization:\n version: 2\nsites:\n www.a.com: A\n")?;
+        let (url, _) = config.find_site("a.com.")?.unwrap();
+        assert_eq!("https://a.com/", url);
+        assert!(Config::from_str("canonicalization:\n version: 5\nsites: {}").is_err());
+        let example = serde_yaml::to_string(&SerConfig::example())?;
+        assert_eq!(
+            url::Version::LATEST,
+            Config::from_str(&example)?.canonicalization.version
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn site_rules() -> Result<()> {
+        let chase = rules::known("chase.com", rules::LATEST).unwrap().schema();
+        let sites = "sites:\n chase.com:\n  increment: 1\n example.com:\n  increment: 1\n";
+        let config = Config::from_str(&format!("site_rules: 1\n{sites}"))?;
+        let (_, site) = config.find_site("chase.com")?.unwrap();
+        assert_eq!(chase, site.schema);
+        let (_, site) = config.find_site("example.com")?.unwrap();
+        assert_eq!(config.default_schema, site.schema);
+        assert_eq!(chase, config.default_schema_for("www.chase.com"));
+        assert!(config.known_rules("example.com", rules::LATEST).is_none());
+        let config = Config::from_str(sites)?;
+        let (_, site) = config.find_site("chase.com")?.unwrap();
+        assert_eq!(config.default_schema, site.schema);
+        assert_eq!(
+            config.default_schema,
+            config.default_schema_for("chase.com")
+        );
+        assert!(config.known_rules("chase.com", rules::LATEST).is_some());
+        let example = serde_yaml::to_string(&SerConfig::example())?;
+        assert_eq!(rules::LATEST, Config::from_str(&example)?.site_rules);
+        Ok(())
+    }
+
+    #[test]
+    fn policy() -> Result<()> {
+        let config = Config::from_str("policy: paranoid\nsites:\n a.com: {increment: 1}\n")?;
+        assert_eq!("[!-~]{32}", config.default_schema);
+        let (_, site) = config.find_site("a.com")?.unwrap();
+        assert_eq!(config.default_schema, site.schema);
+        assert_eq!(256 * 1024, config.kdf.argon2.as_ref().unwrap().memory);
+        assert_eq!(
+            (Some(180), Some(128)),
+            (config.rotate_after, config.min_bits)
+        );
+        let config = Config::from_str(
+            "policy: pci\ndefault_schema: '[a-z]{20}'\nrotate_after: 30\nsites: {}\n",
+        )?;
+        assert_eq!("[a-z]{20}", config.default_schema);
+        assert_eq!((Some(30), Some(80)), (config.rotate_after, config.min_bits));
+        let config = Config::from_str("policy: nist-memorized\nsites:\n a.com: login\n")?;
+        let (_, site) = config.find_site("a.com")?.unwrap();
+        assert_eq!(None, config.rotate_after(site));
+        let config = Config::from_str("sites:\n a.com: login\n")?;
+        let (_, site) = config.find_site("a.com")?.unwrap();
+        assert_eq!(Some(DEFAULT_ROTATE_AFTER), config.rotate_after(site));
+        assert!(Config::from_str("policy: lax\nsites: {}\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn passwordrules() -> Result<()> {
+        let config = Config::from_str(
+            r#"
+            site_rules: 1
+            sites:
+                chase.com:
+                    passwordrules: "maxlength: 8; required: digit; allowed: lower"
+                example.com:
+                    schema: A
+                    passwordrules: "minlength: 12"
+        "#,
+        )?;
+        let (_, site) = config.find_site("chase.com")?.unwrap();
+        assert_eq!("[0-9][0-9a-z]{7}", site.schema);
+        let (_, site) = config.find_site("example.com")?.unwrap();
+        assert_eq!("A", site.schema);
+        assert!(Config::from_str("sites:\n a.com:\n  passwordrules: \"minlength: x\"\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn shadowed_sites() -> Result<()> {
+        let config = Config::from_str(
+            r#"
+            sites:
+                https://a.com/: A
+                a.com:
+                    schema: B
+                    rotated: 2025-06-01
+                b.com: C
+        "#,
+        )?;
+        let (_, site) = config.find_site("a.com")?.unwrap();
+        assert_eq!(
+            ("B", Some("2025-06-01".parse()?)),
+            (site.schema.as_str(), site.rotated)
+        );
+        assert_eq!(
+            [("https://a.com/".to_owned(), "https://a.com/".to_owned())],
+            config.shadowed.as_slice()
+        );
+        assert!(Config::from_str("sites:\n a.com:\n  rotated: June\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn site_subdomains() -> Result<()> {
+        let config = Config::from_str(
+            r#"
+            canonicalization:
+                subdomains: registrable
+                map:
+                    "*.corp.example.com": corp.example.com
+                equivalent:
+                    - [example.com, example.de]
+            sites:
+                example.com: A
+                sso.example.com:
+                    schema: B
+                    subdomains: keep
+                mail.corp.example.com:
+                    schema: C
+                    subdomains: map
+                tenant.example.co.uk:
+                    schema: D
+                    username: me
+        "#,
+        )?;
+        let tests = [
+            ("https://example.com/", "A", "www.example.com"),
+            ("https://example.com/", "A", "login.example.de"),
+            ("https://sso.example.com/", "B", "sso.example.com"),
+            ("https://corp.example.com/", "C", "wiki.corp.example.com"),
+            ("https://me@example.co.uk/", "D", "example.co.uk"),
+        ];
+        for (want_url, want_schema, input) in tests {
+            let (url, site) = config.find_site(input)?.unwrap();
+            assert_eq!(
+                (want_url, want_schema),
+                (url.as_str(), site.schema.as_str())
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn site_paths() -> Result<()> {
+        let config = Config::from_str(
+            r#"
+            canonicalization: {version: 4}
+            sites:
+                host:8443/gitea:
+                    schema: A
+                    path: /gitea
+                host:8443/grafana:
+                    schema: B
+                    path: /grafana/
+                host:
+                    schema: C
+                    port: false
+                    path: /
+        "#,
+        )?;
+        let tests = [
+            (
+                "https://host:8443/gitea",
+                "A",
+                "https://host:8443/gitea/user/login",
+            ),
+            (
+                "https://host:8443/grafana",
+                "B",
+                "host:8443/grafana/d/1?x=y",
+            ),
+            ("https://host/", "C", "host:8443/prometheus"),
+        ];
+        for (want_url, want_schema, input) in tests {
+            let (url, site) = config.find_site(input)?.unwrap();
+            assert_eq!(
+                (want_url, want_schema),
+                (url.as_str(), site.schema.as_str())
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn raw_sites() -> Result<()> {
+        let config = Config::from_str(
+            r#"
+            sites:
+                laptop-luks:
+                    schema: A
+                    raw: true
+                office safe:
+                    schema: B
+                    raw: true
+                laptop-web: C
+        "#,
+        )?;
+        let (url, site) = config.find_site("laptop-luks")?.unwrap();
+        assert_eq!(("laptop-luks", "A"), (url.as_str(), site.schema.as_str()));
+        let (url, _) = config.find_site("office safe")?.unwrap();
+        assert_eq!("office safe", url);
+        assert!(config.find_site("https://laptop-luks/")?.is_none());
+        let (url, _) = config.find_site("laptop-web")?.unwrap();
+        assert_eq!("https://laptop-web/", url);
+        assert!(config.find_raw_site("laptop-web").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn kdf_algorithm() -> Result<()> {
+        use crate::derive::{KdfAlgorithm, ScryptConfig};
+
+        let config = Config::from_str("sites: {}")?;
+        assert_eq!(KdfAlgorithm::Argon2id, config.kdf.algorithm);
+        let config = Config::from_str("kdf:\n algorithm: argon2d\nsites: {}")?;
+        assert_eq!(KdfAlgorithm::Argon2d, config.kdf.algorithm);
+        let config = Config::from_str(
+            r#"
+            kdf:
+                algorithm: scrypt
+                scrypt:
+                    log_n: 17
+            sites: {}
+        "#,
+        )?;
+        assert_eq!(KdfAlgorithm::Scrypt, config.kdf.algorithm);
+        assert_eq!(
+            Some(ScryptConfig {
+                log_n: 17,
+                r: 8,
+                p: 1
+            }),
+            config.kdf.scrypt
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn derivation_version() -> Result<()> {
+        let config = Config::from_str(
+            r#"
+            sites:
+                a.com: A
+                b.com:
+                    schema: B
+                    version: 2
+        "#,
+        )?;
+        let (_, a) = config.find_site("a.com")?.unwrap();
+        let (_, b) = config.find_site("b.com")?.unwrap();
+        assert_eq!(DerivationVersion::V1, config.version(Some(a)));
+        assert_eq!(DerivationVersion::V2, config.version(Some(b)));
+        assert_eq!(DerivationVersion::V1, config.version(None));
+        let example = serde_yaml::to_string(&SerConfig::example())?;
+        assert_eq!(
+            DerivationVersion::LATEST,
+            Config::from_str(&example)?.version(None)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fields() -> Result<()> {
+        let config = Config::from_str(
+            r#"
+            aliases:
+                pin: "[0-9]{4}"
+            sites:
+                bank.com:
+                    schema: A
+                    fields:
+                        pin: pin
+                        answer: "[a-z]{8}"
+        "#,
+        )?;
+        let (_, bank) = config.find_site("bank.com")?.unwrap();
+        assert_eq!("[0-9]{4}", bank.fields["pin"]);
+        assert_eq!("[a-z]{8}", bank.fields["answer"]);
+        Ok(())
+    }
+
+    #[test]
+    fn totp_secret() -> Result<()> {
+        let config = Config::from_str(
+            r#"
+            sites:
+                a.com: A
+                b.com:
+                    schema: A
+                    totp_secret: JBSW Y3DP
+        "#,
+        )?;
+        assert_eq!(None, config.find_site("a.com")?.unwrap().1.totp_secret);
+        assert_eq!(
+            Some("JBSW Y3DP"),
+            config.find_site("b.com")?.unwrap().1.totp_secret.as_deref()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn compat() -> Result<()> {
+        let config = Config::from_str(
+            r#"
+            default_schema: "[a-z]{8}"
+            sites:
+                example.org:
+                    username: contact@example.org
+                    compat:
+                        algorithm: lesspass
+                        length: 20
+        "#,
+        )?;
+        let (_, site) = config.find_site("example.org")?.unwrap();
+        assert_eq!("[a-z]{8}", site.schema);
+        let compat = site.compat.as_ref().unwrap();
+        assert_eq!(crate::compat::Algorithm::Lesspass, compat.algorithm());
+        Ok(())
+    }
+
+    #[test]
+    fn clipboard() -> Result<()> {
+        let config = Config::from_str("clipboard: wl-copy\nsites: {}")?;
+        assert_eq!(Some(ClipboardBackend::WlCopy), config.clipboard);
+        Ok(())
+    }
+
+    #[test]
+    fn update_site() -> Result<()> {
+        let dir = env::temp_dir().join(format!("onepass-update-test-{}", std::process::id()));
+        let path = dir.join("config.yaml");
+        create_dir_all(&dir)?;
+        write(
+            &path,
+            r#"
+            sites:
+                a.com: A
+                b.com:
+                    schema: B
+            "#,
+        )?;
+        let config = Config::from_file(Some(&path))?;
+        config.update_site("https://a.com/", |site| {
+            site.insert("hotp_counter".into(), 3.into());
+        })?;
+        config.update_site("b.com", |site| {
+            site.insert("hotp_counter".into(), 1.into());
+        })?;
+        assert!(config.update_site("c.com", |_| ()).is_err());
+        let config = Config::from_file(Some(&path))?;
+        assert_eq!(2, backup::list(&path)?.len());
+        // Writing keeps the file's permissions, as it may hold secrets.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+            config.update_site("a.com", |_| ())?;
+            assert_eq!(0o600, fs::metadata(&path)?.permissions().mode() & 0o777);
+        }
+
+        // Only the lines of changed settings are rewritten, where they can be.
+        let text = "# mine\n\
+                    sites:\n  \
+                    b.com: # work\n    \
+                    schema: B\n\n    \
+                    hotp_counter: 1  # old\n  \
+                    c.com: C\n";
+        write(&path, text)?;
+        let edited = Config::from_file(Some(&path))?;
+        edited.update_site("b.com", |site| {
+            site.insert("hotp_counter".into(), 2.into());
+            site.insert("username".into(), "me".into());
+        })?;
+        let b = read_to_string(&path)?;
+        edited.update_site("c.com", |site| {
+            site.insert("hotp_counter".into(), 1.into());
+        })?;
+        let c = read_to_string(&path)?;
+
+        std::fs::remove_dir_all(&dir)?;
+        assert_eq!(
+            text.replace(
+                "hotp_counter: 1  # old",
+                "hotp_counter: 2\n    username: me"
+            ),
+            b
+        );
+        assert!(!c.contains('#'));
+        let (_, a) = config.find_site("a.com")?.unwrap();
+        assert_eq!(("A", 3), (a.schema.as_str(), a.hotp_counter));
+        let (_, b) = config.find_site("b.com")?.unwrap();
+        assert_eq!(("B", 1), (b.schema.as_str(), b.hotp_counter));
+        Ok(())
+    }
+
+    #[test]
+    fn cached() -> Result<()> {
+        let dir = env::temp_dir().join(format!("onepass-cache-test-{}", std::process::id()));
+        let path = dir.join("config.yaml");
+        create_dir_all(&dir)?;
+        write(
+            &path,
+            r#"
+            aliases: {pin: "[0-9]{4}"}
+            canonicalization: {version: 3, subdomains: registrable}
+            sites:
+                www.a.co.uk: pin
+                b.com:
+                    username: me
+                    fields: {q: pin}
+            "#,
+        )?;
+        let parsed = Config::from_file_cached(Some(&path), &dir)?;
+        assert_eq!(2, fs::read_dir(&dir)?.count());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(0o700, fs::metadata(&dir)?.permissions().mode() & 0o777);
+            for entry in fs::read_dir(&dir)? {
+                let entry = entry?;
+                if entry.file_name().to_string_lossy().starts_with("config-") {
+                    assert_eq!(0o600, entry.metadata()?.permissions().mode() & 0o777);
+                }
+            }
+        }
+        let cached = Config::from_file_cached(Some(&path), &dir)?;
+        assert_eq!(
+            serde_json::to_value(&parsed)?,
+            serde_json::to_value(&cached)?
+        );
+        let (url, site) = cached.find_site("login.a.co.uk")?.unwrap();
+        assert_eq!(
+            ("https://a.co.uk/", "[0-9]{4}"),
+            (url.as_str(), site.schema.as_str())
+        );
+        assert_eq!(Some(dir.as_path()), cached.cache_dir());
+
+        write(&path, "sites: {c.com: pin}")?;
+        let changed = Config::from_file_cached(Some(&path), &dir)?;
+        std::fs::remove_dir_all(&dir)?;
+        assert!(changed.find_site("a.co.uk")?.is_none());
+        assert!(changed.find_site("c.com")?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn base_config() -> Result<()> {
+        let dir = env::temp_dir().join(format!("onepass-base-test-{}", std::process::id()));
+        let path = dir.join("config.yaml");
+        create_dir_all(&dir)?;
+        write(
+            dir.join("team.yaml"),
+            r#"
+            default_schema: "[a-z]{20}"
+            rotate_after: 90
+            aliases: {pin: "[0-9]{4}", corp: "[A-Z]{10}"}
+            sites:
+                intranet.example.com: corp
+                wiki.example.com:
+                    schema: corp
+                    username: team
+            "#,
+        )?;
+        write(
+            &path,
+            r#"
+            base: team.yaml
+            rotate_after: 0
+            aliases: {pin: "[0-9]{6}"}
+            sites:
+                wiki.example.com: {increment: 2}
+                mine.com: pin
+            "#,
+        )?;
+        let config = Config::from_file_cached(Some(&path), &dir)?;
+        assert_eq!(
+            ("[a-z]{20}", Some(0)),
+            (config.default_schema.as_str(), config.rotate_after)
+        );
+        let (_, mine) = config.find_site("mine.com")?.unwrap();
+        assert_eq!("[0-9]{6}", mine.schema);
+        let (_, wiki) = config.find_site("wiki.example.com")?.unwrap();
+        assert_eq!(
+            ("[A-Z]{10}", Some("team"), 2),
+            (
+                wiki.schema.as_str(),
+                wiki.username.as_deref(),
+                wiki.increment
+            )
+        );
+
+        config.update_site("intranet.example.com", |site| {
+            site.insert("increment".into(), 1.into());
+        })?;
+        let doc: Value = serde_yaml::from_str(&read_to_string(&path)?)?;
+        assert_eq!(
+            Some(1),
+            doc["sites"]["intranet.example.com"]["increment"].as_u64()
+        );
+        assert!(doc["sites"]["intranet.example.com"].get("schema").is_none());
+
+        // Cached, until the base config changes.
+        Config::from_file_cached(Some(&path), &dir)?;
+        write(dir.join("team.yaml"), "sites: {intranet.example.com: pin}")?;
+        let config = Config::from_file_cached(Some(&path), &dir)?;
+        let (_, intranet) = config.find_site("intranet.example.com")?.unwrap();
+        assert_eq!(
+            ("[0-9]{6}", 1),
+            (intranet.schema.as_str(), intranet.increment)
+        );
+        assert!(config.find_site("wiki.example.com")?.is_some());
+
+        // Paths in the base config are relative to its own directory.
+        create_dir_all(dir.join("team"))?;
+        write(
+            dir.join("team/team.yaml"),
+            "words_path: words.txt\nsites: {}",
+        )?;
+        write(&path, "base: team/team.yaml\nsites: {}")?;
+        let config = Config::from_file(Some(&path))?;
+        assert_eq!(config.words_paths(), [dir.join("team/words.txt").into()]);
+
+        // The user's own settings can't come from the base config.
+        write(
+            dir.join("team/team.yaml"),
+            "sync: {remote: https://example.com/configs.git}\nsites: {}",
+        )?;
+        let sync = Config::from_file(Some(&path));
+
+        write(dir.join("team.yaml"), "base: config.yaml\nsites: {}")?;
+        write(&path, "base: team.yaml\nsites: {}")?;
+        let nested = Config::from_file(Some(&path));
+        std::fs::remove_dir_all(&dir)?;
+        assert!(sync.is_err());
+        assert!(nested.is_err());
+        Ok(())
+    }
+
+    // TODO: temp config file
+}
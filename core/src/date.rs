@@ -0,0 +1,157 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Calendar dates, like `2025-06-01`, for when sites' passwords were rotated.
+
+use std::{
+    fmt,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// A day in the proleptic Gregorian calendar, in UTC, written `YYYY-MM-DD`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Date {
+    /// Days since 1970-01-01
+    days: i64,
+}
+
+impl Date {
+    /// Returns today's date in UTC.
+    pub fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        Date::from_unix(secs)
+    }
+
+    /// Returns the date in UTC of `secs` seconds after the Unix epoch.
+    pub fn from_unix(secs: u64) -> Self {
+        Date {
+            days: (secs / 86400) as i64,
+        }
+    }
+
+    /// Returns the number of days from `self` to `later`, negative if `later` is earlier.
+    pub fn days_until(self, later: Date) -> i64 {
+        later.days - self.days
+    }
+
+    /// Returns the date `days` days after `self`.
+    pub fn add_days(self, days: i64) -> Self {
+        Date {
+            days: self.days + days,
+        }
+    }
+
+    /// Returns the year, month, and day, by Howard Hinnant's `civil_from_days`.
+    fn civil(self) -> (i64, u32, u32) {
+        let z = self.days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        (year, month, day)
+    }
+
+    /// Returns the date of a year, month, and day, by Howard Hinnant's `days_from_civil`.
+    fn from_civil(year: i64, month: u32, day: u32) -> Self {
+        let year = year - i64::from(month <= 2);
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let mp = i64::from((month + 9) % 12);
+        let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        Date {
+            days: era * 146097 + doe - 719468,
+        }
+    }
+}
+
+impl FromStr for Date {
+    type Err = anyhow::Error;
+
+    /// Parses a date written `YYYY-MM-DD`.
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || format!("invalid date {s:?}, not like 2025-06-01");
+        let mut parts = s.splitn(3, '-');
+        let mut part = |len: usize| -> Result<u32> {
+            let part = parts.next().filter(|part| part.len() == len);
+            let part = part.filter(|part| part.bytes().all(|b| b.is_ascii_digit()));
+            part.and_then(|part| part.parse().ok())
+                .with_context(invalid)
+        };
+        let (year, month, day) = (part(4)?, part(2)?, part(2)?);
+        let date = Date::from_civil(year.into(), month, day);
+        // Out-of-range months and days would wrap around into other dates.
+        anyhow::ensure!(date.civil() == (year.into(), month, day), invalid());
+        Ok(date)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.civil();
+        write!(f, "{year:04}-{month:02}-{day:02}")
+    }
+}
+
+impl Serialize for Date {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Date {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates() -> Result<()> {
+        let epoch: Date = "1970-01-01".parse()?;
+        assert_eq!(Date { days: 0 }, epoch);
+        let leap: Date = "2024-02-29".parse()?;
+        assert_eq!(19782, epoch.days_until(leap));
+        assert_eq!("2024-03-01", leap.add_days(1).to_string());
+        assert_eq!("1969-12-31", epoch.add_days(-1).to_string());
+        assert_eq!(366, leap.days_until("2025-03-01".parse()?));
+        for bad in [
+            "2023-02-29",
+            "2025-13-01",
+            "2025-6-1",
+            "25-06-01",
+            "2025-06-01x",
+            "",
+        ] {
+            assert!(bad.parse::<Date>().is_err(), "{bad}");
+        }
+        assert!(Date::today() > leap);
+        Ok(())
+    }
+}
@@ -0,0 +1,661 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The derivation pipeline that turns a master password and a site into a password: building the
+//! site's salt, running the KDF, and seeding the RNG that picks the password from its schema.
+//!
+//! This is everything needed to reproduce a password except for second factors, which the CLI
+//! mixes into the key material between the KDF and the seed.
+//!
+//! ```
+//! use onepass::{
+//!     derive::{DerivationVersion, KdfConfig, Site, site_password},
+//!     randexp::Words,
+//!     wordlist::Wordlist,
+//! };
+//!
+//! let site = Site {
+//!     url: "https://a.com/",
+//!     schema: "[a-z]{10}",
+//!     ..Site::new(DerivationVersion::V1)
+//! };
+//! let words = Words(Wordlist::EffLarge.words());
+//! let password = site_password("hunter2", &site, &KdfConfig::default(), &words)?;
+//! assert_eq!("wwjtzfjytg", *password);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use clap::ValueEnum;
+use crypto_bigint::{NonZero, U256};
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::randexp::{Enumerable, Expr, Quantifiable, Words};
+
+/// The key derivation function used to turn the master password and salt into key material.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum KdfAlgorithm {
+    /// Hybrid of Argon2i and Argon2d (recommended)
+    #[default]
+    Argon2id,
+    /// Data-independent memory access; more resistant to side channels
+    Argon2i,
+    /// Data-dependent memory access; resists GPU cracking best, but open to side channels
+    Argon2d,
+    /// scrypt, for compatibility with other tools' derivation chains
+    Scrypt,
+    /// Balloon hashing over SHA-256
+    Balloon,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct KdfConfig {
+    #[serde(default)]
+    pub algorithm: KdfAlgorithm,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub argon2: Option<Argon2Config>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scrypt: Option<ScryptConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balloon: Option<BalloonConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Argon2Config {
+    /// Memory cost in KiB
+    #[serde(default = "default_argon2_memory")]
+    pub memory: u32,
+    #[serde(default = "default_argon2_iterations")]
+    pub iterations: u32,
+    #[serde(default = "default_argon2_parallelism")]
+    pub parallelism: u32,
+}
+
+impl Default for Argon2Config {
+    fn default() -> Self {
+        Argon2Config {
+            memory: default_argon2_memory(),
+            iterations: default_argon2_iterations(),
+            parallelism: default_argon2_parallelism(),
+        }
+    }
+}
+
+fn default_argon2_memory() -> u32 {
+    32 * 1024
+}
+
+fn default_argon2_iterations() -> u32 {
+    3
+}
+
+fn default_argon2_parallelism() -> u32 {
+    1
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ScryptConfig {
+    #[serde(default = "default_scrypt_log_n")]
+    pub log_n: u8,
+    #[serde(default = "default_scrypt_r")]
+    pub r: u32,
+    #[serde(default = "default_scrypt_p")]
+    pub p: u32,
+}
+
+impl Default for ScryptConfig {
+    fn default() -> Self {
+        ScryptConfig {
+            log_n: default_scrypt_log_n(),
+            r: default_scrypt_r(),
+            p: default_scrypt_p(),
+        }
+    }
+}
+
+fn default_scrypt_log_n() -> u8 {
+    15
+}
+
+fn default_scrypt_r() -> u32 {
+    8
+}
+
+fn default_scrypt_p() -> u32 {
+    1
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct BalloonConfig {
+    /// Space cost, in 32-byte blocks
+    #[serde(default = "default_balloon_s_cost")]
+    pub s_cost: u32,
+    #[serde(default = "default_balloon_t_cost")]
+    pub t_cost: u32,
+    #[serde(default = "default_balloon_p_cost")]
+    pub p_cost: u32,
+}
+
+impl Default for BalloonConfig {
+    fn default() -> Self {
+        BalloonConfig {
+            s_cost: default_balloon_s_cost(),
+            t_cost: default_balloon_t_cost(),
+            p_cost: default_balloon_p_cost(),
+        }
+    }
+}
+
+fn default_balloon_s_cost() -> u32 {
+    64 * 1024
+}
+
+fn default_balloon_t_cost() -> u32 {
+    3
+}
+
+fn default_balloon_p_cost() -> u32 {
+    1
+}
+
+impl KdfConfig {
+    /// Returns about how many bytes of memory one derivation takes, for bounding how many run at
+    /// once.
+    pub fn memory(&self) -> u64 {
+        match self.algorithm {
+            KdfAlgorithm::Scrypt => {
+                let ScryptConfig { log_n, r, .. } = self.scrypt.clone().unwrap_or_default();
+                (128 * u64::from(r)) << log_n
+            }
+            KdfAlgorithm::Balloon => {
+                let BalloonConfig { s_cost, p_cost, .. } = self.balloon.clone().unwrap_or_default();
+                32 * u64::from(s_cost) * u64::from(p_cost)
+            }
+            _ => 1024 * u64::from(self.argon2.clone().unwrap_or_default().memory),
+        }
+    }
+
+    /// Fills `out` with the output of the KDF for `password` and `salt`.
+    pub fn derive(&self, password: &[u8], salt: &[u8], out: &mut [u8]) -> Result<()> {
+        let algorithm = match self.algorithm {
+            KdfAlgorithm::Argon2id => Algorithm::Argon2id,
+            KdfAlgorithm::Argon2i => Algorithm::Argon2i,
+            KdfAlgorithm::Argon2d => Algorithm::Argon2d,
+            KdfAlgorithm::Scrypt => {
+                let ScryptConfig { log_n, r, p } = self.scrypt.clone().unwrap_or_default();
+                let params = scrypt::Params::new(log_n, r, p, out.len())
+                    .map_err(|e| anyhow::anyhow!("scrypt::Params::new: {e}"))?;
+                return scrypt::scrypt(password, salt, &params, out)
+                    .map_err(|e| anyhow::anyhow!("scrypt failed: {e}"));
+            }
+            KdfAlgorithm::Balloon => {
+                let BalloonConfig {
+                    s_cost,
+                    t_cost,
+                    p_cost,
+                } = self.balloon.clone().unwrap_or_default();
+                let params = balloon_hash::Params::new(s_cost, t_cost, p_cost)
+                    .map_err(|e| anyhow::anyhow!("balloon_hash::Params::new: {e}"))?;
+                return balloon_hash::Balloon::<sha2::Sha256>::new(
+                    balloon_hash::Algorithm::Balloon,
+                    params,
+                    None,
+                )
+                .hash_into(password, salt, out)
+                .map_err(|e| anyhow::anyhow!("balloon hashing failed: {e}"));
+            }
+        };
+        let Argon2Config {
+            memory,
+            iterations,
+            parallelism,
+        } = self.argon2.clone().unwrap_or_default();
+        let params = Params::new(memory, iterations, parallelism, None)
+            .map_err(|e| anyhow::anyhow!("Params::new: {e}"))?;
+        Argon2::new(algorithm, Version::V0x13, params)
+            .hash_password_into(password, salt, out)
+            .map_err(|e| anyhow::anyhow!("argon2 failed: {e}"))
+    }
+}
+
+impl fmt::Display for KdfAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().ok_or(fmt::Error)?;
+        f.write_str(value.get_name())
+    }
+}
+
+impl fmt::Display for KdfConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.algorithm {
+            KdfAlgorithm::Scrypt => {
+                let ScryptConfig { log_n, r, p } = self.scrypt.clone().unwrap_or_default();
+                write!(f, "{0} (log_n={log_n}, r={r}, p={p})", self.algorithm)
+            }
+            KdfAlgorithm::Balloon => {
+                let BalloonConfig {
+                    s_cost,
+                    t_cost,
+                    p_cost,
+                } = self.balloon.clone().unwrap_or_default();
+                write!(
+                    f,
+                    "{0} (s_cost={s_cost}, t_cost={t_cost}, p_cost={p_cost})",
+                    self.algorithm
+                )
+            }
+            _ => {
+                let Argon2Config {
+                    memory,
+                    iterations,
+                    parallelism,
+                } = self.argon2.clone().unwrap_or_default();
+                write!(
+                    f,
+                    "{0} (m={memory} KiB, t={iterations}, p={parallelism})",
+                    self.algorithm
+                )
+            }
+        }
+    }
+}
+
+/// The version of the pipeline that turns a site into a salt, and the KDF output into a seed.
+///
+/// Passwords derived with a given version never change; improvements to the pipeline get a new
+/// version, which sites opt into in the config.
+///
+/// - **v1**: the salt is `"{increment},{url}"`, and the seed is the key material itself.
+/// - **v2**: the salt is a length-prefixed encoding of a domain tag, the url, and the increment,
+///   and the seed is derived from the key material with BLAKE3 under a per-purpose label.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(try_from = "u32", into = "u32")]
+pub enum DerivationVersion {
+    #[default]
+    V1,
+    V2,
+}
+
+impl DerivationVersion {
+    pub const LATEST: DerivationVersion = DerivationVersion::V2;
+
+    /// Builds the salt for a site. With a `template`, the rendered template takes the place of the
+    /// built-in fields: it is the whole salt in v1, and the only field after the domain tag in v2.
+    pub fn salt(self, template: Option<&str>, inputs: &SaltInputs) -> Result<Vec<u8>> {
+        let SaltInputs { increment, url, .. } = *inputs;
+        Ok(match (self, template) {
+            (DerivationVersion::V1, None) => format!("{increment},{url}").into_bytes(),
+            (DerivationVersion::V1, Some(template)) => inputs.render(template)?.into_bytes(),
+            (DerivationVersion::V2, None) => {
+                length_prefixed(&[b"onepass-v2", url.as_bytes(), &increment.to_le_bytes()])
+            }
+            (DerivationVersion::V2, Some(template)) => {
+                length_prefixed(&[b"onepass-v2-template", inputs.render(template)?.as_bytes()])
+            }
+        })
+    }
+}
+
+/// Returns the salt for a derived key of type `kind` (e.g. `ssh-key`), named `label`.
+///
+/// Derived keys are not sites, so they always use this v2-style salt rather than the site's
+/// derivation version.
+pub fn key_salt(kind: &str, label: &str, increment: u32) -> Vec<u8> {
+    length_prefixed(&[
+        b"onepass-v2-key",
+        kind.as_bytes(),
+        label.as_bytes(),
+        &increment.to_le_bytes(),
+    ])
+}
+
+fn length_prefixed(fields: &[&[u8]]) -> Vec<u8> {
+    let mut res = Vec::new();
+    for field in fields {
+        res.extend_from_slice(&(field.len() as u32).to_le_bytes());
+        res.extend_from_slice(field);
+    }
+    res
+}
+
+/// The values available to salt templates, as `{increment}`, `{url}`, and `{username}`.
+pub struct SaltInputs<'a> {
+    pub increment: u32,
+    pub url: &'a str,
+    pub username: Option<&'a str>,
+}
+
+impl SaltInputs<'_> {
+    /// Renders `template`, replacing each `{name}` with the named value and `{{` and `}}` with
+    /// literal braces.
+    pub fn render(&self, template: &str) -> Result<String> {
+        let mut res = String::new();
+        let mut rest = template;
+        while let Some(i) = rest.find(['{', '}']) {
+            res.push_str(&rest[..i]);
+            rest = &rest[i..];
+            if let Some(tail) = rest.strip_prefix("{{") {
+                res.push('{');
+                rest = tail;
+            } else if let Some(tail) = rest.strip_prefix("}}") {
+                res.push('}');
+                rest = tail;
+            } else if let Some(tail) = rest.strip_prefix('{') {
+                let (name, tail) = tail
+                    .split_once('}')
+                    .context("unterminated placeholder in salt template")?;
+                match name {
+                    "increment" => res.push_str(&self.increment.to_string()),
+                    "url" => res.push_str(self.url),
+                    "username" => res.push_str(self.username.unwrap_or_default()),
+                    _ => anyhow::bail!("unknown salt template placeholder {{{name}}}"),
+                }
+                rest = tail;
+            } else {
+                anyhow::bail!("unmatched }} in salt template");
+            }
+        }
+        res.push_str(rest);
+        Ok(res)
+    }
+}
+
+impl TryFrom<u32> for DerivationVersion {
+    type Error = String;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(DerivationVersion::V1),
+            2 => Ok(DerivationVersion::V2),
+            _ => Err(format!("unknown derivation version {value}")),
+        }
+    }
+}
+
+impl From<DerivationVersion> for u32 {
+    fn from(value: DerivationVersion) -> Self {
+        match value {
+            DerivationVersion::V1 => 1,
+            DerivationVersion::V2 => 2,
+        }
+    }
+}
+
+impl FromStr for DerivationVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.strip_prefix('v').unwrap_or(s);
+        s.parse::<u32>().map_err(|e| e.to_string())?.try_into()
+    }
+}
+
+impl fmt::Display for DerivationVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", u32::from(*self))
+    }
+}
+
+const LABELED_OUTPUT_CONTEXT: &str = "onepass 2025-06 labeled output";
+
+/// Writes the seed of a site's password to `seed`, derived from its key material as described by
+/// its [`DerivationVersion`].
+pub fn password_seed(version: DerivationVersion, key_material: &[u8; 32], seed: &mut [u8; 32]) {
+    match version {
+        DerivationVersion::V1 => seed.copy_from_slice(key_material),
+        DerivationVersion::V2 => {
+            *seed = blake3::derive_key("onepass v2 password", key_material);
+        }
+    }
+}
+
+/// Writes the seed of the output named `label`, e.g. `field:pin`, to `seed`.
+pub fn labeled_seed(key_material: &[u8; 32], label: &str, seed: &mut [u8; 32]) {
+    let mut hasher = blake3::Hasher::new_derive_key(LABELED_OUTPUT_CONTEXT);
+    hasher.update(key_material);
+    hasher.update(label.as_bytes());
+    let mut reader = hasher.finalize_xof();
+    reader.fill(seed);
+    reader.zeroize();
+    hasher.zeroize();
+}
+
+/// Everything about a site that goes into its password, besides the master password and KDF.
+#[derive(Clone, Copy, Debug)]
+pub struct Site<'a> {
+    /// The canonical URL of the site, including the username if any, e.g.
+    /// `https://me@example.com/`
+    pub url: &'a str,
+    pub username: Option<&'a str>,
+    pub increment: u32,
+    pub version: DerivationVersion,
+    pub salt_template: Option<&'a str>,
+    pub schema: &'a str,
+    /// The field to derive instead of the password, e.g. `pin`
+    pub field: Option<&'a str>,
+}
+
+impl Site<'_> {
+    /// A site with no URL, username, increment, template, schema, or field.
+    pub const fn new(version: DerivationVersion) -> Self {
+        Site {
+            url: "",
+            username: None,
+            increment: 0,
+            version,
+            salt_template: None,
+            schema: "",
+            field: None,
+        }
+    }
+}
+
+/// Derives the password of `site`, or its field if it names one, as the CLI would without second
+/// factors.
+pub fn site_password(
+    password: &str,
+    site: &Site,
+    kdf: &KdfConfig,
+    words: &Words,
+) -> Result<Zeroizing<String>> {
+    let salt = site.version.salt(
+        site.salt_template,
+        &SaltInputs {
+            increment: site.increment,
+            url: site.url,
+            username: site.username,
+        },
+    )?;
+    let expr = Expr::parse(site.schema).context("invalid schema")?;
+    // With no words, e.g. from an empty words file, a schema with words matches no passwords.
+    let size = Option::from(NonZero::new(words.size(&expr))).context("schema matches nothing")?;
+    let mut key_material = Zeroizing::new([0u8; 32]);
+    kdf.derive(password.as_bytes(), &salt, &mut *key_material)?;
+    let mut seed = Zeroizing::new([0u8; 32]);
+    match site.field {
+        Some(field) => labeled_seed(&key_material, &format!("field:{field}"), &mut seed),
+        None => password_seed(site.version, &key_material, &mut seed),
+    }
+    let mut rng = ChaCha20Rng::from_seed(*seed);
+    let index = random_below(&mut rng, &size);
+    words.gen_at(&expr, index)
+}
+
+/// Returns a uniformly random number below `bound`, by rejection sampling on 64-bit words.
+///
+/// This draws from `rng` exactly as crypto-bigint's `random_mod` does on 64-bit targets, which
+/// passwords have always been derived with, but also on 32-bit ones like wasm32, where
+/// `random_mod` draws 32-bit words and so picks different passwords.
+pub fn random_below(rng: &mut (impl RngCore + ?Sized), bound: &NonZero<U256>) -> U256 {
+    let mut bound_words = [0u64; 4];
+    for (word, bytes) in bound_words
+        .iter_mut()
+        .zip(bound.to_le_bytes().chunks_exact(8))
+    {
+        *word = u64::from_le_bytes(bytes.try_into().unwrap());
+    }
+    let n_words = bound.bits_vartime().div_ceil(64) as usize;
+    let hi_bound = bound_words[n_words - 1];
+    let mask = !0 >> hi_bound.leading_zeros();
+    let mut hi_word = rng.next_u64() & mask;
+    loop {
+        while hi_word > hi_bound {
+            hi_word = rng.next_u64() & mask;
+        }
+        let mut bytes = Zeroizing::new([0u8; 32]);
+        bytes[(n_words - 1) * 8..n_words * 8].copy_from_slice(&hi_word.to_le_bytes());
+        for i in 0..n_words - 1 {
+            bytes[i * 8..(i + 1) * 8].copy_from_slice(&rng.next_u64().to_le_bytes());
+        }
+        let n = U256::from_le_slice(&*bytes);
+        if n < **bound {
+            return n;
+        }
+        hi_word = rng.next_u64() & mask;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crypto_bigint::RandomMod;
+
+    use super::*;
+
+    const INPUTS: SaltInputs = SaltInputs {
+        increment: 3,
+        url: "https://me@google.com/",
+        username: Some("me"),
+    };
+
+    #[test]
+    fn salt_v1() -> Result<()> {
+        assert_eq!(
+            b"3,https://me@google.com/".as_slice(),
+            DerivationVersion::V1.salt(None, &INPUTS)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn key_salt_stable() {
+        assert_eq!(
+            b"\x0e\0\0\0onepass-v2-key\x07\0\0\0ssh-key\x01\0\0\0a\x04\0\0\0\x02\0\0\0".as_slice(),
+            key_salt("ssh-key", "a", 2)
+        );
+    }
+
+    #[test]
+    fn salt_v2() -> Result<()> {
+        let inputs = SaltInputs {
+            increment: 3,
+            url: "a",
+            username: None,
+        };
+        let salt = DerivationVersion::V2.salt(None, &inputs)?;
+        assert_eq!(
+            b"\x0a\0\0\0onepass-v2\x01\0\0\0a\x04\0\0\0\x03\0\0\0".as_slice(),
+            salt
+        );
+        let inputs = SaltInputs {
+            url: "a\x04",
+            ..inputs
+        };
+        assert_ne!(salt, DerivationVersion::V2.salt(None, &inputs)?);
+        Ok(())
+    }
+
+    #[test]
+    fn salt_template() -> Result<()> {
+        assert_eq!(
+            DerivationVersion::V1.salt(None, &INPUTS)?,
+            DerivationVersion::V1.salt(Some("{increment},{url}"), &INPUTS)?
+        );
+        assert_eq!(
+            "https://me@google.com/#3#me {x}",
+            INPUTS.render("{url}#{increment}#{username} {{x}}")?
+        );
+        assert!(INPUTS.render("{nope}").is_err());
+        assert!(INPUTS.render("{url").is_err());
+        assert!(INPUTS.render("url}").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn kdfs_differ() -> Result<()> {
+        let mut outputs = Vec::new();
+        for algorithm in KdfAlgorithm::value_variants() {
+            let kdf = KdfConfig {
+                algorithm: *algorithm,
+                argon2: Some(Argon2Config {
+                    memory: 64,
+                    ..Default::default()
+                }),
+                scrypt: Some(ScryptConfig {
+                    log_n: 4,
+                    ..Default::default()
+                }),
+                balloon: Some(BalloonConfig {
+                    s_cost: 64,
+                    ..Default::default()
+                }),
+            };
+            let mut out = [0u8; 32];
+            kdf.derive(b"password", b"salt salt salt", &mut out)?;
+            outputs.push(out);
+        }
+        outputs.sort();
+        outputs.dedup();
+        assert_eq!(KdfAlgorithm::value_variants().len(), outputs.len());
+        Ok(())
+    }
+
+    #[test]
+    fn kdf_memory() {
+        let mut kdf = KdfConfig::default();
+        assert_eq!(32 << 20, kdf.memory());
+        kdf.algorithm = KdfAlgorithm::Scrypt;
+        assert_eq!(32 << 20, kdf.memory());
+        kdf.algorithm = KdfAlgorithm::Balloon;
+        assert_eq!(2 << 20, kdf.memory());
+    }
+
+    #[test]
+    fn version_parse() {
+        assert_eq!(Ok(DerivationVersion::V2), "v2".parse());
+        assert_eq!(Ok(DerivationVersion::V1), "1".parse());
+        assert!("3".parse::<DerivationVersion>().is_err());
+    }
+
+    #[test]
+    fn random_below_matches_random_mod() {
+        for bound in [1u128, 26u128.pow(10), u64::MAX as u128 + 2, 7 << 100] {
+            let bound = NonZero::new(U256::from_u128(bound)).unwrap();
+            let mut a = ChaCha20Rng::from_seed([7; 32]);
+            let mut b = a.clone();
+            for _ in 0..100 {
+                assert_eq!(
+                    random_below(&mut a, &bound),
+                    U256::random_mod(&mut b, &bound)
+                );
+            }
+        }
+    }
+}
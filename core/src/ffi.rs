@@ -0,0 +1,313 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A C interface to the derivation, built into the `cdylib`, for native applications and languages
+//! with a C FFI. `include/onepass.h` declares it.
+//!
+//! Functions return `NULL` (or -1) on failure, after which [`onepass_last_error`] describes the
+//! failure. Returned strings belong to the caller, who frees them with [`onepass_free`].
+
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString, c_char, c_int},
+    ptr,
+};
+
+use anyhow::{Context, Result};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::{
+    derive::{DerivationVersion, KdfConfig, Site, site_password},
+    randexp::{Expr, Quantifiable, Words},
+    url::{self, Rules, canonicalize_with},
+    wordlist::{Wordlist, load_eff_large},
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Derives the password of `site` for `master`, matching `schema`, with derivation version 2 and
+/// the default KDF, as for a site configured with just a schema and increment in a
+/// config without a canonicalization version, whose sites are canonicalized under v1.
+///
+/// # Safety
+///
+/// The arguments must be `NULL` or valid NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn onepass_generate(
+    master: *const c_char,
+    site: *const c_char,
+    schema: *const c_char,
+    increment: u32,
+) -> *mut c_char {
+    unsafe {
+        onepass_generate_ex(
+            master,
+            site,
+            ptr::null(),
+            schema,
+            increment,
+            // Pinned rather than the latest, so that a new derivation version doesn't change
+            // the passwords of applications built against this ABI.
+            DerivationVersion::V2.into(),
+        )
+    }
+}
+
+/// Like [`onepass_generate`], but for the account `username` (or none if `NULL`) and with the
+/// derivation version `version`.
+///
+/// # Safety
+///
+/// The arguments must be `NULL` or valid NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn onepass_generate_ex(
+    master: *const c_char,
+    site: *const c_char,
+    username: *const c_char,
+    schema: *const c_char,
+    increment: u32,
+    version: u32,
+) -> *mut c_char {
+    unsafe { onepass_generate_with(master, site, username, schema, increment, version, 1) }
+}
+
+/// Like [`onepass_generate_ex`], but canonicalizing `site` under the canonicalization version
+/// `canonicalization`, the config's `canonicalization.version`.
+///
+/// # Safety
+///
+/// The arguments must be `NULL` or valid NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn onepass_generate_with(
+    master: *const c_char,
+    site: *const c_char,
+    username: *const c_char,
+    schema: *const c_char,
+    increment: u32,
+    version: u32,
+    canonicalization: u32,
+) -> *mut c_char {
+    let password = (|| {
+        let username = unsafe { opt_str(username) }?;
+        let site = unsafe { str(site, "site") }?;
+        let url = canonicalize_with(site, username, &rules(canonicalization)?)?;
+        let site = Site {
+            url: &url,
+            username,
+            increment,
+            schema: unsafe { str(schema, "schema") }?,
+            ..Site::new(version.try_into().map_err(anyhow::Error::msg)?)
+        };
+        site_password(
+            unsafe { str(master, "master") }?,
+            &site,
+            &KdfConfig::default(),
+            &Words(Wordlist::EffLarge.words()),
+        )
+    })();
+    password.and_then(into_c).unwrap_or_else(fail)
+}
+
+/// Returns the canonical URL that `site` is derived from under canonicalization v1, e.g.
+/// `https://example.com/` for `example.com`, with `username` (if not `NULL`) in it.
+///
+/// # Safety
+///
+/// The arguments must be `NULL` or valid NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn onepass_canonicalize(
+    site: *const c_char,
+    username: *const c_char,
+) -> *mut c_char {
+    unsafe { onepass_canonicalize_with(site, username, 1) }
+}
+
+/// Like [`onepass_canonicalize`], but under the canonicalization version `canonicalization`.
+///
+/// # Safety
+///
+/// The arguments must be `NULL` or valid NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn onepass_canonicalize_with(
+    site: *const c_char,
+    username: *const c_char,
+    canonicalization: u32,
+) -> *mut c_char {
+    let url = (|| {
+        let site = unsafe { str(site, "site") }?;
+        canonicalize_with(
+            site,
+            unsafe { opt_str(username) }?,
+            &rules(canonicalization)?,
+        )
+    })();
+    url.map(Zeroizing::new)
+        .and_then(into_c)
+        .unwrap_or_else(fail)
+}
+
+/// Returns the bits of entropy of passwords matching `schema`, rounded up, or -1 on failure.
+///
+/// # Safety
+///
+/// `schema` must be `NULL` or a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn onepass_entropy(schema: *const c_char) -> c_int {
+    let expr = (|| Expr::parse(unsafe { str(schema, "schema") }?))();
+    match expr {
+        Ok(expr) => {
+            LAST_ERROR.set(None);
+            Words(Wordlist::EffLarge.words()).size(&expr).bits() as c_int
+        }
+        Err(e) => {
+            fail::<()>(e);
+            -1
+        }
+    }
+}
+
+/// Loads the EFF's large word list from `contents`, its published file, for libraries built
+/// without the `eff-wordlist` feature, returning 0, or -1 if `contents` is not that file.
+///
+/// # Safety
+///
+/// `contents` must be `NULL` or a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn onepass_load_wordlist(contents: *const c_char) -> c_int {
+    match (|| load_eff_large(unsafe { str(contents, "contents") }?))() {
+        Ok(()) => {
+            LAST_ERROR.set(None);
+            0
+        }
+        Err(e) => {
+            fail::<()>(e);
+            -1
+        }
+    }
+}
+
+/// Wipes and frees a string returned by this library. `NULL` is ignored.
+///
+/// # Safety
+///
+/// `s` must be `NULL` or a string returned by this library that has not yet been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn onepass_free(s: *mut c_char) {
+    if !s.is_null() {
+        unsafe { CString::from_raw(s) }.into_bytes().zeroize();
+    }
+}
+
+/// Describes the last failure on this thread, or returns `NULL` if there was none. The string is
+/// valid until the next call into this library on the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn onepass_last_error() -> *const c_char {
+    LAST_ERROR.with_borrow(|error| error.as_deref().map_or(ptr::null(), CStr::as_ptr))
+}
+
+/// Returns the rules of the canonicalization version `version`.
+fn rules(version: u32) -> Result<Rules> {
+    let version = url::Version::try_from(version).map_err(anyhow::Error::msg)?;
+    Ok(version.into())
+}
+
+unsafe fn str<'a>(s: *const c_char, name: &str) -> Result<&'a str> {
+    unsafe { opt_str(s) }?.with_context(|| format!("{name} is NULL"))
+}
+
+unsafe fn opt_str<'a>(s: *const c_char) -> Result<Option<&'a str>> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    Ok(Some(
+        unsafe { CStr::from_ptr(s) }
+            .to_str()
+            .context("invalid UTF-8")?,
+    ))
+}
+
+fn into_c(s: Zeroizing<String>) -> Result<*mut c_char> {
+    LAST_ERROR.set(None);
+    Ok(CString::new(s.as_bytes())
+        .context("output contains NUL")?
+        .into_raw())
+}
+
+fn fail<T>(e: anyhow::Error) -> *mut T {
+    let message = CString::new(format!("{e:#}").replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.set(Some(message));
+    ptr::null_mut()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn take(s: *mut c_char) -> Option<String> {
+        if s.is_null() {
+            return None;
+        }
+        let owned = unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_owned();
+        unsafe { onepass_free(s) };
+        Some(owned)
+    }
+
+    #[test]
+    fn generate() {
+        let password = take(unsafe {
+            onepass_generate_ex(
+                c"hunter2".as_ptr(),
+                c"a.com".as_ptr(),
+                ptr::null(),
+                c"[a-z]{10}".as_ptr(),
+                0,
+                1,
+            )
+        });
+        assert_eq!(password.as_deref(), Some("wwjtzfjytg"));
+        assert!(onepass_last_error().is_null());
+
+        // Without a version, passwords are version 2's, whatever the latest is.
+        let (master, site, schema) = (
+            c"hunter2".as_ptr(),
+            c"a.com".as_ptr(),
+            c"[a-z]{10}".as_ptr(),
+        );
+        assert_eq!(
+            take(unsafe { onepass_generate_ex(master, site, ptr::null(), schema, 0, 2) }),
+            take(unsafe { onepass_generate(master, site, schema, 0) })
+        );
+
+        let password = take(unsafe {
+            onepass_generate(c"hunter2".as_ptr(), ptr::null(), c"[a-z]{10}".as_ptr(), 0)
+        });
+        assert_eq!(password, None);
+        let error = unsafe { CStr::from_ptr(onepass_last_error()) };
+        assert_eq!(error.to_str(), Ok("site is NULL"));
+    }
+
+    #[test]
+    fn helpers() {
+        let url = take(unsafe { onepass_canonicalize(c"example.com".as_ptr(), c"me".as_ptr()) });
+        assert_eq!(url.as_deref(), Some("https://me@example.com/"));
+        assert_eq!(unsafe { onepass_entropy(c"[0-9]{4}".as_ptr()) }, 14);
+        assert_eq!(unsafe { onepass_entropy(c"[".as_ptr()) }, -1);
+        assert_eq!(
+            unsafe { onepass_load_wordlist(c"11111\tabacus".as_ptr()) },
+            -1
+        );
+    }
+}
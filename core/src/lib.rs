@@ -0,0 +1,53 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The library behind onepass, a mostly-stateless deterministic password manager: everything
+//! needed to reproduce its passwords, without its command line interface.
+//!
+//! The [`randexp`] module contains the schema engine, which maps a uniformly random number onto
+//! the set of passwords matching a regex-like schema. The [`derive`] module contains the rest of
+//! the derivation pipeline. [`url`] canonicalizes site names, [`wordlist`] has the built-in word
+//! lists, [`rules`] sites' password rules, and [`policy`] named bundles of settings. `config`
+//! parses the config file, with `date` for its dates and `backup` for snapshots of it, so that
+//! other programs can reproduce passwords exactly. [`compat`] has other password managers'
+//! algorithms, for sites whose passwords were made by them.
+//!
+//! The `ffi` module exposes the derivation to C. With the `wasm` and `python` features, the
+//! `wasm` and `python` modules expose it to JavaScript and Python. `backup`, `config`, `date`,
+//! and `ffi` are not built for wasm.
+//!
+//! The API follows semver, separately from the CLI: before 1.0, breaking changes bump the minor
+//! version. Changes that would derive different passwords are breaking changes to both.
+
+#[cfg(not(target_family = "wasm"))]
+pub mod backup;
+pub mod compat;
+#[cfg(not(target_family = "wasm"))]
+pub mod config;
+#[cfg(not(target_family = "wasm"))]
+pub mod date;
+pub mod derive;
+#[cfg(not(target_family = "wasm"))]
+pub mod ffi;
+pub mod policy;
+#[cfg(feature = "python")]
+mod python;
+pub mod randexp;
+pub mod rules;
+pub mod url;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod wordlist;
+
+include!(concat!(env!("OUT_DIR"), "/wordlist.rs"));
@@ -0,0 +1,82 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Named bundles of settings, like `policy: pci`, for configs that would rather pick one than
+//! tune the minimum entropy, default schema, KDF, and rotation window each.
+//!
+//! A policy only fills in the settings the config leaves out. Its default schema and KDF are part
+//! of the passwords derived from them, so a policy never changes once added; stricter settings
+//! come as a new policy.
+
+use serde::{Deserialize, Serialize};
+
+use crate::derive::{Argon2Config, KdfAlgorithm, KdfConfig};
+
+/// A named bundle of settings.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Policy {
+    /// NIST SP 800-63B's memorized secrets: long passphrases, never expiring
+    NistMemorized,
+    /// PCI DSS 4.0's passwords: letters and digits, changed every 90 days
+    Pci,
+    /// Long passwords of every printable character, with an expensive KDF
+    Paranoid,
+}
+
+impl Policy {
+    /// The fewest bits of entropy `audit` lets a schema have.
+    pub fn min_bits(self) -> u32 {
+        match self {
+            Policy::NistMemorized => 64,
+            Policy::Pci => 80,
+            Policy::Paranoid => 128,
+        }
+    }
+
+    /// The schema of sites without one.
+    pub fn default_schema(self) -> &'static str {
+        match self {
+            Policy::NistMemorized => "[:word:](-[:word:]){5}",
+            Policy::Pci => "[A-Za-z][0-9][!-~]{14}",
+            Policy::Paranoid => "[!-~]{32}",
+        }
+    }
+
+    /// The KDF of the master password.
+    pub fn kdf(self) -> KdfConfig {
+        let argon2 = match self {
+            Policy::NistMemorized | Policy::Pci => Argon2Config::default(),
+            Policy::Paranoid => Argon2Config {
+                memory: 256 * 1024,
+                iterations: 4,
+                ..Default::default()
+            },
+        };
+        KdfConfig {
+            algorithm: KdfAlgorithm::Argon2id,
+            argon2: Some(argon2),
+            ..Default::default()
+        }
+    }
+
+    /// The days after which passwords are due for rotation, or 0 for never.
+    pub fn rotate_after(self) -> u32 {
+        match self {
+            Policy::NistMemorized => 0,
+            Policy::Pci => 90,
+            Policy::Paranoid => 180,
+        }
+    }
+}
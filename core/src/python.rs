@@ -0,0 +1,120 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The `onepass` Python module, with the `python` feature, so that provisioning and migration
+//! scripts can derive passwords with the same code as the CLI instead of reimplementing it.
+//!
+//! ```python
+//! import onepass
+//!
+//! onepass.generate("hunter2", "a.com", "[a-z]{10}", version=1)  # 'wwjtzfjytg'
+//! ```
+
+use std::collections::BTreeSet;
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    derive::{DerivationVersion, KdfConfig, Site, site_password},
+    randexp::{Expr, Quantifiable, Words},
+    url,
+    wordlist::Wordlist,
+};
+
+/// Derives the password of `site` (a URL or host name) for `master`, matching `schema`, or the
+/// field `field` of the site, as the CLI would with the default KDF and no second factors.
+/// `canonicalization` is the config's `canonicalization.version`, by default 1, as in configs
+/// without one. `version` defaults to 2 rather than the latest, so that a new derivation version
+/// doesn't change the passwords of scripts that leave it out. `words` replaces the EFF word list,
+/// like a words file.
+#[pyfunction]
+#[pyo3(signature = (
+    master, site, schema, increment=0, username=None, version=2, salt_template=None,
+    field=None, words=None, canonicalization=1,
+))]
+#[allow(clippy::too_many_arguments)]
+fn generate(
+    py: Python<'_>,
+    master: &str,
+    site: &str,
+    schema: &str,
+    increment: u32,
+    username: Option<&str>,
+    version: u32,
+    salt_template: Option<&str>,
+    field: Option<&str>,
+    words: Option<Vec<String>>,
+    canonicalization: u32,
+) -> PyResult<String> {
+    let version = DerivationVersion::try_from(version).map_err(PyValueError::new_err)?;
+    let url = canonicalize(site, username, canonicalization)?;
+    let site = Site {
+        url: &url,
+        username,
+        increment,
+        version,
+        salt_template,
+        schema,
+        field,
+    };
+    // The KDF takes a while, so let other Python threads run meanwhile.
+    let password = py.detach(|| {
+        with_words(words, |words| {
+            site_password(master, &site, &KdfConfig::default(), words)
+        })
+    });
+    Ok(password.map_err(value_error)?.to_string())
+}
+
+/// Returns the canonical URL that `site` is derived from, with `username` in it if given, under
+/// the canonicalization version `canonicalization`.
+#[pyfunction]
+#[pyo3(signature = (site, username=None, canonicalization=1))]
+fn canonicalize(site: &str, username: Option<&str>, canonicalization: u32) -> PyResult<String> {
+    let version = url::Version::try_from(canonicalization).map_err(PyValueError::new_err)?;
+    url::canonicalize_with(site, username, &version.into()).map_err(value_error)
+}
+
+/// Returns the bits of entropy of passwords matching `schema`, rounded up.
+#[pyfunction]
+#[pyo3(signature = (schema, words=None))]
+fn entropy(schema: &str, words: Option<Vec<String>>) -> PyResult<u32> {
+    let expr = Expr::parse(schema).map_err(value_error)?;
+    Ok(with_words(words, |words| words.size(&expr).bits()))
+}
+
+/// Calls `f` with `words`, deduplicated and sorted as the CLI does with a words file, or else
+/// the EFF word list.
+fn with_words<T>(words: Option<Vec<String>>, f: impl FnOnce(&Words) -> T) -> T {
+    match words {
+        Some(words) => {
+            let words: BTreeSet<_> = words.iter().map(|word| word.trim()).collect();
+            let words: Vec<_> = words.into_iter().filter(|word| !word.is_empty()).collect();
+            f(&Words(&words))
+        }
+        None => f(&Words(Wordlist::EffLarge.words())),
+    }
+}
+
+fn value_error(e: anyhow::Error) -> PyErr {
+    PyValueError::new_err(format!("{e:#}"))
+}
+
+#[pymodule]
+fn onepass(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(generate, m)?)?;
+    m.add_function(wrap_pyfunction!(canonicalize, m)?)?;
+    m.add_function(wrap_pyfunction!(entropy, m)?)?;
+    Ok(())
+}
@@ -12,7 +12,31 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::cmp;
+//! An exact-counting random expression engine.
+//!
+//! This module parses a small regular-expression-like language into an [`Expr`] and maps every
+//! string matching that expression to a unique index. [`Quantifiable::size`] counts how many
+//! strings an expression matches, and [`Enumerable::gen_at`] produces the string at a given index,
+//! so picking a uniformly random index in `[0, size)` picks a uniformly random matching string.
+//!
+//! ```
+//! use crypto_bigint::U256;
+//! use onepass::randexp::{Enumerable, Expr, Quantifiable, Words};
+//!
+//! let expr = Expr::parse("[:word:]-[0-9]")?;
+//! let words = Words(&["correct", "horse"]);
+//! assert_eq!(U256::from(20u32), words.size(&expr));
+//! assert_eq!("correct-0", *words.gen_at(&expr, U256::ZERO)?);
+//! assert_eq!("horse-0", *words.gen_at(&expr, U256::ONE)?);
+//! assert_eq!("correct-1", *words.gen_at(&expr, U256::from(2u32))?);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::{
+    cmp,
+    fmt::{self, Write},
+    str::FromStr,
+};
 
 use anyhow::{Context, Result};
 use crypto_bigint::{NonZero, U256};
@@ -28,6 +52,7 @@ use nom::{
     multi::many1,
     sequence::{delimited, preceded, separated_pair},
 };
+use serde::{Deserialize, Serialize};
 use zeroize::Zeroizing;
 
 /// Expr represents a subset of regular expressions that allows for literal strings, character
@@ -39,24 +64,42 @@ use zeroize::Zeroizing;
 /// matching the language to be mapped to a U256, producing a unique (assuming the language does
 /// not have multiple valid ways of recognizing a given string) string for each different number in
 /// the half-open interval `[0, expr.size())`.
+///
+/// The supported syntax is:
+///
+/// - `[:word:]` and `[:Word:]` for a lowercase or capitalized word from the dictionary,
+/// - `[a-z0-9]` for character classes, with `\` escaping `\]^-` within them, and `\d` and `\w`
+///   for the usual shorthands,
+/// - `(...)` for groups,
+/// - `{n}` and `{min,max}` for repetition of the preceding expression, and
+/// - anything else as a literal, with `\` escaping the special characters `[]{}()|\`.
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub(crate) enum Expr {
+pub enum Expr {
+    /// A single word from the dictionary, as written.
     Word,
+    /// A single word from the dictionary, with its first letter capitalized.
     WOrd,
+    /// A literal string, matching only itself.
     Literal(String),
+    /// A single character from any of a set of ranges.
     CharClass(CharClass),
+    /// Each of the contained expressions in turn.
     Sequence(Vec<Expr>),
+    /// The contained expression repeated between `min` and `max` times, inclusive.
     Repeat(Box<Expr>, u32, u32),
 }
 
+/// An inclusive range of characters within a [`CharClass`].
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub(crate) struct CharRange {
+pub struct CharRange {
     start: char,
     end: char,
 }
 
+/// A set of characters, stored as sorted, non-overlapping, non-adjacent [`CharRange`]s so that
+/// each character in the class is counted exactly once.
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub(crate) struct CharClass {
+pub struct CharClass {
     ranges: Vec<CharRange>,
 }
 
@@ -96,6 +139,83 @@ impl CharClass {
         ranges.push(current);
         CharClass { ranges }
     }
+
+    /// Returns whether `c` is in the class.
+    pub fn contains(&self, c: char) -> bool {
+        self.ranges
+            .iter()
+            .any(|CharRange { start, end }| (*start..=*end).contains(&c))
+    }
+
+    /// Returns the class of the characters in either `self` or `other`.
+    pub fn union(&self, other: &CharClass) -> CharClass {
+        CharClass::from_ranges(self.ranges.iter().chain(&other.ranges).cloned().collect())
+    }
+
+    /// Returns the characters in the class, in order.
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.ranges
+            .iter()
+            .flat_map(|CharRange { start, end }| char_iter::new(*start, *end))
+    }
+}
+
+impl FromIterator<char> for CharClass {
+    /// Returns the class of the characters of `iter`.
+    fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> Self {
+        CharClass::from_ranges(
+            iter.into_iter()
+                .map(|c| CharRange { start: c, end: c })
+                .collect(),
+        )
+    }
+}
+
+impl FromStr for CharClass {
+    type Err = anyhow::Error;
+
+    /// Parses a character class in schema syntax, like `[a-z]` or `\d`.
+    fn from_str(s: &str) -> Result<Self> {
+        match Expr::parse(s)? {
+            Expr::CharClass(cc) => Ok(cc),
+            _ => anyhow::bail!("{s} is not a character class, like [a-z]"),
+        }
+    }
+}
+
+impl fmt::Display for CharClass {
+    /// Writes the class in schema syntax, e.g. `[0-9a-f]`, escaping characters where needed.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let write = |f: &mut fmt::Formatter<'_>, c: char| {
+            if matches!(c, '\\' | ']' | '^' | '-') {
+                f.write_char('\\')?;
+            }
+            f.write_char(c)
+        };
+        f.write_char('[')?;
+        for CharRange { start, end } in &self.ranges {
+            write(f, *start)?;
+            if end > start {
+                f.write_char('-')?;
+                write(f, *end)?;
+            }
+        }
+        f.write_char(']')
+    }
+}
+
+impl Serialize for CharClass {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for CharClass {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
 }
 
 fn u256_to_usize(n: &U256) -> usize {
@@ -119,6 +239,16 @@ fn u256_saturating_pow(base: &U256, mut exp: u32) -> U256 {
 }
 
 impl Expr {
+    /// Parses a schema string into an expression, failing if any of the input is left over.
+    ///
+    /// ```
+    /// use onepass::randexp::Expr;
+    ///
+    /// let expr = Expr::parse("a{3,5}")?;
+    /// assert_eq!(Expr::Repeat(Box::new(Expr::Literal("a".into())), 3, 5), expr);
+    /// assert!(Expr::parse("[a-z").is_err());
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
     pub fn parse(input: &str) -> Result<Self> {
         let (rem, expr) = Expr::parse_expr(input).finish().map_err(|e| {
             anyhow::anyhow!("Parse error at {}: {}", e.input.len(), e.code.description())
@@ -183,8 +313,8 @@ impl Expr {
             return fail().parse(input);
         }
         let (input, rs) = many1(alt((
-            separated_pair(none_of("\\]"), char('-'), none_of("\\]")),
-            map(none_of("\\]"), |c| (c, c)),
+            separated_pair(Expr::parse_class_char, char('-'), Expr::parse_class_char),
+            map(Expr::parse_class_char, |c| (c, c)),
         )))
         .parse(input)?;
         let rs = rs
@@ -194,6 +324,10 @@ impl Expr {
         Ok((input, CharClass::from_ranges(rs)))
     }
 
+    fn parse_class_char(input: &str) -> IResult<&str, char> {
+        alt((preceded(char('\\'), one_of("\\]^-")), none_of("\\]"))).parse(input)
+    }
+
     fn parse_char_class(input: &str) -> IResult<&str, Expr> {
         let (input, cc) =
             delimited(char('['), Expr::parse_char_class_inner, char(']')).parse(input)?;
@@ -241,15 +375,34 @@ impl Expr {
     }
 }
 
-pub(crate) trait Quantifiable<Node> {
+/// Counts the strings matched by a node.
+///
+/// Sizes saturate at `U256::MAX` rather than overflowing; schemas that large are not usefully
+/// enumerable anyway.
+pub trait Quantifiable<Node> {
+    /// Returns the number of distinct strings matching `node`.
     fn size(&self, node: &Node) -> U256;
 }
 
-pub(crate) trait Enumerable<Node>: Quantifiable<Node> {
+/// Generates the strings matched by a node by index.
+pub trait Enumerable<Node>: Quantifiable<Node> {
+    /// Returns the string at `index` among those matching `node`, where `index` is in
+    /// `[0, self.size(node))`. Distinct indices produce distinct strings.
     fn gen_at(&self, node: &Node, index: U256) -> Result<Zeroizing<String>>;
 }
 
-pub(crate) struct WordCount(pub usize);
+/// A [`Quantifiable`] that knows only how many words are in the dictionary. This is all that is
+/// needed to count an expression, e.g. for computing entropy before a word list is loaded.
+///
+/// ```
+/// use crypto_bigint::U256;
+/// use onepass::randexp::{Expr, Quantifiable, WordCount};
+///
+/// let expr = Expr::parse("[:word:](-[:word:]){2}")?;
+/// assert_eq!(U256::from(7776u64.pow(3)), WordCount(7776).size(&expr));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct WordCount(pub usize);
 
 impl Quantifiable<Expr> for WordCount {
     fn size(&self, expr: &Expr) -> U256 {
@@ -278,21 +431,84 @@ impl Quantifiable<Expr> for WordCount {
     }
 }
 
-pub(crate) struct Words<'a>(pub &'a [&'a str]);
+/// A list of words that can be counted and indexed without being a slice of them, e.g. a large
+/// file indexed on first use.
+///
+/// [`Words`] only calls these methods for expressions with words in them.
+pub trait WordList {
+    /// Returns the number of words in the list.
+    fn len(&self) -> usize;
 
-impl Quantifiable<Expr> for Words<'_> {
+    /// Returns the word at `index`, which is below [`len`](WordList::len).
+    fn word(&self, index: usize) -> &str;
+
+    /// Returns whether the list has no words.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl WordList for [&str] {
+    fn len(&self) -> usize {
+        <[&str]>::len(self)
+    }
+
+    fn word(&self, index: usize) -> &str {
+        self[index]
+    }
+}
+
+impl WordList for Vec<&str> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn word(&self, index: usize) -> &str {
+        self[index]
+    }
+}
+
+impl<const N: usize> WordList for [&str; N] {
+    fn len(&self) -> usize {
+        N
+    }
+
+    fn word(&self, index: usize) -> &str {
+        self[index]
+    }
+}
+
+/// A dictionary of words, used to both count and generate expressions.
+///
+/// The words should be distinct; duplicate words would make distinct indices produce identical
+/// strings, which overstates the entropy of the schema.
+pub struct Words<'a, L: WordList + ?Sized = [&'a str]>(pub &'a L);
+
+impl<L: WordList + ?Sized> Quantifiable<Expr> for Words<'_, L> {
     fn size(&self, node: &Expr) -> U256 {
-        WordCount(self.0.len()).size(node)
+        // Expressions without words don't need the list, which may be costly to count.
+        let len = if has_words(node) { self.0.len() } else { 0 };
+        WordCount(len).size(node)
     }
 }
 
-impl Enumerable<Expr> for Words<'_> {
+/// Returns whether `expr` has a word in it.
+fn has_words(expr: &Expr) -> bool {
+    match expr {
+        Expr::Word | Expr::WOrd => true,
+        Expr::Literal(_) | Expr::CharClass(_) => false,
+        Expr::Sequence(exprs) => exprs.iter().any(has_words),
+        Expr::Repeat(expr, _, _) => has_words(expr),
+    }
+}
+
+impl<L: WordList + ?Sized> Enumerable<Expr> for Words<'_, L> {
     fn gen_at(&self, expr: &Expr, index: U256) -> Result<Zeroizing<String>> {
         let mut index = Zeroizing::new(index);
         let res = match expr {
-            Expr::Word => String::from(self.0[u256_to_usize(&index)]),
+            Expr::Word => String::from(self.0.word(u256_to_usize(&index))),
             Expr::WOrd => {
-                let mut chars = self.0[u256_to_usize(&index)].chars();
+                let mut chars = self.0.word(u256_to_usize(&index)).chars();
                 let first = chars.next().context("empty word")?.to_uppercase();
                 first.chain(chars).collect()
             }
@@ -409,6 +625,16 @@ mod tests {
             }),
             expr
         );
+        let Expr::CharClass(cc) = Expr::parse("[a-c0x-z]")? else {
+            unreachable!()
+        };
+        assert_eq!("0abcxyz", cc.chars().collect::<String>());
+        for class in ["[0-9A-Za-z]", "[\\-\\\\]", "[!-~]", "[\\]a]", "[\\^]"] {
+            assert_eq!(class, class.parse::<CharClass>()?.to_string());
+        }
+        let digits: CharClass = "[0-4]".parse()?;
+        assert_eq!("[0-9a]", digits.union(&"[5-9a]".parse()?).to_string());
+        assert!("a".parse::<CharClass>().is_err());
         Ok(())
     }
 
@@ -504,4 +730,26 @@ mod tests {
         assert_eq!("Bob", *wl.gen_at(&expr, U256::ZERO)?);
         Ok(())
     }
+
+    #[test]
+    fn enumerate_without_words() -> Result<()> {
+        /// A list too costly to count, like a large file not yet indexed.
+        struct Unindexed;
+
+        impl WordList for Unindexed {
+            fn len(&self) -> usize {
+                panic!("counted")
+            }
+
+            fn word(&self, _index: usize) -> &str {
+                panic!("indexed")
+            }
+        }
+
+        let expr = Expr::parse("[0-9]{4}")?;
+        let wl = Words(&Unindexed);
+        assert_eq!(U256::from(10000u32), wl.size(&expr));
+        assert_eq!("0000", *wl.gen_at(&expr, U256::ZERO)?);
+        Ok(())
+    }
 }
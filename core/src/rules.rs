@@ -0,0 +1,536 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sites' rules for their passwords, like a maximum length or a required digit, and schemas that
+//! follow them.
+//!
+//! [`PasswordRules`] compiles to a schema whose every password follows the rules, and checks
+//! whether a schema's passwords might not. Rules for some popular sites are built in, found with
+//! [`known`], to choose the schemas of sites without one of their own.
+//!
+//! Rules also parse from the `passwordrules` format that Safari and iOS read from sites, e.g.
+//! `minlength: 12; required: upper, lower, digit; allowed: [-_]`.
+//!
+//! The built-in rules are part of the passwords derived from them, so they are versioned like
+//! canonicalization: an entry never changes once added. A site whose rules change gets a new
+//! entry, used only by configs asking for the version of the built-in rules it came in.
+
+use std::{collections::HashMap, fmt, str::FromStr, sync::OnceLock};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::randexp::{CharClass, Expr, WordList, Words};
+
+/// The latest version of the built-in rules.
+pub const LATEST: u32 = 1;
+
+/// The length of passwords from [`PasswordRules::schema`], where the rules allow it.
+const DEFAULT_LENGTH: usize = 16;
+
+/// The built-in rules, by domain, each with the version of the rules it came in.
+const SITES: &str = include_str!("rules/sites.json");
+
+/// What a site accepts as a password.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PasswordRules {
+    /// The fewest characters a password may have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<usize>,
+    /// The most characters a password may have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+    /// Classes of characters a password must have one of each of
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required: Vec<CharClass>,
+    /// The characters a password may have besides the required ones; with neither, any printable
+    /// ASCII
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed: Option<CharClass>,
+    /// The most times a character may appear in a row, which schemas can't express, so it is
+    /// only checked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_consecutive: Option<usize>,
+}
+
+/// A way a schema's passwords might break a site's rules.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Violation {
+    /// Passwords may have `length` characters, fewer than `min`.
+    TooShort { length: usize, min: usize },
+    /// Passwords may have `length` characters, more than `max`.
+    TooLong { length: usize, max: usize },
+    /// Passwords may have a character that isn't allowed.
+    Disallowed(char),
+    /// Passwords may have none of a required class.
+    Missing(CharClass),
+    /// Passwords may have a character more than `max` times in a row.
+    Consecutive { max: usize },
+}
+
+/// A site's built-in rules, with the schema compiled from them.
+#[derive(Debug)]
+pub struct Known {
+    /// The version of the built-in rules the entry came in
+    pub since: u32,
+    pub rules: PasswordRules,
+    schema: String,
+}
+
+impl PasswordRules {
+    /// Checks that the rules can be followed: that there is a character to draw from, and that
+    /// the minimum length is no more than the maximum, which fits a character of each required
+    /// class.
+    pub fn validate(&self) -> Result<()> {
+        let allowed = self.allowed();
+        anyhow::ensure!(
+            allowed.chars().next().is_some(),
+            "no characters are allowed"
+        );
+        anyhow::ensure!(
+            self.required.iter().all(|cc| cc.chars().next().is_some()),
+            "a required class is empty"
+        );
+        let max = self.max_length.unwrap_or(usize::MAX);
+        anyhow::ensure!(
+            self.min_length.unwrap_or(0) <= max,
+            "the minimum length is over the maximum"
+        );
+        anyhow::ensure!(
+            self.required.len() <= max,
+            "the maximum length is too short for one of each required class"
+        );
+        Ok(())
+    }
+
+    /// Returns the characters a password may have: the required and allowed ones, or printable
+    /// ASCII if there are neither.
+    pub fn allowed(&self) -> CharClass {
+        let mut classes = self.required.iter().chain(&self.allowed);
+        match classes.next() {
+            Some(first) => classes.fold(first.clone(), |acc, cc| acc.union(cc)),
+            None => "[!-~]".parse().unwrap(),
+        }
+    }
+
+    /// Returns a schema whose every password follows the rules: a character of each required
+    /// class in turn, then any of the allowed characters, 16 in all or as close as the lengths
+    /// allow.
+    pub fn schema(&self) -> String {
+        let length = DEFAULT_LENGTH
+            .max(self.min_length.unwrap_or(0))
+            .min(self.max_length.unwrap_or(usize::MAX))
+            .max(self.required.len());
+        let mut schema: String = self.required.iter().map(ToString::to_string).collect();
+        match length - self.required.len() {
+            0 => {}
+            1 => schema += &self.allowed().to_string(),
+            rest => schema += &format!("{}{{{rest}}}", self.allowed()),
+        }
+        schema
+    }
+
+    /// Returns the ways the passwords matching `expr`, with words from `words`, might break the
+    /// rules.
+    pub fn check<L: WordList + ?Sized>(&self, words: &Words<L>, expr: &Expr) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let (shortest, longest) = lengths(words, expr);
+        if let Some(min) = self.min_length.filter(|min| shortest < *min) {
+            violations.push(Violation::TooShort {
+                length: shortest,
+                min,
+            });
+        }
+        if let Some(max) = self.max_length.filter(|max| longest > *max) {
+            violations.push(Violation::TooLong {
+                length: longest,
+                max,
+            });
+        }
+        let allowed = self.allowed();
+        if let Some(c) = chars(words, expr).find(|c| !allowed.contains(*c)) {
+            violations.push(Violation::Disallowed(c));
+        }
+        for cc in &self.required {
+            if !always_has(words, expr, cc) {
+                violations.push(Violation::Missing(cc.clone()));
+            }
+        }
+        if let Some(max) = self.max_consecutive.filter(|max| longest > *max) {
+            violations.push(Violation::Consecutive { max });
+        }
+        violations
+    }
+}
+
+impl FromStr for PasswordRules {
+    type Err = anyhow::Error;
+
+    /// Parses rules in the `passwordrules` format: properties separated by semicolons, each of
+    /// `minlength`, `maxlength`, `max-consecutive`, `required`, or `allowed`. Each `required`
+    /// property lists classes a password must have one character of between them, and `allowed`
+    /// properties list more it may have; classes are `upper`, `lower`, `digit`, `special`,
+    /// `ascii-printable`, or characters in brackets, like `[-_.]`. `special` and `ascii-printable`
+    /// leave out the space, which many sites reject even where they say they take it.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut rules = PasswordRules::default();
+        for property in s.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, value) = property
+                .split_once(':')
+                .with_context(|| format!("{property:?} is not like `name: value`"))?;
+            let value = value.trim();
+            let number = || -> Result<usize> {
+                value
+                    .parse()
+                    .with_context(|| format!("invalid {} {value:?}", name.trim()))
+            };
+            match name.trim().to_ascii_lowercase().as_str() {
+                "minlength" => {
+                    rules.min_length = Some(rules.min_length.unwrap_or(0).max(number()?));
+                }
+                "maxlength" => {
+                    rules.max_length = Some(rules.max_length.unwrap_or(usize::MAX).min(number()?));
+                }
+                "max-consecutive" => {
+                    let max = rules.max_consecutive.unwrap_or(usize::MAX).min(number()?);
+                    rules.max_consecutive = Some(max);
+                }
+                "required" => rules.required.push(parse_classes(value)?),
+                "allowed" => {
+                    let classes = parse_classes(value)?;
+                    rules.allowed = Some(match rules.allowed {
+                        Some(allowed) => allowed.union(&classes),
+                        None => classes,
+                    });
+                }
+                name => anyhow::bail!("unknown passwordrules property {name:?}"),
+            }
+        }
+        rules.validate()?;
+        Ok(rules)
+    }
+}
+
+impl Known {
+    /// Returns the schema compiled from the rules.
+    pub fn schema(&self) -> &str {
+        &self.schema
+    }
+}
+
+/// Returns the built-in rules of the site at `host`, or else of the nearest domain it is under,
+/// as of version `version` of the rules; version 0 has none.
+pub fn known(host: &str, version: u32) -> Option<&'static Known> {
+    let mut domain = host.strip_suffix('.').unwrap_or(host);
+    loop {
+        let known = sites()
+            .get(domain)
+            .and_then(|entries| entries.iter().rev().find(|known| known.since <= version));
+        if known.is_some() {
+            return known;
+        }
+        domain = domain.split_once('.')?.1;
+    }
+}
+
+/// Returns the union of the `passwordrules` classes listed in `value`, separated by commas.
+fn parse_classes(value: &str) -> Result<CharClass> {
+    let mut union: Option<CharClass> = None;
+    let mut rest = value.trim_start();
+    while !rest.is_empty() {
+        let class = if let Some(custom) = rest.strip_prefix('[') {
+            // `]` is only itself where another follows it, as the last of the class.
+            let mut end = custom.find(']').context("unclosed [ in passwordrules")?;
+            if custom[end + 1..].starts_with(']') {
+                end += 1;
+            }
+            anyhow::ensure!(end > 0, "empty [] in passwordrules");
+            rest = &custom[end + 1..];
+            custom[..end].chars().collect()
+        } else {
+            let end = rest.find(',').unwrap_or(rest.len());
+            let name = rest[..end].trim();
+            rest = &rest[end..];
+            let schema = match name.to_ascii_lowercase().as_str() {
+                "upper" => "[A-Z]",
+                "lower" => "[a-z]",
+                "digit" => "[0-9]",
+                "special" => "[!-/:-@[-`{-~]",
+                "ascii-printable" => "[!-~]",
+                "unicode" => anyhow::bail!("passwordrules class unicode is not supported"),
+                _ => anyhow::bail!("unknown passwordrules class {name:?}"),
+            };
+            schema.parse()?
+        };
+        union = Some(match union {
+            Some(union) => union.union(&class),
+            None => class,
+        });
+        rest = rest.trim_start();
+        if let Some(next) = rest.strip_prefix(',') {
+            rest = next.trim_start();
+        } else {
+            anyhow::ensure!(rest.is_empty(), "expected , in passwordrules at {rest:?}");
+        }
+    }
+    union.context("no classes in passwordrules property")
+}
+
+fn sites() -> &'static HashMap<String, Vec<Known>> {
+    static SITES_BY_DOMAIN: OnceLock<HashMap<String, Vec<Known>>> = OnceLock::new();
+    SITES_BY_DOMAIN.get_or_init(|| {
+        #[derive(Deserialize)]
+        struct Entry {
+            since: u32,
+            #[serde(flatten)]
+            rules: PasswordRules,
+        }
+        // Checked in the tests.
+        let sites: HashMap<String, Vec<Entry>> = serde_json::from_str(SITES).unwrap();
+        sites
+            .into_iter()
+            .map(|(domain, entries)| {
+                let entries = entries
+                    .into_iter()
+                    .map(|Entry { since, rules }| Known {
+                        since,
+                        schema: rules.schema(),
+                        rules,
+                    })
+                    .collect();
+                (domain, entries)
+            })
+            .collect()
+    })
+}
+
+/// Returns each of the words of `words` as `expr`, a word, writes it.
+fn each_word<'a, L: WordList + ?Sized>(
+    words: &'a Words<L>,
+    expr: &'a Expr,
+) -> impl Iterator<Item = String> + 'a {
+    (0..words.0.len()).map(move |i| {
+        let word = words.0.word(i);
+        let mut chars = word.chars();
+        match (expr, chars.next()) {
+            (Expr::WOrd, Some(first)) => first.to_uppercase().chain(chars).collect(),
+            _ => word.to_owned(),
+        }
+    })
+}
+
+/// Returns the fewest and most characters of the strings matching `expr`.
+fn lengths<L: WordList + ?Sized>(words: &Words<L>, expr: &Expr) -> (usize, usize) {
+    match expr {
+        Expr::Word | Expr::WOrd => {
+            let lengths = each_word(words, expr).map(|word| word.chars().count());
+            lengths.fold((usize::MAX, 0), |(min, max), len| {
+                (min.min(len), max.max(len))
+            })
+        }
+        Expr::Literal(s) => (s.chars().count(), s.chars().count()),
+        Expr::CharClass(_) => (1, 1),
+        Expr::Sequence(exprs) => exprs.iter().fold((0, 0), |(min, max), expr| {
+            let (expr_min, expr_max) = lengths(words, expr);
+            (min.saturating_add(expr_min), max.saturating_add(expr_max))
+        }),
+        Expr::Repeat(expr, min, max) => {
+            let (expr_min, expr_max) = lengths(words, expr);
+            (
+                expr_min.saturating_mul(*min as usize),
+                expr_max.saturating_mul(*max as usize),
+            )
+        }
+    }
+}
+
+/// Returns the characters the strings matching `expr` may have, possibly with repeats.
+fn chars<'a, L: WordList + ?Sized>(
+    words: &'a Words<L>,
+    expr: &'a Expr,
+) -> Box<dyn Iterator<Item = char> + 'a> {
+    match expr {
+        Expr::Word | Expr::WOrd => {
+            Box::new(each_word(words, expr).flat_map(|word| word.chars().collect::<Vec<_>>()))
+        }
+        Expr::Literal(s) => Box::new(s.chars()),
+        Expr::CharClass(cc) => Box::new(cc.chars()),
+        Expr::Sequence(exprs) => Box::new(exprs.iter().flat_map(|expr| chars(words, expr))),
+        Expr::Repeat(_, _, 0) => Box::new(std::iter::empty()),
+        Expr::Repeat(expr, _, _) => chars(words, expr),
+    }
+}
+
+/// Returns whether every string matching `expr` has a character of `cc`.
+fn always_has<L: WordList + ?Sized>(words: &Words<L>, expr: &Expr, cc: &CharClass) -> bool {
+    match expr {
+        Expr::Word | Expr::WOrd => {
+            each_word(words, expr).all(|word| word.chars().any(|c| cc.contains(c)))
+        }
+        Expr::Literal(s) => s.chars().any(|c| cc.contains(c)),
+        Expr::CharClass(chars) => chars.chars().all(|c| cc.contains(c)),
+        Expr::Sequence(exprs) => exprs.iter().any(|expr| always_has(words, expr, cc)),
+        Expr::Repeat(expr, min, _) => *min > 0 && always_has(words, expr, cc),
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::TooShort { length, min } => write!(
+                f,
+                "passwords may have {length} characters, fewer than the minimum of {min}"
+            ),
+            Violation::TooLong { length, max } => write!(
+                f,
+                "passwords may have {length} characters, more than the maximum of {max}"
+            ),
+            Violation::Disallowed(c) => write!(f, "passwords may have {c:?}, which isn't allowed"),
+            Violation::Missing(cc) => write!(f, "passwords may have none of {cc}"),
+            Violation::Consecutive { max } => write!(
+                f,
+                "passwords may have a character more than {max} times in a row"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin() -> Result<()> {
+        let words = Words(&[]);
+        for (domain, entries) in sites() {
+            for known in entries {
+                known.rules.validate()?;
+                let expr = Expr::parse(known.schema())?;
+                assert_eq!(known.rules.check(&words, &expr), [], "{domain}");
+                assert!((1..=LATEST).contains(&known.since), "{domain}");
+            }
+        }
+        let chase = known("secure.chase.com", LATEST).unwrap();
+        assert_eq!(
+            "[A-Za-z][0-9][!#-%+/=@~][!#-%+/-9=@-Za-z~]{13}",
+            chase.schema()
+        );
+        assert!(known("chase.com.", LATEST).is_some());
+        assert!(known("chase.com", 0).is_none());
+        assert!(known("notchase.com", LATEST).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn schema() -> Result<()> {
+        let rules = PasswordRules {
+            max_length: Some(8),
+            required: vec!["[0-9]".parse()?],
+            allowed: Some("[a-z]".parse()?),
+            ..Default::default()
+        };
+        assert_eq!("[0-9][0-9a-z]{7}", rules.schema());
+        let rules = PasswordRules {
+            min_length: Some(20),
+            ..Default::default()
+        };
+        assert_eq!("[!-~]{20}", rules.schema());
+        let rules = PasswordRules {
+            min_length: Some(9),
+            max_length: Some(8),
+            ..Default::default()
+        };
+        assert!(rules.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn passwordrules() -> Result<()> {
+        let rules: PasswordRules =
+            "minlength: 8; maxlength: 12; required: upper, lower; required: digit; \
+             allowed: [-_]; max-consecutive: 2;"
+                .parse()?;
+        assert_eq!(
+            PasswordRules {
+                min_length: Some(8),
+                max_length: Some(12),
+                required: vec!["[A-Za-z]".parse()?, "[0-9]".parse()?],
+                allowed: Some("[\\-_]".parse()?),
+                max_consecutive: Some(2),
+            },
+            rules
+        );
+        assert_eq!("[A-Za-z][0-9][\\-0-9A-Z_a-z]{10}", rules.schema());
+        let rules: PasswordRules = "Required: [abc], special; MinLength: 20".parse()?;
+        assert_eq!(rules.required, ["[!-/:-@[-`a-c{-~]".parse::<CharClass>()?]);
+        assert_eq!(Some(20), rules.min_length);
+        let rules: PasswordRules = "allowed: []], [-]".parse()?;
+        assert_eq!(Some("[\\-\\]]".parse()?), rules.allowed);
+        for bad in [
+            "minlength 8",
+            "minlength: eight",
+            "required: upper lower",
+            "required: unicode",
+            "allowed: [ab",
+            "allowed: []",
+            "colour: blue",
+            "minlength: 9; maxlength: 8",
+        ] {
+            assert!(bad.parse::<PasswordRules>().is_err(), "{bad}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn check() -> Result<()> {
+        let rules = PasswordRules {
+            min_length: Some(8),
+            max_length: Some(16),
+            required: vec!["[0-9]".parse()?],
+            allowed: Some("[A-Za-z\\-]".parse()?),
+            ..Default::default()
+        };
+        let words = Words(&["correct", "horse"]);
+        let check = |schema| rules.check(&words, &Expr::parse(schema).unwrap());
+        assert_eq!(check("[a-z]{8}[0-9]"), []);
+        assert_eq!(
+            check("[A-Za-z0-9]{20}"),
+            [
+                Violation::TooLong {
+                    length: 20,
+                    max: 16
+                },
+                Violation::Missing("[0-9]".parse()?)
+            ]
+        );
+        assert_eq!(
+            check("[:Word:]-[:word:]_[0-9]"),
+            [
+                Violation::TooLong {
+                    length: 17,
+                    max: 16
+                },
+                Violation::Disallowed('_')
+            ]
+        );
+        assert_eq!(
+            check("[:word:][0-9]{0,1}"),
+            [
+                Violation::TooShort { length: 5, min: 8 },
+                Violation::Missing("[0-9]".parse()?)
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,804 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Canonicalization of site names into the URLs that passwords are derived from.
+
+mod lookalike;
+mod namespace;
+mod psl;
+
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+pub use lookalike::lookalike;
+pub use namespace::Namespace;
+
+/// How site names are canonicalized beyond parsing them as URLs, set by the config's
+/// `canonicalization` key.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Rules {
+    /// The version of the steps below, which sets their defaults
+    #[serde(default, skip_serializing_if = "is_v1")]
+    pub version: Version,
+    /// Whether the hosts of schemes other than `https:` and the like, whose hosts are always
+    /// lowercased, are lowercased too
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lowercase: Option<bool>,
+    /// Whether a leading `www.` is stripped from hosts, unless what is left is a public suffix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strip_www: Option<bool>,
+    /// Whether the trailing dot of fully qualified hosts, like `example.com.`, is dropped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strip_trailing_dot: Option<bool>,
+    /// Whether the default ports of well-known schemes other than `https:` and the like, whose
+    /// default ports are always dropped, are dropped too, like 22 for `ssh:`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drop_default_port: Option<bool>,
+    /// Whether IP addresses are read the same in every scheme and spelling: bare IPv6 addresses
+    /// like `fe80::1` are bracketed, dotted IPv4 addresses are decimal even with leading zeros,
+    /// and IPv4 addresses are never taken for domain names, as they can be in `ssh:` URLs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip_literals: Option<bool>,
+    /// Whether the internationalized hosts of schemes other than `https:` and the like, whose
+    /// hosts are always in punycode, are converted to punycode too, rather than percent-encoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub punycode: Option<bool>,
+    /// Whether a host and port like `localhost:8080` or `host:8443/gitea`, which the URL
+    /// standard reads as a scheme and path, is read as a host and port
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_port: Option<bool>,
+    /// Whether typed identifiers like `app:com.example.android` and `wifi:HomeNetwork` are
+    /// canonicalized by their namespace rather than as URLs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespaces: Option<bool>,
+    #[serde(default)]
+    pub subdomains: Subdomains,
+    /// The hosts that hosts map to under [`Subdomains::Map`], by host or by `*.` pattern
+    /// matching the subdomains of a host, e.g. `*.corp.example.com: sso.example.com`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub map: BTreeMap<String, String>,
+    /// Groups of domains that are one site, e.g. `[amazon.com, amazon.de, amazon.co.jp]`, whose
+    /// hosts are canonicalized to the first domain's: `smile.amazon.de` to `smile.amazon.com`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub equivalent: Vec<Vec<String>>,
+    /// Whether ports given in URLs are kept; if not, all of a host's ports share its password
+    #[serde(default = "keep_port", skip_serializing_if = "is_kept")]
+    pub port: bool,
+    /// The path prefix that URLs under it are cut to, e.g. `/gitea` for
+    /// `host:8443/gitea/user/login`, so that services sharing a host can be told apart while
+    /// their pages are not; `/` drops paths altogether. Set per site.
+    #[serde(skip)]
+    pub path: Option<String>,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            version: Version::default(),
+            lowercase: None,
+            strip_www: None,
+            strip_trailing_dot: None,
+            drop_default_port: None,
+            ip_literals: None,
+            punycode: None,
+            host_port: None,
+            namespaces: None,
+            subdomains: Subdomains::default(),
+            map: BTreeMap::new(),
+            equivalent: Vec::new(),
+            port: true,
+            path: None,
+        }
+    }
+}
+
+impl From<Version> for Rules {
+    /// Returns the rules of `version`, with none of its steps set apart from it.
+    fn from(version: Version) -> Self {
+        Rules {
+            version,
+            ..Rules::default()
+        }
+    }
+}
+
+/// The version of the canonicalization steps.
+///
+/// Passwords of sites canonicalized under a given version never change, so new steps get a new
+/// version, which configs opt into; a step can also be turned on or off on its own.
+///
+/// - **v1**: hosts are lowercased, and default ports dropped, only where the URL standard does.
+/// - **v2**: all hosts are lowercased, a leading `www.` and trailing dots are stripped, and the
+///   default ports of other well-known schemes are dropped.
+/// - **v3**: IP addresses are normalized alike in every scheme and spelling.
+/// - **v4**: internationalized hosts are in punycode in every scheme, and a host and port like
+///   `localhost:8080` is not taken for a scheme and path, and typed identifiers like
+///   `wifi:HomeNetwork` are canonicalized by their namespace.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(try_from = "u32", into = "u32")]
+pub enum Version {
+    #[default]
+    V1,
+    V2,
+    V3,
+    V4,
+}
+
+impl Version {
+    pub const LATEST: Version = Version::V4;
+}
+
+/// The default ports of schemes the URL standard knows no default ports for.
+const DEFAULT_PORTS: &[(&str, u16)] = &[
+    ("git", 9418),
+    ("imap", 143),
+    ("imaps", 993),
+    ("ldap", 389),
+    ("ldaps", 636),
+    ("mysql", 3306),
+    ("pop3", 110),
+    ("pop3s", 995),
+    ("postgres", 5432),
+    ("postgresql", 5432),
+    ("rdp", 3389),
+    ("redis", 6379),
+    ("sftp", 22),
+    ("smtp", 25),
+    ("ssh", 22),
+    ("vnc", 5900),
+];
+
+fn is_v1(version: &Version) -> bool {
+    *version == Version::V1
+}
+
+fn keep_port() -> bool {
+    true
+}
+
+fn is_kept(port: &bool) -> bool {
+    *port
+}
+
+impl Rules {
+    /// Returns whether the step `step`, added in version `since`, is taken: if it is not set,
+    /// whether the rules' version has it.
+    fn step(&self, step: Option<bool>, since: Version) -> bool {
+        step.unwrap_or(self.version >= since)
+    }
+
+    /// Splits `input` into its namespace and the identifier in it, if it is a typed identifier
+    /// and the rules canonicalize those by namespace.
+    pub fn namespace<'a>(&self, input: &'a str) -> Option<(Namespace, &'a str)> {
+        Namespace::parse(input).filter(|_| self.step(self.namespaces, Version::V4))
+    }
+
+    /// Returns the `map` rule that `host` matches, as its pattern and target: its own rule if it
+    /// has one, else the longest pattern matching it.
+    pub fn map_rule(&self, host: &str) -> Option<(&str, &str)> {
+        self.map
+            .iter()
+            .filter_map(|(pattern, target)| {
+                let (suffix, rank) = match pattern.strip_prefix("*.") {
+                    Some(suffix) => (suffix, suffix.len()),
+                    None => (pattern.as_str(), usize::MAX),
+                };
+                let suffix = idna::domain_to_ascii(suffix).ok()?;
+                let matches = match rank {
+                    usize::MAX => host == suffix,
+                    _ => host
+                        .strip_suffix(suffix.as_str())
+                        .is_some_and(|sub| sub.ends_with('.')),
+                };
+                matches.then_some((rank, (pattern.as_str(), target.as_str())))
+            })
+            .max_by_key(|&(rank, _)| rank)
+            .map(|(_, rule)| rule)
+    }
+
+    /// Returns the domain of an `equivalent` group that `host` is or is under, the longest if
+    /// there are several, with the first domain of its group.
+    pub fn equivalent_domain(&self, host: &str) -> Option<(&str, &str)> {
+        self.equivalent
+            .iter()
+            .filter_map(|group| Some((group, group.first()?)))
+            .flat_map(|(group, first)| group.iter().map(move |domain| (domain, first)))
+            .filter_map(|(domain, first)| {
+                let suffix = idna::domain_to_ascii(domain).ok()?;
+                let matches = host
+                    .strip_suffix(suffix.as_str())
+                    .is_some_and(|sub| sub.is_empty() || sub.ends_with('.'));
+                matches.then_some((suffix.len(), (domain.as_str(), first.as_str())))
+            })
+            .max_by_key(|&(len, _)| len)
+            .map(|(_, domains)| domains)
+    }
+}
+
+/// What becomes of the subdomains in site hosts.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Subdomains {
+    /// Hosts are kept as given
+    #[default]
+    Keep,
+    /// Hosts are reduced to their registrable domain under the Public Suffix List, e.g.
+    /// `login.example.co.uk` to `example.co.uk`
+    Registrable,
+    /// Hosts are replaced by the config's `map` rules, and kept if none match
+    Map,
+}
+
+/// Returns the canonical URL of the site `input`, which may be a URL, a host name, or a typed
+/// identifier like `ssh:host` (see [`Namespace`]), with `username` if given, e.g.
+/// `https://me@example.com/` for `example.com` and `me`.
+pub fn canonicalize(input: &str, username: Option<&str>) -> Result<String> {
+    canonicalize_with(input, username, &Rules::default())
+}
+
+/// Returns the canonical URL of the site `input` as [`canonicalize`] does, under `rules`.
+pub fn canonicalize_with(input: &str, username: Option<&str>, rules: &Rules) -> Result<String> {
+    if let Some((namespace, value)) = rules.namespace(input) {
+        return namespace::canonicalize(namespace, value, username, rules);
+    }
+    let ips = rules.step(rules.ip_literals, Version::V3);
+    let input = match ips {
+        true => ip_literal(input),
+        false => Cow::Borrowed(input),
+    };
+    let input = input.as_ref();
+    let https = || Url::parse(format!("https://{input}").as_ref());
+    let mut url = match Url::parse(input) {
+        // A host and port, like `host:8443/gitea`, rather than a scheme and path.
+        Ok(url)
+            if rules.step(rules.host_port, Version::V4)
+                && url.cannot_be_a_base()
+                && url.path().starts_with(|c: char| c.is_ascii_digit()) =>
+        {
+            https().unwrap_or(url)
+        }
+        url => url.or_else(|_| https()).context("invalid url")?,
+    };
+    // Hosts of special schemes like `https:` are already in punycode, but others are
+    // percent-encoded as given, so `ssh://münchen.de` would differ from `ssh://xn--mnchen-3ya.de`.
+    if rules.step(rules.punycode, Version::V4)
+        && !url.is_special()
+        && let Some(host) = domain(&url, ips)
+    {
+        let host = percent_decode_str(host)
+            .decode_utf8()
+            .context("invalid host")?;
+        if !host.is_ascii() {
+            let host = idna::domain_to_ascii(&host).context("invalid host")?;
+            url.set_host(Some(&host)).context("invalid host")?;
+        }
+    }
+    if let Some(host) = domain(&url, ips) {
+        let mut host = host.to_owned();
+        if rules.step(rules.lowercase, Version::V2) {
+            host.make_ascii_lowercase();
+        }
+        if rules.step(rules.strip_trailing_dot, Version::V2) {
+            host.truncate(host.trim_end_matches('.').len());
+        }
+        if rules.step(rules.strip_www, Version::V2)
+            && let Some(rest) = host.strip_prefix("www.")
+            && psl::registrable_domain(rest).is_some()
+        {
+            host = rest.to_owned();
+        }
+        if Some(host.as_str()) != url.host_str() {
+            url.set_host(Some(&host)).context("invalid host")?;
+        }
+    }
+    if rules.step(rules.drop_default_port, Version::V2)
+        && let Some(&(_, port)) = DEFAULT_PORTS
+            .iter()
+            .find(|(scheme, _)| *scheme == url.scheme())
+        && url.port() == Some(port)
+    {
+        url.set_port(None)
+            .map_err(|_| anyhow::anyhow!("failed dropping port"))?;
+    }
+    let host = domain(&url, ips).map(|domain| match rules.subdomains {
+        Subdomains::Keep => domain,
+        Subdomains::Registrable => psl::registrable_domain(domain).unwrap_or(domain),
+        Subdomains::Map => rules.map_rule(domain).map_or(domain, |(_, target)| target),
+    });
+    if let Some(host) = host
+        && Some(host) != url.host_str()
+    {
+        let host = idna::domain_to_ascii(host).context("invalid host")?;
+        url.set_host(Some(&host)).context("invalid host")?;
+    }
+    if let Some(host) = domain(&url, ips)
+        && let Some((domain, first)) = rules.equivalent_domain(host)
+        && domain != first
+    {
+        let domain = idna::domain_to_ascii(domain).context("invalid host")?;
+        let sub = &host[..host.len() - domain.len()];
+        let host = idna::domain_to_ascii(&format!("{sub}{first}")).context("invalid host")?;
+        url.set_host(Some(&host)).context("invalid host")?;
+    }
+    if !rules.port {
+        url.set_port(None)
+            .map_err(|_| anyhow::anyhow!("failed dropping port"))?;
+    }
+    if let Some(prefix) = &rules.path {
+        let prefix = prefix.trim_end_matches('/');
+        if url
+            .path()
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        {
+            url.set_path(prefix);
+            url.set_query(None);
+            url.set_fragment(None);
+        }
+    }
+    if let Some(username) = username {
+        url.set_username(username)
+            .map_err(|_| anyhow::anyhow!("failed setting username"))?;
+    }
+    Ok(url.into())
+}
+
+/// Rewrites the IP address host of `input` as people mean it: the bare IPv6 address `fe80::1` as
+/// `[fe80::1]`, and the IPv4 address `192.168.001.010` as `192.168.1.10`, which URLs would read
+/// as octal.
+fn ip_literal(input: &str) -> Cow<'_, str> {
+    if input.parse::<Ipv6Addr>().is_ok() {
+        return Cow::Owned(format!("[{input}]"));
+    }
+    let start = input.find("://").map_or(0, |i| i + 3);
+    let end = input[start..]
+        .find(['/', '?', '#'])
+        .map_or(input.len(), |i| start + i);
+    let start = input[start..end]
+        .rfind('@')
+        .map_or(start, |i| start + i + 1);
+    let end = input[start..end].find(':').map_or(end, |i| start + i);
+    let octets: Option<Vec<u8>> = input[start..end]
+        .split('.')
+        .map(|octet| {
+            let digits =
+                (1..=3).contains(&octet.len()) && octet.bytes().all(|b| b.is_ascii_digit());
+            digits.then(|| octet.parse().ok()).flatten()
+        })
+        .collect();
+    match octets.as_deref() {
+        Some(&[a, b, c, d]) => {
+            let ip = Ipv4Addr::new(a, b, c, d);
+            Cow::Owned(format!("{}{ip}{}", &input[..start], &input[end..]))
+        }
+        _ => Cow::Borrowed(input),
+    }
+}
+
+/// Returns the domain of `url`, which with `ips` is never an IPv4 address, as the hosts of
+/// schemes like `ssh:` can be.
+fn domain(url: &Url, ips: bool) -> Option<&str> {
+    url.domain()
+        .filter(|host| !(ips && host.parse::<Ipv4Addr>().is_ok()))
+}
+
+impl TryFrom<u32> for Version {
+    type Error = String;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Version::V1),
+            2 => Ok(Version::V2),
+            3 => Ok(Version::V3),
+            4 => Ok(Version::V4),
+            _ => Err(format!("unknown canonicalization version {value}")),
+        }
+    }
+}
+
+impl From<Version> for u32 {
+    fn from(value: Version) -> Self {
+        match value {
+            Version::V1 => 1,
+            Version::V2 => 2,
+            Version::V3 => 3,
+            Version::V4 => 4,
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", u32::from(*self))
+    }
+}
+
+impl fmt::Display for Rules {
+    /// Writes the version and any steps set apart from it, e.g. `v1, strip_www on`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.version)?;
+        for (name, step) in [
+            ("lowercase", self.lowercase),
+            ("strip_www", self.strip_www),
+            ("strip_trailing_dot", self.strip_trailing_dot),
+            ("drop_default_port", self.drop_default_port),
+            ("ip_literals", self.ip_literals),
+            ("punycode", self.punycode),
+            ("host_port", self.host_port),
+            ("namespaces", self.namespaces),
+        ] {
+            if let Some(step) = step {
+                write!(f, ", {name} {}", if step { "on" } else { "off" })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Subdomains {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().ok_or(fmt::Error)?;
+        f.write_str(value.get_name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonicalize_identity() -> Result<()> {
+        let tests = [
+            "https://google.com/",
+            "mailto:me@example.com",
+            "http://localhost/",
+        ];
+        for url in tests {
+            assert_eq!(String::from(url), canonicalize(url, None)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn canonicalize_host() -> Result<()> {
+        let tests = [
+            ("https://google.com/", "google.com"),
+            ("https://iphone.local/", "iphone.local"),
+            ("https://localhost/", "localhost"),
+        ];
+        for (want, inp) in tests {
+            assert_eq!(String::from(want), canonicalize(inp, None)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn canonicalize_username() -> Result<()> {
+        let tests = [
+            ("https://test@example.com/", ("example.com", "test")),
+            (
+                "https://foo%40bar@baz.com/",
+                ("https://baz.com/", "foo@bar"),
+            ),
+        ];
+        for (want, (url, username)) in tests {
+            assert_eq!(String::from(want), canonicalize(url, Some(username))?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn canonicalize_idn() -> Result<()> {
+        let rules = Rules {
+            punycode: Some(true),
+            ..Default::default()
+        };
+        let tests = [
+            ("https://xn--mnchen-3ya.de/", "münchen.de"),
+            ("https://xn--mnchen-3ya.de/", "MÜNCHEN.DE"),
+            ("https://xn--mnchen-3ya.de/", "mu\u{308}nchen.de"),
+            ("https://xn--mnchen-3ya.de/", "xn--mnchen-3ya.de"),
+            ("https://xn--mnchen-3ya.de/", "https://XN--MNCHEN-3YA.de/"),
+            ("ssh://xn--mnchen-3ya.de", "ssh://münchen.de"),
+            ("ssh://xn--mnchen-3ya.de", "ssh://xn--mnchen-3ya.de"),
+            ("ssh://xn--mnchen-3ya.de", "ssh://M%C3%BCnchen.de"),
+            // ASCII hosts of other schemes are kept as given.
+            ("ssh://Example.com", "ssh://Example.com"),
+        ];
+        for (want, inp) in tests {
+            assert_eq!(want, canonicalize_with(inp, None, &rules)?, "{inp}");
+        }
+        assert!(canonicalize_with("ssh://a%FF.de", None, &rules).is_err());
+
+        // v1 keeps the hosts of other schemes percent-encoded, as the URL standard does.
+        let tests = [
+            ("https://xn--mnchen-3ya.de/", "münchen.de"),
+            ("smb://m%C3%BCnchen.de/share", "smb://münchen.de/share"),
+            ("ssh://a%FF.de", "ssh://a%FF.de"),
+        ];
+        for (want, inp) in tests {
+            assert_eq!(want, canonicalize(inp, None)?, "{inp}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn canonicalize_registrable() -> Result<()> {
+        let rules = Rules {
+            subdomains: Subdomains::Registrable,
+            ..Default::default()
+        };
+        let tests = [
+            ("https://example.co.uk/", "login.accounts.example.co.uk"),
+            ("https://example.co.uk/a", "https://www.example.co.uk/a"),
+            ("https://me.github.io/", "https://x.me.github.io/"),
+            ("https://co.uk/", "co.uk"),
+            ("https://localhost/", "localhost"),
+            ("https://127.0.0.1/", "127.0.0.1"),
+            ("https://xn--bcher-kva.de/", "www.bücher.de"),
+            ("mailto:me@example.com", "mailto:me@example.com"),
+        ];
+        for (want, inp) in tests {
+            assert_eq!(want, canonicalize_with(inp, None, &rules)?, "{inp}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn canonicalize_map() -> Result<()> {
+        let rules = Rules {
+            subdomains: Subdomains::Map,
+            map: [
+                ("*.corp.example.com", "sso.example.com"),
+                ("*.eu.corp.example.com", "eu.example.com"),
+                ("dev.eu.corp.example.com", "dev.example.com"),
+                ("*.bücher.de", "bücher.de"),
+            ]
+            .into_iter()
+            .map(|(pattern, target)| (pattern.into(), target.into()))
+            .collect(),
+            ..Default::default()
+        };
+        let tests = [
+            ("https://sso.example.com/", "mail.corp.example.com"),
+            ("https://sso.example.com/", "a.b.corp.example.com"),
+            ("https://corp.example.com/", "corp.example.com"),
+            ("https://eu.example.com/", "mail.eu.corp.example.com"),
+            ("https://dev.example.com/", "dev.eu.corp.example.com"),
+            ("https://xn--bcher-kva.de/", "www.bücher.de"),
+            ("https://xcorp.example.com/", "xcorp.example.com"),
+            ("ssh://sso.example.com", "ssh://git.corp.example.com"),
+        ];
+        for (want, inp) in tests {
+            assert_eq!(want, canonicalize_with(inp, None, &rules)?, "{inp}");
+        }
+        assert_eq!(
+            rules.map_rule("mail.eu.corp.example.com"),
+            Some(("*.eu.corp.example.com", "eu.example.com"))
+        );
+        assert_eq!(rules.map_rule("corp.example.com"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn canonicalize_equivalent() -> Result<()> {
+        let rules = Rules {
+            equivalent: vec![
+                vec![
+                    "amazon.com".into(),
+                    "amazon.de".into(),
+                    "amazon.co.jp".into(),
+                ],
+                vec!["bücher.de".into(), "buecher.de".into()],
+            ],
+            ..Default::default()
+        };
+        let tests = [
+            ("https://amazon.com/", "amazon.de"),
+            ("https://smile.amazon.com/", "smile.amazon.de"),
+            ("https://amazon.com/", "https://amazon.co.jp"),
+            ("https://amazon.com/", "amazon.com"),
+            ("https://xamazon.de/", "xamazon.de"),
+            ("https://www.xn--bcher-kva.de/", "www.buecher.de"),
+            ("ssh://amazon.com:2222", "ssh://amazon.de:2222"),
+        ];
+        for (want, inp) in tests {
+            assert_eq!(want, canonicalize_with(inp, None, &rules)?, "{inp}");
+        }
+        let rules = Rules {
+            subdomains: Subdomains::Registrable,
+            ..rules
+        };
+        assert_eq!(
+            "https://amazon.com/",
+            canonicalize_with("smile.amazon.co.jp", None, &rules)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn canonicalize_ip_literals() -> Result<()> {
+        let v3 = Rules {
+            version: Version::V3,
+            ..Default::default()
+        };
+        let tests = [
+            ("https://192.168.1.1/", "192.168.1.1"),
+            ("https://192.168.1.10/", "192.168.001.010"),
+            (
+                "http://admin@192.168.1.1:8080/admin",
+                "http://admin@192.168.001.001:8080/admin",
+            ),
+            ("https://[::1]/", "::1"),
+            ("https://[2001:db8::1]/", "2001:DB8:0:0::1"),
+            ("https://[2001:db8::1]:8443/", "[2001:db8:0000::1]:8443"),
+            ("ssh://192.168.1.2", "ssh://192.168.001.002:22"),
+        ];
+        for (want, inp) in tests {
+            assert_eq!(want, canonicalize_with(inp, None, &v3)?, "{inp}");
+        }
+        let v4 = Rules {
+            version: Version::V4,
+            ..Default::default()
+        };
+        assert_eq!(
+            "ssh:192.168.1.2",
+            canonicalize_with("ssh:192.168.001.002", None, &v4)?
+        );
+
+        let registrable = Rules {
+            subdomains: Subdomains::Registrable,
+            ..v3.clone()
+        };
+        assert_eq!(
+            "ssh://10.0.0.1",
+            canonicalize_with("ssh://10.0.0.1", None, &registrable)?
+        );
+
+        // v1 reads leading zeros as octal, as URLs do, and takes IPv4 hosts of `ssh:` for domains.
+        let v1 = Rules {
+            subdomains: Subdomains::Registrable,
+            ..Default::default()
+        };
+        let tests = [
+            ("https://192.168.1.8/", "192.168.001.010"),
+            ("ssh://0.1", "ssh://10.0.0.1"),
+        ];
+        for (want, inp) in tests {
+            assert_eq!(want, canonicalize_with(inp, None, &v1)?, "{inp}");
+        }
+        assert!(canonicalize_with("2001:db8::1", None, &v1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn canonicalize_port_path() -> Result<()> {
+        let v4 = Rules {
+            version: Version::V4,
+            ..Default::default()
+        };
+        let tests = [
+            (
+                "https://host:8443/gitea",
+                "https://host:8443/gitea",
+                true,
+                None,
+            ),
+            ("https://host:8443/gitea", "host:8443/gitea", true, None),
+            ("https://localhost:8080/", "localhost:8080", true, None),
+            ("https://localhost:3000/", "LOCALHOST:3000", true, None),
+            ("https://example.org/", "example.org:443", true, None),
+            ("https://host/gitea/", "host:8443/gitea/", false, None),
+            (
+                "https://host:8443/gitea",
+                "https://host:8443/gitea/user/login?next=/#top",
+                true,
+                Some("/gitea/"),
+            ),
+            (
+                "https://host:8443/grafana/d/1",
+                "host:8443/grafana/d/1",
+                true,
+                Some("/gitea"),
+            ),
+            (
+                "https://host:8443/giteax",
+                "host:8443/giteax",
+                true,
+                Some("/gitea"),
+            ),
+            ("https://host/", "host/a/b?c", true, Some("/")),
+        ];
+        for (want, inp, port, path) in tests {
+            let rules = Rules {
+                port,
+                path: path.map(Into::into),
+                ..v4.clone()
+            };
+            assert_eq!(want, canonicalize_with(inp, None, &rules)?, "{inp}");
+        }
+
+        // v1 takes a host and port for a scheme and path, as the URL standard does.
+        let tests = [
+            ("localhost:8080", "localhost:8080"),
+            ("example.org:443", "example.org:443"),
+            ("foo.com:22", "foo.com:22"),
+            ("https://host:8443/gitea", "https://host:8443/gitea"),
+        ];
+        for (want, inp) in tests {
+            assert_eq!(want, canonicalize(inp, None)?, "{inp}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn canonicalize_versions() -> Result<()> {
+        let v2 = Rules {
+            version: Version::V2,
+            ..Default::default()
+        };
+        let tests = [
+            (
+                "https://www.example.com/",
+                "https://example.com/",
+                "www.example.com",
+            ),
+            (
+                "https://www.example.com./",
+                "https://example.com/",
+                "WWW.Example.com.",
+            ),
+            ("https://www.co.uk/", "https://www.co.uk/", "www.co.uk"),
+            (
+                "ssh://Git.Example.com:22",
+                "ssh://git.example.com",
+                "ssh://Git.Example.com:22",
+            ),
+            ("ssh://host:2222", "ssh://host:2222", "ssh://host:2222"),
+            ("https://a.com/", "https://a.com/", "https://a.com:443"),
+        ];
+        for (want_v1, want_v2, inp) in tests {
+            assert_eq!(want_v1, canonicalize(inp, None)?, "{inp}");
+            assert_eq!(want_v2, canonicalize_with(inp, None, &v2)?, "{inp}");
+        }
+        let rules = Rules {
+            strip_www: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(
+            "https://a.com./",
+            canonicalize_with("www.a.com.", None, &rules)?
+        );
+        let rules = Rules {
+            strip_www: Some(false),
+            ..v2
+        };
+        assert_eq!(
+            "https://www.a.com/",
+            canonicalize_with("www.a.com.", None, &rules)?
+        );
+        assert_eq!("v2, strip_www off", rules.to_string());
+        Ok(())
+    }
+}
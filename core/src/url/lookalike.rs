@@ -0,0 +1,193 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Detection of lookalike domains, like `paypa1.com` or `pаypal.com` (with a Cyrillic `а`) for
+//! `paypal.com`.
+//!
+//! A phishing site gets a password of its own, which is useless to the phisher but hides the
+//! attack from someone who then wonders why their password does not work; warning first lets
+//! them notice.
+
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
+use url::Url;
+
+use super::psl;
+
+/// Characters that look like ASCII letters, with the letters.
+const CONFUSABLES: &[(char, char)] = &[
+    // Cyrillic
+    ('а', 'a'),
+    ('в', 'b'),
+    ('е', 'e'),
+    ('һ', 'h'),
+    ('і', 'i'),
+    ('ј', 'j'),
+    ('к', 'k'),
+    ('м', 'm'),
+    ('н', 'h'),
+    ('о', 'o'),
+    ('р', 'p'),
+    ('с', 'c'),
+    ('т', 't'),
+    ('у', 'y'),
+    ('х', 'x'),
+    ('ѕ', 's'),
+    ('ԁ', 'd'),
+    ('ԛ', 'q'),
+    ('ԝ', 'w'),
+    // Greek
+    ('α', 'a'),
+    ('ε', 'e'),
+    ('ι', 'i'),
+    ('κ', 'k'),
+    ('ν', 'v'),
+    ('ο', 'o'),
+    ('ρ', 'p'),
+    ('τ', 't'),
+    ('υ', 'u'),
+    ('χ', 'x'),
+    // Latin
+    ('ı', 'i'),
+    ('ɡ', 'g'),
+    ('ǀ', 'l'),
+    // Digits
+    ('0', 'o'),
+    ('1', 'l'),
+    ('3', 'e'),
+    ('5', 's'),
+];
+
+/// Letter pairs that look like one letter.
+const PAIRS: &[(&str, &str)] = &[("rn", "m"), ("vv", "w")];
+
+/// Returns whether the sites of the canonical URLs `a` and `b` have different domains that look
+/// alike: the same but for homoglyphs, like `раураl.com` and `paypal.com`, or for one typo, like
+/// `paypa.com`. Subdomains of one registrable domain belong to one owner, so never look alike.
+pub fn lookalike(a: &str, b: &str) -> bool {
+    let (Some(a), Some(b)) = (registrable(a), registrable(b)) else {
+        return false;
+    };
+    if a == b {
+        return false;
+    }
+    let (a, b) = (skeleton(&a), skeleton(&b));
+    if a == b {
+        return true;
+    }
+    // A typo in a short name is another name altogether, like `ab.com` and `ac.com`.
+    let name = |domain: &str| {
+        domain
+            .split('.')
+            .next()
+            .map_or(0, |name| name.chars().count())
+    };
+    name(&a).min(name(&b)) >= 5 && distance(&a, &b) <= 1
+}
+
+/// Returns the registrable domain of the canonical URL `url`, or its host if it has none.
+fn registrable(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let host = url.domain()?;
+    let host = psl::registrable_domain(host).unwrap_or(host);
+    Some(host.trim_end_matches('.').to_owned())
+}
+
+/// Returns the domain `domain` as it looks: in Unicode, without accents, and with homoglyphs
+/// replaced by the ASCII letters they look like.
+fn skeleton(domain: &str) -> String {
+    let (domain, _) = idna::domain_to_unicode(domain);
+    let mut skeleton: String = domain
+        .nfkd()
+        .filter(|&c| !is_combining_mark(c))
+        .flat_map(char::to_lowercase)
+        .map(|c| {
+            CONFUSABLES
+                .iter()
+                .find(|&&(confusable, _)| confusable == c)
+                .map_or(c, |&(_, letter)| letter)
+        })
+        .collect();
+    for (pair, letter) in PAIRS {
+        skeleton = skeleton.replace(pair, letter);
+    }
+    skeleton
+}
+
+/// Returns the number of insertions, deletions, substitutions, and transpositions of adjacent
+/// characters that turn `a` into `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    // The distances from prefixes of `a` to the prefixes of `b`, for the last two rows.
+    let mut before: Vec<usize> = Vec::new();
+    let mut last: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (last[j] + 1).min(row[j - 1] + 1).min(last[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(before[j - 2] + 1);
+            }
+        }
+        before = std::mem::replace(&mut last, row);
+    }
+    last[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookalikes() {
+        let tests = [
+            ("https://paypa1.com/", true),
+            ("https://xn--pypal-4ve.com/", true),
+            ("https://paypal.co/", true),
+            ("https://paypai.com/", true),
+            ("https://payapl.com/", true),
+            ("https://pàypal.com/", true),
+            ("https://paypal.com/", false),
+            ("https://www.paypal.com/", false),
+            ("https://login.paypal.com/", false),
+            ("https://example.com/", false),
+            ("https://paypal-login.com/", false),
+            ("mailto:me@paypal.com", false),
+        ];
+        for (url, want) in tests {
+            assert_eq!(lookalike(url, "https://paypal.com/"), want, "{url}");
+        }
+        assert!(lookalike(
+            "https://rnicrosoft.com/",
+            "https://microsoft.com/"
+        ));
+        assert!(!lookalike("https://ab.com/", "https://ac.com/"));
+    }
+
+    #[test]
+    fn distances() {
+        let tests = [
+            ("", "", 0),
+            ("abc", "abc", 0),
+            ("abc", "abd", 1),
+            ("abc", "ab", 1),
+            ("abc", "acb", 1),
+            ("abc", "cba", 2),
+            ("kitten", "sitting", 3),
+        ];
+        for (a, b, want) in tests {
+            assert_eq!(distance(a, b), want, "{a} {b}");
+        }
+    }
+}
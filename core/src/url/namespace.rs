@@ -0,0 +1,250 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed identifiers for credentials that are not websites', like `app:com.example.android`,
+//! `ssh:host22`, and `wifi:HomeNetwork`.
+//!
+//! Each canonicalizes to `NAMESPACE:[USERNAME@]VALUE`, with the username percent-encoded. The
+//! namespace is part of what is salted, and no URL canonicalizes to that form, so identifiers
+//! never share passwords with websites or with each other's namespaces.
+
+use std::fmt;
+
+use anyhow::{Context, Result, bail, ensure};
+use clap::ValueEnum;
+use percent_encoding::{
+    AsciiSet, CONTROLS, NON_ALPHANUMERIC, percent_decode_str, utf8_percent_encode,
+};
+
+use super::{Rules, Subdomains, Version, canonicalize_with};
+
+/// Usernames keep only the characters that are unreserved in URLs.
+const USERNAME: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// SSIDs are verbatim but for the characters that would make them ambiguous.
+const SSID: &AsciiSet = &CONTROLS.add(b'%').add(b'@');
+
+/// The kinds of typed identifiers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum Namespace {
+    /// A mobile app, by its Android package name or iOS bundle ID, e.g. `app:com.example.android`
+    App,
+    /// An SSH server, by its host and port, e.g. `ssh:host22` or `ssh:git@example.com:2222`
+    Ssh,
+    /// A Wi-Fi network, by its SSID, e.g. `wifi:HomeNetwork`
+    Wifi,
+}
+
+impl Namespace {
+    /// Splits `input` into its namespace and the identifier in it, if it is a typed identifier.
+    /// `ssh://host` is a URL, not an identifier.
+    pub fn parse(input: &str) -> Option<(Namespace, &str)> {
+        let (prefix, value) = input.split_once(':')?;
+        let namespace = Namespace::from_str(prefix, true).ok()?;
+        if value.starts_with("//") {
+            return None;
+        }
+        Some((namespace, value))
+    }
+}
+
+/// Returns the canonical form of the identifier `value` in `namespace`, for `username` if given.
+pub(super) fn canonicalize(
+    namespace: Namespace,
+    value: &str,
+    username: Option<&str>,
+    rules: &Rules,
+) -> Result<String> {
+    let (given, value) = match namespace {
+        Namespace::Ssh => match value.rsplit_once('@') {
+            Some((username, host)) => (Some(username), host),
+            None => (None, value),
+        },
+        _ => (None, value),
+    };
+    let value = match namespace {
+        Namespace::App => app(value, rules)?,
+        Namespace::Ssh => ssh(value, rules)?,
+        Namespace::Wifi => wifi(value)?,
+    };
+    let given = given
+        .map(|username| percent_decode_str(username).decode_utf8())
+        .transpose()
+        .context("invalid username")?;
+    Ok(match username.or(given.as_deref()) {
+        Some(username) => {
+            let username = utf8_percent_encode(username, USERNAME);
+            format!("{namespace}:{username}@{value}")
+        }
+        None => format!("{namespace}:{value}"),
+    })
+}
+
+fn app(id: &str, rules: &Rules) -> Result<String> {
+    let segments = id.split('.');
+    ensure!(
+        segments.clone().count() >= 2
+            && segments.clone().all(|segment| {
+                !segment.is_empty()
+                    && segment
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            }),
+        "invalid app identifier {id:?}; expected a package name or bundle ID like com.example.app"
+    );
+    let mut id = id.to_owned();
+    if rules.step(rules.lowercase, Version::V2) {
+        id.make_ascii_lowercase();
+    }
+    Ok(id)
+}
+
+/// Canonicalizes the host and port as those of an `ssh://` URL, under the host steps of `rules`
+/// but none of its site-specific ones or its equivalent domains.
+fn ssh(host: &str, rules: &Rules) -> Result<String> {
+    let rules = Rules {
+        subdomains: Subdomains::Keep,
+        equivalent: Vec::new(),
+        port: true,
+        path: None,
+        ..rules.clone()
+    };
+    let url = canonicalize_with(&format!("ssh://{host}"), None, &rules)
+        .with_context(|| format!("invalid ssh host {host:?}"))?;
+    let host = url.strip_prefix("ssh://").unwrap_or(&url);
+    if host.is_empty() || host.contains(['/', '?', '#']) {
+        bail!("invalid ssh host {host:?}; expected a host and optional port like host:2222");
+    }
+    Ok(host.to_owned())
+}
+
+fn wifi(ssid: &str) -> Result<String> {
+    ensure!(
+        (1..=32).contains(&ssid.len()),
+        "invalid SSID {ssid:?}; SSIDs are 1 to 32 bytes"
+    );
+    Ok(utf8_percent_encode(ssid, SSID).to_string())
+}
+
+impl fmt::Display for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().ok_or(fmt::Error)?;
+        f.write_str(value.get_name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::url::canonicalize;
+
+    /// v1 with identifiers canonicalized by namespace, and none of the other steps.
+    fn namespaces() -> Rules {
+        Rules {
+            namespaces: Some(true),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Namespace::parse("app:com.example"),
+            Some((Namespace::App, "com.example"))
+        );
+        assert_eq!(Namespace::parse("SSH:host"), Some((Namespace::Ssh, "host")));
+        assert_eq!(Namespace::parse("ssh://host"), None);
+        assert_eq!(Namespace::parse("mailto:me@example.com"), None);
+        assert_eq!(Namespace::parse("example.com"), None);
+    }
+
+    #[test]
+    fn identifiers() -> Result<()> {
+        let tests = [
+            ("app:com.example.android", "app:com.example.android", None),
+            ("app:com.Example.App", "app:com.Example.App", None),
+            (
+                "app:me%40x.com@com.example",
+                "app:com.example",
+                Some("me@x.com"),
+            ),
+            ("ssh:host22", "ssh:host22", None),
+            ("ssh:git@example.com:2222", "ssh:git@example.com:2222", None),
+            ("ssh:me@example.com", "ssh:git@example.com", Some("me")),
+            ("ssh:a%40b@host", "ssh:a%40b@host", None),
+            ("wifi:HomeNetwork", "wifi:HomeNetwork", None),
+            ("wifi:Caf%40 Net", "wifi:Caf@ Net", None),
+            ("wifi:me@Home", "wifi:Home", Some("me")),
+        ];
+        let rules = namespaces();
+        for (want, inp, username) in tests {
+            assert_eq!(want, canonicalize_with(inp, username, &rules)?, "{inp}");
+        }
+        for inp in [
+            "app:example",
+            "app:com..example",
+            "app:com.ex ample",
+            "ssh:host/path",
+            "ssh:",
+            "wifi:",
+            "wifi:0123456789abcdef0123456789abcdef0",
+        ] {
+            assert!(canonicalize_with(inp, None, &rules).is_err(), "{inp}");
+        }
+
+        let v2 = Rules {
+            version: Version::V2,
+            ..namespaces()
+        };
+        let tests = [
+            ("app:com.example.app", "APP:com.Example.App"),
+            ("ssh:example.com", "ssh:Example.com:22"),
+            ("wifi:HomeNetwork", "wifi:HomeNetwork"),
+        ];
+        for (want, inp) in tests {
+            assert_eq!(want, canonicalize_with(inp, None, &v2)?, "{inp}");
+        }
+        let v4 = Rules {
+            version: Version::V4,
+            ..Default::default()
+        };
+        assert_eq!(
+            "ssh:xn--mnchen-3ya.de",
+            canonicalize_with("ssh:münchen.de:22", None, &v4)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn v1_urls() -> Result<()> {
+        // Before v4, identifiers are URLs like any other, so their passwords don't change.
+        for inp in [
+            "wifi:x@y",
+            "wifi:a%b",
+            "app:foo",
+            "app:com.example/path",
+            "ssh:a/b",
+            "ssh:host?x",
+            "ssh:Host:22",
+        ] {
+            assert_eq!(inp, canonicalize(inp, None)?, "{inp}");
+        }
+        assert!(canonicalize("wifi:Home", Some("me")).is_err());
+        Ok(())
+    }
+}
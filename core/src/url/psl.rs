@@ -0,0 +1,133 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The Public Suffix List, from an embedded snapshot.
+//!
+//! The snapshot is pinned: sites canonicalized to their registrable domains derive from it, so
+//! updating it can change their passwords, and is done only with a new canonicalization version.
+
+use std::{collections::HashSet, sync::OnceLock};
+
+/// The snapshot, dated 2023-02-09, under the Mozilla Public License 2.0.
+const LIST: &str = include_str!("public_suffix_list.dat");
+
+/// The parsed rules, with internationalized names in punycode, as hosts in URLs are.
+struct Rules {
+    /// Suffixes that are public, e.g. `co.uk`
+    suffixes: HashSet<String>,
+    /// Suffixes all of whose children are public, e.g. `ck` for `*.ck`
+    wildcards: HashSet<String>,
+    /// Exceptions to wildcards, e.g. `www.ck` for `!www.ck`
+    exceptions: HashSet<String>,
+}
+
+fn rules() -> &'static Rules {
+    static RULES: OnceLock<Rules> = OnceLock::new();
+    RULES.get_or_init(|| {
+        let mut rules = Rules {
+            suffixes: HashSet::new(),
+            wildcards: HashSet::new(),
+            exceptions: HashSet::new(),
+        };
+        for line in LIST.lines() {
+            let Some(rule) = line.split_whitespace().next() else {
+                continue;
+            };
+            if rule.starts_with("//") {
+                continue;
+            }
+            let (set, rule) = if let Some(rule) = rule.strip_prefix('!') {
+                (&mut rules.exceptions, rule)
+            } else if let Some(rule) = rule.strip_prefix("*.") {
+                (&mut rules.wildcards, rule)
+            } else {
+                (&mut rules.suffixes, rule)
+            };
+            if let Ok(rule) = idna::domain_to_ascii(rule) {
+                set.insert(rule);
+            }
+        }
+        rules
+    })
+}
+
+/// Returns the registrable domain of `host`, its public suffix and one more label, e.g.
+/// `example.co.uk` for `login.example.co.uk`, or `None` if `host` is itself a public suffix.
+/// Hosts are expected in lowercase punycode, as URLs have them.
+pub(super) fn registrable_domain(host: &str) -> Option<&str> {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    let rules = rules();
+    // Each suffix of the host, shortest first, with its number of labels.
+    let suffixes = host
+        .char_indices()
+        .filter(|&(_, c)| c == '.')
+        .map(|(i, _)| &host[i + 1..])
+        .rev()
+        .chain([host])
+        .zip(1..);
+    // The labels of the public suffix; without a matching rule, it is the last label.
+    let mut public = 1;
+    let mut parent = None;
+    for (suffix, labels) in suffixes {
+        if rules.exceptions.contains(suffix) {
+            public = labels - 1;
+            break;
+        }
+        if rules.suffixes.contains(suffix) || parent.is_some_and(|p| rules.wildcards.contains(p)) {
+            public = labels;
+        }
+        parent = Some(suffix);
+    }
+    let start = host
+        .char_indices()
+        .filter(|&(_, c)| c == '.')
+        .map(|(i, _)| i + 1)
+        .rev()
+        .nth(public)
+        .unwrap_or(0);
+    let domain = &host[start..];
+    (domain.split('.').count() > public).then_some(domain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registrable_domains() {
+        let tests = [
+            ("login.accounts.example.co.uk", Some("example.co.uk")),
+            ("example.co.uk", Some("example.co.uk")),
+            ("www.example.com", Some("example.com")),
+            ("example.com.", Some("example.com")),
+            ("co.uk", None),
+            ("com", None),
+            ("localhost", None),
+            ("a.b.localhost", Some("b.localhost")),
+            // Private domains count too, so tenants stay distinct.
+            ("me.github.io", Some("me.github.io")),
+            ("x.me.github.io", Some("me.github.io")),
+            // Wildcards and their exceptions.
+            ("a.b.ck", Some("a.b.ck")),
+            ("b.ck", None),
+            ("www.ck", Some("www.ck")),
+            ("a.www.ck", Some("www.ck")),
+            // 公司.cn
+            ("a.b.xn--55qx5d.cn", Some("b.xn--55qx5d.cn")),
+        ];
+        for (host, want) in tests {
+            assert_eq!(registrable_domain(host), want, "{host}");
+        }
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! JavaScript bindings to the derivation, with the `wasm` feature, for browser extensions and
+//! offline pages that need the same passwords as the CLI.
+//!
+//! ```text
+//! cargo build --lib --release --target wasm32-unknown-unknown --no-default-features --features wasm
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/onepass.wasm
+//! ```
+//!
+//! `--no-default-features` leaves out the EFF word list, so pages with `[:word:]` schemas pass its
+//! published file to `loadWordlist` first, or build with `--features wasm,eff-wordlist`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    derive::{DerivationVersion, KdfConfig, Site, site_password},
+    randexp::{Expr, Quantifiable, Words},
+    url,
+    wordlist::Wordlist,
+};
+
+/// Derives the password of `site` (a URL or host name) for `master`, matching `schema`, for the
+/// account `username` if given, with derivation version `version` and the default KDF. `version`
+/// defaults to 2, not the latest, so that a new derivation version doesn't change the passwords
+/// of pages that leave it out. `site` is canonicalized under the canonicalization version
+/// `canonicalization`, the config's `canonicalization.version`, or by default v1, as in configs
+/// without one.
+#[wasm_bindgen]
+pub fn generate(
+    master: &str,
+    site: &str,
+    schema: &str,
+    increment: u32,
+    username: Option<String>,
+    version: Option<u32>,
+    canonicalization: Option<u32>,
+) -> Result<String, JsError> {
+    let version = match version {
+        Some(version) => DerivationVersion::try_from(version).map_err(|e| JsError::new(&e))?,
+        None => DerivationVersion::V2,
+    };
+    let url = canonicalize(site, username.clone(), canonicalization)?;
+    let site = Site {
+        url: &url,
+        username: username.as_deref(),
+        increment,
+        schema,
+        ..Site::new(version)
+    };
+    let password = site_password(
+        master,
+        &site,
+        &KdfConfig::default(),
+        &Words(Wordlist::EffLarge.words()),
+    )
+    .map_err(js_error)?;
+    Ok(password.to_string())
+}
+
+/// Returns the canonical URL that `site` is derived from, with `username` in it if given, under
+/// the canonicalization version `canonicalization` (default: 1).
+#[wasm_bindgen]
+pub fn canonicalize(
+    site: &str,
+    username: Option<String>,
+    canonicalization: Option<u32>,
+) -> Result<String, JsError> {
+    let version =
+        url::Version::try_from(canonicalization.unwrap_or(1)).map_err(|e| JsError::new(&e))?;
+    url::canonicalize_with(site, username.as_deref(), &version.into()).map_err(js_error)
+}
+
+/// Returns the bits of entropy of passwords matching `schema`, rounded up.
+#[wasm_bindgen]
+pub fn entropy(schema: &str) -> Result<u32, JsError> {
+    let expr = Expr::parse(schema).map_err(js_error)?;
+    Ok(Words(Wordlist::EffLarge.words()).size(&expr).bits())
+}
+
+/// Loads the EFF's large word list from `contents`, its published file, for builds without the
+/// `eff-wordlist` feature; builds with it only check `contents`.
+#[wasm_bindgen(js_name = loadWordlist)]
+pub fn load_wordlist(contents: &str) -> Result<(), JsError> {
+    crate::wordlist::load_eff_large(contents).map_err(js_error)
+}
+
+fn js_error(e: anyhow::Error) -> JsError {
+    JsError::new(&format!("{e:#}"))
+}
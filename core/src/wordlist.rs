@@ -0,0 +1,320 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The word lists built into onepass, selectable by name for the `[:word:]` and `[:Word:]`
+//! schema classes.
+//!
+//! Each list is part of the passwords derived from it, so a list's words never change once it is
+//! built in; different words make a new list with a new name. The BIP39 lists in languages other
+//! than English are built in with cargo features of their languages' names, or `all-languages`;
+//! there are no Diceware lists in other languages.
+//!
+//! The EFF's large list is built in with the `eff-wordlist` feature, on by default. Builds without
+//! it, for targets where its size matters, load its published file at runtime with
+//! [`load_eff_large`] instead, or pass their own words to [`Words`](crate::randexp::Words). The
+//! EFF's short lists and Reinhold's Diceware list are smaller and always built in, from published
+//! files vendored with their checksums pinned here.
+
+use std::{fmt, sync::OnceLock};
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "eff-wordlist")]
+use crate::EFF_WORDLIST;
+use crate::randexp::{CharClass, Expr, WordList};
+use crate::{DICEWARE_WORDLIST, EFF_SHORT_2_WORDLIST, EFF_SHORT_WORDLIST};
+
+/// The SHA-256 checksum, in hex, of the EFF's published large list, `eff_large_wordlist.txt`.
+pub const EFF_LARGE_SHA256: &str =
+    "addd35536511597a02fa0a9ff1e5284677b8883b83e986e43f15a3db996b903e";
+
+/// The SHA-256 checksum, in hex, of the EFF's first short list, `eff_short_wordlist_1.txt`.
+pub const EFF_SHORT_SHA256: &str =
+    "8f5ca830b8bffb6fe39c9736c024a00a6a6411adb3f83a9be8bfeeb6e067ae69";
+
+/// The SHA-256 checksum, in hex, of the EFF's second short list, `eff_short_wordlist_2_0.txt`.
+pub const EFF_SHORT_2_SHA256: &str =
+    "22b45c52e0bd0bbf03aa522240b111eb4c7c0c1d86c4e518e1be2a7eb2a625e4";
+
+/// The SHA-256 checksum, in hex, of Reinhold's signed Diceware list, `diceware.wordlist.asc`.
+pub const DICEWARE_SHA256: &str =
+    "3cd6164a99e95381f8620aec782a933545bcd5833fa331d267a6829f6665256e";
+
+/// The EFF's list, once loaded by [`load_eff_large`].
+#[cfg(not(feature = "eff-wordlist"))]
+static EFF_LARGE: OnceLock<Box<[&'static str]>> = OnceLock::new();
+
+/// A built-in word list.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Wordlist {
+    /// The EFF's large list: 7776 words for five dice, about 12.9 bits each
+    #[default]
+    EffLarge,
+    /// The EFF's first short list: 1296 short words for four dice, about 10.3 bits each
+    EffShort,
+    /// The EFF's second short list: 1296 longer words for four dice, about 10.3 bits each, unique
+    /// in their first three letters
+    #[serde(rename = "eff-short-2")]
+    #[value(name = "eff-short-2")]
+    EffShort2,
+    /// Reinhold's original Diceware list: 7776 words, numbers, and symbols for five dice, about
+    /// 12.9 bits each
+    Diceware,
+    /// The BIP39 English list: 2048 short words, about 11 bits each, unique in their first four
+    /// letters
+    Bip39,
+    /// The BIP39 Czech list, with the `czech` feature
+    #[cfg(feature = "czech")]
+    Bip39Czech,
+    /// The BIP39 French list, with the `french` feature
+    #[cfg(feature = "french")]
+    Bip39French,
+    /// The BIP39 Italian list, with the `italian` feature
+    #[cfg(feature = "italian")]
+    Bip39Italian,
+    /// The BIP39 Portuguese list, with the `portuguese` feature
+    #[cfg(feature = "portuguese")]
+    Bip39Portuguese,
+    /// The BIP39 Spanish list, with the `spanish` feature
+    #[cfg(feature = "spanish")]
+    Bip39Spanish,
+}
+
+impl Wordlist {
+    /// Returns the words of the list, distinct and in order. Without the `eff-wordlist` feature,
+    /// `eff-large` has no words until [`load_eff_large`] loads them.
+    pub fn words(self) -> &'static [&'static str] {
+        match self {
+            #[cfg(feature = "eff-wordlist")]
+            Wordlist::EffLarge => EFF_WORDLIST,
+            #[cfg(not(feature = "eff-wordlist"))]
+            Wordlist::EffLarge => EFF_LARGE.get().map_or(&[], |words| words),
+            Wordlist::EffShort => EFF_SHORT_WORDLIST,
+            Wordlist::EffShort2 => EFF_SHORT_2_WORDLIST,
+            Wordlist::Diceware => DICEWARE_WORDLIST,
+            Wordlist::Bip39 => bip39::Language::English.word_list(),
+            #[cfg(feature = "czech")]
+            Wordlist::Bip39Czech => bip39::Language::Czech.word_list(),
+            #[cfg(feature = "french")]
+            Wordlist::Bip39French => bip39::Language::French.word_list(),
+            #[cfg(feature = "italian")]
+            Wordlist::Bip39Italian => bip39::Language::Italian.word_list(),
+            #[cfg(feature = "portuguese")]
+            Wordlist::Bip39Portuguese => bip39::Language::Portuguese.word_list(),
+            #[cfg(feature = "spanish")]
+            Wordlist::Bip39Spanish => bip39::Language::Spanish.word_list(),
+        }
+    }
+}
+
+/// Loads the EFF's large list from `contents`, its published file with a dice roll before each
+/// word, for [`Wordlist::EffLarge`] in builds without the `eff-wordlist` feature. Fails unless
+/// `contents` has the file's pinned checksum, [`EFF_LARGE_SHA256`], so its words are the ones
+/// built in elsewhere. Builds with the feature only check the checksum.
+pub fn load_eff_large(contents: &str) -> Result<()> {
+    let sha256 = format!("{:x}", Sha256::digest(contents));
+    if sha256 != EFF_LARGE_SHA256 {
+        anyhow::bail!("word list has SHA-256 checksum {sha256}, not {EFF_LARGE_SHA256}");
+    }
+    #[cfg(not(feature = "eff-wordlist"))]
+    if EFF_LARGE.get().is_none() {
+        use anyhow::Context;
+        let contents: &'static str = String::from(contents).leak();
+        let words = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(line.split('\t').nth(1).context("invalid word list")?.trim()))
+            .collect::<Result<_>>()?;
+        let _ = EFF_LARGE.set(words);
+    }
+    Ok(())
+}
+
+/// Restrictions on the words a site's passwords draw from, e.g. to fit its passphrases into a
+/// length-limited field without switching lists. A filtered list is another list, so changing a
+/// site's filter changes the passwords with words in them.
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct WordFilter {
+    /// The most characters a word may have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_len: Option<usize>,
+    /// A character class, like `[a-z]`, that holds every character of a word
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chars: Option<String>,
+}
+
+impl WordFilter {
+    /// Returns whether the filter keeps every word.
+    pub fn is_empty(&self) -> bool {
+        self.max_len.is_none() && self.chars.is_none()
+    }
+
+    /// Returns the words of `list` that pass the filter, in the list's order. The list is only
+    /// filtered once a schema draws from it.
+    pub fn apply<'a, L: WordList + ?Sized>(&self, list: &'a L) -> Result<Filtered<'a, L>> {
+        let chars = match self.chars.as_deref().map(Expr::parse).transpose()? {
+            Some(Expr::CharClass(chars)) => Some(chars),
+            Some(_) => anyhow::bail!("word characters must be a character class, like [a-z]"),
+            None => None,
+        };
+        Ok(Filtered {
+            list,
+            max_len: self.max_len,
+            chars,
+            index: OnceLock::new(),
+        })
+    }
+}
+
+impl fmt::Display for WordFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(max_len) = self.max_len {
+            parts.push(format!("at most {max_len} characters"));
+        }
+        if let Some(chars) = &self.chars {
+            parts.push(format!("only {chars}"));
+        }
+        f.write_str(&parts.join(", "))
+    }
+}
+
+/// The words of a list that pass a [`WordFilter`].
+pub struct Filtered<'a, L: ?Sized> {
+    list: &'a L,
+    max_len: Option<usize>,
+    chars: Option<CharClass>,
+    /// The indices in `list` of the words passing the filter, unless it keeps every word
+    index: OnceLock<Option<Box<[usize]>>>,
+}
+
+impl<L: WordList + ?Sized> Filtered<'_, L> {
+    fn index(&self) -> Option<&[usize]> {
+        let index = self.index.get_or_init(|| {
+            if self.max_len.is_none() && self.chars.is_none() {
+                return None;
+            }
+            let passes = |word: &str| {
+                self.max_len.is_none_or(|max| word.chars().count() <= max)
+                    && self
+                        .chars
+                        .as_ref()
+                        .is_none_or(|cc| word.chars().all(|c| cc.contains(c)))
+            };
+            let index = (0..self.list.len()).filter(|&i| passes(self.list.word(i)));
+            Some(index.collect())
+        });
+        index.as_deref()
+    }
+}
+
+impl<L: WordList + ?Sized> WordList for Filtered<'_, L> {
+    fn len(&self) -> usize {
+        self.index().map_or_else(|| self.list.len(), <[usize]>::len)
+    }
+
+    fn word(&self, index: usize) -> &str {
+        match self.index() {
+            Some(indices) => self.list.word(indices[index]),
+            None => self.list.word(index),
+        }
+    }
+}
+
+impl fmt::Display for Wordlist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().ok_or(fmt::Error)?;
+        f.write_str(value.get_name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn lists() {
+        load_eff_large(include_str!("../eff_large_wordlist.txt")).unwrap();
+        for list in Wordlist::value_variants() {
+            let words = list.words();
+            let len = match list {
+                Wordlist::EffLarge | Wordlist::Diceware => 7776,
+                Wordlist::EffShort | Wordlist::EffShort2 => 1296,
+                _ => 2048,
+            };
+            assert_eq!(words.len(), len, "{list}");
+            assert_eq!(words.iter().collect::<HashSet<_>>().len(), len, "{list}");
+        }
+        assert_eq!(Wordlist::Bip39.words()[0], "abandon");
+        assert_eq!(Wordlist::EffShort.words()[0], "acid");
+        assert_eq!(Wordlist::EffShort2.words()[0], "aardvark");
+        assert_eq!(Wordlist::Diceware.words()[..3], ["a", "a&p", "a's"]);
+        assert_eq!(Wordlist::EffLarge.to_string(), "eff-large");
+        assert_eq!(Wordlist::EffShort2.to_string(), "eff-short-2");
+    }
+
+    #[test]
+    fn checksums() {
+        let sha256 = |contents: &str| format!("{:x}", Sha256::digest(contents));
+        let short = include_str!("../eff_short_wordlist_1.txt");
+        assert_eq!(sha256(short), EFF_SHORT_SHA256);
+        let short_2 = include_str!("../eff_short_wordlist_2_0.txt");
+        assert_eq!(sha256(short_2), EFF_SHORT_2_SHA256);
+        let diceware = include_str!("../diceware.wordlist.asc");
+        assert_eq!(sha256(diceware), DICEWARE_SHA256);
+    }
+
+    #[test]
+    fn filter() {
+        let words = ["a", "bb", "ccc", "Dd", "é"];
+        let words = |filter: WordFilter| {
+            let filtered = filter.apply(&words).unwrap();
+            (0..filtered.len())
+                .map(|i| filtered.word(i).to_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(words(WordFilter::default()), ["a", "bb", "ccc", "Dd", "é"]);
+        let max_len = WordFilter {
+            max_len: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(words(max_len.clone()), ["a", "bb", "Dd", "é"]);
+        let chars = WordFilter {
+            chars: Some("[a-z]".into()),
+            ..max_len
+        };
+        assert_eq!(words(chars.clone()), ["a", "bb"]);
+        assert_eq!(chars.to_string(), "at most 2 characters, only [a-z]");
+        let literal = WordFilter {
+            chars: Some("abc".into()),
+            ..Default::default()
+        };
+        assert!(literal.apply(&["a"]).is_err());
+    }
+
+    #[test]
+    fn load() {
+        let eff = include_str!("../eff_large_wordlist.txt");
+        load_eff_large(eff).unwrap();
+        assert_eq!(Wordlist::EffLarge.words().len(), 7776);
+        assert_eq!(Wordlist::EffLarge.words()[0], "abacus");
+        assert!(load_eff_large(&eff[1..]).is_err());
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Spelling secrets out for screen readers and speech synthesis, for `--a11y`: one character per
+//! line, with its case, kind, or name said explicitly, e.g. `capital Q`, `digit 7`, `tilde`.
+
+/// Returns `secret` spelled out, one line per character, after a line giving its length.
+pub(crate) fn spell(secret: &str) -> String {
+    let count = secret.chars().count();
+    let mut spelled = format!("{count} characters:\n");
+    for c in secret.chars() {
+        spelled += &say(c);
+        spelled.push('\n');
+    }
+    spelled
+}
+
+/// Returns how to say `c`.
+fn say(c: char) -> String {
+    if c.is_ascii_digit() {
+        return format!("digit {c}");
+    }
+    if c.is_uppercase() {
+        return format!("capital {c}");
+    }
+    if c.is_lowercase() {
+        return format!("lowercase {c}");
+    }
+    let name = match c {
+        ' ' => "space",
+        '!' => "exclamation mark",
+        '"' => "quotation mark",
+        '#' => "number sign",
+        '$' => "dollar sign",
+        '%' => "percent sign",
+        '&' => "ampersand",
+        '\'' => "apostrophe",
+        '(' => "left parenthesis",
+        ')' => "right parenthesis",
+        '*' => "asterisk",
+        '+' => "plus sign",
+        ',' => "comma",
+        '-' => "hyphen",
+        '.' => "period",
+        '/' => "slash",
+        ':' => "colon",
+        ';' => "semicolon",
+        '<' => "less-than sign",
+        '=' => "equals sign",
+        '>' => "greater-than sign",
+        '?' => "question mark",
+        '@' => "at sign",
+        '[' => "left bracket",
+        '\\' => "backslash",
+        ']' => "right bracket",
+        '^' => "caret",
+        '_' => "underscore",
+        '`' => "backtick",
+        '{' => "left brace",
+        '|' => "vertical bar",
+        '}' => "right brace",
+        '~' => "tilde",
+        c => return format!("symbol {c}, code point U+{:04X}", u32::from(c)),
+    };
+    name.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spelled() {
+        assert_eq!(
+            "5 characters:\ncapital Q\ndigit 7\ntilde\nlowercase é\nsymbol €, code point U+20AC\n",
+            spell("Q7~é€")
+        );
+        for c in '!'..='~' {
+            assert!(!say(c).starts_with("symbol"), "{c}");
+        }
+    }
+}
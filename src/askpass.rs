@@ -0,0 +1,126 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing of the prompts that programs like ssh and git pass to their askpass helpers, so that
+//! onepass can be one.
+
+use url::Url;
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Want {
+    Password,
+    Username,
+}
+
+/// What a prompt asks for, and for which site.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Prompt {
+    pub want: Want,
+    /// The site, as a URL or a host name
+    pub site: String,
+    /// The account named in the prompt, if any
+    pub username: Option<String>,
+}
+
+impl Prompt {
+    /// Parses `prompt`, returning `None` if it is not an askpass prompt for a site's username or
+    /// password. Site names never contain spaces, so nothing that could be one is taken for a
+    /// prompt.
+    pub fn parse(prompt: &str) -> Option<Self> {
+        let prompt = prompt.trim();
+        if !prompt.contains(char::is_whitespace) {
+            return None;
+        }
+        let lower = prompt.to_lowercase();
+        let want = if lower.starts_with("username") {
+            Want::Username
+        } else if lower.contains("password") {
+            Want::Password
+        } else {
+            return None;
+        };
+
+        // git: "Password for 'https://user@example.com': "
+        if let Some(mut url) = prompt
+            .split('\'')
+            .skip(1)
+            .step_by(2)
+            .find_map(|quoted| Url::parse(quoted).ok().filter(|url| url.has_host()))
+        {
+            let username = Some(url.username().to_owned()).filter(|username| !username.is_empty());
+            url.set_username("").ok()?;
+            url.set_password(None).ok()?;
+            return Some(Prompt {
+                want,
+                site: url.into(),
+                username,
+            });
+        }
+
+        // ssh: "user@host's password: " or "(user@host) Password: "
+        let account = prompt.split_whitespace().find_map(|word| {
+            let word = word.trim_start_matches('(').trim_end_matches(')');
+            let word = word.strip_suffix("'s").unwrap_or(word);
+            word.split_once('@')
+                .filter(|(user, host)| !user.is_empty() && !host.is_empty())
+        })?;
+        Some(Prompt {
+            want,
+            site: account.1.to_owned(),
+            username: Some(account.0.to_owned()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompts() {
+        let prompt = |want, site: &str, username: Option<&str>| {
+            Some(Prompt {
+                want,
+                site: site.into(),
+                username: username.map(Into::into),
+            })
+        };
+        assert_eq!(
+            Prompt::parse("Password for 'https://bob@github.com': "),
+            prompt(Want::Password, "https://github.com/", Some("bob"))
+        );
+        assert_eq!(
+            Prompt::parse("Username for 'https://github.com': "),
+            prompt(Want::Username, "https://github.com/", None)
+        );
+        assert_eq!(
+            Prompt::parse("bob@host.example.com's password: "),
+            prompt(Want::Password, "host.example.com", Some("bob"))
+        );
+        assert_eq!(
+            Prompt::parse("(bob@host) Password: "),
+            prompt(Want::Password, "host", Some("bob"))
+        );
+        assert_eq!(Prompt::parse("example.com"), None);
+        assert_eq!(Prompt::parse("[sudo] password for bob: "), None);
+        assert_eq!(
+            Prompt::parse("Enter passphrase for key '/home/bob/.ssh/id_ed25519': "),
+            None
+        );
+        assert_eq!(
+            Prompt::parse("Are you sure you want to continue connecting (yes/no)? "),
+            None
+        );
+    }
+}
@@ -0,0 +1,501 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checking empirically that passwords are drawn uniformly from their schema, for
+//! `audit-uniformity`.
+//!
+//! A password is drawn by picking a number below its schema's size with [`random_below`] and
+//! mapping it to a string with [`Enumerable::gen_at`], so every index should be equally likely.
+//! An audit draws many passwords that way from throwaway keys, and compares how often each
+//! character turns up at each position with how often it should by Pearson's chi-squared test.
+//! The expected frequencies are worked out exactly from the schema rather than from `gen_at`, so
+//! that a bug in either shows up.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Context, Result};
+use crypto_bigint::{NonZero, U256};
+use onepass::{
+    derive::{DerivationVersion, password_seed, random_below},
+    randexp::{Enumerable, Expr, Quantifiable, WordList, Words},
+};
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+use zeroize::Zeroizing;
+
+use crate::i18n::tr;
+
+/// The chance of an unbiased schema failing the audit, divided among its positions.
+const SIGNIFICANCE: f64 = 0.001;
+
+/// The fewest draws a bin should expect for the chi-squared test to hold; rarer outcomes are
+/// pooled.
+const MIN_EXPECTED: f64 = 5.0;
+
+/// The most characters a schema may match to be audited.
+const MAX_LENGTH: usize = 1024;
+
+/// What a password has at a position: a character, or nothing if it is shorter than that.
+type Outcome = Option<char>;
+
+/// The probabilities of the strings matching an expression, by their length in characters.
+#[derive(Clone, Default)]
+struct Dist(BTreeMap<usize, Strings>);
+
+/// The probability of the strings of some length, and that of each character at each of their
+/// positions together with that length.
+#[derive(Clone)]
+struct Strings {
+    p: f64,
+    chars: Vec<BTreeMap<char, f64>>,
+}
+
+impl Dist {
+    /// Returns the distribution of only the empty string.
+    fn empty() -> Self {
+        let mut dist = Dist::default();
+        dist.add([], 1.0);
+        dist
+    }
+
+    fn strings(&mut self, len: usize) -> &mut Strings {
+        self.0.entry(len).or_insert_with(|| Strings {
+            p: 0.0,
+            chars: vec![BTreeMap::new(); len],
+        })
+    }
+
+    /// Adds the string `s` with probability `p`.
+    fn add(&mut self, s: impl IntoIterator<Item = char>, p: f64) {
+        let s: Vec<_> = s.into_iter().collect();
+        let strings = self.strings(s.len());
+        strings.p += p;
+        for (chars, c) in strings.chars.iter_mut().zip(s) {
+            *chars.entry(c).or_default() += p;
+        }
+    }
+
+    /// Adds the strings of `other`, with their probabilities scaled by `weight`.
+    fn mix(&mut self, other: &Dist, weight: f64) {
+        for (len, theirs) in &other.0 {
+            let ours = self.strings(*len);
+            ours.p += weight * theirs.p;
+            for (ours, theirs) in ours.chars.iter_mut().zip(&theirs.chars) {
+                for (c, p) in theirs {
+                    *ours.entry(*c).or_default() += weight * p;
+                }
+            }
+        }
+    }
+
+    /// Returns the distribution of a string from `self` followed by an independent one from
+    /// `next`, failing if they could be longer than [`MAX_LENGTH`].
+    fn then(&self, next: &Dist) -> Result<Dist> {
+        anyhow::ensure!(
+            self.max_len() + next.max_len() <= MAX_LENGTH,
+            tr("audit-too-long", &[("max", &MAX_LENGTH)])
+        );
+        let mut dist = Dist::default();
+        for (a_len, a) in &self.0 {
+            for (b_len, b) in &next.0 {
+                let strings = dist.strings(a_len + b_len);
+                strings.p += a.p * b.p;
+                for (i, chars) in a.chars.iter().enumerate() {
+                    for (c, p) in chars {
+                        *strings.chars[i].entry(*c).or_default() += p * b.p;
+                    }
+                }
+                for (i, chars) in b.chars.iter().enumerate() {
+                    for (c, p) in chars {
+                        *strings.chars[a_len + i].entry(*c).or_default() += a.p * p;
+                    }
+                }
+            }
+        }
+        Ok(dist)
+    }
+
+    fn max_len(&self) -> usize {
+        self.0.keys().next_back().copied().unwrap_or(0)
+    }
+
+    /// Returns the probability of each outcome at each position up to the longest string.
+    fn positions(&self) -> Vec<BTreeMap<Outcome, f64>> {
+        let mut positions = vec![BTreeMap::new(); self.max_len()];
+        for (len, strings) in &self.0 {
+            for (position, chars) in positions.iter_mut().zip(&strings.chars) {
+                for (c, p) in chars {
+                    *position.entry(Some(*c)).or_default() += p;
+                }
+            }
+            for position in &mut positions[*len..] {
+                *position.entry(None).or_default() += strings.p;
+            }
+        }
+        positions
+    }
+}
+
+/// Returns the distribution of the strings matching `expr`, each index being equally likely.
+fn dist<L: WordList + ?Sized>(words: &Words<L>, expr: &Expr) -> Result<Dist> {
+    let mut dist = Dist::default();
+    match expr {
+        Expr::Word | Expr::WOrd => {
+            let p = 1.0 / words.0.len() as f64;
+            for i in 0..words.0.len() {
+                let mut chars = words.0.word(i).chars();
+                if *expr == Expr::WOrd {
+                    let first = chars.next().context(tr("empty-word", &[]))?;
+                    dist.add(first.to_uppercase().chain(chars), p);
+                } else {
+                    dist.add(chars, p);
+                }
+            }
+        }
+        Expr::Literal(s) => dist.add(s.chars(), 1.0),
+        Expr::CharClass(cc) => {
+            let p = 1.0 / cc.chars().count() as f64;
+            for c in cc.chars() {
+                dist.add([c], p);
+            }
+        }
+        Expr::Sequence(exprs) => {
+            dist = Dist::empty();
+            for expr in exprs {
+                dist = dist.then(&self::dist(words, expr)?)?;
+            }
+        }
+        Expr::Repeat(expr, min, max) => {
+            anyhow::ensure!(
+                *max as usize <= MAX_LENGTH,
+                tr("audit-too-many-repeats", &[("max", &MAX_LENGTH)])
+            );
+            // Each count of repetitions is as likely as the number of strings it makes.
+            let base = self::dist(words, expr)?;
+            let ln_size = to_f64(&words.size(expr)).ln();
+            let ln_weight = |i: u32| match i {
+                0 => 0.0,
+                i => f64::from(i) * ln_size,
+            };
+            let top = (*min..=*max)
+                .map(ln_weight)
+                .fold(f64::NEG_INFINITY, f64::max);
+            let total: f64 = (*min..=*max).map(|i| (ln_weight(i) - top).exp()).sum();
+            let mut power = Dist::empty();
+            for i in 0..=*max {
+                if i >= *min {
+                    dist.mix(&power, (ln_weight(i) - top).exp() / total);
+                }
+                if i < *max {
+                    power = power.then(&base)?;
+                }
+            }
+        }
+    }
+    Ok(dist)
+}
+
+fn to_f64(n: &U256) -> f64 {
+    n.to_le_bytes()
+        .iter()
+        .rev()
+        .fold(0.0, |acc, b| acc * 256.0 + f64::from(*b))
+}
+
+/// Counts of what drawn passwords have at each position, against how likely each outcome is.
+pub(crate) struct Audit {
+    expected: Vec<BTreeMap<Outcome, f64>>,
+    observed: Vec<HashMap<Outcome, u64>>,
+    samples: u64,
+}
+
+/// The chi-squared test of one position.
+pub(crate) struct Position {
+    /// The position, counting from 1.
+    pub(crate) index: usize,
+    pub(crate) chi_squared: f64,
+    pub(crate) degrees_of_freedom: usize,
+    pub(crate) p_value: f64,
+}
+
+impl Audit {
+    /// Starts an audit of the passwords matching `expr`.
+    pub(crate) fn new<L: WordList + ?Sized>(words: &Words<L>, expr: &Expr) -> Result<Self> {
+        let expected = dist(words, expr)?.positions();
+        Ok(Audit {
+            observed: vec![HashMap::new(); expected.len()],
+            expected,
+            samples: 0,
+        })
+    }
+
+    /// Counts what `password` has at each position.
+    pub(crate) fn observe(&mut self, password: &str) -> Result<()> {
+        let mut chars = password.chars();
+        for observed in &mut self.observed {
+            *observed.entry(chars.next()).or_default() += 1;
+        }
+        anyhow::ensure!(chars.next().is_none(), tr("audit-overlong-draw", &[]));
+        self.samples += 1;
+        Ok(())
+    }
+
+    /// Returns the test of each position with enough samples of more than one outcome.
+    pub(crate) fn positions(&self) -> Vec<Position> {
+        self.expected
+            .iter()
+            .zip(&self.observed)
+            .enumerate()
+            .filter_map(|(i, (expected, observed))| {
+                let (chi_squared, degrees_of_freedom) =
+                    chi_squared(expected, observed, self.samples)?;
+                Some(Position {
+                    index: i + 1,
+                    chi_squared,
+                    degrees_of_freedom,
+                    p_value: chi_squared_sf(chi_squared, degrees_of_freedom),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Returns the p-value below which one of `positions` counts as biased: [`SIGNIFICANCE`] divided
+/// among them, so that an unbiased schema fails about that often however long it is.
+pub(crate) fn threshold(positions: &[Position]) -> f64 {
+    SIGNIFICANCE / positions.len().max(1) as f64
+}
+
+/// Draws a password matching `expr` the way a site's is, from key material taken from `keys` in
+/// place of the master password's.
+pub(crate) fn draw<L: WordList + ?Sized>(
+    words: &Words<L>,
+    expr: &Expr,
+    size: &NonZero<U256>,
+    version: DerivationVersion,
+    keys: &mut impl RngCore,
+) -> Result<Zeroizing<String>> {
+    let mut key_material = [0u8; 32];
+    keys.fill_bytes(&mut key_material);
+    let mut seed = [0u8; 32];
+    password_seed(version, &key_material, &mut seed);
+    let mut rng = ChaCha20Rng::from_seed(seed);
+    words.gen_at(expr, random_below(&mut rng, size))
+}
+
+/// Returns Pearson's chi-squared statistic of the `observed` outcomes of `samples` draws against
+/// their `expected` probabilities, with its degrees of freedom, or None if there aren't two bins
+/// of outcomes likely enough to test once rare ones are pooled.
+fn chi_squared(
+    expected: &BTreeMap<Outcome, f64>,
+    observed: &HashMap<Outcome, u64>,
+    samples: u64,
+) -> Option<(f64, usize)> {
+    // An impossible outcome is damning however rarely it turns up.
+    if observed
+        .keys()
+        .any(|outcome| !expected.contains_key(outcome))
+    {
+        return Some((f64::INFINITY, expected.len().max(1)));
+    }
+    let n = samples as f64;
+    let mut outcomes: Vec<_> = expected
+        .iter()
+        .map(|(outcome, p)| (p * n, observed.get(outcome).copied().unwrap_or(0) as f64))
+        .collect();
+    outcomes.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut bins: Vec<(f64, f64)> = Vec::new();
+    let mut pool = (0.0, 0.0);
+    for (e, o) in outcomes {
+        pool = (pool.0 + e, pool.1 + o);
+        if pool.0 >= MIN_EXPECTED {
+            bins.push(pool);
+            pool = (0.0, 0.0);
+        }
+    }
+    match bins.last_mut() {
+        Some(last) => *last = (last.0 + pool.0, last.1 + pool.1),
+        None => return None,
+    }
+    if bins.len() < 2 {
+        return None;
+    }
+    let chi_squared = bins.iter().map(|(e, o)| (o - e).powi(2) / e).sum();
+    Some((chi_squared, bins.len() - 1))
+}
+
+/// Returns the chance of a chi-squared variable with `df` degrees of freedom being at least `x`.
+fn chi_squared_sf(x: f64, df: usize) -> f64 {
+    gamma_q(df as f64 / 2.0, x / 2.0)
+}
+
+/// Returns the regularized upper incomplete gamma function Q(a, x), by its series below `a + 1`
+/// and its continued fraction above, as in Numerical Recipes.
+fn gamma_q(a: f64, x: f64) -> f64 {
+    const EPSILON: f64 = 1e-15;
+    const TINY: f64 = 1e-300;
+    if x <= 0.0 {
+        return 1.0;
+    }
+    if x.is_infinite() {
+        return 0.0;
+    }
+    let scale = (-x + a * x.ln() - ln_gamma(a)).exp();
+    if x < a + 1.0 {
+        let (mut term, mut sum) = (1.0 / a, 1.0 / a);
+        for n in 1..1000 {
+            term *= x / (a + f64::from(n));
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+        return (1.0 - sum * scale).max(0.0);
+    }
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / TINY;
+    let mut d = 1.0 / b;
+    let mut h = d;
+    for i in 1..1000 {
+        let i = f64::from(i);
+        let an = -i * (i - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = b + an / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+    scale * h
+}
+
+/// Returns ln Γ(x) for x ≥ ½, by the Lanczos approximation with g = 7.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    let x = x - 1.0;
+    let sum = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, c)| {
+            sum + c / (x + i as f64 + 1.0)
+        });
+    let t = x + 7.5;
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expected() -> Result<()> {
+        // "a", "b", "aa", and "ab".
+        let expr = Expr::parse("a{0,1}[ab]")?;
+        let positions = dist(&Words(&[]), &expr)?.positions();
+        let close = |position: &BTreeMap<Outcome, f64>, outcome, p: f64| {
+            assert!((position[&outcome] - p).abs() < 1e-12, "{outcome:?}");
+        };
+        assert_eq!(2, positions.len());
+        close(&positions[0], Some('a'), 0.75);
+        close(&positions[0], Some('b'), 0.25);
+        close(&positions[1], None, 0.5);
+        close(&positions[1], Some('a'), 0.25);
+        close(&positions[1], Some('b'), 0.25);
+
+        let expr = Expr::parse("[:Word:]")?;
+        let positions = dist(&Words(&["ox", "cat"]), &expr)?.positions();
+        close(&positions[0], Some('O'), 0.5);
+        close(&positions[0], Some('C'), 0.5);
+        close(&positions[2], None, 0.5);
+        Ok(())
+    }
+
+    #[test]
+    fn p_values() {
+        let close = |a: f64, b: f64| assert!((a - b).abs() < 1e-4, "{a} != {b}");
+        close(0.05, chi_squared_sf(3.841_459, 1));
+        close(0.05, chi_squared_sf(18.307_04, 10));
+        close(0.01, chi_squared_sf(135.807, 100));
+        close(0.5, chi_squared_sf(0.454_936_4, 1));
+        close(1.0, chi_squared_sf(0.0, 3));
+        close(0.0, chi_squared_sf(f64::INFINITY, 3));
+        close(0.0, chi_squared_sf(1000.0, 10));
+    }
+
+    #[test]
+    fn uniform() -> Result<()> {
+        let words = ["correct", "horse", "battery", "staple", "ox"];
+        let words = Words(&words);
+        let expr = Expr::parse("[:Word:]-[0-9]{1,2}[!-~]")?;
+        let size = NonZero::new(words.size(&expr)).unwrap();
+        let mut audit = Audit::new(&words, &expr)?;
+        let mut keys = ChaCha20Rng::from_seed([1; 32]);
+        for _ in 0..20_000 {
+            audit.observe(&draw(
+                &words,
+                &expr,
+                &size,
+                DerivationVersion::V2,
+                &mut keys,
+            )?)?;
+        }
+        let positions = audit.positions();
+        assert_eq!(11, positions.len());
+        let threshold = threshold(&positions);
+        assert!(positions.iter().all(|p| p.p_value >= threshold));
+        Ok(())
+    }
+
+    #[test]
+    fn biased() -> Result<()> {
+        let expr = Expr::parse("[a-d]{2}")?;
+        let mut audit = Audit::new(&Words(&[]), &expr)?;
+        for i in 0..4000 {
+            // Favors "d" at the first position, as taking an index modulo a size would.
+            let first = ['a', 'b', 'c', 'd', 'd'][i % 5];
+            let second = ['a', 'b', 'c', 'd'][i % 4];
+            audit.observe(&format!("{first}{second}"))?;
+        }
+        let positions = audit.positions();
+        assert!(positions[0].p_value < threshold(&positions));
+        assert!(positions[1].p_value > 0.5);
+        assert!(audit.observe("abc").is_err());
+
+        let mut audit = Audit::new(&Words(&[]), &expr)?;
+        audit.observe("ae")?;
+        let positions = audit.positions();
+        assert_eq!(1, positions.len());
+        assert_eq!((2, 0.0), (positions[0].index, positions[0].p_value));
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deriving many sites at once, as for `rekey` or `export --with-secrets`, in parallel.
+//!
+//! Each site's derivation is independent, but the KDF is memory-hard, so running one per core
+//! could exhaust memory on a machine with many cores or a costly KDF. The number run at once is
+//! bounded by half of physical memory over what one derivation takes.
+
+use std::{num::NonZero, thread};
+
+use anyhow::Result;
+use onepass::derive::KdfConfig;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Memory to assume when physical memory is unknown.
+const FALLBACK_MEMORY: u64 = 1 << 30;
+
+/// Returns how many derivations with `kdf` to run at once.
+pub(crate) fn threads(kdf: &KdfConfig) -> usize {
+    let cores = thread::available_parallelism().map_or(1, NonZero::get);
+    let fit = physical_memory().unwrap_or(FALLBACK_MEMORY) / 2 / kdf.memory().max(1);
+    cores.min(usize::try_from(fit).unwrap_or(usize::MAX)).max(1)
+}
+
+/// Applies `f` to each of `items` on up to `threads` threads, returning the results in order or
+/// the first error.
+#[cfg(feature = "parallel")]
+pub(crate) fn map<T, U, F>(threads: usize, items: Vec<T>, f: F) -> Result<Vec<U>>
+where
+    T: Send,
+    U: Send,
+    F: Fn(T) -> Result<U> + Sync,
+{
+    if threads <= 1 || items.len() <= 1 {
+        return items.into_iter().map(f).collect();
+    }
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads.min(items.len()))
+        .build()?;
+    pool.install(|| items.into_par_iter().map(&f).collect())
+}
+
+/// Applies `f` to each of `items` in turn, as a build without the `parallel` feature runs them.
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn map<T, U, F>(_threads: usize, items: Vec<T>, f: F) -> Result<Vec<U>>
+where
+    F: Fn(T) -> Result<U>,
+{
+    items.into_iter().map(f).collect()
+}
+
+#[cfg(unix)]
+fn physical_memory() -> Option<u64> {
+    // SAFETY: takes no pointers.
+    let pages = unsafe { libc::sysconf(libc::_SC_PHYS_PAGES) };
+    // SAFETY: takes no pointers.
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    let pages = u64::try_from(pages).ok()?;
+    let page_size = u64::try_from(page_size).ok()?;
+    pages.checked_mul(page_size)
+}
+
+#[cfg(not(unix))]
+fn physical_memory() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use onepass::derive::Argon2Config;
+
+    use super::*;
+
+    #[test]
+    fn bounded_by_memory() {
+        let kdf = KdfConfig {
+            argon2: Some(Argon2Config {
+                memory: u32::MAX,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let memory = physical_memory().unwrap_or(FALLBACK_MEMORY);
+        if memory < 1 << 43 {
+            assert_eq!(1, threads(&kdf));
+        }
+        assert!(threads(&KdfConfig::default()) >= 1);
+    }
+
+    #[test]
+    fn keeps_order() -> Result<()> {
+        let items: Vec<u32> = (0..100).collect();
+        let doubled = map(4, items.clone(), |i| Ok(2 * i))?;
+        assert_eq!(items.iter().map(|i| 2 * i).collect::<Vec<_>>(), doubled);
+        let failed = map(4, items, |i| {
+            anyhow::ensure!(i != 50, "fifty");
+            Ok(i)
+        });
+        assert!(failed.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,474 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Copying secrets to a clipboard, through one of several backends, since the right mechanism
+//! depends on the platform and on whether there is a display, a terminal, or tmux.
+//!
+//! Where the platform has a way, copied secrets are marked as such, so that clipboard managers
+//! leave them out of their histories: `x-kde-passwordManagerHint` on Wayland (through
+//! `wl-copy --sensitive`), `org.nspasteboard.ConcealedType` on macOS, and
+//! `ExcludeClipboardContentFromMonitorProcessing` on Windows. xclip, xsel, and Termux can only
+//! offer the text itself.
+
+use std::env;
+#[cfg(feature = "clipboard")]
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use anyhow::Result;
+#[cfg(feature = "clipboard")]
+use anyhow::{Context, bail};
+#[cfg(feature = "clipboard")]
+use base64::{Engine, prelude::BASE64_STANDARD};
+pub(crate) use onepass::config::ClipboardBackend;
+use onepass::config::Config;
+#[cfg(feature = "clipboard")]
+use rand_core::{OsRng, TryRngCore};
+#[cfg(feature = "clipboard")]
+use sha2::{Digest, Sha256};
+#[cfg(feature = "clipboard")]
+use zeroize::Zeroizing;
+
+use crate::i18n::tr;
+
+/// How long copied secrets stay on the clipboard, as in pass(1).
+pub(crate) const CLEAR_SECS: u32 = 45;
+
+/// The tmux paste buffer that the tmux backend loads.
+#[cfg(feature = "clipboard")]
+const TMUX_BUFFER: &str = "onepass";
+
+/// Copies stdin to the macOS pasteboard, marked as concealed, by the nspasteboard.org convention.
+#[cfg(feature = "clipboard")]
+const MACOS_CONCEALED: &str = "ObjC.import('AppKit');
+var input = $.NSFileHandle.fileHandleWithStandardInput.readDataToEndOfFile;
+var secret = $.NSString.alloc.initWithDataEncoding(input, $.NSUTF8StringEncoding);
+var board = $.NSPasteboard.generalPasteboard;
+board.clearContents;
+board.setStringForType(secret, $.NSPasteboardTypeString);
+board.setStringForType($(''), 'org.nspasteboard.ConcealedType');";
+
+/// Copies stdin to the Windows clipboard, excluded from clipboard history, cloud sync, and
+/// monitoring clipboard managers.
+#[cfg(feature = "clipboard")]
+const WINDOWS_EXCLUDED: &str = "[Console]::InputEncoding = [Text.Encoding]::UTF8;
+Add-Type -AssemblyName System.Windows.Forms;
+$data = New-Object System.Windows.Forms.DataObject;
+$data.SetText([Console]::In.ReadToEnd());
+$no = [BitConverter]::GetBytes([int32]0);
+$data.SetData('ExcludeClipboardContentFromMonitorProcessing', [IO.MemoryStream]::new($no));
+$data.SetData('CanIncludeInClipboardHistory', [IO.MemoryStream]::new($no));
+$data.SetData('CanUploadToCloudClipboard', [IO.MemoryStream]::new($no));
+[System.Windows.Forms.Clipboard]::SetDataObject($data, $true);";
+
+/// Exits a clearing script unless the clipboard, printed by `PASTE`, still holds the copied
+/// secret, going by [`Copied`]'s hash; when it can't be read back or hashed, it is cleared anyway.
+#[cfg(feature = "clipboard")]
+const STILL_COPIED: &str = r#"sum() {
+    if command -v sha256sum >/dev/null; then sha256sum; else shasum -a 256; fi
+}
+if PASTE >/dev/null 2>&1 && { command -v sha256sum || command -v shasum; } >/dev/null; then
+    pasted=$({ printf %s "$ONEPASS_CLEAR_SALT"; PASTE; } | sum | cut -d ' ' -f 1)
+    [ "$pasted" = "$ONEPASS_CLEAR_HASH" ] || exit 0
+fi"#;
+
+/// Returns the clipboard backend to copy to: `flag` if given, else the configured one, else one
+/// suited to the environment.
+pub(crate) fn choose(config: &Config, flag: Option<ClipboardBackend>) -> ClipboardBackend {
+    flag.or(config.clipboard).unwrap_or_else(detect)
+}
+
+/// Picks the backend most likely to work in this environment.
+fn detect() -> ClipboardBackend {
+    if cfg!(target_os = "macos") {
+        ClipboardBackend::Pbcopy
+    } else if cfg!(windows) {
+        ClipboardBackend::Windows
+    } else if env::var_os("TERMUX_VERSION").is_some() {
+        ClipboardBackend::Termux
+    } else if env::var_os("WAYLAND_DISPLAY").is_some() {
+        ClipboardBackend::WlCopy
+    } else if env::var_os("DISPLAY").is_some() {
+        if on_path("xclip") || !on_path("xsel") {
+            ClipboardBackend::Xclip
+        } else {
+            ClipboardBackend::Xsel
+        }
+    } else if env::var_os("TMUX").is_some() {
+        ClipboardBackend::Tmux
+    } else {
+        ClipboardBackend::Osc52
+    }
+}
+
+/// Returns whether `program` is in a directory on the `PATH`.
+fn on_path(program: &str) -> bool {
+    env::var_os("PATH")
+        .is_some_and(|path| env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+/// A way of copying secrets somewhere they can be pasted from.
+#[cfg(feature = "clipboard")]
+trait Backend {
+    /// The id of the message describing where `copy` puts secrets, e.g. `the-clipboard`.
+    fn destination(&self) -> &'static str;
+
+    fn copy(&self, secret: &str) -> Result<()>;
+
+    /// Removes the copied secret after [`CLEAR_SECS`], from a background process, unless
+    /// something else has been copied since.
+    fn clear_later(&self, copied: &Copied) -> Result<()>;
+}
+
+/// A salted hash of a copied secret, by which the background process that clears it recognizes
+/// it without holding it, so that it leaves alone whatever was copied after.
+#[cfg(feature = "clipboard")]
+struct Copied {
+    salt: String,
+    hash: String,
+}
+
+#[cfg(feature = "clipboard")]
+impl Copied {
+    fn new(secret: &str) -> Result<Self> {
+        let mut salt = [0u8; 16];
+        OsRng.try_fill_bytes(&mut salt)?;
+        let salt = hex::encode(salt);
+        let hash = hex::encode(
+            Sha256::new()
+                .chain_update(&salt)
+                .chain_update(secret)
+                .finalize(),
+        );
+        Ok(Copied { salt, hash })
+    }
+
+    /// Passes the hash to `command`, for [`STILL_COPIED`].
+    fn env<'a>(&self, command: &'a mut Command) -> &'a mut Command {
+        command
+            .env("ONEPASS_CLEAR_SALT", &self.salt)
+            .env("ONEPASS_CLEAR_HASH", &self.hash)
+    }
+}
+
+/// The operations of a [`ClipboardBackend`].
+pub(crate) trait Clipboard {
+    /// Describes where `copy` puts secrets, e.g. "the clipboard", in the user's language.
+    fn destination(self) -> String;
+
+    /// Copies `secret`, and removes it again after [`CLEAR_SECS`].
+    fn copy(self, secret: &str) -> Result<()>;
+}
+
+#[cfg(feature = "clipboard")]
+impl Clipboard for ClipboardBackend {
+    fn destination(self) -> String {
+        tr(backend(self).destination(), &[])
+    }
+
+    fn copy(self, secret: &str) -> Result<()> {
+        let backend = backend(self);
+        backend.copy(secret)?;
+        backend.clear_later(&Copied::new(secret)?)
+    }
+}
+
+/// Without the `clipboard` feature, copying fails.
+#[cfg(not(feature = "clipboard"))]
+impl Clipboard for ClipboardBackend {
+    fn destination(self) -> String {
+        tr("the-clipboard", &[])
+    }
+
+    fn copy(self, _secret: &str) -> Result<()> {
+        anyhow::bail!(tr("built-without-clipboard", &[]));
+    }
+}
+
+#[cfg(feature = "clipboard")]
+fn backend(clipboard: ClipboardBackend) -> &'static dyn Backend {
+    match clipboard {
+        ClipboardBackend::WlCopy => &Hinted {
+            hinted: &["wl-copy", "--sensitive"],
+            plain: &Tool {
+                copy: &["wl-copy"],
+                paste: "wl-paste --no-newline",
+            },
+        },
+        ClipboardBackend::Xclip => &Tool {
+            copy: &["xclip", "-selection", "clipboard"],
+            paste: "xclip -selection clipboard -out",
+        },
+        ClipboardBackend::Xsel => &Tool {
+            copy: &["xsel", "--clipboard", "--input"],
+            paste: "xsel --clipboard --output",
+        },
+        ClipboardBackend::Pbcopy => &Hinted {
+            hinted: &["osascript", "-l", "JavaScript", "-e", MACOS_CONCEALED],
+            plain: &Tool {
+                copy: &["pbcopy"],
+                paste: "pbpaste",
+            },
+        },
+        ClipboardBackend::Termux => &Tool {
+            copy: &["termux-clipboard-set"],
+            paste: "termux-clipboard-get",
+        },
+        ClipboardBackend::Windows => &Hinted {
+            hinted: &[
+                "powershell",
+                "-NoProfile",
+                "-STA",
+                "-Command",
+                WINDOWS_EXCLUDED,
+            ],
+            plain: &Windows,
+        },
+        ClipboardBackend::Osc52 => &Osc52,
+        ClipboardBackend::Tmux => &Tmux,
+    }
+}
+
+/// A clipboard tool that copies its stdin, and clears the clipboard when given nothing, with the
+/// shell command that prints what it copied.
+#[cfg(feature = "clipboard")]
+struct Tool {
+    copy: &'static [&'static str],
+    paste: &'static str,
+}
+
+#[cfg(feature = "clipboard")]
+impl Backend for Tool {
+    fn destination(&self) -> &'static str {
+        "the-clipboard"
+    }
+
+    fn copy(&self, secret: &str) -> Result<()> {
+        pipe(self.copy, secret, false)
+    }
+
+    fn clear_later(&self, copied: &Copied) -> Result<()> {
+        sh_later(Some(self.paste), r#"printf '' | "$@""#, self.copy, copied)
+    }
+}
+
+/// A clipboard tool that marks what it copies as a secret for clipboard managers to leave out of
+/// their histories, falling back on `plain` where it isn't available, e.g. an older wl-copy.
+#[cfg(feature = "clipboard")]
+struct Hinted {
+    hinted: &'static [&'static str],
+    plain: &'static dyn Backend,
+}
+
+#[cfg(feature = "clipboard")]
+impl Backend for Hinted {
+    fn destination(&self) -> &'static str {
+        self.plain.destination()
+    }
+
+    fn copy(&self, secret: &str) -> Result<()> {
+        pipe(self.hinted, secret, true).or_else(|_| self.plain.copy(secret))
+    }
+
+    fn clear_later(&self, copied: &Copied) -> Result<()> {
+        self.plain.clear_later(copied)
+    }
+}
+
+#[cfg(feature = "clipboard")]
+struct Windows;
+
+#[cfg(feature = "clipboard")]
+impl Backend for Windows {
+    fn destination(&self) -> &'static str {
+        "the-clipboard"
+    }
+
+    fn copy(&self, secret: &str) -> Result<()> {
+        pipe(&["clip.exe"], secret, false)
+    }
+
+    fn clear_later(&self, copied: &Copied) -> Result<()> {
+        let script = format!(
+            "Start-Sleep -Seconds {CLEAR_SECS};
+$text = [Text.Encoding]::UTF8.GetBytes($env:ONEPASS_CLEAR_SALT + (Get-Clipboard -Raw));
+$hash = [Security.Cryptography.SHA256]::Create().ComputeHash($text);
+$hash = -join ($hash | ForEach-Object {{ $_.ToString('x2') }});
+if ($hash -eq $env:ONEPASS_CLEAR_HASH) {{ cmd /c 'echo off | clip' }}"
+        );
+        copied
+            .env(&mut Command::new("powershell"))
+            .args(["-NoProfile", "-WindowStyle", "Hidden", "-Command", &script])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .context(tr("failed-scheduling-clear", &[]))?;
+        Ok(())
+    }
+}
+
+/// Copies to the clipboard of the terminal with an OSC 52 escape sequence, which works across
+/// ssh since the terminal is on the local machine. The terminal (and tmux, if any, with
+/// `set-clipboard on`) has to allow it. Few terminals let the clipboard be read back, so it is
+/// cleared whatever it holds by then.
+#[cfg(feature = "clipboard")]
+struct Osc52;
+
+#[cfg(feature = "clipboard")]
+impl Backend for Osc52 {
+    fn destination(&self) -> &'static str {
+        "the-terminal-clipboard"
+    }
+
+    fn copy(&self, secret: &str) -> Result<()> {
+        let mut tty = OpenOptions::new()
+            .write(true)
+            .open("/dev/tty")
+            .context(tr("failed-opening-terminal", &[]))?;
+        let sequence = Zeroizing::new(format!(
+            "\x1b]52;c;{}\x07",
+            Zeroizing::new(BASE64_STANDARD.encode(secret)).as_str()
+        ));
+        tty.write_all(sequence.as_bytes())?;
+        Ok(())
+    }
+
+    fn clear_later(&self, copied: &Copied) -> Result<()> {
+        sh_later(None, r#"printf '\033]52;c;\007' > /dev/tty"#, &[], copied)
+    }
+}
+
+#[cfg(feature = "clipboard")]
+struct Tmux;
+
+#[cfg(feature = "clipboard")]
+impl Backend for Tmux {
+    fn destination(&self) -> &'static str {
+        "the-tmux-buffer"
+    }
+
+    fn copy(&self, secret: &str) -> Result<()> {
+        pipe(
+            &["tmux", "load-buffer", "-b", TMUX_BUFFER, "-"],
+            secret,
+            false,
+        )
+    }
+
+    fn clear_later(&self, copied: &Copied) -> Result<()> {
+        let paste = format!("tmux show-buffer -b {TMUX_BUFFER}");
+        let delete = ["tmux", "delete-buffer", "-b", TMUX_BUFFER];
+        sh_later(Some(&paste), r#""$@""#, &delete, copied)
+    }
+}
+
+/// Runs `command` with `secret` on its stdin, and if `quiet`, without its errors, e.g. for
+/// trying a command that may not be supported.
+#[cfg(feature = "clipboard")]
+fn pipe(command: &[&str], secret: &str, quiet: bool) -> Result<()> {
+    let [program, args @ ..] = command else {
+        unreachable!()
+    };
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(if quiet {
+            Stdio::null()
+        } else {
+            Stdio::inherit()
+        })
+        .spawn()
+        .with_context(|| tr("failed-running", &[("program", &program)]))?;
+    child
+        .stdin
+        .take()
+        .context(tr("no-stdin", &[]))?
+        .write_all(secret.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        bail!(tr(
+            "program-failed-with",
+            &[("program", &program), ("error", &status)]
+        ));
+    }
+    Ok(())
+}
+
+/// Runs the shell command `script`, with `args` as its positional parameters, in a background
+/// process after [`CLEAR_SECS`], if the shell command `paste` still prints the `copied` secret.
+#[cfg(feature = "clipboard")]
+fn sh_later(paste: Option<&str>, script: &str, args: &[&str], copied: &Copied) -> Result<()> {
+    let mut clear = Command::new("sh");
+    copied
+        .env(&mut clear)
+        .arg("-c")
+        .arg(clear_script(paste, script))
+        .arg(CLEAR_SECS.to_string())
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut clear, 0);
+    clear.spawn().context(tr("failed-scheduling-clear", &[]))?;
+    Ok(())
+}
+
+/// Returns the script [`sh_later`] runs, which sleeps for `$0` seconds first.
+#[cfg(feature = "clipboard")]
+fn clear_script(paste: Option<&str>, script: &str) -> String {
+    let check = paste.map(|paste| STILL_COPIED.replace("PASTE", paste));
+    format!("sleep \"$0\"\n{}\n{script}", check.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn chooses() -> Result<()> {
+        let config = Config::from_str("clipboard: wl-copy\nsites: {}")?;
+        assert_eq!(
+            ClipboardBackend::Tmux,
+            choose(&config, Some(ClipboardBackend::Tmux))
+        );
+        assert_eq!(ClipboardBackend::WlCopy, choose(&config, None));
+        Ok(())
+    }
+
+    #[cfg(all(unix, feature = "clipboard"))]
+    #[test]
+    fn clears_only_the_copy() -> Result<()> {
+        let clears = |paste: &str, copied: &str| -> Result<bool> {
+            let script = clear_script(Some(paste), "echo cleared");
+            let output = Copied::new(copied)?
+                .env(&mut Command::new("sh"))
+                .args(["-c", &script, "0"])
+                .output()?;
+            Ok(output.stdout == b"cleared\n")
+        };
+        assert!(clears("printf %s secret", "secret")?);
+        assert!(!clears("printf %s other", "secret")?);
+        // What can't be read back is cleared regardless.
+        assert!(clears("false", "secret")?);
+        Ok(())
+    }
+}
@@ -0,0 +1,365 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Looking over a config for weak settings, for `audit`: schemas with few bits of entropy, sites
+//! that share a salt or are shadowed by another, sites with no rotation date, and derivation
+//! versions, canonicalization versions, and KDF parameters older or weaker than recommended.
+//!
+//! Nothing here needs the master password; the findings are about the config alone. The same
+//! goes for `due`, which lists the sites whose passwords are due for rotation.
+
+use std::{collections::BTreeMap, fmt};
+
+use anyhow::Result;
+use onepass::{
+    config::{Config, SiteConfig},
+    date::Date,
+    derive::{Argon2Config, DerivationVersion, KdfAlgorithm},
+    randexp::{Expr, Quantifiable, WordList, Words},
+    url,
+};
+
+use crate::words;
+
+/// The fewest bits of entropy a password schema may have without a finding, by default.
+pub(crate) const MIN_BITS: u32 = 64;
+
+/// OWASP's minimum Argon2 memory in KiB, for its most passes.
+const ARGON2_MIN_MEMORY: u64 = 7 * 1024;
+
+/// OWASP's minimum Argon2 memory times passes, in KiB: 19 MiB with 2 passes, give or take the
+/// rounding of its other configurations.
+const ARGON2_MIN_COST: u64 = 35 * 1024;
+
+/// OWASP's minimum scrypt cost, as a power of 2.
+const SCRYPT_MIN_LOG_N: u8 = 17;
+
+/// A kind of weakness that `audit` looks for.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub(crate) enum Check {
+    /// A schema that matches too few passwords, or doesn't parse
+    WeakSchema,
+    /// Sites whose salts are the same, so that they derive from the same key
+    SharedSalt,
+    /// A site in the config file that canonicalizes to another configured site
+    Shadowed,
+    /// A site with no `rotated` date
+    NoRotationDate,
+    /// A derivation version older than the latest
+    OldVersion,
+    /// A canonicalization version older than the latest
+    OldCanonicalization,
+    /// KDF parameters below current recommendations
+    WeakKdf,
+}
+
+/// A weakness found in the config, of a site or of the config as a whole.
+#[derive(Debug)]
+pub(crate) struct Finding {
+    pub(crate) check: Check,
+    pub(crate) site: Option<String>,
+    pub(crate) message: String,
+}
+
+impl Finding {
+    fn config(check: Check, message: String) -> Self {
+        Finding {
+            check,
+            site: None,
+            message,
+        }
+    }
+
+    fn site(check: Check, site: &str, message: String) -> Self {
+        Finding {
+            check,
+            site: Some(site.to_owned()),
+            message,
+        }
+    }
+}
+
+/// Returns the weaknesses of `config`, with its words, flagging password schemas with fewer
+/// than `min_bits` bits of entropy. `salt` builds a configured site's salt.
+pub(crate) fn audit(
+    config: &Config,
+    words: &words::Filters,
+    min_bits: u32,
+    salt: impl Fn(&str, &SiteConfig) -> Result<Vec<u8>>,
+) -> Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    let list = words.get(&Default::default())?;
+    if let Some(message) = weak_schema(&Words(&*list), &config.default_schema, min_bits) {
+        findings.push(Finding::config(
+            Check::WeakSchema,
+            format!("the default schema {message}"),
+        ));
+    }
+    if config.default_version != DerivationVersion::LATEST {
+        findings.push(Finding::config(
+            Check::OldVersion,
+            format!(
+                "sites derive with {} by default, not {}; moving a site with `version:` changes \
+                 its password",
+                config.default_version,
+                DerivationVersion::LATEST
+            ),
+        ));
+    }
+    if config.canonicalization.version < url::Version::LATEST {
+        findings.push(Finding::config(
+            Check::OldCanonicalization,
+            format!(
+                "site names are canonicalized under v{}, so spellings that v{} treats alike may \
+                 get different passwords",
+                u32::from(config.canonicalization.version),
+                u32::from(url::Version::LATEST)
+            ),
+        ));
+    }
+    findings.extend(weak_kdf(config).map(|message| Finding::config(Check::WeakKdf, message)));
+
+    let mut sites: Vec<_> = config.sites.iter().collect();
+    sites.sort_by_key(|(url, _)| *url);
+    let mut salts: BTreeMap<Vec<u8>, Vec<&str>> = BTreeMap::new();
+    for (url, site) in &sites {
+        if site.compat.is_none() {
+            let list = words.get(&site.word_filter)?;
+            if let Some(message) = weak_schema(&Words(&*list), &site.schema, min_bits) {
+                findings.push(Finding::site(
+                    Check::WeakSchema,
+                    url,
+                    format!("the schema {message}"),
+                ));
+            }
+        }
+        if let Some(version) = site.version.filter(|v| *v != DerivationVersion::LATEST) {
+            findings.push(Finding::site(
+                Check::OldVersion,
+                url,
+                format!("derives with {version}, not {}", DerivationVersion::LATEST),
+            ));
+        }
+        if site.rotated.is_none() {
+            findings.push(Finding::site(
+                Check::NoRotationDate,
+                url,
+                "has no rotation date (`rotated:`), to tell when its password was last changed"
+                    .into(),
+            ));
+        }
+        // Sites whose salts can't be built fail when generated anyway.
+        if let Ok(salt) = salt(url, site) {
+            salts.entry(salt).or_default().push(url);
+        }
+    }
+    for urls in salts.values().filter(|urls| urls.len() > 1) {
+        for url in urls {
+            let others: Vec<_> = urls.iter().filter(|other| *other != url).copied().collect();
+            findings.push(Finding::site(
+                Check::SharedSalt,
+                url,
+                format!(
+                    "has the same salt as {}, so their passwords derive from the same key",
+                    others.join(", ")
+                ),
+            ));
+        }
+    }
+    for (name, url) in &config.shadowed {
+        findings.push(Finding::site(
+            Check::Shadowed,
+            name,
+            format!(
+                "canonicalizes to {url}, like another site in the config that takes effect \
+                 instead"
+            ),
+        ));
+    }
+    findings.sort_by(|a, b| (&a.site, a.check).cmp(&(&b.site, b.check)));
+    Ok(findings)
+}
+
+/// A site whose password is due for rotation.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct Due<'a> {
+    pub(crate) url: &'a str,
+    pub(crate) rotated: Date,
+    /// Days since the password was rotated
+    pub(crate) age: i64,
+}
+
+/// Returns the sites of `config` whose passwords were rotated more than their `rotate_after`
+/// days before `today`, or more than `days` days if given, oldest first; and the sites with no
+/// rotation date, which can't be told either way. Sites that never expire are neither.
+pub(crate) fn due(config: &Config, days: Option<u32>, today: Date) -> (Vec<Due<'_>>, Vec<&str>) {
+    let (mut due, mut undated) = (Vec::new(), Vec::new());
+    for (url, site) in &config.sites {
+        let Some(window) = days.or_else(|| config.rotate_after(site)) else {
+            continue;
+        };
+        let Some(rotated) = site.rotated else {
+            undated.push(url.as_str());
+            continue;
+        };
+        let age = rotated.days_until(today);
+        if age > i64::from(window) {
+            due.push(Due { url, rotated, age });
+        }
+    }
+    due.sort_by(|a, b| (a.rotated, a.url).cmp(&(b.rotated, b.url)));
+    undated.sort();
+    (due, undated)
+}
+
+/// Returns what is weak about `schema` with `words`, if it doesn't parse or has fewer than
+/// `min_bits` bits of entropy.
+fn weak_schema<L: WordList + ?Sized>(
+    words: &Words<L>,
+    schema: &str,
+    min_bits: u32,
+) -> Option<String> {
+    let Ok(expr) = Expr::parse(schema) else {
+        return Some(format!("`{schema}` doesn't parse"));
+    };
+    let bits = words.size(&expr).bits();
+    (bits < min_bits)
+        .then(|| format!("`{schema}` has about {bits} bits of entropy, under {min_bits}"))
+}
+
+/// Returns what is weak about the config's KDF, if it is below OWASP's recommendations.
+fn weak_kdf(config: &Config) -> Option<String> {
+    let kdf = &config.kdf;
+    let rekey = "changing it changes every password; see `onepass rekey`";
+    match kdf.algorithm {
+        KdfAlgorithm::Argon2id | KdfAlgorithm::Argon2i | KdfAlgorithm::Argon2d => {
+            let Argon2Config {
+                memory, iterations, ..
+            } = kdf.argon2.clone().unwrap_or_default();
+            let (memory, iterations) = (u64::from(memory), u64::from(iterations));
+            if memory < ARGON2_MIN_MEMORY || memory * iterations < ARGON2_MIN_COST {
+                return Some(format!(
+                    "{kdf} is below OWASP's minimum of m=19456 KiB with t=2 (or m=7168 KiB with \
+                     t=5); {rekey}"
+                ));
+            }
+            (kdf.algorithm != KdfAlgorithm::Argon2id)
+                .then(|| format!("{kdf} is not argon2id, the variant RFC 9106 recommends; {rekey}"))
+        }
+        KdfAlgorithm::Scrypt => {
+            let log_n = kdf.scrypt.clone().unwrap_or_default().log_n;
+            (log_n < SCRYPT_MIN_LOG_N).then(|| {
+                format!("{kdf} is below OWASP's minimum of N=2^{SCRYPT_MIN_LOG_N}; {rekey}")
+            })
+        }
+        KdfAlgorithm::Balloon => None,
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Check::WeakSchema => "weak-schema",
+            Check::SharedSalt => "shared-salt",
+            Check::Shadowed => "shadowed",
+            Check::NoRotationDate => "no-rotation-date",
+            Check::OldVersion => "old-version",
+            Check::OldCanonicalization => "old-canonicalization",
+            Check::WeakKdf => "weak-kdf",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use onepass::wordlist::Wordlist;
+
+    use super::*;
+
+    #[test]
+    fn findings() -> Result<()> {
+        let config = Config::from_str(
+            r#"
+            default_version: 2
+            canonicalization: {version: 4}
+            kdf: {algorithm: scrypt}
+            sites:
+                a.com: {schema: "[0-9]{6}", rotated: 2025-01-01}
+                b.com: {schema: "[!-~]{20}", rotated: 2025-01-01, version: 1}
+                www.b.com: {rotated: 2025-01-01}
+                c.com: "[:word:](-[:word:]){5}"
+                d.com: {schema: "[!-~]{20}", rotated: 2025-01-01}
+        "#,
+        )?;
+        let list = words::List::Builtin(Wordlist::default().words());
+        let filters = words::Filters::new(&list);
+        let findings = audit(&config, &filters, MIN_BITS, |url, _| {
+            Ok(match url {
+                "https://c.com/" | "https://d.com/" => b"same".to_vec(),
+                url => url.as_bytes().to_vec(),
+            })
+        })?;
+        let findings: Vec<_> = findings
+            .iter()
+            .map(|finding| (finding.site.as_deref(), finding.check))
+            .collect();
+        assert_eq!(
+            [
+                (None, Check::WeakKdf),
+                (Some("https://a.com/"), Check::WeakSchema),
+                (Some("https://b.com/"), Check::OldVersion),
+                (Some("https://c.com/"), Check::SharedSalt),
+                (Some("https://c.com/"), Check::NoRotationDate),
+                (Some("https://d.com/"), Check::SharedSalt),
+                (Some("www.b.com"), Check::Shadowed),
+            ],
+            findings.as_slice()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn due_sites() -> Result<()> {
+        let config = Config::from_str(
+            r#"
+            rotate_after: 90
+            sites:
+                a.com: {rotated: 2025-01-01}
+                b.com: {rotated: 2025-03-01}
+                c.com: {rotated: 2024-06-01, rotate_after: 730}
+                d.com: {rotated: 2024-12-31}
+                e.com: login
+        "#,
+        )?;
+        let today = "2025-04-01".parse()?;
+        let (sites, undated) = due(&config, None, today);
+        let sites: Vec<_> = sites.iter().map(|due| (due.url, due.age)).collect();
+        assert_eq!([("https://d.com/", 91)], *sites);
+        assert_eq!(["https://e.com/"], *undated);
+        let (sites, _) = due(&config, Some(30), today);
+        let sites: Vec<_> = sites.iter().map(|due| due.url).collect();
+        assert_eq!(
+            [
+                "https://c.com/",
+                "https://d.com/",
+                "https://a.com/",
+                "https://b.com/"
+            ],
+            *sites
+        );
+        Ok(())
+    }
+}
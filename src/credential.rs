@@ -0,0 +1,300 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The protocols of other tools' credential helpers, so that onepass can serve as one.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::i18n::tr;
+
+/// The operations git asks credential helpers to perform.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum GitOperation {
+    /// Print the credentials for a URL
+    Get,
+    /// Store credentials git has used successfully
+    Store,
+    /// Forget credentials that were rejected
+    Erase,
+}
+
+/// A request from git: the `key=value` attributes it sends a helper, up to a blank line.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct GitRequest {
+    attributes: BTreeMap<String, String>,
+}
+
+impl GitRequest {
+    pub fn parse(input: &str) -> Self {
+        let attributes = input
+            .lines()
+            .take_while(|line| !line.is_empty())
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.to_owned(), value.to_owned()))
+            .collect();
+        GitRequest { attributes }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key).map(String::as_str)
+    }
+
+    /// Returns the URL of the site the request is for, e.g. `https://example.com/`. The path is
+    /// left out, so all of a host's repositories share a site.
+    pub fn site(&self) -> Option<String> {
+        Some(format!(
+            "{}://{}/",
+            self.get("protocol")?,
+            self.get("host")?
+        ))
+    }
+}
+
+/// The operations docker asks credential helpers to perform.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum DockerOperation {
+    /// Print the credentials for the registry named on stdin
+    Get,
+    /// Print the registries with credentials and their usernames
+    List,
+    /// Store credentials from a login
+    Store,
+    /// Forget credentials on logout
+    Erase,
+}
+
+/// What docker expects on stdout when a helper has no credentials for a registry.
+pub(crate) const DOCKER_NOT_FOUND: &str = "credentials not found in native keychain";
+
+#[derive(Debug, Serialize)]
+pub(crate) struct DockerCredentials<'a> {
+    #[serde(rename = "ServerURL")]
+    pub server_url: String,
+    #[serde(rename = "Username")]
+    pub username: String,
+    /// Borrowed, so that the password is only copied into the zeroized output
+    #[serde(rename = "Secret")]
+    pub secret: &'a str,
+}
+
+/// Returns the URL of the site for the registry `server`, which docker gives as either a host
+/// (`registry.example.com`) or a URL (`https://index.docker.io/v1/`). The path is left out.
+pub(crate) fn docker_site(server: &str) -> Result<String> {
+    let server = server.trim();
+    let mut url = if server.contains("://") {
+        Url::parse(server)
+    } else {
+        Url::parse(&format!("https://{server}"))
+    }
+    .context(tr("invalid-registry", &[]))?;
+    url.set_path("/");
+    url.set_query(None);
+    url.set_fragment(None);
+    Ok(url.into())
+}
+
+/// The ExecCredential API version used when client-go doesn't say which it wants.
+const KUBE_API_VERSION: &str = "client.authentication.k8s.io/v1";
+
+/// The request client-go passes exec credential plugins in `KUBERNETES_EXEC_INFO`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KubeExecInfo {
+    api_version: String,
+    #[serde(default)]
+    spec: KubeExecSpec,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct KubeExecSpec {
+    cluster: Option<KubeCluster>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KubeCluster {
+    server: String,
+}
+
+impl KubeExecInfo {
+    pub fn parse(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context(tr("invalid-exec-info", &[]))
+    }
+
+    /// Returns the cluster's API server, which client-go only includes with
+    /// `provideClusterInfo: true`.
+    pub fn server(&self) -> Option<&str> {
+        self.spec
+            .cluster
+            .as_ref()
+            .map(|cluster| cluster.server.as_str())
+    }
+}
+
+/// Returns the ExecCredential JSON for client-go carrying `token`, in the API version `info` asks
+/// for.
+pub(crate) fn kube_exec_credential(info: Option<&KubeExecInfo>, token: &str) -> Result<String> {
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct ExecCredential<'a> {
+        api_version: &'a str,
+        kind: &'a str,
+        status: Status<'a>,
+    }
+    #[derive(Serialize)]
+    struct Status<'a> {
+        token: &'a str,
+    }
+    Ok(serde_json::to_string(&ExecCredential {
+        api_version: info.map_or(KUBE_API_VERSION, |info| &info.api_version),
+        kind: "ExecCredential",
+        status: Status { token },
+    })?)
+}
+
+/// Formats for printing a site's credentials as configuration for other tools.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Format {
+    /// A .netrc `machine` line, e.g. for `curl --netrc-file /dev/stdin`
+    Netrc,
+    /// A curl config `user` line, e.g. for `curl -K -`
+    CurlConfig,
+}
+
+impl Format {
+    /// Renders the credentials for the site `url` as a line of configuration.
+    pub fn render(self, url: &str, username: Option<&str>, password: &str) -> Result<String> {
+        Ok(match self {
+            Format::Netrc => {
+                let url = Url::parse(url).context(tr("invalid-url", &[]))?;
+                let host = url.host_str().context(tr("no-host", &[]))?;
+                let mut line = format!("machine {}", quote(host));
+                if let Some(username) = username {
+                    line += &format!(" login {}", quote(username));
+                }
+                line + &format!(" password {}\n", quote(password))
+            }
+            Format::CurlConfig => {
+                let user = match username {
+                    Some(username) => format!("{username}:{password}"),
+                    None => format!(":{password}"),
+                };
+                format!("user = {}\n", quote(&user))
+            }
+        })
+    }
+}
+
+/// Quotes `token` for a .netrc or curl config file if it needs it, in the syntax both curl and
+/// recent GNU inetutils accept.
+fn quote(token: &str) -> String {
+    if !token.is_empty() && !token.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        return token.to_owned();
+    }
+    let mut quoted = String::from('"');
+    for c in token.chars() {
+        match c {
+            '"' | '\\' => quoted.extend(['\\', c]),
+            '\n' => quoted += "\\n",
+            '\t' => quoted += "\\t",
+            c => quoted.push(c),
+        }
+    }
+    quoted + "\""
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn git_request() {
+        let request = GitRequest::parse(
+            "protocol=https\nhost=example.com:8443\npath=a/b.git\nusername=bob\n\nhost=x\n",
+        );
+        assert_eq!(request.site().as_deref(), Some("https://example.com:8443/"));
+        assert_eq!(request.get("username"), Some("bob"));
+        assert_eq!(GitRequest::parse("host=example.com\n").site(), None);
+    }
+
+    #[test]
+    fn docker() -> Result<()> {
+        assert_eq!(
+            docker_site("https://index.docker.io/v1/")?,
+            "https://index.docker.io/"
+        );
+        assert_eq!(
+            docker_site("registry.example.com:5000\n")?,
+            "https://registry.example.com:5000/"
+        );
+        assert_eq!(
+            serde_json::to_string(&DockerCredentials {
+                server_url: "a.com".into(),
+                username: "bob".into(),
+                secret: "x",
+            })?,
+            r#"{"ServerURL":"a.com","Username":"bob","Secret":"x"}"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn kube() -> Result<()> {
+        let info = KubeExecInfo::parse(
+            r#"{"kind":"ExecCredential","apiVersion":"client.authentication.k8s.io/v1beta1",
+                "spec":{"cluster":{"server":"https://k8s.example.com:6443"},"interactive":true}}"#,
+        )?;
+        assert_eq!(info.server(), Some("https://k8s.example.com:6443"));
+        assert_eq!(
+            kube_exec_credential(Some(&info), "t")?,
+            concat!(
+                r#"{"apiVersion":"client.authentication.k8s.io/v1beta1","kind":"ExecCredential","#,
+                r#""status":{"token":"t"}}"#,
+            )
+        );
+        let info = KubeExecInfo::parse(r#"{"apiVersion":"client.authentication.k8s.io/v1"}"#)?;
+        assert_eq!(info.server(), None);
+        assert!(
+            kube_exec_credential(None, "t")?
+                .contains(r#""apiVersion":"client.authentication.k8s.io/v1""#)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn formats() -> Result<()> {
+        assert_eq!(
+            Format::Netrc.render("https://bob@example.com/", Some("bob"), "pw")?,
+            "machine example.com login bob password pw\n"
+        );
+        assert_eq!(
+            Format::Netrc.render("https://example.com/", None, r#"a "b"\"#)?,
+            "machine example.com password \"a \\\"b\\\"\\\\\"\n"
+        );
+        assert_eq!(
+            Format::CurlConfig.render("https://example.com/", Some("bob"), "a b")?,
+            "user = \"bob:a b\"\n"
+        );
+        assert!(
+            Format::Netrc
+                .render("mailto:bob@example.com", None, "pw")
+                .is_err()
+        );
+        Ok(())
+    }
+}
@@ -13,80 +13,253 @@
 // limitations under the License.
 
 use anyhow::{Context, Result};
-use argon2::{Algorithm, Argon2, Params, Version};
+#[cfg(feature = "keyring")]
 use keyring::Entry;
+pub(crate) use onepass::derive::{
+    Argon2Config, BalloonConfig, DerivationVersion, KdfAlgorithm, KdfConfig, SaltInputs,
+    ScryptConfig, key_salt,
+};
+use onepass::derive::{labeled_seed, password_seed};
 use rand_chacha::ChaCha20Rng;
 use rand_core::{RngCore, SeedableRng};
-use rpassword::prompt_password;
+#[cfg(feature = "keyring")]
 use whoami::fallible::username;
-use zeroize::{Zeroize, Zeroizing};
+use zeroize::Zeroizing;
 
-pub(crate) fn read_password(use_keyring: bool, confirm: bool) -> Result<Zeroizing<String>> {
+use crate::{
+    exit::{Failure, prompt_password},
+    factor::Factors,
+    i18n::tr,
+    secmem::Locked,
+};
+
+pub(crate) fn read_password(
+    use_keyring: bool,
+    confirm: bool,
+    check: bool,
+) -> Result<Locked<String>> {
+    #[cfg(not(feature = "keyring"))]
+    let use_keyring = {
+        if use_keyring {
+            eprintln!("{}", tr("keyring-unsupported", &[]));
+        }
+        false
+    };
     let password = use_keyring
         .then(|| read_password_keyring(confirm))
         .transpose()?
         .flatten();
     if let Some(password) = password {
+        if check {
+            check_strength(&password);
+        }
         return Ok(password);
     }
-    let password: Zeroizing<String> = prompt_password("Master password: ")
-        .context("failed reading password")?
+    let password: Locked<String> = prompt_password("master-password", "master-password-prompt")
+        .context(tr("failed-reading-password", &[]))?
         .into();
     if !confirm
         .then(|| check_confirm(&password))
         .transpose()?
         .unwrap_or(true)
     {
-        anyhow::bail!("passwords don't match");
+        anyhow::bail!(tr("passwords-mismatch", &[]));
     }
+    // Confirming or storing in the keyring both suggest that this password is new.
+    if check || confirm || use_keyring {
+        check_strength(&password);
+    }
+    #[cfg(feature = "keyring")]
     if use_keyring {
         let entry = get_onepass_entry()?;
         if let Err(e) = entry.set_password(password.as_str()) {
-            eprintln!("failed storing password in keychain: {e}");
+            eprintln!("{}", tr("failed-storing-password", &[("error", &e)]));
+        }
+    }
+    Ok(password)
+}
+
+/// Prompts for a master password with the message `prompt`, without the keyring. If `new`, the
+/// password is confirmed and checked for strength.
+pub(crate) fn prompt_master_password(prompt: &str, new: bool) -> Result<Locked<String>> {
+    let password: Locked<String> = prompt_password("master-password", prompt)
+        .context(tr("failed-reading-password", &[]))?
+        .into();
+    if new {
+        if !check_confirm(&password)? {
+            anyhow::bail!(tr("passwords-mismatch", &[]));
         }
+        check_strength(&password);
     }
     Ok(password)
 }
 
-fn read_password_keyring(confirm: bool) -> Result<Option<Zeroizing<String>>> {
+#[cfg(feature = "keyring")]
+fn read_password_keyring(confirm: bool) -> Result<Option<Locked<String>>> {
     let entry = get_onepass_entry()?;
-    let password: Zeroizing<String> = match entry.get_password() {
+    let password: Locked<String> = match entry.get_password() {
         Err(keyring::Error::NoEntry) => return Ok(None),
-        r => r.context("failed getting password from keyring")?.into(),
+        r => r.context(tr("failed-getting-password", &[]))?.into(),
     };
     if !confirm
         .then(|| check_confirm(&password))
         .transpose()?
         .unwrap_or(true)
     {
-        anyhow::bail!("passwords don't match");
+        anyhow::bail!(tr("passwords-mismatch", &[]));
     }
     Ok(Some(password))
 }
 
-fn check_confirm(password: &Zeroizing<String>) -> Result<bool> {
-    let confirm: Zeroizing<String> = prompt_password("Confirm: ")
-        .context("failed reading password confirmation")?
+#[cfg(not(feature = "keyring"))]
+fn read_password_keyring(_confirm: bool) -> Result<Option<Locked<String>>> {
+    Ok(None)
+}
+
+fn check_confirm(password: &str) -> Result<bool> {
+    let confirm: Zeroizing<String> = prompt_password("confirm", "confirm-prompt")
+        .context(tr("failed-reading-confirmation", &[]))?
         .into();
-    Ok(password.as_str() == confirm.as_str())
+    Ok(password == confirm.as_str())
+}
+
+/// Prints a warning if the master password looks guessable. Every derived password is a known
+/// function of it, so anyone holding one of them can mount an offline attack on the master
+/// password.
+#[cfg(feature = "strength")]
+fn check_strength(password: &str) {
+    let entropy = zxcvbn::zxcvbn(password, &["onepass"]);
+    if entropy.score() >= zxcvbn::Score::Four {
+        return;
+    }
+    let exponent = format!("{:.0}", entropy.guesses_log10());
+    eprintln!("{}", tr("weak-master-password", &[("exponent", &exponent)]));
+    if let Some(feedback) = entropy.feedback() {
+        if let Some(warning) = feedback.warning() {
+            eprintln!("  {warning}");
+        }
+        for suggestion in feedback.suggestions() {
+            eprintln!("  {suggestion}");
+        }
+    }
+}
+
+#[cfg(not(feature = "strength"))]
+fn check_strength(_password: &str) {}
+
+/// Removes the master password from the keyring.
+#[cfg(feature = "keyring")]
+pub(crate) fn delete_password_keyring() -> Result<()> {
+    Ok(get_onepass_entry()?.delete_credential()?)
+}
+
+/// Returns the pepper in the keyring, if there is one.
+#[cfg(feature = "keyring")]
+pub(crate) fn read_pepper_keyring() -> Result<Option<Zeroizing<Vec<u8>>>> {
+    match get_pepper_entry()?.get_secret() {
+        Err(keyring::Error::NoEntry) => Ok(None),
+        r => Ok(Some(Zeroizing::new(
+            r.context(tr("failed-getting-pepper", &[]))?,
+        ))),
+    }
+}
+
+/// Stores `pepper` in the keyring.
+#[cfg(feature = "keyring")]
+pub(crate) fn store_pepper_keyring(pepper: &[u8]) -> Result<()> {
+    get_pepper_entry()?
+        .set_secret(pepper)
+        .context(tr("failed-storing-pepper", &[]))
+}
+
+#[cfg(feature = "keyring")]
+fn get_onepass_entry() -> Result<Entry> {
+    let user = username().context(tr("failed-getting-username", &[]))?;
+    Entry::new("onepass", &user).context(tr("failed-constructing-entry", &[]))
+}
+
+/// The keyring entry holding the pepper, if it is kept in the keyring rather than the TPM.
+#[cfg(feature = "keyring")]
+fn get_pepper_entry() -> Result<Entry> {
+    let user = username().context(tr("failed-getting-username", &[]))?;
+    Entry::new("onepass-pepper", &user).context(tr("failed-constructing-entry", &[]))
+}
+
+#[cfg(not(feature = "keyring"))]
+pub(crate) fn delete_password_keyring() -> Result<()> {
+    anyhow::bail!(tr("built-without-keyring", &[]));
+}
+
+#[cfg(not(feature = "keyring"))]
+pub(crate) fn read_pepper_keyring() -> Result<Option<Zeroizing<Vec<u8>>>> {
+    anyhow::bail!(tr("built-without-keyring", &[]));
 }
 
-pub fn get_onepass_entry() -> Result<Entry> {
-    let user = username().context("failed getting username")?;
-    Entry::new("onepass", &user).context("failed constructing keyring entry")
+#[cfg(not(feature = "keyring"))]
+pub(crate) fn store_pepper_keyring(_pepper: &[u8]) -> Result<()> {
+    anyhow::bail!(tr("built-without-keyring", &[]));
+}
+
+/// Returns whether `a` and `b` are equal, in time that does not depend on where they differ,
+/// for comparing secrets. It compares their BLAKE3 hashes, whose equality is constant-time.
+pub(crate) fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    blake3::hash(a) == blake3::hash(b)
+}
+
+/// The key material for a site: the KDF output for its salt, mixed with any second factors.
+///
+/// Every output for the site is derived from this. The site's password is seeded as described by
+/// its [`DerivationVersion`]; other outputs are seeded from the BLAKE3 XOF under distinct labels,
+/// so that adding an output never changes any existing one.
+pub(crate) struct KeyMaterial(Locked<[u8; 32]>);
+
+impl KeyMaterial {
+    pub fn derive(password: &str, salt: &[u8], kdf: &KdfConfig, factors: &Factors) -> Result<Self> {
+        let mut key_material = Locked::new([0u8; 32]);
+        kdf.derive(password.as_bytes(), salt, &mut *key_material)
+            .context(Failure::Kdf)?;
+        factors.mix(&mut key_material);
+        Ok(KeyMaterial(key_material))
+    }
+
+    /// Key material previously derived, e.g. from a cache.
+    pub fn from_bytes(key_material: Locked<[u8; 32]>) -> Self {
+        KeyMaterial(key_material)
+    }
+
+    /// The raw key material, for test vectors and caching.
+    pub fn expose(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Returns the RNG for the site's password.
+    pub fn password_rng(&self, version: DerivationVersion) -> Rng {
+        let mut seed = Locked::new([0u8; 32]);
+        password_seed(version, &self.0, &mut seed);
+        Rng::from_seed(&seed)
+    }
+
+    /// Returns the RNG for the output named `label`, e.g. `field:pin`.
+    pub fn labeled_rng(&self, label: &str) -> Rng {
+        let mut seed = Locked::new([0u8; 32]);
+        labeled_seed(&self.0, label, &mut seed);
+        Rng::from_seed(&seed)
+    }
+
+    /// Returns 32 bytes of key material for the output named `label`, e.g. as a private key seed.
+    #[cfg(any(feature = "keys", feature = "history"))]
+    pub fn labeled_key(&self, label: &str) -> Locked<[u8; 32]> {
+        let mut key = Locked::new([0u8; 32]);
+        self.labeled_rng(label).fill_bytes(&mut *key);
+        key
+    }
 }
 
-pub(crate) struct Rng(ChaCha20Rng);
+pub(crate) struct Rng(Locked<ChaCha20Rng>);
 
 impl Rng {
-    pub fn from_password_salt(password: Zeroizing<String>, salt: String) -> Result<Self> {
-        let mut key_material = Zeroizing::new([0u8; 32]);
-        let params =
-            Params::new(32 * 1024, 3, 1, None).map_err(|e| anyhow::anyhow!("Params::new: {e}"))?;
-        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
-            .hash_password_into(password.as_bytes(), salt.as_bytes(), &mut *key_material)
-            .map_err(|e| anyhow::anyhow!("argon2 failed: {e}"))?;
-        Ok(Rng(ChaCha20Rng::from_seed(*key_material)))
+    fn from_seed(seed: &[u8; 32]) -> Self {
+        Rng(Locked::new(ChaCha20Rng::from_seed(*seed)))
     }
 }
 
@@ -102,13 +275,50 @@ impl RngCore for Rng {
     }
 }
 
-impl Drop for Rng {
-    fn drop(&mut self) {
-        unsafe {
-            let ptr = &mut self.0 as *mut ChaCha20Rng as *mut u8;
-            let size = std::mem::size_of::<ChaCha20Rng>();
-            let slice = std::slice::from_raw_parts_mut(ptr, size);
-            slice.zeroize();
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn v1_stable() -> Result<()> {
+        use crypto_bigint::NonZero;
+        use onepass::{
+            derive::random_below,
+            randexp::{Enumerable, Expr, Quantifiable, Words},
+        };
+
+        let inputs = SaltInputs {
+            increment: 0,
+            url: "https://a.com/",
+            username: None,
+        };
+        let salt = DerivationVersion::V1.salt(None, &inputs)?;
+        let key =
+            KeyMaterial::derive("hunter2", &salt, &KdfConfig::default(), &Factors::default())?;
+        let mut rng = key.password_rng(DerivationVersion::V1);
+        let expr = Expr::parse("[a-z]{10}")?;
+        let words = Words(&[]);
+        let index = random_below(&mut rng, &NonZero::new(words.size(&expr)).unwrap());
+        assert_eq!("wwjtzfjytg", *words.gen_at(&expr, index)?);
+        Ok(())
+    }
+
+    #[test]
+    fn labeled_outputs() -> Result<()> {
+        let key = KeyMaterial(Locked::new([1u8; 32]));
+        let mut outputs: Vec<_> = ["field:a", "field:b", "field:ab"]
+            .into_iter()
+            .map(|label| key.labeled_rng(label).next_u64())
+            .collect();
+        outputs.push(key.password_rng(DerivationVersion::V1).next_u64());
+        outputs.push(key.password_rng(DerivationVersion::V2).next_u64());
+        assert_eq!(
+            key.labeled_rng("field:a").next_u64(),
+            key.labeled_rng("field:a").next_u64()
+        );
+        outputs.sort();
+        outputs.dedup();
+        assert_eq!(5, outputs.len());
+        Ok(())
     }
 }
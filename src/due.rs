@@ -0,0 +1,66 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `onepass due`: listing the sites whose passwords are older than the config's `rotate_after`,
+//! failing with [`Failure::Due`] so that scripts and cron jobs can tell.
+
+use anyhow::Result;
+use onepass::{config::Config, date::Date};
+
+use crate::{config_audit, exit::Failure, i18n::tr, porcelain::Porcelain};
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct DueArgs {
+    /// Count passwords as due after this many days, instead of the config's `rotate_after`
+    #[arg(long, value_name = "N")]
+    days: Option<u32>,
+
+    /// Print nothing, only exiting with status 8 if any password is due
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+/// Lists the sites due for rotation, failing with [`Failure::Due`] if there are any.
+pub(crate) fn command(config: &Config, args: DueArgs, porcelain: bool) -> Result<()> {
+    let (due, undated) = config_audit::due(config, args.days, Date::today());
+    if args.quiet {
+        return match due.is_empty() {
+            true => Ok(()),
+            false => Err(Failure::Due.into()),
+        };
+    }
+    if porcelain {
+        let mut out = Porcelain::new();
+        for site in &due {
+            out.line("url", site.url)?
+                .line("rotated", &site.rotated.to_string())?
+                .line("age", &site.age.to_string())?;
+        }
+    } else {
+        for site in &due {
+            println!(
+                "{}: rotated {}, {} days ago",
+                site.url, site.rotated, site.age
+            );
+        }
+        if !undated.is_empty() {
+            eprintln!("{}", tr("undated-sites", &[("count", &undated.len())]));
+        }
+    }
+    if due.is_empty() {
+        return Ok(());
+    }
+    eprintln!("{}", tr("due", &[("count", &due.len())]));
+    Err(Failure::Due.into())
+}
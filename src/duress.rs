@@ -0,0 +1,103 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Duress passwords: alternate master passwords, for unlocking under coercion, that onepass
+//! recognizes by a fingerprint in the config.
+//!
+//! Every master password derives plausible passwords for every site, so a duress password needs
+//! nothing special to make decoys; onepass behaves the same with it as with any other. The
+//! fingerprint only lets onepass run the config's duress command when it is entered. Nothing in
+//! the config tells the real master password apart from any other.
+
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use rand_core::{OsRng, TryRngCore};
+use zeroize::Zeroizing;
+
+use crate::{
+    crypto::{KdfConfig, ct_eq},
+    i18n::tr,
+};
+
+/// The length of a fingerprint's salt.
+const SALT_LEN: usize = 16;
+
+/// Returns a new fingerprint of `password`: a random salt and the hash of the password under
+/// the default KDF with it, in hex, e.g. `0123…:4567…`.
+pub(crate) fn fingerprint(password: &str) -> Result<String> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.try_fill_bytes(&mut salt)?;
+    let hash = hash(password, &salt)?;
+    Ok(format!("{}:{}", hex::encode(salt), hex::encode(*hash)))
+}
+
+/// Returns whether `password` is the one `fingerprint` was made from.
+pub(crate) fn matches(fingerprint: &str, password: &str) -> Result<bool> {
+    let invalid = || {
+        tr(
+            "invalid-fingerprint",
+            &[("fingerprint", &format!("{fingerprint:?}"))],
+        )
+    };
+    let (salt, expected) = fingerprint.split_once(':').with_context(invalid)?;
+    let salt = hex::decode(salt).ok().filter(|salt| salt.len() == SALT_LEN);
+    let salt = salt.with_context(invalid)?;
+    let expected: [u8; 32] = hex::decode(expected)
+        .ok()
+        .and_then(|expected| expected.try_into().ok())
+        .with_context(invalid)?;
+    Ok(ct_eq(&*hash(password, &salt)?, &expected))
+}
+
+/// Runs the shell command `script` in a background process, with no input or output, so that
+/// nothing on the screen shows it ran.
+pub(crate) fn alert(script: &str) {
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(script)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    let _ = command.spawn();
+}
+
+/// Hashes `password` with `salt` under the default KDF, which stays the same whatever the
+/// config's KDF is.
+fn hash(password: &str, salt: &[u8]) -> Result<Zeroizing<[u8; 32]>> {
+    let mut hash = Zeroizing::new([0u8; 32]);
+    KdfConfig::default().derive(password.as_bytes(), salt, &mut *hash)?;
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprints() -> Result<()> {
+        let fingerprint = fingerprint("under duress")?;
+        assert_eq!(SALT_LEN * 2 + 1 + 64, fingerprint.len());
+        assert!(matches(&fingerprint, "under duress")?);
+        assert!(!matches(&fingerprint, "the real one")?);
+        assert_ne!(fingerprint, super::fingerprint("under duress")?);
+        for bad in ["", "00:00", &fingerprint[1..]] {
+            assert!(matches(bad, "under duress").is_err(), "{bad}");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Exit statuses that tell classes of failure apart, for scripts, and `--non-interactive`, under
+//! which onepass fails rather than prompting or writing the config.
+//!
+//! Errors are classed by a [`Failure`] anywhere in their chain, added as context or returned as
+//! the error itself; any other error exits with 1, and clap's usage errors with 2.
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::Result;
+
+use crate::i18n::tr;
+
+/// Whether prompting and writing the config are to fail, set once from `--non-interactive`.
+static NON_INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// A class of failure, with an exit status of its own.
+#[derive(Debug)]
+pub(crate) enum Failure {
+    /// The config file is missing or invalid
+    Config,
+    /// A site that has to be configured isn't
+    UnknownSite(String),
+    /// The KDF failed, e.g. for lack of memory
+    Kdf,
+    /// Something would have to be prompted for under `--non-interactive`, by its message id
+    Prompt(&'static str),
+    /// The config would have to be written under `--non-interactive`
+    ReadOnly,
+    /// Passwords are due for rotation, which `due` has already reported; not an error as such
+    Due,
+}
+
+impl Failure {
+    /// The exit status of the failure.
+    pub(crate) fn status(&self) -> u8 {
+        match self {
+            Failure::Config => 3,
+            Failure::UnknownSite(_) => 4,
+            Failure::Kdf => 5,
+            Failure::Prompt(_) => 6,
+            Failure::ReadOnly => 7,
+            Failure::Due => 8,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Config => f.write_str(&tr("config-failure", &[])),
+            Failure::UnknownSite(site) => f.write_str(&tr("unknown-site", &[("site", site)])),
+            Failure::Kdf => f.write_str(&tr("kdf-failure", &[])),
+            Failure::Prompt(what) => f.write_str(&tr("would-prompt", &[("what", &tr(what, &[]))])),
+            Failure::ReadOnly => f.write_str(&tr("read-only", &[])),
+            Failure::Due => f.write_str(&tr("passwords-due", &[])),
+        }
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// Returns the exit status for `error`.
+pub(crate) fn status(error: &anyhow::Error) -> u8 {
+    error.downcast_ref::<Failure>().map_or(1, Failure::status)
+}
+
+/// Makes prompts and config writes fail from now on.
+pub(crate) fn set_non_interactive() {
+    NON_INTERACTIVE.store(true, Ordering::Relaxed);
+}
+
+/// Returns whether prompts and config writes fail.
+pub(crate) fn non_interactive() -> bool {
+    NON_INTERACTIVE.load(Ordering::Relaxed)
+}
+
+/// Prompts for `what`, the id of a message like `master-password`, on the terminal without
+/// echoing, unless under `--non-interactive`. The prompt is the message `prompt`.
+pub(crate) fn prompt_password(what: &'static str, prompt: &str) -> Result<String> {
+    if non_interactive() {
+        return Err(Failure::Prompt(what).into());
+    }
+    Ok(rpassword::prompt_password(tr(prompt, &[]))?)
+}
+
+/// Prints `error` and its causes on stderr, like anyhow does but in the user's language.
+pub(crate) fn report(error: &anyhow::Error) {
+    if let Some(Failure::Due) = error.downcast_ref() {
+        return;
+    }
+    eprintln!("{}: {error}", tr("error", &[]));
+    let mut causes = error.chain().skip(1).peekable();
+    if causes.peek().is_some() {
+        eprintln!("\n{}:", tr("caused-by", &[]));
+        for cause in causes {
+            eprintln!("    {cause}");
+        }
+    }
+}
+
+/// Fails under `--non-interactive`, before anything writes the config.
+pub(crate) fn writable() -> Result<()> {
+    if non_interactive() {
+        return Err(Failure::ReadOnly.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+
+    use super::*;
+
+    #[test]
+    fn statuses() {
+        let error = anyhow::anyhow!("bad yaml").context(Failure::Config);
+        assert_eq!(3, status(&error));
+        assert_eq!(tr("config-failure", &[]), error.to_string());
+        let error = anyhow::Error::from(Failure::UnknownSite("a.com".into())).context("askpass");
+        assert_eq!(4, status(&error));
+        let error: Result<()> = Err(anyhow::anyhow!("out of memory")).context(Failure::Kdf);
+        assert_eq!(5, status(&error.unwrap_err()));
+        assert_eq!(8, status(&Failure::Due.into()));
+        assert_eq!(1, status(&anyhow::anyhow!("other")));
+    }
+}
@@ -0,0 +1,338 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Exports of the configured sites in other password managers' import formats, for sharing with
+//! people who use them.
+
+use std::{
+    fmt::Write,
+    io::{Write as _, stdout},
+};
+
+use anyhow::Result;
+use base64::{Engine, prelude::BASE64_STANDARD};
+use clap::ValueEnum;
+use onepass::config::Config;
+use rand_chacha::ChaCha20Rng;
+use rand_core::SeedableRng;
+use serde::Serialize;
+use zeroize::Zeroizing;
+
+use crate::{KeyArgs, batch, kit, pass_name, raw::Encoding, site_secrets, words};
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct ExportArgs {
+    /// The format to export in
+    #[arg(long, value_enum)]
+    format: Format,
+
+    /// Also derive and export the sites' passwords and fields, reading the master password
+    #[arg(long)]
+    with_secrets: bool,
+
+    #[command(flatten)]
+    key: KeyArgs,
+}
+
+/// The formats sites can be exported in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Format {
+    /// KeePass 2 XML, for KeePass's "KeePass XML (2.x)" import
+    KeepassXml,
+    /// Bitwarden's unencrypted JSON export, for its "Bitwarden (json)" import
+    BitwardenJson,
+}
+
+/// A site to export.
+pub(crate) struct Entry {
+    /// The site's name, as `onepass ls` lists it
+    pub title: String,
+    pub url: String,
+    pub username: Option<String>,
+    /// How the site's secrets are derived, for regenerating them with onepass
+    pub notes: String,
+    pub secrets: Option<Secrets>,
+}
+
+/// The derived secrets of a site, with `--with-secrets`.
+pub(crate) struct Secrets {
+    pub password: Zeroizing<String>,
+    /// The site's fields, by name
+    pub fields: Vec<(String, Zeroizing<String>)>,
+    /// The site's configured TOTP secret, if any
+    pub totp_secret: Option<String>,
+}
+
+impl Format {
+    pub fn render(self, entries: &[Entry]) -> Result<Zeroizing<String>> {
+        Ok(match self {
+            Format::KeepassXml => keepass_xml(entries),
+            Format::BitwardenJson => bitwarden_json(entries)?,
+        })
+    }
+}
+
+/// Prints the configured sites in `args.format`, with their secrets if `args.with_secrets`.
+pub(crate) fn command(config: &Config, args: ExportArgs) -> Result<()> {
+    let words = words::List::open(config.words_paths(), config.wordlist, config.cache_dir())?;
+    let words = words::Filters::new(&words);
+    let password = args
+        .with_secrets
+        .then(|| args.key.read_password(config))
+        .transpose()?;
+
+    let mut sites: Vec<_> = config.sites.keys().collect();
+    sites.sort();
+    let mut found = Vec::with_capacity(sites.len());
+    for name in sites {
+        if let Some((_, site)) = config.find_site(name)? {
+            found.push((name, site));
+        }
+    }
+    let entries = batch::map(args.key.batch_threads(config)?, found, |(name, site)| {
+        let secrets = match &password {
+            Some(password) => Some(site_secrets(
+                config,
+                &words,
+                &args.key,
+                password,
+                (name, site),
+            )?),
+            None => None,
+        };
+        Ok(Entry {
+            title: pass_name(name).into(),
+            url: name.clone(),
+            username: site.username.clone(),
+            notes: kit::site_details(site).join("\n"),
+            secrets,
+        })
+    })?;
+    stdout().write_all(args.format.render(&entries)?.as_bytes())?;
+    Ok(())
+}
+
+fn keepass_xml(entries: &[Entry]) -> Zeroizing<String> {
+    let mut res = Zeroizing::new(String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>\n",
+        "<KeePassFile>\n",
+        "\t<Meta>\n\t\t<Generator>onepass</Generator>\n\t</Meta>\n",
+        "\t<Root>\n\t\t<Group>\n",
+    )));
+    writeln!(res, "\t\t\t<UUID>{}</UUID>", keepass_uuid("group")).unwrap();
+    res.push_str("\t\t\t<Name>onepass</Name>\n");
+    for entry in entries {
+        res.push_str("\t\t\t<Entry>\n");
+        writeln!(res, "\t\t\t\t<UUID>{}</UUID>", keepass_uuid(&entry.url)).unwrap();
+        keepass_string(&mut res, "Title", &entry.title, false);
+        if let Some(username) = &entry.username {
+            keepass_string(&mut res, "UserName", username, false);
+        }
+        if let Some(secrets) = &entry.secrets {
+            keepass_string(&mut res, "Password", &secrets.password, true);
+            for (name, value) in &secrets.fields {
+                keepass_string(&mut res, name, value, true);
+            }
+        }
+        keepass_string(&mut res, "URL", &entry.url, false);
+        keepass_string(&mut res, "Notes", &entry.notes, false);
+        res.push_str("\t\t\t</Entry>\n");
+    }
+    res.push_str("\t\t</Group>\n\t</Root>\n</KeePassFile>\n");
+    res
+}
+
+/// Returns the UUID of the KeePass entry or group named `name`, which is derived from it so that
+/// exporting again updates entries rather than duplicating them.
+fn keepass_uuid(name: &str) -> String {
+    let hash = blake3::derive_key("onepass keepass-xml uuid", name.as_bytes());
+    BASE64_STANDARD.encode(&hash[..16])
+}
+
+fn bitwarden_json(entries: &[Entry]) -> Result<Zeroizing<String>> {
+    #[derive(Serialize)]
+    struct Export<'a> {
+        encrypted: bool,
+        folders: [(); 0],
+        items: Vec<Item<'a>>,
+    }
+    #[derive(Serialize)]
+    struct Item<'a> {
+        id: String,
+        /// 1 for logins
+        #[serde(rename = "type")]
+        kind: u8,
+        name: &'a str,
+        notes: &'a str,
+        fields: Vec<Field<'a>>,
+        login: Login<'a>,
+    }
+    #[derive(Serialize)]
+    struct Field<'a> {
+        name: &'a str,
+        value: &'a str,
+        /// 1 for hidden fields
+        #[serde(rename = "type")]
+        kind: u8,
+    }
+    #[derive(Serialize)]
+    struct Login<'a> {
+        uris: [Uri<'a>; 1],
+        username: Option<&'a str>,
+        password: Option<&'a str>,
+        totp: Option<&'a str>,
+    }
+    #[derive(Serialize)]
+    struct Uri<'a> {
+        uri: &'a str,
+    }
+
+    let items = entries
+        .iter()
+        .map(|entry| {
+            let secrets = entry.secrets.as_ref();
+            // Like KeePass UUIDs, item ids are derived from the URL.
+            let seed = blake3::derive_key("onepass bitwarden-json id", entry.url.as_bytes());
+            Ok(Item {
+                id: Encoding::Uuid
+                    .encode(&mut ChaCha20Rng::from_seed(seed), None)?
+                    .to_string(),
+                kind: 1,
+                name: &entry.title,
+                notes: &entry.notes,
+                fields: secrets.map_or_else(Vec::new, |secrets| {
+                    secrets
+                        .fields
+                        .iter()
+                        .map(|(name, value)| Field {
+                            name,
+                            value,
+                            kind: 1,
+                        })
+                        .collect()
+                }),
+                login: Login {
+                    uris: [Uri { uri: &entry.url }],
+                    username: entry.username.as_deref(),
+                    password: secrets.map(|secrets| secrets.password.as_str()),
+                    totp: secrets.and_then(|secrets| secrets.totp_secret.as_deref()),
+                },
+            })
+        })
+        .collect::<Result<_>>()?;
+    let export = Export {
+        encrypted: false,
+        folders: [],
+        items,
+    };
+    let mut res = Zeroizing::new(serde_json::to_string_pretty(&export)?);
+    res.push('\n');
+    Ok(res)
+}
+
+fn keepass_string(res: &mut String, key: &str, value: &str, protect: bool) {
+    res.push_str("\t\t\t\t<String>\n\t\t\t\t\t<Key>");
+    push_escaped(res, key);
+    res.push_str(if protect {
+        "</Key>\n\t\t\t\t\t<Value ProtectInMemory=\"True\">"
+    } else {
+        "</Key>\n\t\t\t\t\t<Value>"
+    });
+    push_escaped(res, value);
+    res.push_str("</Value>\n\t\t\t\t</String>\n");
+}
+
+/// Appends `s` to `res`, escaped for XML text and attribute values.
+fn push_escaped(res: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            c => res.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> [Entry; 2] {
+        [
+            Entry {
+                title: "a.com".into(),
+                url: "https://a.com/".into(),
+                username: Some("bob".into()),
+                notes: "schema [a-z]{10}\nfield pin [0-9]{4}".into(),
+                secrets: Some(Secrets {
+                    password: Zeroizing::new("a<b&c".into()),
+                    fields: vec![("pin".into(), Zeroizing::new("1234".into()))],
+                    totp_secret: Some("JBSWY3DPEHPK3PXP".into()),
+                }),
+            },
+            Entry {
+                title: "b.com".into(),
+                url: "https://b.com/".into(),
+                username: None,
+                notes: "schema [a-z]{10}".into(),
+                secrets: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn keepass() -> Result<()> {
+        let xml = Format::KeepassXml.render(&entries())?;
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.contains(
+            "<Key>Password</Key>\n\t\t\t\t\t<Value ProtectInMemory=\"True\">a&lt;b&amp;c</Value>"
+        ));
+        assert!(xml.contains("<Key>pin</Key>\n\t\t\t\t\t<Value ProtectInMemory=\"True\">1234"));
+        assert!(xml.contains("<Value>schema [a-z]{10}\nfield pin [0-9]{4}</Value>"));
+        assert_eq!(xml.matches("<Entry>").count(), 2);
+        assert_eq!(xml.matches("<Key>Password</Key>").count(), 1);
+        assert_eq!(xml.matches("<Key>UserName</Key>").count(), 1);
+        assert_ne!(
+            keepass_uuid("https://a.com/"),
+            keepass_uuid("https://b.com/")
+        );
+        assert_eq!(keepass_uuid("https://a.com/").len(), 24);
+        Ok(())
+    }
+
+    #[test]
+    fn bitwarden() -> Result<()> {
+        let json: serde_json::Value =
+            serde_json::from_str(&Format::BitwardenJson.render(&entries())?)?;
+        assert_eq!(json["encrypted"], false);
+        let [a, b] = json["items"].as_array().unwrap().as_slice() else {
+            panic!("expected two items");
+        };
+        assert_eq!(a["name"], "a.com");
+        assert_eq!(a["type"], 1);
+        assert_eq!(a["login"]["uris"][0]["uri"], "https://a.com/");
+        assert_eq!(a["login"]["username"], "bob");
+        assert_eq!(a["login"]["password"], "a<b&c");
+        assert_eq!(a["login"]["totp"], "JBSWY3DPEHPK3PXP");
+        assert_eq!(a["fields"][0]["name"], "pin");
+        assert_eq!(a["fields"][0]["value"], "1234");
+        assert_eq!(b["login"]["password"], serde_json::Value::Null);
+        assert_eq!(b["fields"], serde_json::json!([]));
+        assert_eq!(a["id"].as_str().map(str::len), Some(36));
+        assert_ne!(a["id"], b["id"]);
+        Ok(())
+    }
+}
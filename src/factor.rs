@@ -0,0 +1,251 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    fs::read,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result};
+use base64::{Engine, prelude::BASE64_STANDARD};
+pub(crate) use onepass::config::{Fido2Config, PepperSource};
+use zeroize::Zeroizing;
+
+use crate::{
+    crypto::read_pepper_keyring,
+    i18n::tr,
+    ssh_agent::{self, Agent},
+    tpm,
+};
+
+const FACTORS_CONTEXT: &str = "onepass 2025-06 second factors";
+const YUBIKEY_CHALLENGE_CONTEXT: &str = "onepass 2025-06 yubikey challenge";
+const FIDO2_SALT_CONTEXT: &str = "onepass 2025-06 fido2 salt";
+const FIDO2_CLIENT_DATA_CONTEXT: &str = "onepass 2025-06 fido2 client data";
+const SSH_AGENT_MESSAGE: &[u8] = b"onepass 2025-06 ssh-agent second factor";
+
+/// Retrieves the pepper from `source`; see [`Factors::add_pepper`].
+pub(crate) fn read_pepper(source: PepperSource, dir: &Path) -> Result<Zeroizing<Vec<u8>>> {
+    Ok(match source {
+        PepperSource::Tpm => tpm::unseal(dir)?,
+        PepperSource::Keyring => read_pepper_keyring()?.context(tr("no-pepper", &[]))?,
+    })
+}
+
+/// Secrets beyond the master password that are mixed into the key material.
+///
+/// With no factors, the key material is exactly the KDF output, so adding support for factors does
+/// not change any existing passwords. Otherwise the KDF output is used as a BLAKE3 key over a
+/// digest of all of the factors, each tagged with a label so that e.g. a keyfile can never stand
+/// in for some other kind of factor.
+#[derive(Default)]
+pub(crate) struct Factors(Vec<(&'static str, Zeroizing<Vec<u8>>)>);
+
+impl Factors {
+    pub fn push(&mut self, label: &'static str, secret: Zeroizing<Vec<u8>>) {
+        self.0.push((label, secret));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn labels(&self) -> impl Iterator<Item = &'static str> {
+        self.0.iter().map(|(label, _)| *label)
+    }
+
+    pub fn add_keyfile(&mut self, path: &Path) -> Result<()> {
+        let contents = Zeroizing::new(
+            read(path)
+                .with_context(|| tr("failed-reading-keyfile", &[("path", &path.display())]))?,
+        );
+        if contents.is_empty() {
+            anyhow::bail!(tr("empty-keyfile", &[("path", &path.display())]));
+        }
+        let hash = blake3::hash(&contents);
+        self.push("keyfile", Zeroizing::new(hash.as_bytes().to_vec()));
+        Ok(())
+    }
+
+    /// Sends a challenge derived from `salt` to the HMAC-SHA1 challenge-response `slot` of an
+    /// attached YubiKey via `ykman`, and adds the response as a factor.
+    pub fn add_yubikey(&mut self, slot: u8, salt: &[u8]) -> Result<()> {
+        let challenge = blake3::derive_key(YUBIKEY_CHALLENGE_CONTEXT, salt);
+        let output = Command::new("ykman")
+            .args([
+                "otp",
+                "calculate",
+                &slot.to_string(),
+                &hex::encode(challenge),
+            ])
+            .output()
+            .context(tr("failed-running", &[("program", &"ykman")]))?;
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!(tr(
+                "program-failed-with",
+                &[("program", &"ykman"), ("error", &error.trim())]
+            ));
+        }
+        let stdout = Zeroizing::new(output.stdout);
+        let response =
+            std::str::from_utf8(&stdout).context(tr("invalid-output", &[("program", &"ykman")]))?;
+        let response = Zeroizing::new(
+            hex::decode(response.trim()).context(tr("invalid-output", &[("program", &"ykman")]))?,
+        );
+        if response.len() != 20 {
+            anyhow::bail!(tr("yubikey-length", &[("length", &response.len())]));
+        }
+        self.push("yubikey", response);
+        Ok(())
+    }
+
+    /// Requests an assertion with the hmac-secret extension from a FIDO2 authenticator via
+    /// `fido2-assert`, using a salt derived from `salt`, and adds the hmac-secret output as a
+    /// factor.
+    pub fn add_fido2(&mut self, config: &Fido2Config, salt: &[u8]) -> Result<()> {
+        let device = match &config.device {
+            Some(device) => device.clone(),
+            None => fido2_first_device()?,
+        };
+        let client_data_hash = blake3::derive_key(FIDO2_CLIENT_DATA_CONTEXT, salt);
+        let hmac_salt = blake3::derive_key(FIDO2_SALT_CONTEXT, salt);
+        let input = Zeroizing::new(format!(
+            "{}\n{}\n{}\n{}\n",
+            BASE64_STANDARD.encode(client_data_hash),
+            config.rp_id,
+            config.credential_id,
+            BASE64_STANDARD.encode(hmac_salt),
+        ));
+        let mut child = Command::new("fido2-assert")
+            .args(["-G", "-h", &device])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .context(tr("failed-running", &[("program", &"fido2-assert")]))?;
+        child
+            .stdin
+            .take()
+            .context(tr("no-stdin", &[]))?
+            .write_all(input.as_bytes())?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            anyhow::bail!(tr("program-failed", &[("program", &"fido2-assert")]));
+        }
+        let stdout = Zeroizing::new(output.stdout);
+        let stdout = std::str::from_utf8(&stdout)
+            .context(tr("invalid-output", &[("program", &"fido2-assert")]))?;
+        // The hmac-secret is the last line of output when -h is given.
+        let secret = stdout
+            .lines()
+            .last()
+            .context(tr("invalid-output", &[("program", &"fido2-assert")]))?;
+        let secret = Zeroizing::new(
+            BASE64_STANDARD
+                .decode(secret.trim())
+                .context(tr("invalid-output", &[("program", &"fido2-assert")]))?,
+        );
+        if secret.len() != 32 {
+            anyhow::bail!(tr("hmac-secret-length", &[("length", &secret.len())]));
+        }
+        self.push("fido2", secret);
+        Ok(())
+    }
+
+    /// Asks ssh-agent to sign a fixed message with the key given as an OpenSSH public key line,
+    /// and adds the signature as a factor. Only key types with deterministic signatures are
+    /// accepted.
+    pub fn add_ssh_agent(&mut self, public_key: &str) -> Result<()> {
+        let blob = ssh_agent::parse_public_key(public_key)?;
+        let key_type = ssh_agent::key_type(&blob)?;
+        if !["ssh-ed25519", "ssh-rsa"].contains(&key_type) {
+            anyhow::bail!(tr("nondeterministic-key", &[("type", &key_type)]));
+        }
+        let signature = Agent::connect()?.sign(&blob, SSH_AGENT_MESSAGE)?;
+        self.push("ssh-agent", signature);
+        Ok(())
+    }
+
+    /// Retrieves the pepper from `source` and adds it as a factor. `dir` is the directory holding
+    /// any pepper-related files, i.e. the config directory.
+    pub fn add_pepper(&mut self, source: PepperSource, dir: &Path) -> Result<()> {
+        self.push("pepper", read_pepper(source, dir)?);
+        Ok(())
+    }
+
+    pub fn mix(&self, key_material: &mut [u8; 32]) {
+        if self.is_empty() {
+            return;
+        }
+        let mut hasher = blake3::Hasher::new_derive_key(FACTORS_CONTEXT);
+        for (label, secret) in &self.0 {
+            hasher.update(&(label.len() as u64).to_le_bytes());
+            hasher.update(label.as_bytes());
+            hasher.update(&(secret.len() as u64).to_le_bytes());
+            hasher.update(secret);
+        }
+        let digest = hasher.finalize();
+        let mixed = blake3::keyed_hash(key_material, digest.as_bytes());
+        key_material.copy_from_slice(mixed.as_bytes());
+    }
+}
+
+fn fido2_first_device() -> Result<String> {
+    let output = Command::new("fido2-token")
+        .arg("-L")
+        .output()
+        .context(tr("failed-running", &[("program", &"fido2-token")]))?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!(tr(
+            "program-failed-with",
+            &[("program", &"fido2-token"), ("error", &error.trim())]
+        ));
+    }
+    // Lines look like "/dev/hidraw4: vendor=0x1050, product=0x0407 (Yubico YubiKey OTP+FIDO+CCID)"
+    let stdout = String::from_utf8(output.stdout)
+        .context(tr("invalid-output", &[("program", &"fido2-token")]))?;
+    let line = stdout.lines().next().context(tr("no-fido2-devices", &[]))?;
+    let (device, _) = line.split_once(": ").unwrap_or((line, ""));
+    Ok(device.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_factors_is_identity() {
+        let mut key = [7u8; 32];
+        Factors::default().mix(&mut key);
+        assert_eq!([7u8; 32], key);
+    }
+
+    #[test]
+    fn factors_are_labeled() {
+        let secret = || Zeroizing::new(b"secret".to_vec());
+        let mut a = Factors::default();
+        a.push("keyfile", secret());
+        let mut b = Factors::default();
+        b.push("other", secret());
+        let (mut ka, mut kb) = ([0u8; 32], [0u8; 32]);
+        a.mix(&mut ka);
+        b.mix(&mut kb);
+        assert_ne!([0u8; 32], ka);
+        assert_ne!(ka, kb);
+    }
+}
@@ -0,0 +1,257 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An opt-in log of which sites' secrets were derived and when, never the secrets themselves,
+//! kept in a `history` file next to the config. Each event is sealed to an X25519 key derived
+//! from the master password, so recording one needs only the public key in the file's first
+//! line, and reading them needs the master password.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce, aead::Aead};
+use onepass::date::Date;
+use rand_core::{OsRng, TryRngCore};
+use serde::{Deserialize, Serialize};
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+use crate::i18n::tr;
+
+/// The name of the log's file, in the config directory.
+pub(crate) const FILE: &str = "history";
+
+/// The start of the log's first line, which the public key follows.
+const HEADER: &str = "onepass-history v1 ";
+
+/// The derivation of a site's secret.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub(crate) struct Event {
+    /// Seconds since the Unix epoch
+    pub time: u64,
+    /// The site's canonical URL, with its username if any, as salted
+    pub url: String,
+    pub increment: u32,
+    /// The label of the output derived, like `field:pin` or `raw`, if not the password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+impl Event {
+    /// Returns an event for the site `url` now.
+    pub fn now(url: &str, increment: u32, output: Option<&str>) -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        Event {
+            time,
+            url: url.into(),
+            increment,
+            output: output.map(Into::into),
+        }
+    }
+
+    /// The event's time in UTC, like `2025-06-01 12:34:56`.
+    pub fn time(&self) -> String {
+        let secs = self.time;
+        format!(
+            "{} {:02}:{:02}:{:02}",
+            Date::from_unix(secs),
+            secs / 3600 % 24,
+            secs / 60 % 60,
+            secs % 60
+        )
+    }
+
+    /// The site's canonical URL, without its username.
+    pub fn site(&self) -> String {
+        without_username(&self.url)
+    }
+}
+
+/// Returns the URL `url` without its username, or a raw identifier as it is.
+pub(crate) fn without_username(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(mut parsed) if !parsed.username().is_empty() => {
+            let _ = parsed.set_username("");
+            parsed.into()
+        }
+        _ => url.into(),
+    }
+}
+
+/// Starts a log at `path` sealed to the public key of `identity`, failing if there is one.
+pub(crate) fn init(path: &Path, identity: &[u8; 32]) -> Result<()> {
+    let public = PublicKey::from(&StaticSecret::from(*identity));
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .with_context(|| tr("failed-creating", &[("path", &path.display())]))?;
+    writeln!(
+        file,
+        "{HEADER}{}",
+        BASE64_STANDARD.encode(public.as_bytes())
+    )?;
+    Ok(())
+}
+
+/// Appends `event` to the log at `path`, if there is one.
+pub(crate) fn record(path: &Path, event: &Event) -> Result<()> {
+    let file = match fs::File::open(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        file => file.with_context(|| tr("failed-opening", &[("path", &path.display())]))?,
+    };
+    let mut header = String::new();
+    BufReader::new(file).read_line(&mut header)?;
+    let public = header
+        .trim_end()
+        .strip_prefix(HEADER)
+        .and_then(|public| BASE64_STANDARD.decode(public).ok())
+        .and_then(|public| <[u8; 32]>::try_from(public).ok())
+        .with_context(|| tr("invalid-history", &[("path", &path.display())]))?;
+    let sealed = seal(&PublicKey::from(public), &serde_json::to_vec(event)?)?;
+    // One write per line, so that concurrent runs don't interleave events.
+    let line = format!("{}\n", BASE64_STANDARD.encode(sealed));
+    let mut file = OpenOptions::new().append(true).open(path)?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Reads the events of the log at `path`, oldest first, opening them with `identity`.
+pub(crate) fn read(path: &Path, identity: &[u8; 32]) -> Result<Vec<Event>> {
+    let text =
+        fs::read_to_string(path).with_context(|| tr("no-history", &[("path", &path.display())]))?;
+    let mut lines = text.lines();
+    let identity = StaticSecret::from(*identity);
+    let public = BASE64_STANDARD.encode(PublicKey::from(&identity).as_bytes());
+    anyhow::ensure!(
+        lines.next() == Some(&format!("{HEADER}{public}")),
+        tr("history-other-key", &[])
+    );
+    let mut events = Vec::new();
+    for (n, line) in lines.enumerate() {
+        let invalid = || {
+            tr(
+                "invalid-event",
+                &[("line", &(n + 2)), ("path", &path.display())],
+            )
+        };
+        let sealed = BASE64_STANDARD.decode(line).with_context(invalid)?;
+        let event = open(&identity, &sealed).with_context(invalid)?;
+        events.push(serde_json::from_slice(&event).with_context(invalid)?);
+    }
+    Ok(events)
+}
+
+/// Encrypts `plaintext` to `public` with a fresh ephemeral key: the ephemeral public key, then
+/// the plaintext sealed with ChaCha20-Poly1305 under a key derived from their shared secret.
+fn seal(public: &PublicKey, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut ephemeral = Zeroizing::new([0; 32]);
+    OsRng.try_fill_bytes(&mut *ephemeral)?;
+    let ephemeral = StaticSecret::from(*ephemeral);
+    let ephemeral_public = PublicKey::from(&ephemeral);
+    let shared = ephemeral.diffie_hellman(public);
+    anyhow::ensure!(shared.was_contributory(), tr("invalid-history-key", &[]));
+    let cipher = cipher(shared.as_bytes(), &ephemeral_public, public);
+    let mut sealed = ephemeral_public.as_bytes().to_vec();
+    // Every key is used once, so a fixed nonce is never reused.
+    let ciphertext = cipher.encrypt(&Nonce::default(), plaintext);
+    sealed.extend(ciphertext.expect("events are far shorter than a ChaCha20 keystream"));
+    Ok(sealed)
+}
+
+/// Decrypts `sealed`, as sealed by [`seal`] to the public key of `identity`.
+fn open(identity: &StaticSecret, sealed: &[u8]) -> Result<Vec<u8>> {
+    anyhow::ensure!(sealed.len() >= 32 + 16, tr("truncated-event", &[]));
+    let (ephemeral, ciphertext) = sealed.split_at(32);
+    let ephemeral = PublicKey::from(<[u8; 32]>::try_from(ephemeral)?);
+    let shared = identity.diffie_hellman(&ephemeral);
+    let cipher = cipher(shared.as_bytes(), &ephemeral, &PublicKey::from(identity));
+    cipher
+        .decrypt(&Nonce::default(), ciphertext)
+        .map_err(|_| anyhow::anyhow!(tr("failed-authentication", &[])))
+}
+
+/// The cipher of a sealed event, keyed from its shared secret and public keys.
+fn cipher(shared: &[u8; 32], ephemeral: &PublicKey, public: &PublicKey) -> ChaCha20Poly1305 {
+    let mut hasher = blake3::Hasher::new_derive_key("onepass 2025-06 history");
+    hasher.update(shared);
+    hasher.update(ephemeral.as_bytes());
+    hasher.update(public.as_bytes());
+    let key = Zeroizing::new(*hasher.finalize().as_bytes());
+    ChaCha20Poly1305::new(Key::from_slice(&*key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealing() -> Result<()> {
+        let identity = StaticSecret::from([7; 32]);
+        let sealed = seal(&PublicKey::from(&identity), b"hello")?;
+        assert_eq!(32 + 5 + 16, sealed.len());
+        assert_eq!(b"hello".as_slice(), open(&identity, &sealed)?);
+        assert_ne!(seal(&PublicKey::from(&identity), b"hello")?, sealed);
+        for i in [0, 32, 40] {
+            let mut tampered = sealed.clone();
+            tampered[i] ^= 1;
+            assert!(open(&identity, &tampered).is_err(), "{i}");
+        }
+        assert!(open(&StaticSecret::from([8; 32]), &sealed).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn log() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("onepass-history-test-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(FILE);
+        let event = Event::now("https://me@a.com/", 1, None);
+        record(&path, &event)?;
+        assert!(!path.exists());
+        init(&path, &[7; 32])?;
+        assert!(init(&path, &[7; 32]).is_err());
+        record(&path, &event)?;
+        record(&path, &Event::now("laptop-luks", 0, Some("field:pin")))?;
+        let events = read(&path, &[7; 32]);
+        let other = read(&path, &[8; 32]);
+        fs::remove_dir_all(&dir)?;
+        let events = events?;
+        assert_eq!(2, events.len());
+        assert_eq!(event, events[0]);
+        assert_eq!("https://a.com/", events[0].site());
+        assert_eq!(
+            ("laptop-luks", Some("field:pin")),
+            (events[1].site().as_str(), events[1].output.as_deref())
+        );
+        assert!(other.is_err());
+        assert_eq!(
+            "1970-01-02 03:04:05",
+            Event {
+                time: 97445,
+                ..event
+            }
+            .time()
+        );
+        Ok(())
+    }
+}
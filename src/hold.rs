@@ -0,0 +1,191 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Showing a secret on the terminal only until a key is pressed, for `--hold`, and then erasing
+//! the lines it took up. The secret starts out masked, and a key reveals it or copies it.
+
+#[cfg(unix)]
+use anyhow::Context;
+use anyhow::Result;
+
+use crate::i18n::tr;
+
+/// What is shown in place of a masked secret, the same whatever its length.
+#[cfg(unix)]
+const MASK: &str = "••••••••••••";
+
+/// A way to copy the secret, returning what happened, e.g. "copied to the clipboard".
+pub(crate) type Copier<'a> = &'a dyn Fn(&str) -> Result<String>;
+
+/// Writes `secret` to the terminal, masked until space is pressed, copying it with `copy` when
+/// c is, until another key is pressed, and then erases it again.
+#[cfg(unix)]
+pub(crate) fn show(secret: &str, copy: Copier) -> Result<()> {
+    use std::{
+        fs::OpenOptions,
+        io::{Read, Write},
+        os::fd::AsRawFd,
+    };
+
+    let mut tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .context(tr("failed-opening-terminal", &[]))?;
+    let fd = tty.as_raw_fd();
+    let raw = Raw::enter(fd)?;
+    // The keys, as shown under the secret.
+    let keys = tr("hold-keys", &[]);
+    let (mut revealed, mut status) = (false, keys.clone());
+    // The rows shown, to erase before showing anything else.
+    let mut rows = 0;
+    let shown = (|| -> Result<()> {
+        loop {
+            erase(&mut tty, rows)?;
+            let line = if revealed { secret } else { MASK };
+            write!(tty, "{line}\r\n{status}")?;
+            tty.flush()?;
+            rows = self::rows(line, columns(fd)) + self::rows(&status, columns(fd));
+            // The end of the input clears it too.
+            let mut key = [0u8; 1];
+            if tty.read(&mut key)? == 0 {
+                return Ok(());
+            }
+            match key[0] {
+                b' ' => revealed = !revealed,
+                b'c' => {
+                    status = match copy(secret) {
+                        Ok(copied) => format!("{copied}; {keys}"),
+                        Err(e) => {
+                            let error = format!("{e:#}");
+                            format!("{}; {keys}", tr("failed-copying", &[("error", &error)]))
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    })();
+    // Erased even after a failed read.
+    erase(&mut tty, rows)?;
+    drop(raw);
+    shown
+}
+
+/// Erases the last `rows` rows of `tty`, ending on the first of them.
+#[cfg(unix)]
+fn erase(tty: &mut impl std::io::Write, rows: usize) -> Result<()> {
+    // A count of 0 would move up a row anyway.
+    if rows > 1 {
+        write!(tty, "\x1b[{}A", rows - 1)?;
+    }
+    if rows > 0 {
+        write!(tty, "\r\x1b[J")?;
+    }
+    tty.flush()?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn show(_secret: &str, _copy: Copier) -> Result<()> {
+    anyhow::bail!(tr("hold-unsupported", &[]));
+}
+
+/// Returns the number of terminal rows `line` takes up, `columns` wide.
+fn rows(line: &str, columns: usize) -> usize {
+    line.chars().count().div_ceil(columns.max(1)).max(1)
+}
+
+/// Returns the width of the terminal `fd`, or 80 if it can't be told.
+#[cfg(unix)]
+fn columns(fd: libc::c_int) -> usize {
+    // SAFETY: winsize is plain integers, for which zero is valid.
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    // SAFETY: TIOCGWINSZ writes a winsize to the pointer, which outlives the call.
+    match unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) } {
+        0 if size.ws_col > 0 => size.ws_col.into(),
+        _ => 80,
+    }
+}
+
+/// The terminal in raw mode, so that one keypress can be read without echoing it; dropping it
+/// restores the terminal.
+#[cfg(unix)]
+struct Raw {
+    fd: libc::c_int,
+    saved: libc::termios,
+}
+
+#[cfg(unix)]
+impl Raw {
+    fn enter(fd: libc::c_int) -> Result<Self> {
+        // SAFETY: termios is plain integers, for which zero is valid.
+        let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+        // SAFETY: tcgetattr writes a termios to the pointer, which outlives the call.
+        if unsafe { libc::tcgetattr(fd, &mut saved) } != 0 {
+            return Err(std::io::Error::last_os_error())
+                .context(tr("failed-reading-terminal-mode", &[]));
+        }
+        // SAFETY: reads the termios from the pointer, which outlives the call.
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw_mode(&saved)) } != 0 {
+            return Err(std::io::Error::last_os_error())
+                .context(tr("failed-setting-terminal-mode", &[]));
+        }
+        Ok(Raw { fd, saved })
+    }
+}
+
+/// Returns the mode `saved` with keys read one at a time and not echoed. Ctrl-C and Ctrl-\ are
+/// read as keys too, rather than killing onepass with the secret still on the screen.
+#[cfg(unix)]
+fn raw_mode(saved: &libc::termios) -> libc::termios {
+    let mut raw = *saved;
+    raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+    raw.c_cc[libc::VMIN] = 1;
+    raw.c_cc[libc::VTIME] = 0;
+    raw
+}
+
+#[cfg(unix)]
+impl Drop for Raw {
+    fn drop(&mut self) {
+        // SAFETY: reads the termios from the pointer, which outlives the call.
+        unsafe { libc::tcsetattr(self.fd, libc::TCSANOW, &self.saved) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrapped_rows() {
+        assert_eq!(1, rows("", 80));
+        assert_eq!(1, rows(&"x".repeat(80), 80));
+        assert_eq!(2, rows(&"x".repeat(81), 80));
+        assert_eq!(3, rows("héllo", 2));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn keys_not_signals() {
+        // SAFETY: termios is plain integers, for which zero is valid.
+        let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+        saved.c_lflag = libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN;
+        let raw = raw_mode(&saved);
+        assert_eq!(0, raw.c_lflag & (libc::ICANON | libc::ECHO | libc::ISIG));
+        assert_eq!(libc::IEXTEN, raw.c_lflag & libc::IEXTEN);
+        assert_eq!(1, raw.c_cc[libc::VMIN]);
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A REST API on a loopback address, for `onepass serve --http`: the same calls as the JSON-RPC
+//! server, as endpoints taking JSON bodies, for clients with the server's bearer token.
+
+use std::net::SocketAddr;
+
+use anyhow::Result;
+#[cfg(feature = "http")]
+use anyhow::{anyhow, ensure};
+use serde_json::Value;
+#[cfg(feature = "http")]
+use serde_json::json;
+#[cfg(feature = "http")]
+use tiny_http::{Header, Response, Server};
+
+#[cfg(feature = "http")]
+use crate::crypto::ct_eq;
+use crate::{i18n::tr, rpc::Call};
+
+/// Listens on `addr`, which must be a loopback address since requests and responses are in the
+/// clear.
+#[cfg(feature = "http")]
+pub(crate) fn bind(addr: SocketAddr) -> Result<Server> {
+    ensure!(
+        addr.ip().is_loopback(),
+        tr("not-loopback", &[("address", &addr)])
+    );
+    Server::http(addr).map_err(|e| anyhow!("{e}"))
+}
+
+/// Parses the bearer token given with `--token`, refusing a blank one, which would let any local
+/// process in.
+pub(crate) fn parse_token(token: &str) -> Result<String, String> {
+    match token.trim().is_empty() {
+        true => Err(tr("blank-token", &[])),
+        false => Ok(token.into()),
+    }
+}
+
+/// Answers requests from clients with `token`, until the process is killed.
+#[cfg(feature = "http")]
+pub(crate) fn serve(
+    server: Server,
+    token: &str,
+    mut handle: impl FnMut(Call) -> Result<Value>,
+) -> Result<()> {
+    eprintln!(
+        "{}",
+        tr("serving-on", &[("address", &server.server_addr())])
+    );
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+    for mut request in server.incoming_requests() {
+        let authorized = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Authorization"))
+            .is_some_and(|header| authorized(header.value.as_str(), token));
+        let mut body = String::new();
+        let (status, value) = if !authorized {
+            (401, json!({"error": "missing or wrong bearer token"}))
+        } else if let Err(e) = request.as_reader().read_to_string(&mut body) {
+            (400, json!({"error": e.to_string()}))
+        } else {
+            answer(request.method().as_str(), request.url(), &body, &mut handle)
+        };
+        let response = Response::from_string(value.to_string())
+            .with_status_code(status)
+            .with_header(content_type.clone());
+        if let Err(e) = request.respond(response) {
+            eprintln!("{}", tr("failed-responding", &[("error", &e)]));
+        }
+    }
+    Ok(())
+}
+
+/// Returns whether the `Authorization` header `header` carries `token`.
+#[cfg(feature = "http")]
+fn authorized(header: &str, token: &str) -> bool {
+    header
+        .strip_prefix("Bearer ")
+        .is_some_and(|given| ct_eq(given.as_bytes(), token.as_bytes()))
+}
+
+/// Returns the status and JSON body of the response to an authorized request.
+#[cfg(feature = "http")]
+fn answer(
+    method: &str,
+    url: &str,
+    body: &str,
+    handle: &mut impl FnMut(Call) -> Result<Value>,
+) -> (u16, Value) {
+    let result = route(method, url, body).and_then(|call| {
+        // Failures of the calls themselves, e.g. unconfigured sites.
+        handle(call).map_err(|e| (422, format!("{e:#}")))
+    });
+    match result {
+        Ok(value) => (200, value),
+        Err((status, message)) => (status, json!({"error": message})),
+    }
+}
+
+/// Parses the call of a request, or returns its error status and message.
+#[cfg(feature = "http")]
+fn route(method: &str, url: &str, body: &str) -> Result<Call, (u16, String)> {
+    let path = url.split_once('?').map_or(url, |(path, _)| path);
+    let (allowed, call) = match path {
+        "/sites" => ("GET", "listSites"),
+        "/generate" => ("POST", "generate"),
+        "/totp" => ("POST", "totp"),
+        _ => return Err((404, format!("no endpoint {path}"))),
+    };
+    if method != allowed {
+        return Err((405, format!("{path} takes {allowed}")));
+    }
+    let params = match body.trim() {
+        "" => Value::Null,
+        body => serde_json::from_str(body).map_err(|e| (400, e.to_string()))?,
+    };
+    Call::parse(call, params).map_err(|e| (400, e.message))
+}
+
+/// Without the `http` feature, there is no server to bind.
+#[cfg(not(feature = "http"))]
+pub(crate) enum Server {}
+
+#[cfg(not(feature = "http"))]
+pub(crate) fn bind(_addr: SocketAddr) -> Result<Server> {
+    anyhow::bail!(tr("built-without-http", &[]));
+}
+
+#[cfg(not(feature = "http"))]
+pub(crate) fn serve(
+    server: Server,
+    _token: &str,
+    _handle: impl FnMut(Call) -> Result<Value>,
+) -> Result<()> {
+    match server {}
+}
+
+#[cfg(all(test, feature = "http"))]
+mod tests {
+    use super::*;
+    use crate::rpc::TotpParams;
+
+    #[test]
+    fn tokens() {
+        assert!(authorized("Bearer s3cret", "s3cret"));
+        assert!(!authorized("Bearer s3cre", "s3cret"));
+        assert!(!authorized("s3cret", "s3cret"));
+        assert!(!authorized("Basic s3cret", "s3cret"));
+    }
+
+    #[test]
+    fn routes() {
+        assert_eq!(route("GET", "/sites", ""), Ok(Call::ListSites));
+        assert_eq!(
+            route("POST", "/totp", r#"{"site": "a.com"}"#),
+            Ok(Call::Totp(TotpParams {
+                site: "a.com".into(),
+                ..Default::default()
+            }))
+        );
+        assert_eq!(route("GET", "/generate", "").map_err(|e| e.0), Err(405));
+        assert_eq!(route("POST", "/generate", "").map_err(|e| e.0), Err(400));
+        assert_eq!(route("POST", "/generate", "{").map_err(|e| e.0), Err(400));
+        assert_eq!(route("POST", "/bump", "{}").map_err(|e| e.0), Err(404));
+    }
+
+    #[test]
+    fn answers() {
+        let mut handle = |call| match call {
+            Call::ListSites => Ok(json!(["a.com"])),
+            _ => anyhow::bail!("nope"),
+        };
+        assert_eq!(
+            answer("GET", "/sites?x=1", "", &mut handle),
+            (200, json!(["a.com"]))
+        );
+        assert_eq!(
+            answer("POST", "/totp", r#"{"site": "a.com"}"#, &mut handle),
+            (422, json!({"error": "nope"}))
+        );
+    }
+}
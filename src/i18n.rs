@@ -0,0 +1,164 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Translations of messages on stderr (prompts, warnings, errors, and `--verbose` output), chosen
+//! by the usual locale variables. Stdout, porcelain, and anything else read by programs stays in
+//! English.
+//!
+//! Catalogs are in `i18n/LANG.ftl`, in the subset of [Fluent](https://projectfluent.org/) that
+//! onepass needs: `id = text` lines, indented continuation lines, `#` comments, and placeables
+//! `{ $name }` for arguments and `{ "text" }` for text Fluent would otherwise trim, like trailing
+//! spaces. Messages missing from a catalog fall back to English.
+
+use std::{collections::HashMap, env, fmt, sync::OnceLock};
+
+/// The English catalog, which has every message.
+const ENGLISH: &str = include_str!("i18n/en.ftl");
+
+/// The other catalogs, by language code.
+const CATALOGS: &[(&str, &str)] = &[("es", include_str!("i18n/es.ftl"))];
+
+/// Messages by id, as unformatted patterns.
+type Catalog = HashMap<&'static str, String>;
+
+/// Returns the message `id` in the user's language, with `args` filled into its placeables.
+pub(crate) fn tr(id: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    static ENGLISH_CATALOG: OnceLock<Catalog> = OnceLock::new();
+    static USER_CATALOG: OnceLock<Option<Catalog>> = OnceLock::new();
+    let user = USER_CATALOG.get_or_init(|| {
+        let language = user_language()?;
+        let (_, text) = CATALOGS.iter().find(|(code, _)| *code == language)?;
+        Some(parse(text))
+    });
+    let pattern = user
+        .as_ref()
+        .and_then(|catalog| catalog.get(id))
+        .or_else(|| ENGLISH_CATALOG.get_or_init(|| parse(ENGLISH)).get(id));
+    match pattern {
+        Some(pattern) => format(pattern, args),
+        None => id.to_owned(),
+    }
+}
+
+/// Returns the language of messages from the locale variables, in gettext’s order of precedence.
+fn user_language() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| language(&value).map(str::to_owned))
+}
+
+/// Returns the language code of a locale like `es_MX.UTF-8`, or `None` for the C locale.
+fn language(locale: &str) -> Option<&str> {
+    let code = locale.split(['_', '-', '.', '@']).next()?;
+    (!code.is_empty() && code != "C" && code != "POSIX").then_some(code)
+}
+
+/// Parses the messages of a catalog.
+fn parse(text: &'static str) -> Catalog {
+    let mut catalog = Catalog::new();
+    let mut last = None;
+    for line in text.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some(id) = last
+                && let Some(pattern) = catalog.get_mut(id)
+                && !line.trim().is_empty()
+            {
+                if !pattern.is_empty() {
+                    pattern.push('\n');
+                }
+                pattern.push_str(line.trim());
+            }
+            continue;
+        }
+        last = None;
+        if line.starts_with('#') {
+            continue;
+        }
+        if let Some((id, pattern)) = line.split_once('=') {
+            let id = id.trim();
+            catalog.insert(id, pattern.trim().to_owned());
+            last = Some(id);
+        }
+    }
+    catalog
+}
+
+/// Fills `args` into the placeables of `pattern`, leaving unknown ones as written.
+fn format(pattern: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let mut out = String::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        let placeable = &rest[start..start + len + 1];
+        let inner = placeable[1..len].trim();
+        if let Some(name) = inner.strip_prefix('$') {
+            match args.iter().find(|(arg, _)| *arg == name) {
+                Some((_, value)) => out.push_str(&value.to_string()),
+                None => out.push_str(placeable),
+            }
+        } else if let Some(text) = inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+            out.push_str(text);
+        } else {
+            out.push_str(placeable);
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    /// Returns the names of the arguments a pattern takes.
+    fn variables(pattern: &str) -> BTreeSet<&str> {
+        pattern
+            .split('{')
+            .skip(1)
+            .filter_map(|s| s.split('}').next()?.trim().strip_prefix('$'))
+            .collect()
+    }
+
+    #[test]
+    fn catalogs() {
+        let english = parse(ENGLISH);
+        for (code, text) in CATALOGS {
+            for (id, pattern) in parse(text) {
+                let original = english.get(id).unwrap_or_else(|| panic!("{code}: {id}"));
+                assert_eq!(variables(original), variables(&pattern), "{code}: {id}");
+            }
+        }
+    }
+
+    #[test]
+    fn formats() {
+        let catalog = parse("a = x { $n } y\nprompt = Pass:{ \" \" }\nlong = one\n  two\n# c\n");
+        assert_eq!("x 3 y", format(&catalog["a"], &[("n", &3)]));
+        assert_eq!("x { $n } y", format(&catalog["a"], &[]));
+        assert_eq!("Pass: ", format(&catalog["prompt"], &[]));
+        assert_eq!("one\ntwo", catalog["long"]);
+        assert_eq!(Some("es"), language("es_MX.UTF-8"));
+        assert_eq!(Some("pt"), language("pt-BR"));
+        assert_eq!(None, language("C.UTF-8"));
+        assert_eq!(None, language("POSIX"));
+    }
+}
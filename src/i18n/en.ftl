@@ -0,0 +1,246 @@
+# Messages on stderr, in English, which every other catalog falls back to.
+#
+# Placeables like { $site } are filled in by onepass; { " " } keeps a trailing space.
+
+## Errors
+
+error = Error
+caused-by = Caused by
+config-failure = failed to read config
+unknown-site = { $site } is not configured
+kdf-failure = failed deriving key material
+would-prompt = { $what } would be prompted for (--non-interactive)
+read-only = the config would be written (--non-interactive)
+passwords-due = passwords are due for rotation
+failed-reading-password = failed reading password
+passwords-mismatch = passwords don't match
+failed-getting-password = failed getting password from keyring
+failed-reading-confirmation = failed reading password confirmation
+failed-getting-pepper = failed getting pepper from keyring
+failed-storing-pepper = failed storing pepper in keyring
+failed-getting-username = failed getting username
+failed-constructing-entry = failed constructing keyring entry
+built-without-keyring = onepass was built without keyring support
+not-loopback = { $address } is not a loopback address
+blank-token = the token must not be blank
+built-without-http = onepass was built without HTTP support
+invalid-config-path = invalid config path
+failed-running = failed running { $program }
+failed-running-tpm2 = failed running { $program }; the TPM pepper needs tpm2-tools installed
+git-failed = git { $command } failed: { $error }
+enclosing-repository = { $dir } is inside the git repository { $repository }, which sync would push; sync needs a repository of the config directory's own
+failed-reaching = failed reaching { $remote }: { $error }
+rebase-failed = git rebase failed, so nothing was synced: { $error }
+invalid-stage = invalid config in stage { $stage } of the rebase
+sync-conflicts =
+    changed differently here and on the remote, so nothing was synced:
+    { $conflicts }
+    rerun with --prefer local or --prefer remote to pick a side
+audit-too-long = schemas matching over { $max } characters can't be audited
+empty-word = empty word
+audit-too-many-repeats = schemas repeating over { $max } times can't be audited
+audit-overlong-draw = drew a password longer than the schema allows
+built-without-clipboard = onepass was built without clipboard support
+failed-scheduling-clear = failed scheduling clipboard clear
+failed-opening-terminal = failed opening terminal
+no-stdin = no stdin
+program-failed = { $program } failed
+program-failed-with = { $program } failed: { $error }
+invalid-output = invalid { $program } output
+invalid-registry = invalid registry
+invalid-exec-info = invalid KUBERNETES_EXEC_INFO
+invalid-url = invalid url
+no-host = site has no host
+invalid-fingerprint = invalid duress fingerprint { $fingerprint }
+no-pepper = no pepper in the keyring
+failed-reading-keyfile = failed reading keyfile { $path }
+empty-keyfile = keyfile { $path } is empty
+yubikey-length = unexpected yubikey response length { $length }
+hmac-secret-length = unexpected hmac-secret length { $length }
+nondeterministic-key = { $type } signatures are not deterministic; use an ed25519 or rsa key
+no-fido2-devices = no fido2 devices found
+failed-creating = failed creating { $path }
+failed-opening = failed opening { $path }
+invalid-history = invalid history file { $path }
+no-history = failed reading { $path }; `onepass history --init` starts one
+history-other-key = the history is sealed to another key, from another master password or second factors
+invalid-event = invalid event on line { $line } of { $path }
+invalid-history-key = invalid history key
+truncated-event = truncated
+failed-authentication = failed authentication
+hold-unsupported = --hold needs a Unix terminal
+failed-reading-terminal-mode = failed reading terminal mode
+failed-setting-terminal-mode = failed setting terminal mode
+no-full-name = the export has no full name
+failed-finding-keyring = failed finding session keyring
+failed-adding-key = failed adding key to keyring
+failed-setting-timeout = failed setting key timeout
+key-caching-unsupported = key caching is only supported on Linux
+mnemonic-words = BIP39 mnemonics have 12, 15, 18, 21, or 24 words
+invalid-base32 = invalid base32 secret
+built-without-qr = onepass was built without QR code support
+uuid-length = UUIDs have a fixed length
+invalid-request = invalid request
+failed-reading-message = failed reading message
+message-too-long = message too long ({ $bytes } bytes)
+no-origin = { $url } has no origin
+failed-reading-origins = failed reading allowed origins
+failed-opening-origins = failed opening allowed origins
+allow-origin = Allow your browser to fill passwords for { $origin }?
+prompt-failed = authorization prompt failed: { $status }
+invalid-range-response = invalid range response
+false-positive-rate = the false positive rate must be between 0 and 1
+no-hashes = no hashes in { $path }
+not-sha1 = { $path } line { $line }: not a SHA-1 hash
+invalid-filter = invalid Bloom filter
+invalid-hash-line = invalid hash file line { $line }
+invalid-range-line = invalid range response line { $line }
+failed-checking-debugger = failed checking for a debugger in /proc/self/status
+debugger = refusing to run under a debugger (process { $pid })
+no-tracer-pid = failed checking for a debugger: no TracerPid in /proc/self/status
+failed-undumpable = failed making process undumpable
+memlock-limit = --harden locks all memory, which needs a memlock limit of at least { $min } MiB, not { $limit } KiB (see `ulimit -l`)
+failed-locking-memory = failed locking memory
+harden-unsupported = --harden is only supported on Linux
+failed-connecting-bus = failed connecting to the session bus
+failed-requesting-name = failed requesting the Secret Service name
+secret-service-running = another Secret Service provider (e.g. gnome-keyring) is already running
+lost-bus = lost connection to the session bus
+failed-sending-reply = failed sending reply
+no-auth-sock = SSH_AUTH_SOCK is not set
+failed-connecting-agent = failed connecting to ssh-agent
+agent-unsupported = ssh-agent is only supported on unix
+built-without-agent = onepass was built without ssh-agent support
+built-without-keys = onepass was built without key derivation support
+agent-refused-sign = ssh-agent refused to sign
+unexpected-agent-response = unexpected ssh-agent response
+agent-refused-key = ssh-agent refused the key
+invalid-public-key = invalid ssh public key
+key-type-mismatch = ssh public key type mismatch
+invalid-key-type = invalid ssh key type
+truncated-string = truncated ssh string
+no-sealed-pepper = no TPM-sealed pepper in { $dir }
+failed-reading-words = failed reading words file
+unknown-word-list = { $name } is neither a built-in word list nor a words file
+words-too-large = words files are too large
+invalid-utf8 = invalid UTF-8
+invalid-word-list = invalid word list
+checksum-mismatch = { $url } has SHA-256 checksum { $sha256 }, not the pinned { $pinned }; not saving it
+failed-writing = failed writing { $path }
+empty-word-list = word list is empty
+hold-non-interactive = --hold waits for a keypress, so not with --non-interactive
+missing-config = { $path } doesn't exist
+no-porcelain = this command has no porcelain output
+no-sync-remote = no git remote to sync with; add one to the config, e.g. `{ $example }`
+pepper-exists = a pepper already exists in the { $source }
+invalid-pepper = invalid pepper
+built-without-compat = onepass was built without compat support, which { $algorithm } sites need
+raw-username = { $url } is a raw identifier, so its username needs a salt template with { $placeholder }
+kdf-costs = --kdf-memory, --kdf-iterations, and --kdf-parallelism set Argon2's costs, but the KDF is { $kdf }; set its costs in the config instead
+failed-reading = failed reading { $path }
+failed-reading-password-from = failed reading the master password from { $path }
+failed-reading-env = failed reading ${ $name }
+invalid-totp-secret = invalid totp_secret
+invalid-hotp-secret = invalid hotp_secret
+hotp-unknown-site = { $site } is not in the config; add it there to track its HOTP counter
+invalid-backup-path = invalid backup path
+invalid-word-list-path = invalid word list path
+invalid-schema = invalid schema
+nothing-to-test = nothing to test; `{ $schema }` has no position with enough samples of more than one character
+biased = position { $positions } looks biased (p < { $threshold })
+schema-matches-nothing = schema matches nothing
+wrong-username = { $site } is configured for { $have }, not { $want }
+no-username = no username configured
+no-username-for = no username configured for { $site }
+no-cluster = no cluster given, and client-go didn't provide its server
+weaknesses = { $count } weaknesses found in the config
+due = { $count } passwords due for rotation
+pwned = { $site }'s password appears { $count } times in { $source }; bump the site's increment to change it
+probably-pwned = { $site }'s password is probably in { $source }; bump the site's increment to change it
+not-in-store = { $name } is not in the password store
+secret-service-unsupported = the Secret Service is only available on Linux
+built-without-secret-service = onepass was built without Secret Service support
+not-authorized = not authorized for { $origin }
+site-compat = { $site } is derived with { $algorithm }
+invalid-config = invalid config { $path }
+merge-conflicts =
+    changed differently here and in { $path }, so nothing was merged:
+    { $conflicts }
+    rerun with --prefer local or --prefer other to pick a side
+full-name-mismatch = the config's full_name is { $have }, but the export's is { $want }
+compat-schema = site is derived with { $algorithm }
+no-site = no site given
+
+
+## Prompts, and what they ask for under --non-interactive
+
+master-password-prompt = Master password:{ " " }
+master-password = the master password
+confirm-prompt = Confirm:{ " " }
+confirm = its confirmation
+pepper-prompt = Pepper (hex):{ " " }
+pepper = the pepper
+hidden-label-prompt = Hidden label:{ " " }
+hidden-label = the hidden label
+duress-password-prompt = Duress password:{ " " }
+old-master-password-prompt = Old master password:{ " " }
+new-master-password-prompt = New master password:{ " " }
+
+## Warnings
+
+weak-master-password = warning: master password is weak (crackable in about 10^{ $exponent } guesses)
+lookalike-site =
+    WARNING: { $site } is not in the config, but looks like the configured site { $like }.
+    WARNING: if a link or page brought you here, it may be a phishing site; check its address before using this password.
+copied = copied to { $destination }; clearing in { $seconds } seconds
+the-clipboard = the clipboard
+the-terminal-clipboard = the terminal's clipboard
+the-tmux-buffer = the tmux buffer onepass
+hold-keys = space: reveal or hide, c: copy, any other key: clear
+failed-copying = failed copying: { $error }
+weak-words-file = WARNING: the default schema `{ $schema }` has about { $bits } bits of entropy with this list, where it has { $builtin } with the built-in { $wordlist }
+rule-violation = WARNING: { $site }: { $violation }.
+rule-schema = WARNING: the site may reject this password; the schema `{ $schema }` follows its rules.
+failed-caching = failed caching key material: { $error }
+failed-recording-history = warning: failed recording history: { $error }
+undated-sites = { $count } sites have no rotation date and were skipped; see `onepass audit`
+keyring-unsupported = warning: onepass was built without keyring support, so can't use it
+failed-storing-password = failed storing password in keychain: { $error }
+failed-responding = warning: failed responding: { $error }
+not-synced = warning: not synced: { $error }
+not-pushed = warning: committed but not pushed; run `onepass sync` later: { $error }
+
+
+## --verbose
+
+output-bits = output has { $bits } bits of entropy
+schema-bits = schema has about { $bits } bits of entropy (0x{ $count } possible passwords)
+derivation = derivation: { $derivation }
+salt = salt: { $salt }
+kdf = kdf: { $kdf }
+second-factors = second factors: { $factors }
+key-material-cached = key material: cached
+
+## Results of commands
+
+totp-remaining = ({ $seconds }s remaining)
+bumped = bumped { $url } to increment { $increment }
+pepper-stored = stored; add `pepper: { $source }` to your config to use it
+no-backups = no backups of { $path } yet
+restored = restored { $path }; `onepass config restore` undoes this
+words-file-saved = saved { $list }; use it with `words_path: { $path }` in the config
+recording-history = recording history in { $path }
+built-without-history = onepass was built without history support
+breach-filter-written = wrote { $hashes } hashes to { $path } as { $bytes } bytes, with { $functions } hash functions
+serving = serving { $count } sites
+token = token: { $token }
+up-to-date = already up to date with { $path }
+merged = merged { $path }; sites added: { $added }, changed: { $changed }, removed: { $removed }
+import-skipped = skipping { $name }: { $reason }
+imported = imported { $count } sites
+duress-instructions = add this to your config, with a `command:` to run when it is entered
+rekey-keyring = once done, store the new master password with `onepass --reset-keyring SITE`
+serving-on = serving on http://{ $address }/
+made-repository = made { $dir } a git repository
+committed = committed changes to { $file }
+synced = synced with { $remote }
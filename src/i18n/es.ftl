@@ -0,0 +1,244 @@
+# Mensajes en stderr, en español.
+
+## Errores
+
+error = Error
+caused-by = Causado por
+config-failure = no se pudo leer la configuración
+unknown-site = { $site } no está configurado
+kdf-failure = no se pudo derivar el material de la clave
+would-prompt = habría que pedir { $what } (--non-interactive)
+read-only = habría que escribir la configuración (--non-interactive)
+passwords-due = hay contraseñas que deben rotarse
+failed-reading-password = no se pudo leer la contraseña
+passwords-mismatch = las contraseñas no coinciden
+failed-getting-password = no se pudo obtener la contraseña del llavero
+failed-reading-confirmation = no se pudo leer la confirmación de la contraseña
+failed-getting-pepper = no se pudo obtener la pimienta del llavero
+failed-storing-pepper = no se pudo guardar la pimienta en el llavero
+failed-getting-username = no se pudo obtener el nombre de usuario
+failed-constructing-entry = no se pudo construir la entrada del llavero
+built-without-keyring = onepass se compiló sin soporte para el llavero
+not-loopback = { $address } no es una dirección de bucle local
+blank-token = el token no puede estar en blanco
+built-without-http = onepass se compiló sin soporte para HTTP
+invalid-config-path = ruta de configuración no válida
+failed-running = no se pudo ejecutar { $program }
+failed-running-tpm2 = no se pudo ejecutar { $program }; la pimienta del TPM necesita que tpm2-tools esté instalado
+git-failed = git { $command } falló: { $error }
+enclosing-repository = { $dir } está dentro del repositorio git { $repository }, que sync enviaría entero; sync necesita un repositorio propio del directorio de la configuración
+failed-reaching = no se pudo alcanzar { $remote }: { $error }
+rebase-failed = git rebase falló, así que no se sincronizó nada: { $error }
+invalid-stage = configuración no válida en la etapa { $stage } del rebase
+sync-conflicts =
+    cambió de forma distinta aquí y en el remoto, así que no se sincronizó nada:
+    { $conflicts }
+    vuelva a ejecutar con --prefer local o --prefer remote para elegir un lado
+audit-too-long = no se pueden auditar esquemas que coinciden con más de { $max } caracteres
+empty-word = palabra vacía
+audit-too-many-repeats = no se pueden auditar esquemas que se repiten más de { $max } veces
+audit-overlong-draw = se sacó una contraseña más larga de lo que permite el esquema
+built-without-clipboard = onepass se compiló sin soporte para el portapapeles
+failed-scheduling-clear = no se pudo programar el borrado del portapapeles
+failed-opening-terminal = no se pudo abrir la terminal
+no-stdin = no hay entrada estándar
+program-failed = { $program } falló
+program-failed-with = { $program } falló: { $error }
+invalid-output = salida de { $program } no válida
+invalid-registry = registro no válido
+invalid-exec-info = KUBERNETES_EXEC_INFO no válido
+invalid-url = url no válida
+no-host = el sitio no tiene host
+invalid-fingerprint = huella de coacción no válida { $fingerprint }
+no-pepper = no hay pimienta en el llavero
+failed-reading-keyfile = no se pudo leer el archivo de clave { $path }
+empty-keyfile = el archivo de clave { $path } está vacío
+yubikey-length = longitud inesperada de la respuesta de la yubikey: { $length }
+hmac-secret-length = longitud inesperada del hmac-secret: { $length }
+nondeterministic-key = las firmas { $type } no son deterministas; use una clave ed25519 o rsa
+no-fido2-devices = no se encontraron dispositivos fido2
+failed-creating = no se pudo crear { $path }
+failed-opening = no se pudo abrir { $path }
+invalid-history = archivo de historial no válido { $path }
+no-history = no se pudo leer { $path }; `onepass history --init` crea uno
+history-other-key = el historial está sellado con otra clave, de otra contraseña maestra o de otros segundos factores
+invalid-event = evento no válido en la línea { $line } de { $path }
+invalid-history-key = clave de historial no válida
+truncated-event = truncado
+failed-authentication = falló la autenticación
+hold-unsupported = --hold necesita una terminal Unix
+failed-reading-terminal-mode = no se pudo leer el modo de la terminal
+failed-setting-terminal-mode = no se pudo cambiar el modo de la terminal
+no-full-name = la exportación no tiene nombre completo
+failed-finding-keyring = no se encontró el llavero de la sesión
+failed-adding-key = no se pudo añadir la clave al llavero
+failed-setting-timeout = no se pudo fijar la caducidad de la clave
+key-caching-unsupported = la caché de claves solo funciona en Linux
+mnemonic-words = las mnemotecnias BIP39 tienen 12, 15, 18, 21 o 24 palabras
+invalid-base32 = secreto base32 no válido
+built-without-qr = onepass se compiló sin soporte para códigos QR
+uuid-length = los UUID tienen una longitud fija
+invalid-request = petición no válida
+failed-reading-message = no se pudo leer el mensaje
+message-too-long = mensaje demasiado largo ({ $bytes } bytes)
+no-origin = { $url } no tiene origen
+failed-reading-origins = no se pudieron leer los orígenes permitidos
+failed-opening-origins = no se pudieron abrir los orígenes permitidos
+allow-origin = ¿Permitir que el navegador rellene contraseñas para { $origin }?
+prompt-failed = la petición de autorización falló: { $status }
+invalid-range-response = respuesta de rango no válida
+false-positive-rate = la tasa de falsos positivos debe estar entre 0 y 1
+no-hashes = no hay hashes en { $path }
+not-sha1 = { $path } línea { $line }: no es un hash SHA-1
+invalid-filter = filtro de Bloom no válido
+invalid-hash-line = línea del archivo de hashes no válida { $line }
+invalid-range-line = línea de la respuesta de rango no válida { $line }
+failed-checking-debugger = no se pudo comprobar si hay un depurador en /proc/self/status
+debugger = no se ejecuta bajo un depurador (proceso { $pid })
+no-tracer-pid = no se pudo comprobar si hay un depurador: no hay TracerPid en /proc/self/status
+failed-undumpable = no se pudo impedir el volcado del proceso
+memlock-limit = --harden bloquea toda la memoria, lo que necesita un límite de memlock de al menos { $min } MiB, no { $limit } KiB (vea `ulimit -l`)
+failed-locking-memory = no se pudo bloquear la memoria
+harden-unsupported = --harden solo funciona en Linux
+failed-connecting-bus = no se pudo conectar al bus de la sesión
+failed-requesting-name = no se pudo pedir el nombre del Secret Service
+secret-service-running = ya se está ejecutando otro proveedor del Secret Service (p. ej. gnome-keyring)
+lost-bus = se perdió la conexión con el bus de la sesión
+failed-sending-reply = no se pudo enviar la respuesta
+no-auth-sock = SSH_AUTH_SOCK no está definido
+failed-connecting-agent = no se pudo conectar a ssh-agent
+agent-unsupported = ssh-agent solo funciona en unix
+built-without-agent = onepass se compiló sin soporte para ssh-agent
+built-without-keys = onepass se compiló sin soporte para derivar claves
+agent-refused-sign = ssh-agent se negó a firmar
+unexpected-agent-response = respuesta inesperada de ssh-agent
+agent-refused-key = ssh-agent rechazó la clave
+invalid-public-key = clave pública ssh no válida
+key-type-mismatch = el tipo de la clave pública ssh no coincide
+invalid-key-type = tipo de clave ssh no válido
+truncated-string = cadena ssh truncada
+no-sealed-pepper = no hay pimienta sellada por el TPM en { $dir }
+failed-reading-words = no se pudo leer el archivo de palabras
+unknown-word-list = { $name } no es ni una lista de palabras incorporada ni un archivo de palabras
+words-too-large = los archivos de palabras son demasiado grandes
+invalid-utf8 = UTF-8 no válido
+invalid-word-list = lista de palabras no válida
+checksum-mismatch = { $url } tiene la suma SHA-256 { $sha256 }, no la fijada { $pinned }; no se guarda
+failed-writing = no se pudo escribir { $path }
+empty-word-list = la lista de palabras está vacía
+hold-non-interactive = --hold espera a que se pulse una tecla, así que no va con --non-interactive
+missing-config = { $path } no existe
+no-porcelain = esta orden no tiene salida porcelain
+no-sync-remote = no hay un remoto git con el que sincronizar; añada uno a la configuración, p. ej. `{ $example }`
+pepper-exists = ya hay una pimienta en { $source }
+invalid-pepper = pimienta no válida
+built-without-compat = onepass se compiló sin soporte de compatibilidad, que necesitan los sitios { $algorithm }
+raw-username = { $url } es un identificador sin procesar, así que su nombre de usuario necesita una plantilla de sal con { $placeholder }
+kdf-costs = --kdf-memory, --kdf-iterations y --kdf-parallelism fijan los costes de Argon2, pero la KDF es { $kdf }; fije sus costes en la configuración
+failed-reading = no se pudo leer { $path }
+failed-reading-password-from = no se pudo leer la contraseña maestra de { $path }
+failed-reading-env = no se pudo leer ${ $name }
+invalid-totp-secret = totp_secret no válido
+invalid-hotp-secret = hotp_secret no válido
+hotp-unknown-site = { $site } no está en la configuración; añádalo para llevar la cuenta de su contador HOTP
+invalid-backup-path = ruta de copia de seguridad no válida
+invalid-word-list-path = ruta de lista de palabras no válida
+invalid-schema = esquema no válido
+nothing-to-test = nada que probar; `{ $schema }` no tiene ninguna posición con suficientes muestras de más de un carácter
+biased = la posición { $positions } parece sesgada (p < { $threshold })
+schema-matches-nothing = el esquema no coincide con nada
+wrong-username = { $site } está configurado para { $have }, no para { $want }
+no-username = no hay ningún nombre de usuario configurado
+no-username-for = no hay ningún nombre de usuario configurado para { $site }
+no-cluster = no se dio ningún clúster, y client-go no indicó su servidor
+weaknesses = se encontraron { $count } debilidades en la configuración
+due = { $count } contraseñas deben rotarse
+pwned = la contraseña de { $site } aparece { $count } veces en { $source }; incremente el sitio para cambiarla
+probably-pwned = la contraseña de { $site } probablemente está en { $source }; incremente el sitio para cambiarla
+not-in-store = { $name } no está en el almacén de contraseñas
+secret-service-unsupported = el Secret Service solo está disponible en Linux
+built-without-secret-service = onepass se compiló sin soporte para el Secret Service
+not-authorized = no autorizado para { $origin }
+site-compat = { $site } se deriva con { $algorithm }
+invalid-config = configuración no válida { $path }
+merge-conflicts =
+    cambió de forma distinta aquí y en { $path }, así que no se fusionó nada:
+    { $conflicts }
+    vuelva a ejecutar con --prefer local o --prefer other para elegir un lado
+full-name-mismatch = el full_name de la configuración es { $have }, pero el de la exportación es { $want }
+compat-schema = el sitio se deriva con { $algorithm }
+no-site = no se dio ningún sitio
+
+
+## Preguntas, y lo que piden bajo --non-interactive
+
+master-password-prompt = Contraseña maestra:{ " " }
+master-password = la contraseña maestra
+confirm-prompt = Confirmar:{ " " }
+confirm = su confirmación
+pepper-prompt = Pimienta (hex):{ " " }
+pepper = la pimienta
+hidden-label-prompt = Etiqueta oculta:{ " " }
+hidden-label = la etiqueta oculta
+duress-password-prompt = Contraseña de coacción:{ " " }
+old-master-password-prompt = Contraseña maestra anterior:{ " " }
+new-master-password-prompt = Contraseña maestra nueva:{ " " }
+
+## Advertencias
+
+weak-master-password = aviso: la contraseña maestra es débil (se descifra en unos 10^{ $exponent } intentos)
+lookalike-site =
+    AVISO: { $site } no está en la configuración, pero se parece al sitio configurado { $like }.
+    AVISO: si llegó aquí por un enlace o una página, puede ser un sitio de phishing; compruebe su dirección antes de usar esta contraseña.
+copied = copiada en { $destination }; se borrará en { $seconds } segundos
+the-clipboard = el portapapeles
+the-terminal-clipboard = el portapapeles de la terminal
+the-tmux-buffer = el búfer de tmux onepass
+hold-keys = espacio: mostrar u ocultar, c: copiar, cualquier otra tecla: borrar
+failed-copying = no se pudo copiar: { $error }
+weak-words-file = AVISO: el esquema por defecto `{ $schema }` tiene unos { $bits } bits de entropía con esta lista, y { $builtin } con la lista incorporada { $wordlist }
+rule-violation = AVISO: { $site }: { $violation }.
+rule-schema = AVISO: puede que el sitio rechace esta contraseña; el esquema `{ $schema }` sigue sus reglas.
+failed-caching = no se pudo guardar en caché el material de la clave: { $error }
+failed-recording-history = aviso: no se pudo registrar el historial: { $error }
+undated-sites = { $count } sitios no tienen fecha de rotación y se omitieron; véase `onepass audit`
+keyring-unsupported = aviso: onepass se compiló sin soporte para el llavero, así que no puede usarlo
+failed-storing-password = no se pudo guardar la contraseña en el llavero: { $error }
+failed-responding = aviso: no se pudo responder: { $error }
+not-synced = aviso: no se sincronizó: { $error }
+not-pushed = aviso: se hizo commit pero no se envió; ejecute `onepass sync` más tarde: { $error }
+
+
+## --verbose
+
+output-bits = la salida tiene { $bits } bits de entropía
+schema-bits = el esquema tiene unos { $bits } bits de entropía (0x{ $count } contraseñas posibles)
+derivation = derivación: { $derivation }
+salt = sal: { $salt }
+kdf = kdf: { $kdf }
+second-factors = segundos factores: { $factors }
+key-material-cached = material de la clave: en caché
+
+## Resultados de las órdenes
+
+totp-remaining = (quedan { $seconds } s)
+bumped = { $url } pasó al incremento { $increment }
+pepper-stored = guardada; añada `pepper: { $source }` a su configuración para usarla
+no-backups = todavía no hay copias de seguridad de { $path }
+restored = restaurada { $path }; `onepass config restore` lo deshace
+words-file-saved = guardada { $list }; úsela con `words_path: { $path }` en la configuración
+recording-history = registrando el historial en { $path }
+built-without-history = onepass se compiló sin soporte para el historial
+breach-filter-written = escritos { $hashes } hashes en { $path } como { $bytes } bytes, con { $functions } funciones hash
+serving = sirviendo { $count } sitios
+token = token: { $token }
+up-to-date = ya está al día con { $path }
+merged = fusionado { $path }; sitios añadidos: { $added }, cambiados: { $changed }, eliminados: { $removed }
+import-skipped = se omite { $name }: { $reason }
+imported = importados { $count } sitios
+duress-instructions = añada esto a su configuración, con un `command:` que ejecutar cuando se introduzca
+rekey-keyring = al terminar, guarde la contraseña maestra nueva con `onepass --reset-keyring SITE`
+serving-on = sirviendo en http://{ $address }/
+made-repository = { $dir } es ahora un repositorio git
+committed = se hizo commit de los cambios en { $file }
+synced = sincronizado con { $remote }
@@ -0,0 +1,286 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Imports of other deterministic password managers' exported profiles as sites, with the
+//! compatibility settings that keep their passwords.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Result, bail};
+use clap::ValueEnum;
+use onepass::{
+    compat::{
+        Compat, Options, lesspass,
+        spectre::{self, Template},
+    },
+    config::SiteConfig,
+    url::canonicalize,
+};
+use serde::Deserialize;
+
+use crate::i18n::tr;
+
+/// The managers whose exports can be imported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Source {
+    /// LessPass's JSON export of its password profiles
+    Lesspass,
+    /// Spectre's (or Master Password's) JSON export of a user, as `.mpsites.json`
+    Spectre,
+}
+
+/// The sites of an export.
+#[derive(Debug, Default)]
+pub(crate) struct Import {
+    /// The sites, by name
+    pub sites: BTreeMap<String, SiteConfig>,
+    /// The user's full name, for Spectre
+    pub full_name: Option<String>,
+    /// The sites that could not be imported, with why
+    pub skipped: Vec<(String, String)>,
+}
+
+impl Source {
+    pub fn parse(self, json: &str) -> Result<Import> {
+        match self {
+            Source::Lesspass => lesspass(json),
+            Source::Spectre => spectre(json),
+        }
+    }
+}
+
+impl Import {
+    /// Adds the site `name`, derived with `compat` from the increment `counter - 1`, unless the
+    /// name is not a valid site or is already taken.
+    fn add(&mut self, name: &str, username: Option<&str>, counter: u32, mut compat: Compat) {
+        let Ok(url) = canonicalize(name, None) else {
+            self.skip(name, "not a valid site name");
+            return;
+        };
+        let Some(increment) = counter.checked_sub(1) else {
+            self.skip(name, "counter 0");
+            return;
+        };
+        if self.sites.contains_key(name) {
+            self.skip(name, "more than one profile for the site");
+            return;
+        }
+        // Some managers know sites by names that are not quite their hosts.
+        if compat.site_name(&url).ok().as_deref() != Some(name) {
+            compat.site = Some(name.into());
+        }
+        let site = SiteConfig {
+            increment,
+            username: username
+                .filter(|username| !username.is_empty())
+                .map(Into::into),
+            compat: Some(compat),
+            ..Default::default()
+        };
+        self.sites.insert(name.into(), site);
+    }
+
+    fn skip(&mut self, name: &str, reason: &str) {
+        self.skipped.push((name.into(), reason.into()));
+    }
+}
+
+fn lesspass(json: &str) -> Result<Import> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Export {
+        Profiles(Vec<Profile>),
+        /// As the LessPass API lists profiles
+        Results {
+            results: Vec<Profile>,
+        },
+    }
+    #[derive(Deserialize)]
+    struct Profile {
+        site: String,
+        #[serde(default)]
+        login: String,
+        lowercase: bool,
+        uppercase: bool,
+        #[serde(alias = "numbers")]
+        digits: bool,
+        symbols: bool,
+        length: usize,
+        counter: u32,
+        #[serde(default = "version_2")]
+        version: u32,
+    }
+    fn version_2() -> u32 {
+        2
+    }
+
+    let profiles = match serde_json::from_str(json)? {
+        Export::Profiles(profiles) | Export::Results { results: profiles } => profiles,
+    };
+    let mut import = Import::default();
+    for profile in profiles {
+        if profile.version != 2 {
+            import.skip(&profile.site, "LessPass version 1 profile");
+            continue;
+        }
+        let options = lesspass::Options {
+            length: profile.length,
+            lowercase: profile.lowercase,
+            uppercase: profile.uppercase,
+            digits: profile.digits,
+            symbols: profile.symbols,
+        };
+        let compat = Compat {
+            options: Options::Lesspass(options),
+            site: None,
+        };
+        import.add(&profile.site, Some(&profile.login), profile.counter, compat);
+    }
+    Ok(import)
+}
+
+fn spectre(json: &str) -> Result<Import> {
+    #[derive(Deserialize)]
+    struct Export {
+        user: User,
+        #[serde(default)]
+        sites: BTreeMap<String, Site>,
+    }
+    #[derive(Deserialize)]
+    struct User {
+        full_name: String,
+    }
+    #[derive(Deserialize)]
+    struct Site {
+        #[serde(rename = "type")]
+        kind: u32,
+        counter: u32,
+        algorithm: u32,
+        #[serde(default)]
+        login_name: Option<String>,
+    }
+
+    let export: Export = serde_json::from_str(json)?;
+    if export.user.full_name.is_empty() {
+        bail!(tr("no-full-name", &[]));
+    }
+    let mut import = Import {
+        full_name: Some(export.user.full_name),
+        ..Default::default()
+    };
+    for (name, site) in export.sites {
+        if site.algorithm != 3 {
+            import.skip(&name, &format!("algorithm version {}", site.algorithm));
+            continue;
+        }
+        // Spectre's result types; the others are stored passwords or derived keys.
+        let template = match site.kind {
+            16 => Template::Maximum,
+            17 => Template::Long,
+            18 => Template::Medium,
+            19 => Template::Basic,
+            20 => Template::Short,
+            21 => Template::Pin,
+            30 => Template::Name,
+            31 => Template::Phrase,
+            _ => {
+                import.skip(&name, "not a generated password");
+                continue;
+            }
+        };
+        let compat = Compat {
+            options: Options::Spectre(spectre::Options { template }),
+            site: None,
+        };
+        let login = site.login_name.as_deref();
+        import.add(&name, login, site.counter, compat);
+    }
+    Ok(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use onepass::compat::Algorithm;
+
+    use super::*;
+
+    #[test]
+    fn lesspass_profiles() -> Result<()> {
+        let import = Source::Lesspass.parse(
+            r#"{"results": [
+                {"site": "example.org", "login": "contact@example.org", "lowercase": true,
+                 "uppercase": true, "numbers": true, "symbols": false, "length": 14,
+                 "counter": 2, "version": 2},
+                {"site": "Example", "login": "", "lowercase": true, "uppercase": true,
+                 "digits": true, "symbols": true, "length": 16, "counter": 1},
+                {"site": "old.org", "lowercase": true, "uppercase": true, "digits": true,
+                 "symbols": true, "length": 12, "counter": 1, "version": 1}
+            ]}"#,
+        )?;
+        let site = &import.sites["example.org"];
+        assert_eq!(site.increment, 1);
+        assert_eq!(site.username.as_deref(), Some("contact@example.org"));
+        let compat = site.compat.as_ref().unwrap();
+        assert_eq!(compat.site, None);
+        assert_eq!(
+            compat.options,
+            Options::Lesspass(lesspass::Options {
+                length: 14,
+                symbols: false,
+                ..Default::default()
+            })
+        );
+        let site = &import.sites["Example"];
+        assert_eq!(site.username, None);
+        assert_eq!(
+            site.compat.as_ref().unwrap().site.as_deref(),
+            Some("Example")
+        );
+        assert_eq!(import.skipped.len(), 1);
+        assert!(Source::Lesspass.parse("[]")?.sites.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn spectre_sites() -> Result<()> {
+        let import = Source::Spectre.parse(
+            r#"{
+                "export": {"format": 1, "redacted": true},
+                "user": {"full_name": "Robert Lee Mitchell", "algorithm": 3},
+                "sites": {
+                    "masterpasswordapp.com": {"type": 17, "counter": 1, "algorithm": 3,
+                                              "login_name": "rlm"},
+                    "bank.com": {"type": 21, "counter": 3, "algorithm": 3},
+                    "stored.com": {"type": 1056, "counter": 1, "algorithm": 3},
+                    "old.com": {"type": 17, "counter": 1, "algorithm": 2}
+                }
+            }"#,
+        )?;
+        assert_eq!(import.full_name.as_deref(), Some("Robert Lee Mitchell"));
+        let site = &import.sites["masterpasswordapp.com"];
+        assert_eq!(site.username.as_deref(), Some("rlm"));
+        assert_eq!(
+            site.compat.as_ref().unwrap().algorithm(),
+            Algorithm::Spectre
+        );
+        let site = &import.sites["bank.com"];
+        assert_eq!(site.increment, 2);
+        assert_eq!(
+            site.compat.as_ref().unwrap().to_string(),
+            "spectre (pin template)"
+        );
+        assert_eq!(import.skipped.len(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Caching of derived key material in the Linux session keyring, so that repeated derivations
+//! within a TTL need neither the master password nor the KDF.
+
+#[cfg(target_os = "linux")]
+use std::{ffi::CString, io};
+
+#[cfg(target_os = "linux")]
+use anyhow::Context;
+use anyhow::Result;
+
+use crate::{i18n::tr, secmem::Locked};
+
+const CACHE_CONTEXT: &str = "onepass 2025-06 key cache";
+
+/// Returns the description of the cache entry for key material derived with `inputs`, i.e.
+/// everything the key material depends on besides the secrets themselves. It is visible in
+/// `/proc/keys`, so it is hashed.
+pub(crate) fn description(inputs: &[&[u8]]) -> String {
+    let mut hasher = blake3::Hasher::new_derive_key(CACHE_CONTEXT);
+    for input in inputs {
+        hasher.update(&(input.len() as u64).to_le_bytes());
+        hasher.update(input);
+    }
+    format!("onepass:{}", hasher.finalize().to_hex())
+}
+
+#[cfg(target_os = "linux")]
+const KEY_TYPE: &std::ffi::CStr = c"user";
+
+/// Returns the ID of the session keyring, falling back to the user session keyring rather than
+/// creating a new session keyring, which would not outlive the process, if there is none.
+#[cfg(target_os = "linux")]
+fn session_keyring() -> libc::c_long {
+    // SAFETY: takes no pointers.
+    unsafe {
+        libc::syscall(
+            libc::SYS_keyctl,
+            libc::KEYCTL_GET_KEYRING_ID,
+            libc::KEY_SPEC_SESSION_KEYRING,
+            0,
+        )
+    }
+}
+
+/// Looks up unexpired key material cached under `description`.
+#[cfg(target_os = "linux")]
+pub(crate) fn get(description: &str) -> Option<Locked<[u8; 32]>> {
+    let description = CString::new(description).ok()?;
+    // SAFETY: the strings are NUL-terminated and outlive the call.
+    let id = unsafe {
+        libc::syscall(
+            libc::SYS_keyctl,
+            libc::KEYCTL_SEARCH,
+            session_keyring(),
+            KEY_TYPE.as_ptr(),
+            description.as_ptr(),
+            0,
+        )
+    };
+    if id < 0 {
+        return None;
+    }
+    let mut key = Locked::new([0u8; 32]);
+    // SAFETY: the buffer is valid for writes of its length.
+    let len = unsafe {
+        libc::syscall(
+            libc::SYS_keyctl,
+            libc::KEYCTL_READ,
+            id,
+            key.as_mut_ptr(),
+            key.len(),
+        )
+    };
+    (len == key.len() as libc::c_long).then_some(key)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn get(_description: &str) -> Option<Locked<[u8; 32]>> {
+    None
+}
+
+/// Caches `key` under `description` for `ttl` seconds.
+#[cfg(target_os = "linux")]
+pub(crate) fn put(description: &str, key: &[u8; 32], ttl: u32) -> Result<()> {
+    let description = CString::new(description)?;
+    let keyring = session_keyring();
+    if keyring < 0 {
+        return Err(io::Error::last_os_error()).context(tr("failed-finding-keyring", &[]));
+    }
+    // SAFETY: the strings are NUL-terminated and the payload is valid for reads of its length.
+    let id = unsafe {
+        libc::syscall(
+            libc::SYS_add_key,
+            KEY_TYPE.as_ptr(),
+            description.as_ptr(),
+            key.as_ptr(),
+            key.len(),
+            keyring,
+        )
+    };
+    if id < 0 {
+        return Err(io::Error::last_os_error()).context(tr("failed-adding-key", &[]));
+    }
+    // SAFETY: takes no pointers.
+    if unsafe { libc::syscall(libc::SYS_keyctl, libc::KEYCTL_SET_TIMEOUT, id, ttl) } < 0 {
+        let err = io::Error::last_os_error();
+        // SAFETY: takes no pointers.
+        unsafe { libc::syscall(libc::SYS_keyctl, libc::KEYCTL_INVALIDATE, id) };
+        return Err(err).context(tr("failed-setting-timeout", &[]));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn put(_description: &str, _key: &[u8; 32], _ttl: u32) -> Result<()> {
+    anyhow::bail!(tr("key-caching-unsupported", &[]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn descriptions() {
+        assert_ne!(description(&[b"ab", b"c"]), description(&[b"a", b"bc"]));
+        assert!(description(&[]).starts_with("onepass:"));
+    }
+}
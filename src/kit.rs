@@ -0,0 +1,295 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A printable emergency kit: everything but the secrets needed to regenerate passwords.
+
+use std::fmt::Write;
+
+use onepass::{url::Subdomains, wordlist::Wordlist};
+
+use crate::config::{Config, SiteConfig};
+
+const INSTRUCTIONS: &[&str] = &[
+    concat!(
+        "Install onepass ",
+        env!("CARGO_PKG_VERSION"),
+        " or later from ",
+        env!("CARGO_PKG_REPOSITORY"),
+        "."
+    ),
+    "Recreate the settings, aliases, and sites below in ~/.config/onepass/config.yaml.",
+    "Restore any second factors listed below; they are not included here.",
+    "Run `onepass SITE` and enter your master password.",
+];
+
+/// The contents of the kit, independent of how it is rendered.
+struct Kit {
+    settings: Vec<(&'static str, String)>,
+    aliases: Vec<(String, String)>,
+    sites: Vec<(String, Vec<String>)>,
+}
+
+impl Kit {
+    fn new(config: &Config) -> Self {
+        let mut settings = vec![
+            (
+                "default derivation version",
+                config.default_version.to_string(),
+            ),
+            ("key derivation function", config.kdf.to_string()),
+            ("default schema", config.default_schema.clone()),
+        ];
+        if let Some(template) = &config.salt_template {
+            settings.push(("salt template", template.clone()));
+        }
+        if let Some(name) = &config.full_name {
+            settings.push(("full name", name.clone()));
+        }
+        settings.push(("canonicalization", config.canonicalization.to_string()));
+        if config.canonicalization.subdomains != Subdomains::Keep {
+            settings.push(("subdomains", config.canonicalization.subdomains.to_string()));
+        }
+        if !config.canonicalization.port {
+            settings.push(("ports", "dropped".into()));
+        }
+        for (pattern, host) in &config.canonicalization.map {
+            settings.push(("subdomain map", format!("{pattern} → {host}")));
+        }
+        for group in &config.canonicalization.equivalent {
+            settings.push(("equivalent domains", group.join(", ")));
+        }
+        if config.site_rules != 0 {
+            settings.push((
+                "built-in site rules",
+                format!("version {}", config.site_rules),
+            ));
+        }
+        if config.words_path.is_empty() && config.wordlist != Wordlist::default() {
+            settings.push(("word list", format!("{} (built in)", config.wordlist)));
+        }
+        for path in &config.words_path {
+            settings.push((
+                "word list",
+                format!("{} (keep a copy; it is not included here)", path.display()),
+            ));
+        }
+        if let Some(path) = &config.keyfile {
+            settings.push(("keyfile", path.display().to_string()));
+        }
+        if let Some(slot) = config.yubikey_slot {
+            settings.push(("YubiKey challenge-response slot", slot.to_string()));
+        }
+        if let Some(fido2) = &config.fido2 {
+            settings.push((
+                "FIDO2 credential",
+                format!("{} (relying party {})", fido2.credential_id, fido2.rp_id),
+            ));
+        }
+        if let Some(key) = &config.ssh_agent_key {
+            settings.push(("ssh-agent key", key.clone()));
+        }
+        if let Some(source) = config.pepper {
+            settings.push(("pepper", source.to_string()));
+        }
+
+        let mut aliases: Vec<_> = config
+            .aliases
+            .iter()
+            .map(|(name, schema)| (name.clone(), schema.clone()))
+            .collect();
+        aliases.sort();
+
+        let mut sites: Vec<_> = config
+            .sites
+            .iter()
+            .map(|(url, site)| (url.clone(), site_details(site)))
+            .collect();
+        sites.sort();
+
+        Kit {
+            settings,
+            aliases,
+            sites,
+        }
+    }
+}
+
+/// Describes how the secrets of `site` are derived, one detail per line.
+pub(crate) fn site_details(site: &SiteConfig) -> Vec<String> {
+    let mut details = vec![match &site.compat {
+        Some(compat) => format!("compat {compat}"),
+        None => format!("schema {}", site.schema),
+    }];
+    if site.increment != 0 {
+        details.push(format!("increment {}", site.increment));
+    }
+    if let Some(username) = &site.username {
+        details.push(format!("username {username}"));
+    }
+    if let Some(version) = site.version {
+        details.push(format!("derivation version {version}"));
+    }
+    if let Some(template) = &site.salt_template {
+        details.push(format!("salt template {template}"));
+    }
+    if site.raw {
+        details.push("raw identifier, salted verbatim".into());
+    }
+    if let Some(subdomains) = site.subdomains {
+        details.push(format!("subdomains {subdomains}"));
+    }
+    if let Some(port) = site.port {
+        details.push(format!("port {}", if port { "kept" } else { "dropped" }));
+    }
+    if let Some(path) = &site.path {
+        details.push(format!("path prefix {path}"));
+    }
+    if !site.word_filter.is_empty() {
+        details.push(format!("words {}", site.word_filter));
+    }
+    for (name, schema) in &site.fields {
+        details.push(format!("field {name} {schema}"));
+    }
+    if site.totp_secret.is_some() {
+        details.push("stored TOTP secret (not included)".into());
+    }
+    if site.hotp_secret.is_some() {
+        details.push("stored HOTP secret (not included)".into());
+    }
+    if site.hotp_counter != 0 {
+        details.push(format!("HOTP counter {}", site.hotp_counter));
+    }
+    details
+}
+
+/// Renders the kit for `config` as plain text.
+pub(crate) fn render_text(config: &Config) -> String {
+    let kit = Kit::new(config);
+    let mut res = String::from("onepass emergency kit\n=====================\n\n");
+    res.push_str("This kit contains no secrets. To regenerate a password:\n");
+    for (i, step) in INSTRUCTIONS.iter().enumerate() {
+        writeln!(res, "  {}. {step}", i + 1).unwrap();
+    }
+    res.push_str("\nSettings\n--------\n");
+    for (name, value) in &kit.settings {
+        writeln!(res, "  {name}: {value}").unwrap();
+    }
+    if !kit.aliases.is_empty() {
+        res.push_str("\nAliases\n-------\n");
+        for (name, schema) in &kit.aliases {
+            writeln!(res, "  {name}: {schema}").unwrap();
+        }
+    }
+    res.push_str("\nSites\n-----\n");
+    for (url, details) in &kit.sites {
+        writeln!(res, "  {url}").unwrap();
+        for detail in details {
+            writeln!(res, "    {detail}").unwrap();
+        }
+    }
+    res
+}
+
+/// Renders the kit for `config` as a standalone HTML document.
+pub(crate) fn render_html(config: &Config) -> String {
+    let kit = Kit::new(config);
+    let mut res = String::from(concat!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n",
+        "<title>onepass emergency kit</title>\n",
+        "<style>body { font-family: sans-serif; } code { font-size: 1.1em; } ",
+        "td { padding: 0.2em 1em 0.2em 0; vertical-align: top; }</style>\n",
+        "</head>\n<body>\n<h1>onepass emergency kit</h1>\n",
+        "<p>This kit contains no secrets. To regenerate a password:</p>\n<ol>\n",
+    ));
+    for step in INSTRUCTIONS {
+        writeln!(res, "<li>{}</li>", escape(step)).unwrap();
+    }
+    res.push_str("</ol>\n<h2>Settings</h2>\n<table>\n");
+    for (name, value) in &kit.settings {
+        writeln!(
+            res,
+            "<tr><td>{}</td><td><code>{}</code></td></tr>",
+            escape(name),
+            escape(value)
+        )
+        .unwrap();
+    }
+    res.push_str("</table>\n");
+    if !kit.aliases.is_empty() {
+        res.push_str("<h2>Aliases</h2>\n<table>\n");
+        for (name, schema) in &kit.aliases {
+            writeln!(
+                res,
+                "<tr><td>{}</td><td><code>{}</code></td></tr>",
+                escape(name),
+                escape(schema)
+            )
+            .unwrap();
+        }
+        res.push_str("</table>\n");
+    }
+    res.push_str("<h2>Sites</h2>\n<table>\n");
+    for (url, details) in &kit.sites {
+        let details: Vec<_> = details.iter().map(|detail| escape(detail)).collect();
+        writeln!(
+            res,
+            "<tr><td>{}</td><td><code>{}</code></td></tr>",
+            escape(url),
+            details.join("<br>")
+        )
+        .unwrap();
+    }
+    res.push_str("</table>\n</body>\n</html>\n");
+    res
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn no_secrets() -> Result<()> {
+        let config = Config::from_str(
+            r#"
+            aliases:
+                pin: "[0-9]{4}"
+            sites:
+                a.com: pin
+                b.com:
+                    schema: "<[a-z]>{4}"
+                    username: me
+                    totp_secret: JBSWY3DP
+        "#,
+        )?;
+        let text = render_text(&config);
+        assert!(text.contains("  https://a.com/\n    schema [0-9]{4}\n"));
+        assert!(text.contains("    username me\n"));
+        assert!(!text.contains("JBSWY3DP"));
+        let html = render_html(&config);
+        assert!(html.contains("schema &lt;[a-z]&gt;{4}<br>username me"));
+        assert!(!html.contains("JBSWY3DP"));
+        Ok(())
+    }
+}
@@ -12,55 +12,283 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-mod config;
+mod a11y;
+mod askpass;
+mod audit;
+mod batch;
+mod clipboard;
+mod config_audit;
+mod credential;
 mod crypto;
-mod randexp;
-mod url;
+mod due;
+mod duress;
+mod exit;
+mod export;
+mod factor;
+#[cfg(feature = "history")]
+mod history;
+mod hold;
+mod http;
+mod i18n;
+mod import;
+mod keycache;
+#[cfg(feature = "keys")]
+mod keys;
+mod kit;
+mod merge;
+mod native_host;
+mod otp;
+mod pepper;
+mod porcelain;
+mod pwned;
+mod raw;
+mod rpc;
+mod secmem;
+#[cfg(all(target_os = "linux", feature = "secret-service"))]
+mod secret_service;
+mod serve;
+mod ssh_agent;
+mod sync;
+mod tpm;
+mod vectors;
+mod words;
 
 use std::{
-    collections::BTreeSet,
-    fs::read_to_string,
-    io::{IsTerminal, Write, stdout},
+    borrow::Cow,
+    cell::OnceCell,
+    collections::BTreeMap,
+    fs::{self, read_to_string},
+    io::{self, BufRead, IsTerminal, Write, stderr, stdin, stdout},
     path::Path,
+    process::ExitCode,
+    thread::sleep,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{Context, Result};
-use clap::Parser;
-use config::Config;
-use crypto::{Rng, get_onepass_entry, read_password};
-use crypto_bigint::{NonZero, RandomMod, U256};
-use randexp::{Enumerable, Expr, Quantifiable, Words};
-use url::canonicalize;
+use clap::{
+    Parser, Subcommand,
+    builder::{PossibleValuesParser, TypedValueParser},
+};
+use clipboard::{Clipboard, ClipboardBackend};
+use config::{Bump, Config, SiteConfig};
+use credential::{
+    DockerCredentials, DockerOperation, Format, GitOperation, GitRequest, KubeExecInfo,
+};
+use crypto::{
+    DerivationVersion, KdfAlgorithm, KdfConfig, KeyMaterial, SaltInputs, delete_password_keyring,
+    key_salt, prompt_master_password, read_password,
+};
+use crypto_bigint::{NonZero, U256};
+use exit::Failure;
+use factor::{Factors, Fido2Config, PepperSource};
+use i18n::tr;
+#[cfg(feature = "keys")]
+use keys::{AgeKey, SshKey, WgKey};
+use native_host::{Origins, Request, Response};
+use onepass::{
+    EFF_WORDLIST, backup,
+    compat::{self, Compat},
+    config,
+    date::Date,
+    derive::random_below,
+    randexp::{Enumerable, Expr, Quantifiable, WordList, Words},
+    rules,
+    url::{Rules, Subdomains, canonicalize_with},
+    wordlist::Wordlist,
+};
+use porcelain::Porcelain;
+use rand_chacha::ChaCha20Rng;
+use rand_core::{OsRng, RngCore, SeedableRng, TryRngCore};
+use raw::Encoding;
+use secmem::Locked;
+use serde::Serialize;
+use url::Url;
+use zeroize::Zeroizing;
 
 #[derive(Debug, Parser)]
-#[command(version, about)]
+#[command(
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The site for which to generate a password
-    site: String,
+    #[arg(required_unless_present_any = ["random", "hidden"])]
+    site: Option<String>,
+
+    /// Derive the password of an account kept out of the config, named by LABEL in a salt domain
+    /// of its own, prompting for the label if not given, so it stays out of shell history
+    ///
+    /// Nothing about the account is written anywhere: not the config, the key material cache,
+    /// or the clipboard.
+    #[arg(
+        long,
+        value_name = "LABEL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "",
+        conflicts_with_all = [
+            "site", "random", "compat", "format", "copy", "copy_osc52", "copy_tmux", "hold",
+            "username", "derivation_version", "salt_template", "raw",
+        ],
+    )]
+    hidden: Option<String>,
+
+    /// Generate a one-off password from the OS random number generator instead of deriving it
+    ///
+    /// No master password is read. If a site is given, only its schema is used.
+    #[arg(long)]
+    random: bool,
 
     /// Override the path of the config file (default: ~/.config/onepass/config.yaml)
     #[arg(
         short = 'f',
         long = "config",
         env = "ONEPASS_CONFIG_FILE",
-        value_name = "CONFIG_FILE"
+        value_name = "CONFIG_FILE",
+        global = true
     )]
     config_path: Option<Box<Path>>,
 
-    /// Read words from the specified newline-separated dictionary file (by default, uses words
-    /// from the EFF large word list)
+    /// Never prompt or write the config, failing instead with a distinct exit status, for scripts
+    /// (the master password can still come from --password-fd, --password-env, the keyring, or
+    /// the key material cache)
+    #[arg(long, env = "ONEPASS_NON_INTERACTIVE", global = true)]
+    non_interactive: bool,
+
+    /// Refuse to run under a debugger, keep other processes from attaching to onepass or reading
+    /// its memory, and lock all of its memory into RAM (Linux only)
+    #[arg(long, env = "ONEPASS_HARDEN", global = true)]
+    harden: bool,
+
+    /// Neither read nor write the cache of the compiled config and words file indexes in
+    /// ~/.cache/onepass
+    #[arg(long, env = "ONEPASS_NO_CACHE", global = true)]
+    no_cache: bool,
+
+    /// Read words from the specified newline-separated dictionary file, or if given more than
+    /// once, from all of them combined (by default, uses the config's words files or built-in word
+    /// list, or else the EFF large word list)
     #[arg(
         short,
         long = "words",
         env = "ONEPASS_WORDS_FILE",
         value_name = "WORDS_FILE"
     )]
-    words_path: Option<Box<Path>>,
+    words_path: Vec<Box<Path>>,
+
+    /// Read words from the named built-in word list instead
+    #[arg(long, value_enum, value_name = "NAME", conflicts_with = "words_path")]
+    wordlist: Option<Wordlist>,
+
+    /// Draw only words of at most N characters from the word list, e.g. to fit a passphrase into
+    /// a length-limited field (overrides the site's `word_filter`)
+    #[arg(long, value_name = "N")]
+    max_word_len: Option<usize>,
+
+    /// Draw only words made of the characters in CLASS, like `[a-z]`, from the word list
+    /// (overrides the site's `word_filter`)
+    #[arg(long, value_name = "CLASS")]
+    word_chars: Option<String>,
+
+    #[command(flatten)]
+    key: KeyArgs,
 
     /// Override schema to use for this site (may be a configured alias)
     #[arg(short, long)]
     schema: Option<String>,
 
+    #[command(flatten)]
+    site_args: SiteArgs,
+
+    /// Print raw random bytes in this encoding instead of a password matching the schema, e.g. for
+    /// API tokens
+    #[arg(long, conflicts_with_all = ["schema", "field"])]
+    encoding: Option<Encoding>,
+
+    /// The number of bytes to print with --encoding (default: 32)
+    #[arg(long, value_name = "BYTES", requires = "encoding")]
+    length: Option<usize>,
+
+    /// Generate the named field of this site (e.g. a PIN) instead of its password
+    #[arg(long, value_name = "NAME")]
+    field: Option<String>,
+
+    /// Derive the password with another password manager's algorithm, keeping passwords made by
+    /// it, with the site's configured options for it if any (default: the site's `compat`)
+    #[arg(
+        long,
+        value_name = "ALGORITHM",
+        conflicts_with_all = ["random", "schema", "encoding", "field"],
+    )]
+    compat: Option<compat::Algorithm>,
+
+    /// Print the site's credentials as configuration for another tool
+    #[arg(long, value_enum, conflicts_with_all = ["random", "porcelain"])]
+    format: Option<Format>,
+
+    /// Copy the password to the clipboard instead of printing it, clearing it after 45 seconds
+    #[arg(long, conflicts_with = "format")]
+    copy: bool,
+
+    /// The clipboard to copy to (default: from the config, or else detected)
+    #[arg(long, value_enum, env = "ONEPASS_CLIPBOARD", global = true)]
+    clipboard: Option<ClipboardBackend>,
+
+    /// Copy the password to the terminal's clipboard with an OSC 52 escape sequence instead of
+    /// printing it, e.g. over ssh; short for --copy --clipboard osc52
+    #[arg(long, conflicts_with_all = ["format", "copy"])]
+    copy_osc52: bool,
+
+    /// Load the password into the tmux paste buffer "onepass" instead of printing it; short for
+    /// --copy --clipboard tmux
+    #[arg(long, conflicts_with_all = ["format", "copy", "copy_osc52"])]
+    copy_tmux: bool,
+
+    /// Show the password on the terminal, masked until revealed with space or copied with c,
+    /// until another key is pressed, then erase it, e.g. while the screen is shared
+    #[arg(long, conflicts_with_all = ["format", "copy", "copy_osc52", "copy_tmux", "porcelain"])]
+    hold: bool,
+
+    /// Also spell the password out on stderr, one character per line with its case or name, for
+    /// screen readers and speech synthesis
+    #[arg(long, conflicts_with_all = ["format", "hold"])]
+    a11y: bool,
+
+    /// Print verbose password entropy output
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Print stable key=value lines for scripts instead of human-readable output, in the given
+    /// version of the format (default: the latest)
+    #[arg(
+        long,
+        value_name = "VERSION",
+        num_args = 0..=1,
+        require_equals = true,
+        value_parser = clap::value_parser!(u32).range(1..=porcelain::LATEST as i64),
+        global = true
+    )]
+    porcelain: Option<Option<u32>>,
+}
+
+impl Args {
+    /// Returns the version of the porcelain format asked for, if any; plain `--porcelain` asks for
+    /// the latest.
+    fn porcelain(&self) -> Option<u32> {
+        self.porcelain
+            .map(|version| version.unwrap_or(porcelain::LATEST))
+    }
+}
+
+/// Options for overriding how a site's salt is built.
+#[derive(Debug, Default, clap::Args)]
+struct SiteArgs {
     /// Override increment to use for this site
     #[arg(short, long, value_name = "NUM")]
     increment: Option<u32>,
@@ -69,6 +297,66 @@ struct Args {
     #[arg(short, long)]
     username: Option<String>,
 
+    /// Override the derivation pipeline version to use for this site
+    #[arg(long, value_name = "VERSION")]
+    derivation_version: Option<DerivationVersion>,
+
+    /// Override the salt template to use for this site, e.g. "{url}#{increment}#{username}"
+    #[arg(long, value_name = "TEMPLATE")]
+    salt_template: Option<String>,
+
+    /// Salt the site name verbatim instead of as a URL, for identifiers like "laptop-luks"
+    #[arg(long)]
+    raw: bool,
+}
+
+/// Options for reading the master password and deriving key material from it.
+#[derive(Debug, clap::Args)]
+struct KeyArgs {
+    /// Mix the contents of the specified file into the key material, as a second factor
+    #[arg(long, env = "ONEPASS_KEYFILE", value_name = "KEYFILE")]
+    keyfile: Option<Box<Path>>,
+
+    /// Mix the response of a YubiKey HMAC-SHA1 challenge-response slot into the key material, as
+    /// a second factor (requires ykman)
+    #[arg(long, value_name = "SLOT", value_parser = clap::value_parser!(u8).range(1..=2))]
+    yubikey: Option<u8>,
+
+    /// Mix the hmac-secret output of the FIDO2 credential with this base64 id into the key
+    /// material, as a second factor (requires libfido2 tools)
+    #[arg(long, value_name = "CREDENTIAL_ID")]
+    fido2_credential: Option<String>,
+
+    /// Mix a signature from ssh-agent into the key material, as a second factor, using the key
+    /// with this OpenSSH public key (e.g. the contents of ~/.ssh/id_ed25519.pub)
+    #[arg(long, value_name = "PUBLIC_KEY")]
+    ssh_agent_key: Option<String>,
+
+    /// Mix a pepper from the specified source into the key material, as a second factor
+    #[arg(long, value_name = "SOURCE")]
+    pepper: Option<PepperSource>,
+
+    /// Override the key derivation function (default: argon2id)
+    #[arg(long, value_name = "ALGORITHM")]
+    kdf: Option<KdfAlgorithm>,
+
+    /// Override the Argon2 memory cost in KiB (default: 32768)
+    #[arg(long, value_name = "KIB")]
+    kdf_memory: Option<u32>,
+
+    /// Override the Argon2 number of iterations (default: 3)
+    #[arg(long, value_name = "NUM")]
+    kdf_iterations: Option<u32>,
+
+    /// Override the Argon2 degree of parallelism (default: 1)
+    #[arg(long, value_name = "NUM")]
+    kdf_parallelism: Option<u32>,
+
+    /// Cache derived key material in the session keyring for this many seconds, so that
+    /// repeating a derivation needs no master password or second factors (Linux only; 0 disables)
+    #[arg(long, env = "ONEPASS_CACHE_TTL", value_name = "SECS")]
+    cache_ttl: Option<u32>,
+
     /// Use the system keyring to store the master password
     #[arg(
         short,
@@ -92,84 +380,2758 @@ struct Args {
     #[arg(short, long)]
     confirm: bool,
 
-    /// Print verbose password entropy output
+    /// Warn if the master password looks weak (done automatically when first storing it)
+    #[arg(long)]
+    check_master: bool,
+
+    /// Read the master password from the first line of file descriptor FD instead of the
+    /// keyring or a prompt, e.g. `--password-fd 3 3<<<"$password"`
+    #[arg(
+        long,
+        env = "ONEPASS_PASSWORD_FD",
+        value_name = "FD",
+        conflicts_with_all = ["confirm", "reset_keyring"]
+    )]
+    password_fd: Option<u32>,
+
+    /// Read the master password from the environment variable NAME instead of the keyring or a
+    /// prompt
+    #[arg(
+        long,
+        value_name = "NAME",
+        conflicts_with_all = ["password_fd", "confirm", "reset_keyring"]
+    )]
+    password_env: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Manage a pepper sealed in the TPM, whose sealed blobs are kept in the config directory
+    #[command(subcommand)]
+    Tpm(pepper::PepperCommand),
+
+    /// Manage a pepper stored in the system keyring
+    #[command(subcommand)]
+    Keyring(pepper::PepperCommand),
+
+    /// List or restore the snapshots of the config file that onepass takes before changing it
+    #[command(subcommand)]
+    Config(ConfigCommand),
+
+    /// Print the fingerprint of a new duress password, for the config's `duress:`; entering it
+    /// in place of the master password derives decoys and runs the duress command
+    DuressFingerprint,
+
+    /// Derive an ed25519 ssh keypair, printing its private key in OpenSSH format
+    SshKey(SshKeyArgs),
+
+    /// Derive an age X25519 identity, printing it with its recipient in age-keygen format
+    AgeKey(KeyPairArgs),
+
+    /// Derive a WireGuard keypair, printing its private key in base64
+    WgKey(KeyPairArgs),
+
+    /// Print every configured site's password (and fields) under both an old and a new master
+    /// password, as a checklist for changing master passwords
+    Rekey(RekeyArgs),
+
+    /// Print an emergency kit for disaster recovery, with the config and instructions for
+    /// regenerating passwords but no secrets
+    Kit {
+        /// Print an HTML document instead of plain text
+        #[arg(long)]
+        html: bool,
+    },
+
+    /// Derive a BIP39 mnemonic seed phrase, e.g. for a cryptocurrency wallet
+    Seed(SeedArgs),
+
+    /// Derive a TOTP secret for a site, printing an otpauth:// URI to enroll it in the site's
+    /// two-factor authentication
+    TotpSecret(TotpSecretArgs),
+
+    /// Print a site's current TOTP code, from its configured totp_secret or else its derived one
+    Totp(TotpArgs),
+
+    /// Print a site's next HOTP code, from its configured hotp_secret or else its derived one,
+    /// and advance its hotp_counter in the config
+    Hotp(HotpArgs),
+
+    /// Serve passwords to a browser extension over the native messaging protocol on stdio
+    NativeHost(NativeHostArgs),
+
+    /// Print a configured site's password like `pass show`, followed by its login and URL
+    Show(ShowArgs),
+
+    /// List the configured sites like `pass ls`
+    Ls,
+
+    /// Serve the passwords of configured sites to desktop applications as a read-only Secret
+    /// Service provider on the D-Bus session bus (Linux only)
+    SecretService {
+        #[command(flatten)]
+        key: KeyArgs,
+    },
+
+    /// Act as a git credential helper, answering `get` with the username and password of the
+    /// site for the repository host, e.g. with `git config credential.helper '!onepass
+    /// git-credential'`
+    GitCredential {
+        #[arg(value_enum)]
+        operation: GitOperation,
+
+        #[command(flatten)]
+        key: KeyArgs,
+    },
+
+    /// Act as a docker credential helper, deriving registry passwords instead of storing them
+    ///
+    /// Docker runs `docker-credential-NAME` for `"credsStore": "NAME"` in its config, so install
+    /// a script named e.g. `docker-credential-onepass` that runs `onepass docker-credential "$@"`.
+    DockerCredential {
+        #[arg(value_enum)]
+        operation: DockerOperation,
+
+        #[command(flatten)]
+        key: KeyArgs,
+    },
+
+    /// Act as a client-go exec credential plugin, printing an ExecCredential with a cluster's
+    /// derived password as its bearer token
+    KubeCredential {
+        /// The cluster's site (default: its API server, with `provideClusterInfo: true`)
+        cluster: Option<String>,
+
+        #[command(flatten)]
+        key: KeyArgs,
+    },
+
+    /// Serve passwords to other programs, e.g. editors and launchers that embed onepass
+    Serve(serve::ServeArgs),
+
+    /// Commit changes to the config to the git repository in its directory, rebase them onto the
+    /// remote's in the config's `sync:`, and push them, so that machines share increments
+    Sync(SyncArgs),
+
+    /// Export the configured sites for another password manager, without their passwords and
+    /// fields unless --with-secrets is given
+    Export(export::ExportArgs),
+
+    /// Add the sites of another deterministic password manager's export to the config, with the
+    /// compatibility settings that keep their passwords
+    Import(ImportArgs),
+
+    /// Merge another copy of the config into this one, e.g. another machine's, taking the larger
+    /// increment of each site and adding sites from either
+    Merge(MergeArgs),
+
+    /// Manage word list files
+    #[command(subcommand)]
+    Wordlist(WordlistCommand),
+
+    /// Print the canonical URL and salt a site derives from, and the trail of its bumps
+    ///
+    /// The URL and salt come with the config rules that applied, to check whether two spellings
+    /// of a site share a password. The trail lists the site's bumps, oldest first, with the
+    /// increment each bumped from, its date, and its reason. `onepass info SITE` is the same
+    /// command, under the name to reach for when looking up a site's history.
+    #[command(visible_alias = "info")]
+    Url(UrlArgs),
+
+    /// Look over the config for weak settings: schemas with few bits of entropy, sites sharing a
+    /// salt, sites with no rotation date, and old derivation versions and KDF parameters
+    Audit(AuditArgs),
+
+    /// Bump a configured site's increment, rotating its password, and record the bump in the site's
+    /// trail, which `url` shows
+    Bump(BumpArgs),
+
+    /// List the sites whose passwords are due for rotation, failing if there are any, to run
+    /// from cron or a systemd timer
+    Due(due::DueArgs),
+
+    /// List when sites' secrets were derived, from the encrypted history that `--init` starts;
+    /// the secrets themselves are never recorded
+    History(HistoryArgs),
+
+    /// Check that passwords are drawn uniformly from a schema, by chi-squared tests of the
+    /// characters at each position of many drawn from throwaway keys
+    AuditUniformity(AuditUniformityArgs),
+
+    /// Check whether a site's password appears in the breaches Have I Been Pwned knows of, over
+    /// the network unless given a local list; only the first five hex digits of its SHA-1 hash
+    /// are sent
+    Pwned(PwnedArgs),
+
+    /// Build a Bloom filter from a file of breached passwords' SHA-1 hashes, like Pwned
+    /// Passwords' download, for `pwned --list` to check against without the network
+    BuildBreachFilter(BuildBreachFilterArgs),
+
+    /// Print derivation test vectors as JSON, for checking other implementations
+    Vectors {
+        /// The throwaway master password to use
+        #[arg(long, default_value = "correct horse battery staple")]
+        master_password: String,
+    },
+}
+
+impl Command {
+    /// Whether the command has a porcelain output format; the others print formats set by other
+    /// programs' protocols, or documents for people.
+    fn has_porcelain(&self) -> bool {
+        !matches!(
+            self,
+            Command::Kit { .. }
+                | Command::NativeHost(_)
+                | Command::SecretService { .. }
+                | Command::GitCredential { .. }
+                | Command::DockerCredential { .. }
+                | Command::KubeCredential { .. }
+                | Command::Serve(_)
+                | Command::Export(_)
+                | Command::Import(_)
+                | Command::Merge(_)
+                | Command::Sync(_)
+                | Command::Vectors { .. }
+        )
+    }
+}
+
+#[derive(Debug, clap::Args)]
+struct SshKeyArgs {
+    /// The name of the key, e.g. "github"; different names give unrelated keys
+    label: String,
+
+    /// Rotate the key by deriving it with this increment instead
+    #[arg(short, long, value_name = "NUM", default_value_t = 0)]
+    increment: u32,
+
+    /// The key's comment (default: the label)
+    #[arg(short = 'C', long)]
+    comment: Option<String>,
+
+    /// Print the public key instead, in OpenSSH format
+    #[arg(long)]
+    public: bool,
+
+    /// Add the key to the running ssh-agent instead of printing it
+    #[arg(long, conflicts_with = "public")]
+    add: bool,
+
+    #[command(flatten)]
+    key: KeyArgs,
+}
+
+#[derive(Debug, clap::Args)]
+struct KeyPairArgs {
+    /// The name of the key; different names give unrelated keys
+    label: String,
+
+    /// Rotate the key by deriving it with this increment instead
+    #[arg(short, long, value_name = "NUM", default_value_t = 0)]
+    increment: u32,
+
+    /// Print only the public key
+    #[arg(long)]
+    public: bool,
+
+    #[command(flatten)]
+    key: KeyArgs,
+}
+
+#[derive(Debug, clap::Args)]
+struct RekeyArgs {
+    /// Print the report as JSON
+    #[arg(long, conflicts_with = "porcelain")]
+    json: bool,
+
+    #[command(flatten)]
+    key: KeyArgs,
+}
+
+#[derive(Debug, clap::Args)]
+struct SeedArgs {
+    /// The name of the seed; different names give unrelated seeds
+    label: String,
+
+    /// The number of words in the mnemonic
+    #[arg(
+        long,
+        default_value_t = 24,
+        value_parser = PossibleValuesParser::new(["12", "15", "18", "21", "24"])
+            .map(|words| words.parse::<usize>().unwrap()),
+    )]
+    words: usize,
+
+    /// Rotate the seed by deriving it with this increment instead
+    #[arg(short, long, value_name = "NUM", default_value_t = 0)]
+    increment: u32,
+
+    #[command(flatten)]
+    key: KeyArgs,
+}
+
+#[derive(Debug, clap::Args)]
+struct TotpSecretArgs {
+    /// The site for which to derive the secret
+    site: String,
+
+    /// Also print the URI as a QR code, for scanning with an authenticator app
+    #[arg(long, conflicts_with = "porcelain")]
+    qr: bool,
+
+    #[command(flatten)]
+    site_args: SiteArgs,
+
+    #[command(flatten)]
+    key: KeyArgs,
+}
+
+#[derive(Debug, clap::Args)]
+struct TotpArgs {
+    /// The site for which to print the code
+    site: String,
+
+    /// Keep printing the current code until interrupted
     #[arg(short, long)]
-    verbose: bool,
+    watch: bool,
+
+    #[command(flatten)]
+    site_args: SiteArgs,
+
+    #[command(flatten)]
+    key: KeyArgs,
+}
+
+#[derive(Debug, clap::Args)]
+struct HotpArgs {
+    /// The site for which to print the code
+    site: String,
+
+    /// Print an otpauth:// URI to enroll the site's derived secret at its current counter instead
+    #[arg(long)]
+    uri: bool,
+
+    /// Also print the URI as a QR code, for scanning with an authenticator app
+    #[arg(long, requires = "uri", conflicts_with = "porcelain")]
+    qr: bool,
+
+    #[command(flatten)]
+    site_args: SiteArgs,
+
+    #[command(flatten)]
+    key: KeyArgs,
+}
+
+#[derive(Debug, clap::Args)]
+struct BumpArgs {
+    /// The site whose increment to bump
+    site: String,
+
+    /// Why, to remember later, e.g. "breach 2025-06"
+    #[arg(long)]
+    reason: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+struct UrlArgs {
+    /// The site, as it would be given to generate its password
+    site: String,
+
+    #[command(flatten)]
+    site_args: SiteArgs,
+}
+
+#[derive(Debug, clap::Args)]
+// -c copies, as in pass(1), so --confirm only has its long form here.
+#[command(mut_arg("confirm", |arg| arg.short(None)))]
+struct ShowArgs {
+    /// The site, as listed by `onepass ls`; a pass-style directory prefix like `web/` is ignored
+    name: String,
+
+    /// Copy the password to the clipboard instead, clearing it after 45 seconds
+    #[arg(short = 'c', long)]
+    clip: bool,
+
+    #[command(flatten)]
+    key: KeyArgs,
+}
+
+#[derive(Debug, clap::Args)]
+struct NativeHostArgs {
+    /// The arguments browsers pass to native hosts, identifying the calling extension
+    #[arg(hide = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    caller: Vec<String>,
+
+    #[command(flatten)]
+    key: KeyArgs,
+}
+
+#[derive(Debug, clap::Args)]
+struct ImportArgs {
+    /// The manager the export is from
+    #[arg(long, value_enum)]
+    from: import::Source,
+
+    /// The exported file, e.g. LessPass's profiles or Spectre's .mpsites.json
+    path: Box<Path>,
+
+    /// Print the imported sites as config YAML instead of adding them to the config file, which
+    /// loses its comments
+    #[arg(long)]
+    print: bool,
+}
+
+#[derive(Debug, clap::Args)]
+struct MergeArgs {
+    /// The other copy of the config
+    other: Box<Path>,
+
+    /// The copy both came from, e.g. a backup from before they diverged, so that sites deleted
+    /// or changed on one side are told apart from sites added on the other
+    #[arg(long, value_name = "CONFIG")]
+    base: Option<Box<Path>>,
+
+    /// Where a site or setting was changed differently in both, keep this config's change
+    /// (local) or the other's (other), instead of failing
+    #[arg(long, value_enum, value_name = "SIDE")]
+    prefer: Option<merge::Prefer>,
+
+    /// Print the merged config instead of writing it to the config file, which loses its comments
+    #[arg(long)]
+    print: bool,
+}
+
+#[derive(Debug, clap::Args)]
+struct AuditArgs {
+    /// The fewest bits of entropy a site's password schema may have, instead of the config's
+    /// `min_bits` or 64
+    #[arg(long, value_name = "BITS")]
+    min_bits: Option<u32>,
+}
+
+#[derive(Debug, clap::Args)]
+struct HistoryArgs {
+    /// Only list this site's events
+    site: Option<String>,
+
+    /// Only list events on or after this day, like 2025-06-01
+    #[arg(long, value_name = "DATE")]
+    since: Option<Date>,
+
+    /// Start recording the history, sealed to a key derived from the master password
+    #[arg(long, conflicts_with_all = ["site", "since"])]
+    init: bool,
+
+    #[command(flatten)]
+    key: KeyArgs,
+}
+
+#[derive(Debug, clap::Args)]
+struct AuditUniformityArgs {
+    /// The schema, or an alias of one (default: the default schema)
+    schema: Option<String>,
+
+    /// How many passwords to draw
+    #[arg(long, value_name = "N", default_value_t = 100_000)]
+    samples: u64,
 }
 
-include!(concat!(env!("OUT_DIR"), "/wordlist.rs"));
+#[derive(Debug, clap::Args)]
+struct PwnedArgs {
+    /// The site
+    site: String,
+
+    /// Check against a local breach list instead of the network: a file of SHA-1 hashes ordered
+    /// by hash, as Pwned Passwords publishes, or a filter from `build-breach-filter`
+    #[arg(long, value_name = "FILE")]
+    list: Option<Box<Path>>,
+
+    #[command(flatten)]
+    key: KeyArgs,
+}
+
+#[derive(Debug, clap::Args)]
+struct BuildBreachFilterArgs {
+    /// The hashes, one per line in hex, each optionally followed by a colon and a count
+    input: Box<Path>,
+
+    /// Where to write the filter
+    #[arg(short, long, value_name = "FILE")]
+    output: Box<Path>,
+
+    /// The share of passwords not in the list that the filter will report as breached anyway
+    #[arg(long, value_name = "RATE", default_value_t = 0.001)]
+    false_positive_rate: f64,
+}
 
-fn main() -> Result<()> {
+#[derive(Debug, Subcommand)]
+enum WordlistCommand {
+    /// Download a well-known word list into the config directory, checking it against the pinned
+    /// SHA-256 checksum of its published file
+    Fetch {
+        #[arg(value_enum)]
+        list: words::Download,
+    },
+    /// Check a words file for duplicate, blank, non-ASCII, and multi-word lines, and for whether it
+    /// has enough words for the default schema
+    Check { path: Box<Path> },
+    /// Print the number of words in a list, their bits of entropy and lengths, and the lengths of
+    /// passphrases made from them, to compare lists for a schema
+    Stats {
+        /// A built-in list's name, or else the path of a words file
+        #[arg(value_name = "NAME|FILE")]
+        list: String,
+    },
+}
+
+#[derive(Debug, clap::Args)]
+struct SyncArgs {
+    /// Where a site or setting was changed differently here and on the remote, keep this side's
+    /// change, instead of failing
+    #[arg(long, value_enum, value_name = "SIDE")]
+    prefer: Option<merge::Prefer>,
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// List the snapshots of the config file, newest first
+    Backups,
+    /// Replace the config file with a snapshot, after taking a snapshot of it, so that restoring
+    /// again undoes this
+    Restore {
+        /// Which snapshot, counting from 1 for the newest, as numbered by `config backups`
+        #[arg(default_value_t = 1)]
+        n: usize,
+    },
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            exit::report(&e);
+            ExitCode::from(exit::status(&e))
+        }
+    }
+}
+
+fn run() -> Result<()> {
+    secmem::disable_core_dumps();
     let mut args = Args::parse();
-    if args.no_keyring {
-        args.keyring = Some(false);
+    if args.harden {
+        secmem::harden()?;
     }
 
-    let config = Config::from_file(args.config_path.as_deref()).context("failed to read config")?;
+    if args.non_interactive {
+        anyhow::ensure!(!args.hold, tr("hold-non-interactive", &[]));
+        exit::set_non_interactive();
+    }
+    let path = args
+        .config_path
+        .take()
+        .map_or_else(Config::default_path, Ok)
+        .context(Failure::Config)?;
+    // A missing config would otherwise be created from the example.
+    if args.non_interactive && !path.exists() {
+        return Err(
+            anyhow::anyhow!(tr("missing-config", &[("path", &path.display())]))
+                .context(Failure::Config),
+        );
+    }
+    let porcelain = args.porcelain().is_some();
+    // Restoring a backup has to work while the config itself is broken.
+    if let Some(Command::Config(command)) = &args.command {
+        return config_command(&path, command, porcelain);
+    }
+    let mut config = load_config(&path, args.no_cache)?;
 
-    let words: Option<Box<str>> = args
-        .words_path
-        .or_else(|| config.words_path())
-        .map(|path| read_to_string(path).map(|s| s.into()))
-        .transpose()
-        .context("failed reading words file")?;
-    let words: Option<Box<[&str]>> = words
-        .as_deref()
-        .map(|words| {
-            words
-                .lines()
-                .map(|line| line.trim())
-                .filter(|line| !line.is_empty())
-                .collect::<BTreeSet<_>>()
-        })
-        .map(|words| words.into_iter().collect());
-    let words = Words::from(words.as_deref().unwrap_or(EFF_WORDLIST));
-
-    let site = config.find_site(&args.site)?;
-    let url = site.as_ref().map_or(&args.site, |(url, _)| url);
-    let url = canonicalize(
-        url,
-        args.username
+    if porcelain && !args.command.as_ref().is_none_or(Command::has_porcelain) {
+        anyhow::bail!(tr("no-porcelain", &[]));
+    }
+    match args.command.take() {
+        Some(Command::Config(_)) => unreachable!("config commands run before reading the config"),
+        Some(Command::Tpm(command)) => {
+            pepper::command(&config, PepperSource::Tpm, command, porcelain)
+        }
+        Some(Command::Keyring(command)) => {
+            pepper::command(&config, PepperSource::Keyring, command, porcelain)
+        }
+        Some(Command::Vectors { master_password }) => {
+            vectors::print_vectors(&master_password, EFF_WORDLIST)
+        }
+        Some(Command::DuressFingerprint) => duress_fingerprint_command(porcelain),
+        #[cfg(feature = "keys")]
+        Some(Command::SshKey(ssh_key)) => ssh_key_command(&config, ssh_key, porcelain),
+        #[cfg(feature = "keys")]
+        Some(Command::AgeKey(age_key)) => age_key_command(&config, age_key, porcelain),
+        #[cfg(feature = "keys")]
+        Some(Command::WgKey(wg_key)) => wg_key_command(&config, wg_key, porcelain),
+        #[cfg(feature = "keys")]
+        Some(Command::Seed(seed)) => seed_command(&config, seed, porcelain),
+        #[cfg(not(feature = "keys"))]
+        Some(Command::SshKey(_) | Command::AgeKey(_) | Command::WgKey(_) | Command::Seed(_)) => {
+            anyhow::bail!(tr("built-without-keys", &[]))
+        }
+        Some(Command::Rekey(rekey)) => rekey_command(&config, rekey, porcelain),
+        Some(Command::Kit { html }) => {
+            print!(
+                "{}",
+                if html {
+                    kit::render_html(&config)
+                } else {
+                    kit::render_text(&config)
+                }
+            );
+            Ok(())
+        }
+        Some(Command::TotpSecret(totp_secret)) => {
+            totp_secret_command(&config, totp_secret, porcelain)
+        }
+        Some(Command::Totp(totp)) => totp_command(&config, totp, porcelain),
+        Some(Command::Hotp(hotp)) if !hotp.uri => {
+            pull_config(&path, &mut config, args.no_cache)?;
+            let message = format!("Advance the HOTP counter of {}", hotp.site);
+            hotp_command(&config, hotp, porcelain)?;
+            push_config(&path, &config, &message)
+        }
+        Some(Command::Hotp(hotp)) => hotp_command(&config, hotp, porcelain),
+        Some(Command::NativeHost(native_host)) => native_host_command(&config, native_host),
+        Some(Command::Show(show)) => show_command(&config, show, args.clipboard, porcelain),
+        Some(Command::SecretService { key }) => secret_service_command(&config, key),
+        Some(Command::DockerCredential { operation, key }) => {
+            docker_credential_command(&config, operation, key)
+        }
+        Some(Command::KubeCredential { cluster, key }) => {
+            kube_credential_command(&config, cluster, key)
+        }
+        Some(Command::GitCredential { operation, key }) => {
+            git_credential_command(&config, operation, key)
+        }
+        Some(Command::Serve(serve)) => serve::command(&path, config, serve, args.no_cache),
+        Some(Command::Export(export)) => export::command(&config, export),
+        Some(Command::Import(import)) if !import.print => {
+            pull_config(&path, &mut config, args.no_cache)?;
+            let message = format!("Import sites from {}", import.path.display());
+            import_command(&config, import)?;
+            push_config(&path, &config, &message)
+        }
+        Some(Command::Import(import)) => import_command(&config, import),
+        Some(Command::Merge(merge)) if !merge.print => {
+            pull_config(&path, &mut config, args.no_cache)?;
+            let message = format!("Merge {}", merge.other.display());
+            merge_command(&config, &path, merge)?;
+            push_config(&path, &config, &message)
+        }
+        Some(Command::Merge(merge)) => merge_command(&config, &path, merge),
+        Some(Command::Sync(sync)) => {
+            exit::writable()?;
+            let example = "sync: {remote: git@example.com:me/onepass-config.git}";
+            let remote = (config.sync.as_ref())
+                .with_context(|| tr("no-sync-remote", &[("example", &example)]))?;
+            sync::sync(&path, remote, sync.prefer)
+        }
+        Some(Command::Url(url)) => url_command(&config, url, porcelain),
+        Some(Command::Wordlist(command)) => wordlist_command(&config, command, porcelain),
+        Some(Command::Audit(audit)) => audit_command(&config, audit, porcelain),
+        Some(Command::Bump(bump)) => {
+            pull_config(&path, &mut config, args.no_cache)?;
+            let (url, bump) = bump_site(&config, &bump.site, bump.reason)?;
+            let increment = bump.increment + 1;
+            if porcelain {
+                Porcelain::new()
+                    .line("url", &url)?
+                    .line("increment", &increment.to_string())?;
+            } else {
+                eprintln!(
+                    "{}",
+                    tr("bumped", &[("url", &url), ("increment", &increment)])
+                );
+            }
+            push_config(&path, &config, &format!("Bump the increment of {url}"))
+        }
+        Some(Command::Due(due)) => due::command(&config, due, porcelain),
+        #[cfg(feature = "history")]
+        Some(Command::History(history)) => history_command(&config, history, porcelain),
+        #[cfg(not(feature = "history"))]
+        Some(Command::History(_)) => anyhow::bail!(tr("built-without-history", &[])),
+        Some(Command::AuditUniformity(audit)) => {
+            audit_uniformity_command(&config, audit, porcelain)
+        }
+        Some(Command::Pwned(pwned)) => pwned_command(&config, pwned, porcelain),
+        Some(Command::BuildBreachFilter(build)) => build_breach_filter_command(build, porcelain),
+        Some(Command::Ls) if porcelain => {
+            let mut sites: Vec<_> = config.sites.iter().collect();
+            sites.sort_by_key(|(url, _)| *url);
+            let mut out = Porcelain::new();
+            for (url, site) in sites {
+                out.line("url", url)?
+                    .maybe("username", site.username.as_deref())?;
+            }
+            Ok(())
+        }
+        Some(Command::Ls) => {
+            let mut names: Vec<_> = config.sites.keys().map(|url| pass_name(url)).collect();
+            names.sort();
+            println!("Password Store");
+            for (i, name) in names.iter().enumerate() {
+                let branch = if i + 1 == names.len() { '└' } else { '├' };
+                println!("{branch}── {name}");
+            }
+            Ok(())
+        }
+        None => match args.site.as_deref().and_then(askpass::Prompt::parse) {
+            Some(prompt) => askpass_command(&config, prompt, args.key),
+            None => generate(args, &config),
+        },
+    }
+}
+
+/// The second factors to mix into key material, before any of them are read.
+#[derive(Debug)]
+struct FactorSettings {
+    keyfile: Option<Box<Path>>,
+    yubikey: Option<u8>,
+    fido2: Option<Fido2Config>,
+    ssh_agent_key: Option<String>,
+    pepper: Option<PepperSource>,
+}
+
+/// A site's salt, and the inputs it was built from that other outputs need.
+struct Salt {
+    version: DerivationVersion,
+    salt: Vec<u8>,
+    url: String,
+    username: Option<String>,
+    increment: u32,
+}
+
+impl Salt {
+    /// Derives the password of the site with `compat`, from the master password `password`.
+    #[cfg(feature = "compat")]
+    fn compat_password(
+        &self,
+        config: &Config,
+        compat: &Compat,
+        password: &str,
+    ) -> Result<Zeroizing<String>> {
+        let name = compat.site_name(&self.url)?;
+        let site = compat::Site {
+            name: &name,
+            login: self.username.as_deref(),
+            user: config.full_name.as_deref(),
+            // Increments count from 0, and the other algorithms' counters from 1.
+            counter: self.increment + 1,
+        };
+        compat.password(password, &site)
+    }
+
+    #[cfg(not(feature = "compat"))]
+    fn compat_password(
+        &self,
+        _config: &Config,
+        compat: &Compat,
+        _password: &str,
+    ) -> Result<Zeroizing<String>> {
+        anyhow::bail!(tr(
+            "built-without-compat",
+            &[("algorithm", &compat.algorithm())]
+        ));
+    }
+}
+
+impl SiteArgs {
+    /// Returns the configured site `name`, which with `--raw` is only a raw site.
+    fn find_site<'a>(
+        &self,
+        config: &'a Config,
+        name: &str,
+    ) -> Result<Option<(String, &'a SiteConfig)>> {
+        if self.raw {
+            Ok(config.find_raw_site(name))
+        } else {
+            config.find_site(name)
+        }
+    }
+
+    /// Builds the salt for the site `name`, which `site` is the config of if it was found.
+    fn salt(
+        &self,
+        config: &Config,
+        name: &str,
+        site: Option<&(String, &SiteConfig)>,
+    ) -> Result<Salt> {
+        let site_config = site.map(|(_, site)| *site);
+        let url = site.map_or(name, |(url, _)| url.as_str());
+        let username = self
+            .username
             .as_deref()
-            .or_else(|| site.as_ref().and_then(|(_, site)| site.username.as_deref())),
+            .or_else(|| site_config.and_then(|site| site.username.as_deref()));
+        let version = self
+            .derivation_version
+            .unwrap_or_else(|| config.version(site_config));
+        let salt_template = self
+            .salt_template
+            .as_deref()
+            .or_else(|| config.salt_template(site_config));
+        let url = if self.raw || site_config.is_some_and(|site| site.raw) {
+            // A URL would carry the username, but a raw identifier has nowhere to put it.
+            if username.is_some() && salt_template.is_none() {
+                anyhow::bail!(tr(
+                    "raw-username",
+                    &[("url", &url), ("placeholder", &"{username}")]
+                ));
+            }
+            url.to_owned()
+        } else {
+            config.canonicalize(url, username, site_config)?
+        };
+        let increment = self
+            .increment
+            .unwrap_or_else(|| site_config.map_or(0, |site| site.increment));
+        let salt = version.salt(
+            salt_template,
+            &SaltInputs {
+                increment,
+                url: &url,
+                username,
+            },
+        )?;
+        Ok(Salt {
+            version,
+            salt,
+            url,
+            username: username.map(|username| username.into()),
+            increment,
+        })
+    }
+}
+
+impl KeyArgs {
+    /// Returns the configured KDF, with any overrides from the command line.
+    fn kdf(&self, config: &Config) -> Result<KdfConfig> {
+        let mut kdf = config.kdf.clone();
+        if let Some(algorithm) = self.kdf {
+            kdf.algorithm = algorithm;
+        }
+        if self.kdf_memory.is_some()
+            || self.kdf_iterations.is_some()
+            || self.kdf_parallelism.is_some()
+        {
+            // Other KDFs would silently ignore them, deriving from parameters nobody asked for.
+            anyhow::ensure!(
+                matches!(
+                    kdf.algorithm,
+                    KdfAlgorithm::Argon2id | KdfAlgorithm::Argon2i | KdfAlgorithm::Argon2d
+                ),
+                tr("kdf-costs", &[("kdf", &kdf.algorithm)])
+            );
+            let argon2 = kdf.argon2.get_or_insert_default();
+            argon2.memory = self.kdf_memory.unwrap_or(argon2.memory);
+            argon2.iterations = self.kdf_iterations.unwrap_or(argon2.iterations);
+            argon2.parallelism = self.kdf_parallelism.unwrap_or(argon2.parallelism);
+        }
+        Ok(kdf)
+    }
+
+    /// Returns the configured second factors, with any overrides from the command line.
+    fn factor_settings(&self, config: &Config) -> FactorSettings {
+        FactorSettings {
+            keyfile: self.keyfile.clone().or_else(|| config.keyfile_path()),
+            yubikey: self.yubikey.or(config.yubikey_slot),
+            fido2: match (self.fido2_credential.clone(), config.fido2.clone()) {
+                (Some(credential_id), Some(fido2)) => Some(Fido2Config {
+                    credential_id,
+                    ..fido2
+                }),
+                (Some(credential_id), None) => Some(Fido2Config::new(credential_id)),
+                (None, fido2) => fido2,
+            },
+            ssh_agent_key: self
+                .ssh_agent_key
+                .clone()
+                .or_else(|| config.ssh_agent_key.clone()),
+            pepper: self.pepper.or(config.pepper),
+        }
+    }
+
+    /// Collects the configured second factors, with any overrides from the command line.
+    fn factors(&self, config: &Config, salt: &[u8]) -> Result<Factors> {
+        let settings = self.factor_settings(config);
+        let mut factors = Factors::default();
+        if let Some(path) = settings.keyfile {
+            factors.add_keyfile(&path)?;
+        }
+        if let Some(slot) = settings.yubikey {
+            factors.add_yubikey(slot, salt)?;
+        }
+        if let Some(fido2) = settings.fido2 {
+            factors.add_fido2(&fido2, salt)?;
+        }
+        if let Some(key) = settings.ssh_agent_key {
+            factors.add_ssh_agent(&key)?;
+        }
+        if let Some(source) = settings.pepper {
+            factors.add_pepper(source, config.config_dir()?)?;
+        }
+        Ok(factors)
+    }
+
+    /// Returns how many sites to derive at once. Second factors that may ask for a touch or a
+    /// confirmation for every site are asked one at a time.
+    fn batch_threads(&self, config: &Config) -> Result<usize> {
+        let settings = self.factor_settings(config);
+        if settings.yubikey.is_some()
+            || settings.fido2.is_some()
+            || settings.ssh_agent_key.is_some()
+        {
+            return Ok(1);
+        }
+        Ok(batch::threads(&self.kdf(config)?))
+    }
+
+    /// Derives the key material for `salt`, or takes it from the cache if enabled. If `verbose`,
+    /// prints the KDF and second factors used.
+    fn key_material(&self, config: &Config, salt: &[u8], verbose: bool) -> Result<KeyMaterial> {
+        let kdf = self.kdf(config)?;
+        let ttl = self.cache_ttl.or(config.cache_ttl).unwrap_or(0);
+        let cache = (ttl > 0).then(|| {
+            let settings = format!("{:?}", self.factor_settings(config));
+            keycache::description(&[salt, kdf.to_string().as_bytes(), settings.as_bytes()])
+        });
+        if let Some(key) = cache.as_deref().and_then(keycache::get) {
+            if verbose {
+                eprintln!("{}", tr("key-material-cached", &[]));
+            }
+            return Ok(KeyMaterial::from_bytes(key));
+        }
+
+        let factors = self.factors(config, salt)?;
+        if verbose {
+            eprintln!("{}", tr("kdf", &[("kdf", &kdf)]));
+            if !factors.is_empty() {
+                let labels = factors.labels().collect::<Vec<_>>().join(", ");
+                eprintln!("{}", tr("second-factors", &[("factors", &labels)]));
+            }
+        }
+        let password = self.read_password(config)?;
+        let key = KeyMaterial::derive(&password, salt, &kdf, &factors)?;
+        if let Some(cache) = cache
+            && let Err(e) = keycache::put(&cache, key.expose(), ttl)
+        {
+            let error = format!("{e:#}");
+            eprintln!("{}", tr("failed-caching", &[("error", &error)]));
+        }
+        Ok(key)
+    }
+
+    fn read_password(&self, config: &Config) -> Result<Locked<String>> {
+        if self.reset_keyring {
+            delete_password_keyring()?;
+        }
+        let password = match self.supplied_password()? {
+            Some(password) => password,
+            None => {
+                let use_keyring =
+                    !self.no_keyring && self.keyring.or(config.use_keyring).unwrap_or(false);
+                read_password(use_keyring, self.confirm, self.check_master)?
+            }
+        };
+        if let Some(duress) = &config.duress
+            && duress::matches(&duress.fingerprint, &password)?
+            && let Some(command) = &duress.command
+        {
+            duress::alert(command);
+        }
+        Ok(password)
+    }
+
+    /// Returns the master password given by `--password-fd` or `--password-env`, if either.
+    fn supplied_password(&self) -> Result<Option<Locked<String>>> {
+        let mut password = Zeroizing::new(String::new());
+        if let Some(fd) = self.password_fd {
+            let path = format!("/dev/fd/{fd}");
+            let file =
+                fs::File::open(&path).with_context(|| tr("failed-opening", &[("path", &path)]))?;
+            io::BufReader::new(file)
+                .read_line(&mut password)
+                .with_context(|| tr("failed-reading-password-from", &[("path", &path)]))?;
+        } else if let Some(name) = &self.password_env {
+            *password = std::env::var(name)
+                .with_context(|| tr("failed-reading-env", &[("name", &name)]))?;
+        } else {
+            return Ok(None);
+        }
+        let len = password.trim_end_matches(['\r', '\n']).len();
+        password.truncate(len);
+        Ok(Some(std::mem::take(&mut *password).into()))
+    }
+
+    /// Derives the seed of the key of type `kind` (e.g. `ssh-key`) named `label`.
+    #[cfg(any(feature = "keys", feature = "history"))]
+    fn derive_key(
+        &self,
+        config: &Config,
+        kind: &str,
+        label: &str,
+        increment: u32,
+    ) -> Result<Locked<[u8; 32]>> {
+        let salt = key_salt(kind, label, increment);
+        Ok(self.key_material(config, &salt, false)?.labeled_key(kind))
+    }
+}
+
+/// The master password of derivations, read from `key` when first needed.
+struct Master<'a> {
+    key: &'a KeyArgs,
+    password: OnceCell<Locked<String>>,
+}
+
+impl<'a> Master<'a> {
+    fn new(key: &'a KeyArgs) -> Self {
+        Master {
+            key,
+            password: OnceCell::new(),
+        }
+    }
+
+    /// Reads the master password up front, e.g. when there will be no terminal to prompt on.
+    fn read(key: &'a KeyArgs, config: &Config) -> Result<Self> {
+        let master = Master::new(key);
+        master.password(config)?;
+        Ok(master)
+    }
+
+    fn password(&self, config: &Config) -> Result<&str> {
+        if self.password.get().is_none() {
+            let _ = self.password.set(self.key.read_password(config)?);
+        }
+        Ok(self.password.get().unwrap())
+    }
+
+    /// Derives the key material for `salt`, from the master password if it has been read and
+    /// otherwise as `KeyArgs::key_material` does.
+    fn key_material(&self, config: &Config, salt: &[u8]) -> Result<KeyMaterial> {
+        match self.password.get() {
+            Some(password) => {
+                let factors = self.key.factors(config, salt)?;
+                KeyMaterial::derive(password, salt, &self.key.kdf(config)?, &factors)
+            }
+            None => self.key.key_material(config, salt, false),
+        }
+    }
+}
+
+#[cfg(feature = "keys")]
+fn ssh_key_command(config: &Config, args: SshKeyArgs, porcelain: bool) -> Result<()> {
+    let seed = args
+        .key
+        .derive_key(config, "ssh-key", &args.label, args.increment)?;
+    let key = SshKey::from_seed(&seed, args.comment.as_deref().unwrap_or(&args.label));
+    if args.add {
+        ssh_agent::Agent::connect()?.add(&key)?;
+        if porcelain {
+            Porcelain::new().line("public-key", &key.public_openssh())?;
+        }
+    } else if porcelain {
+        let mut out = Porcelain::new();
+        out.line("public-key", &key.public_openssh())?;
+        if !args.public {
+            out.line("private-key", &key.private_openssh())?;
+        }
+    } else if args.public {
+        println!("{}", key.public_openssh());
+    } else {
+        stdout().write_all(key.private_openssh().as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Derives the one-time password secret labeled `label` (`totp` or `hotp`) of the site `name`,
+/// returning it with the site's salt.
+fn derive_otp_secret(
+    config: &Config,
+    name: &str,
+    site_args: &SiteArgs,
+    master: &Master,
+    label: &str,
+) -> Result<(Zeroizing<Vec<u8>>, Salt)> {
+    let site = site_args.find_site(config, name)?;
+    let salt = site_args.salt(config, name, site.as_ref())?;
+    let key = master.key_material(config, &salt.salt)?;
+    let mut secret = Zeroizing::new(vec![0u8; otp::SECRET_LEN]);
+    key.labeled_rng(label).fill_bytes(&mut secret);
+    Ok((secret, salt))
+}
+
+fn totp_secret_command(config: &Config, args: TotpSecretArgs, porcelain: bool) -> Result<()> {
+    let (secret, Salt { url, username, .. }) = derive_otp_secret(
+        config,
+        &args.site,
+        &args.site_args,
+        &Master::new(&args.key),
+        "totp",
     )?;
-    let schema = args.schema.as_ref().map_or_else(
-        || {
-            site.as_ref()
-                .map_or(&config.default_schema, |(_, site)| &site.schema)
-        },
-        |schema| config.aliases.get(schema).unwrap_or(schema),
+    let uri = otp::otpauth_uri(&secret, &url, username.as_deref(), None)?;
+    if porcelain {
+        Porcelain::new().line("uri", &uri)?;
+        return Ok(());
+    }
+    if args.qr {
+        stdout().write_all(otp::qr_code(&uri)?.as_bytes())?;
+        println!();
+    }
+    print_secret(&uri)
+}
+
+fn totp_command(config: &Config, args: TotpArgs, porcelain: bool) -> Result<()> {
+    let stored = args
+        .site_args
+        .find_site(config, &args.site)?
+        .and_then(|(_, site)| site.totp_secret.as_deref());
+    let secret = match stored {
+        Some(secret) => otp::decode_secret(secret).context(tr("invalid-totp-secret", &[]))?,
+        None => {
+            derive_otp_secret(
+                config,
+                &args.site,
+                &args.site_args,
+                &Master::new(&args.key),
+                "totp",
+            )?
+            .0
+        }
+    };
+    let now = || -> Result<u64> { Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs()) };
+    if porcelain {
+        // With --watch, each code is printed as it comes into effect.
+        let mut out = Porcelain::new();
+        loop {
+            let now = now()?;
+            let expires = now - now % otp::TOTP_PERIOD + otp::TOTP_PERIOD;
+            out.line("code", &otp::totp(&secret, now))?
+                .line("expires", &expires.to_string())?;
+            if !args.watch {
+                return Ok(());
+            }
+            sleep(Duration::from_secs(expires - now));
+        }
+    }
+    if !args.watch {
+        let now = now()?;
+        print_secret(&otp::totp(&secret, now))?;
+        if stderr().is_terminal() {
+            let seconds = otp::TOTP_PERIOD - now % otp::TOTP_PERIOD;
+            eprintln!("{}", tr("totp-remaining", &[("seconds", &seconds)]));
+        }
+        return Ok(());
+    }
+    let mut stdout = stdout();
+    loop {
+        let now = now()?;
+        write!(
+            stdout,
+            "\r{} ({:2}s remaining)",
+            otp::totp(&secret, now),
+            otp::TOTP_PERIOD - now % otp::TOTP_PERIOD
+        )?;
+        stdout.flush()?;
+        sleep(Duration::from_secs(1));
+    }
+}
+
+fn hotp_command(config: &Config, args: HotpArgs, porcelain: bool) -> Result<()> {
+    let (_, site) = args
+        .site_args
+        .find_site(config, &args.site)?
+        .with_context(|| Failure::UnknownSite(args.site.clone()))
+        .with_context(|| tr("hotp-unknown-site", &[("site", &args.site)]))?;
+    if !args.uri {
+        exit::writable()?;
+    }
+    let counter = site.hotp_counter;
+    let secret = match &site.hotp_secret {
+        Some(secret) if !args.uri => {
+            otp::decode_secret(secret).context(tr("invalid-hotp-secret", &[]))?
+        }
+        _ => {
+            let (secret, Salt { url, username, .. }) = derive_otp_secret(
+                config,
+                &args.site,
+                &args.site_args,
+                &Master::new(&args.key),
+                "hotp",
+            )?;
+            if args.uri {
+                let uri = otp::otpauth_uri(&secret, &url, username.as_deref(), Some(counter))?;
+                if porcelain {
+                    Porcelain::new().line("uri", &uri)?;
+                    return Ok(());
+                }
+                if args.qr {
+                    stdout().write_all(otp::qr_code(&uri)?.as_bytes())?;
+                    println!();
+                }
+                return print_secret(&uri);
+            }
+            secret
+        }
+    };
+    let code = otp::hotp(&secret, counter, 6);
+    config.update_site(&args.site, |site| {
+        site.insert("hotp_counter".into(), (counter + 1).into());
+    })?;
+    if porcelain {
+        Porcelain::new()
+            .line("code", &code)?
+            .line("counter", &counter.to_string())?;
+        return Ok(());
+    }
+    print_secret(&code)
+}
+
+fn load_config(path: &Path, no_cache: bool) -> Result<Config> {
+    match Config::default_cache_dir() {
+        Ok(cache_dir) if !no_cache => Config::from_file_cached(Some(path), &cache_dir),
+        _ => Config::from_file(Some(path)),
+    }
+    .context(Failure::Config)
+}
+
+/// Bumps the increment of the configured site `name` in the config file, rotating its password,
+/// and sets its `rotated` date to today, adding the bump, with `reason`, to its trail. Returns the
+/// site's URL and the bump.
+fn bump_site(config: &Config, name: &str, reason: Option<String>) -> Result<(String, Bump)> {
+    let (url, site) = config
+        .find_site(name)?
+        .with_context(|| Failure::UnknownSite(name.into()))?;
+    exit::writable()?;
+    let bump = Bump {
+        increment: site.increment,
+        date: Date::today(),
+        reason,
+    };
+    let entry = serde_yaml::to_value(&bump)?;
+    config.update_site(name, |site| {
+        site.insert("increment".into(), (bump.increment + 1).into());
+        site.insert("rotated".into(), bump.date.to_string().into());
+        let bumps = site
+            .entry("bumps".into())
+            .or_insert_with(|| serde_yaml::Value::Sequence(Vec::new()));
+        if let Some(bumps) = bumps.as_sequence_mut() {
+            bumps.push(entry);
+        }
+    })?;
+    Ok((url, bump))
+}
+
+/// Brings the config up to date with its git remote, if it has one, before a command changes it.
+fn pull_config(path: &Path, config: &mut Config, no_cache: bool) -> Result<()> {
+    if let Some(sync) = &config.sync
+        && !exit::non_interactive()
+    {
+        sync::pull(path, sync)?;
+        *config = load_config(path, no_cache)?;
+    }
+    Ok(())
+}
+
+/// Commits and pushes a command's change to the config, if it has a git remote.
+fn push_config(path: &Path, config: &Config, message: &str) -> Result<()> {
+    match &config.sync {
+        Some(sync) => sync::push(path, sync, message),
+        None => Ok(()),
+    }
+}
+
+fn config_command(path: &Path, command: &ConfigCommand, porcelain: bool) -> Result<()> {
+    match command {
+        ConfigCommand::Backups => {
+            let backups = backup::list(path)?;
+            let mut out = Porcelain::new();
+            for (n, backup) in backups.iter().enumerate() {
+                let n = (n + 1).to_string();
+                if porcelain {
+                    let backup = backup.to_str().context(tr("invalid-backup-path", &[]))?;
+                    out.line("n", &n)?.line("path", backup)?;
+                } else {
+                    println!("{n:>3}  {}", backup.display());
+                }
+            }
+            if backups.is_empty() && !porcelain {
+                eprintln!("{}", tr("no-backups", &[("path", &path.display())]));
+            }
+        }
+        ConfigCommand::Restore { n } => {
+            exit::writable()?;
+            let backup = backup::restore(path, *n)?;
+            if porcelain {
+                let backup = backup.to_str().context(tr("invalid-backup-path", &[]))?;
+                Porcelain::new().line("restored", backup)?;
+            } else {
+                eprintln!("{}", tr("restored", &[("path", &backup.display())]));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn wordlist_command(config: &Config, command: WordlistCommand, porcelain: bool) -> Result<()> {
+    match command {
+        WordlistCommand::Fetch { list } => {
+            let path = words::fetch(list, config.config_dir()?)?;
+            let path = path.to_str().context(tr("invalid-word-list-path", &[]))?;
+            if porcelain {
+                Porcelain::new().line("path", path)?;
+            } else {
+                eprintln!(
+                    "{}",
+                    tr("words-file-saved", &[("list", &list), ("path", &path)])
+                );
+            }
+        }
+        WordlistCommand::Check { path } => {
+            let contents = read_to_string(&path)
+                .with_context(|| tr("failed-reading", &[("path", &path.display())]))?;
+            let check = words::Check::new(&contents);
+            let bits_per_word = format!("{:.1}", check.bits_per_word());
+            if porcelain {
+                let mut out = Porcelain::new();
+                out.line("words", &check.words.len().to_string())?
+                    .line("bits-per-word", &bits_per_word)?;
+                for (line, issue) in &check.issues {
+                    match issue {
+                        words::Issue::Blank => out.line("blank", &line.to_string())?,
+                        words::Issue::Duplicate(word) => out.line("duplicate", word)?,
+                        words::Issue::NonAscii(word) => out.line("non-ascii", word)?,
+                        words::Issue::Whitespace(word) => out.line("whitespace", word)?,
+                    };
+                }
+            } else {
+                for (line, issue) in &check.issues {
+                    println!("{}:{line}: {issue}", path.display());
+                }
+                println!(
+                    "{} words, {bits_per_word} bits of entropy per word",
+                    check.words.len()
+                );
+            }
+            // A list with fewer words than the built-in one weakens every password it would be
+            // drawn into.
+            let expr = Expr::parse(&config.default_schema).context(tr("invalid-schema", &[]))?;
+            let bits = Words::from(&*check.words).size(&expr).bits();
+            let builtin = Words::from(config.wordlist.words()).size(&expr).bits();
+            if bits < builtin {
+                eprintln!(
+                    "{}",
+                    tr(
+                        "weak-words-file",
+                        &[
+                            ("schema", &config.default_schema),
+                            ("bits", &bits),
+                            ("builtin", &builtin),
+                            ("wordlist", &config.wordlist),
+                        ]
+                    )
+                );
+            }
+        }
+        WordlistCommand::Stats { list } => {
+            let stats = words::Stats::new(&words::List::named(&list)?)?;
+            if porcelain {
+                let mut out = Porcelain::new();
+                out.line("words", &stats.words.to_string())?
+                    .line("bits-per-word", &format!("{:.1}", stats.bits_per_word()))?
+                    .line("average-length", &format!("{:.1}", stats.average_length))?
+                    .line("max-length", &stats.max_length.to_string())?;
+                for count in words::PASSPHRASE_WORDS {
+                    let bits = count as f64 * stats.bits_per_word();
+                    out.line("count", &count.to_string())?
+                        .line("bits", &format!("{bits:.1}"))?
+                        .line("length", &format!("{:.1}", stats.passphrase_length(count)))?;
+                }
+            } else {
+                println!(
+                    "{} words, {:.1} bits of entropy per word",
+                    stats.words,
+                    stats.bits_per_word()
+                );
+                println!(
+                    "word length: {:.1} average, {} longest",
+                    stats.average_length, stats.max_length
+                );
+                println!("words  bits  characters (with separators)");
+                for count in words::PASSPHRASE_WORDS {
+                    let bits = count as f64 * stats.bits_per_word();
+                    let length = stats.passphrase_length(count);
+                    println!("{count:>5}  {bits:>4.0}  {length:>10.1}");
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Draws `args.samples` passwords of the schema from throwaway keys and reports whether their
+/// characters look uniform at each position.
+fn audit_uniformity_command(
+    config: &Config,
+    args: AuditUniformityArgs,
+    porcelain: bool,
+) -> Result<()> {
+    let schema = schema(config, None, None, args.schema.as_deref(), None);
+    let expr = Expr::parse(schema).context(tr("invalid-schema", &[]))?;
+    let words = words::List::open(config.words_paths(), config.wordlist, config.cache_dir())?;
+    let words = Words(&words);
+    let size = nonzero_size(&words, &expr)?;
+    let mut audit = audit::Audit::new(&words, &expr)?;
+    let mut keys = ChaCha20Rng::from_os_rng();
+    for _ in 0..args.samples {
+        let password = audit::draw(&words, &expr, &size, config.default_version, &mut keys)?;
+        audit.observe(&password)?;
+    }
+    let positions = audit.positions();
+    let threshold = audit::threshold(&positions);
+    if porcelain {
+        let mut out = Porcelain::new();
+        out.line("samples", &args.samples.to_string())?
+            .line("threshold", &format!("{threshold:.3e}"))?;
+        for position in &positions {
+            out.line("position", &position.index.to_string())?
+                .line("chi-squared", &format!("{:.4}", position.chi_squared))?
+                .line(
+                    "degrees-of-freedom",
+                    &position.degrees_of_freedom.to_string(),
+                )?
+                .line("p-value", &format!("{:.3e}", position.p_value))?;
+        }
+    } else {
+        for position in &positions {
+            println!(
+                "position {}: χ²({}) = {:.2}, p = {:.3}",
+                position.index, position.degrees_of_freedom, position.chi_squared, position.p_value
+            );
+        }
+    }
+    anyhow::ensure!(
+        !positions.is_empty(),
+        tr("nothing-to-test", &[("schema", &schema)])
     );
-    let increment = args
-        .increment
-        .unwrap_or_else(|| site.map_or(0, |(_, site)| site.increment));
-    let expr = Expr::parse(schema).context("invalid schema")?;
-    let size = words.size(&expr);
-    let salt = format!("{0},{1}", increment, &url);
+    let biased: Vec<_> = positions
+        .iter()
+        .filter(|position| position.p_value < threshold)
+        .map(|position| position.index.to_string())
+        .collect();
+    let p = format!("{threshold:.1e}");
+    anyhow::ensure!(
+        biased.is_empty(),
+        tr(
+            "biased",
+            &[("positions", &biased.join(", ")), ("threshold", &p)]
+        )
+    );
+    if !porcelain {
+        println!(
+            "no sign of bias in {} passwords (p ≥ {threshold:.1e} at each position)",
+            args.samples
+        );
+    }
+    Ok(())
+}
 
-    if args.verbose {
+/// Prints how the site `args.site` is canonicalized and salted, without deriving anything.
+fn url_command(config: &Config, args: UrlArgs, porcelain: bool) -> Result<()> {
+    let site = args.site_args.find_site(config, &args.site)?;
+    let site_config = site.as_ref().map(|(_, site)| *site);
+    let salt = args.site_args.salt(config, &args.site, site.as_ref())?;
+    let raw = args.site_args.raw || site_config.is_some_and(|site| site.raw);
+    let rules = config.rules(site_config);
+    let namespace = rules.namespace(&salt.url).map(|(namespace, _)| namespace);
+    let kind = match namespace {
+        _ if raw => "raw".to_owned(),
+        Some(namespace) => namespace.to_string(),
+        None => "url".to_owned(),
+    };
+    let is_url = !raw && namespace.is_none();
+    // The site's host, canonicalized under `rules` without equivalent domains.
+    let host = |subdomains| -> Result<Option<String>> {
+        let rules = Rules {
+            subdomains,
+            equivalent: Vec::new(),
+            ..rules.clone().into_owned()
+        };
+        let url = canonicalize_with(&args.site, None, &rules)?;
+        Ok(Url::parse(&url)?.domain().map(str::to_owned))
+    };
+    // The map rule the site's host matched, were its subdomains mapped.
+    let map_rule = match rules.subdomains {
+        Subdomains::Map if is_url => host(Subdomains::Keep)?
+            .as_deref()
+            .and_then(|host| rules.map_rule(host))
+            .map(|(pattern, target)| (pattern.to_owned(), target.to_owned())),
+        _ => None,
+    };
+    let equivalent = match is_url {
+        true => host(rules.subdomains)?
+            .as_deref()
+            .and_then(|host| rules.equivalent_domain(host))
+            .filter(|(domain, first)| domain != first)
+            .map(|(domain, first)| (domain.to_owned(), first.to_owned())),
+        false => None,
+    };
+    let salt_text = std::str::from_utf8(&salt.salt)
+        .ok()
+        .filter(|salt| !salt.contains(char::is_control));
+    let compat = site_config.and_then(|site| site.compat.as_ref());
+    let salt_template = args
+        .site_args
+        .salt_template
+        .as_deref()
+        .or_else(|| config.salt_template(site_config));
+    let bumps = site_config.map_or(&[][..], |site| &site.bumps);
+
+    if porcelain {
+        let mut out = Porcelain::new();
+        out.line("url", &salt.url)?
+            .maybe("site", site.as_ref().map(|(url, _)| url.as_str()))?
+            .line("kind", &kind)?;
+        if !raw {
+            out.line("canonicalization", &rules.to_string())?;
+        }
+        if is_url {
+            out.line("subdomains", &rules.subdomains.to_string())?
+                .maybe(
+                    "map-rule",
+                    map_rule.as_ref().map(|(pattern, _)| pattern.as_str()),
+                )?
+                .maybe(
+                    "map-target",
+                    map_rule.as_ref().map(|(_, target)| target.as_str()),
+                )?
+                .maybe(
+                    "equivalent",
+                    equivalent.as_ref().map(|(domain, _)| domain.as_str()),
+                )?
+                .maybe(
+                    "equivalent-to",
+                    equivalent.as_ref().map(|(_, first)| first.as_str()),
+                )?
+                .line("port", if rules.port { "kept" } else { "dropped" })?
+                .maybe("path", rules.path.as_deref())?;
+        }
+        out.maybe("username", salt.username.as_deref())?
+            .line("version", &salt.version.to_string())?
+            .line("increment", &salt.increment.to_string())?
+            .maybe("salt-template", salt_template)?
+            .line("salt-hex", &hex::encode(&salt.salt))?
+            .maybe("compat", compat.map(|compat| compat.to_string()).as_deref())?;
+        for bump in bumps {
+            out.line("bump", &bump.increment.to_string())?
+                .line("date", &bump.date.to_string())?
+                .maybe("reason", bump.reason.as_deref())?;
+        }
+        return Ok(());
+    }
+
+    println!("url: {}", salt.url);
+    match &site {
+        Some((url, _)) => println!("site: {url}"),
+        None => println!("site: none; {} is not in the config", args.site),
+    }
+    println!("kind: {kind}");
+    if !raw {
+        println!("canonicalization: {}", *rules);
+    }
+    if is_url {
+        let per_site = |set: bool| if set { " (per site)" } else { "" };
+        println!(
+            "subdomains: {}{}",
+            rules.subdomains,
+            per_site(site_config.is_some_and(|site| site.subdomains.is_some()))
+        );
+        if let Some((pattern, target)) = &map_rule {
+            println!("map rule: {pattern} → {target}");
+        }
+        if let Some((domain, first)) = &equivalent {
+            println!("equivalent domains: {domain} → {first}");
+        }
+        println!(
+            "port: {}{}",
+            if rules.port { "kept" } else { "dropped" },
+            per_site(site_config.is_some_and(|site| site.port.is_some()))
+        );
+        if let Some(path) = &rules.path {
+            println!("path prefix: {path}");
+        }
+    }
+    if let Some(username) = &salt.username {
+        println!("username: {username}");
+    }
+    println!("derivation version: {}", salt.version);
+    println!("increment: {}", salt.increment);
+    if let Some(template) = salt_template {
+        println!("salt template: {template}");
+    }
+    match salt_text {
+        Some(text) => println!("salt: {text}"),
+        None => println!("salt (hex): {}", hex::encode(&salt.salt)),
+    }
+    if let Some(compat) = compat {
+        println!("compat: {compat}, which derives from the site name instead of the salt");
+    }
+    for bump in bumps {
+        let reason = bump.reason.as_ref().map(|reason| format!(": {reason}"));
+        println!(
+            "bumped from {} on {}{}",
+            bump.increment,
+            bump.date,
+            reason.unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct RekeyReport {
+    url: String,
+    old: String,
+    new: String,
+    fields: BTreeMap<String, RekeyField>,
+}
+
+#[derive(Serialize)]
+struct RekeyField {
+    old: String,
+    new: String,
+}
+
+/// Generates the output of `schema`, i.e. the password or else the field `field`, from `key`,
+/// drawing words from those of `words` that pass `site`'s word filter.
+fn site_output(
+    words: &words::Filters,
+    site: Option<&SiteConfig>,
+    key: &KeyMaterial,
+    version: DerivationVersion,
+    schema: &str,
+    field: Option<&str>,
+) -> Result<Zeroizing<String>> {
+    let filter = site
+        .map(|site| site.word_filter.clone())
+        .unwrap_or_default();
+    let words = words.get(&filter)?;
+    let words = Words(&*words);
+    let expr = Expr::parse(schema).context(tr("invalid-schema", &[]))?;
+    let mut rng = match field {
+        Some(field) => key.labeled_rng(&format!("field:{field}")),
+        None => key.password_rng(version),
+    };
+    let index = random_below(&mut rng, &nonzero_size(&words, &expr)?);
+    words.gen_at(&expr, index)
+}
+
+/// Returns the number of passwords matching `expr`, failing if there are none, as when a word
+/// filter leaves no words.
+fn nonzero_size<L: WordList + ?Sized>(words: &Words<L>, expr: &Expr) -> Result<NonZero<U256>> {
+    Option::from(NonZero::new(words.size(expr))).context(tr("schema-matches-nothing", &[]))
+}
+
+/// Returns the schema to use: `schema`, which may be an alias, or else the schema of `site`'s
+/// field `field` or password, or else the default, which for the password of an unconfigured
+/// website `name` may come from its built-in rules.
+fn schema<'a>(
+    config: &'a Config,
+    name: Option<&str>,
+    site: Option<&'a SiteConfig>,
+    schema: Option<&'a str>,
+    field: Option<&str>,
+) -> &'a str {
+    if let Some(schema) = schema {
+        return config.aliases.get(schema).map_or(schema, String::as_str);
+    }
+    match (site, field) {
+        (Some(site), Some(field)) => site.fields.get(field).unwrap_or(&config.default_schema),
+        (Some(site), None) => &site.schema,
+        (None, None) => name.map_or(&config.default_schema, |name| {
+            config.default_schema_for(name)
+        }),
+        (None, Some(_)) => &config.default_schema,
+    }
+}
+
+/// Derives the password of the site `name`, with its config if it has any, returning it with the
+/// site's salt.
+fn site_password(
+    config: &Config,
+    words: &words::Filters,
+    name: &str,
+    master: &Master,
+) -> Result<(Salt, Zeroizing<String>)> {
+    let site = config.find_site(name)?;
+    let salt = SiteArgs::default().salt(config, name, site.as_ref())?;
+    let password = match site.as_ref().map(|(_, site)| *site) {
+        Some(SiteConfig {
+            compat: Some(compat),
+            ..
+        }) => salt.compat_password(config, compat, master.password(config)?)?,
+        site => {
+            let schema = site.map_or_else(|| config.default_schema_for(name), |site| &site.schema);
+            let key = master.key_material(config, &salt.salt)?;
+            site_output(words, site, &key, salt.version, schema, None)?
+        }
+    };
+    record_history(config, &salt, None);
+    Ok((salt, password))
+}
+
+/// Derives the password and fields of the configured site `name` from the master password
+/// `password`, with the KDF and second factors of `key`, for exporting or rekeying it.
+fn site_secrets(
+    config: &Config,
+    words: &words::Filters,
+    key: &KeyArgs,
+    password: &str,
+    (name, site): (&str, &SiteConfig),
+) -> Result<export::Secrets> {
+    let salt = SiteArgs::default().salt(config, name, Some(&(name.into(), site)))?;
+    // Compat sites without fields need no key material.
+    let key = match (&site.compat, site.fields.is_empty()) {
+        (Some(_), true) => None,
+        _ => {
+            let factors = key.factors(config, &salt.salt)?;
+            Some(KeyMaterial::derive(
+                password,
+                &salt.salt,
+                &key.kdf(config)?,
+                &factors,
+            )?)
+        }
+    };
+    let mut fields = Vec::new();
+    for (field, schema) in &site.fields {
+        let key = key.as_ref().unwrap();
+        let output = site_output(words, Some(site), key, salt.version, schema, Some(field))?;
+        fields.push((field.clone(), output));
+    }
+    let password = match (&site.compat, &key) {
+        (Some(compat), _) => salt.compat_password(config, compat, password)?,
+        (None, Some(key)) => site_output(words, Some(site), key, salt.version, &site.schema, None)?,
+        (None, None) => unreachable!(),
+    };
+    Ok(export::Secrets {
+        password,
+        fields,
+        totp_secret: site.totp_secret.clone(),
+    })
+}
+
+/// Answers an askpass prompt with the username or password of the configured site it names.
+fn askpass_command(config: &Config, prompt: askpass::Prompt, key: KeyArgs) -> Result<()> {
+    let (_, site) = config
+        .find_site(&prompt.site)?
+        .with_context(|| Failure::UnknownSite(prompt.site.clone()))?;
+    if let (Some(want), Some(have)) = (&prompt.username, &site.username)
+        && want != have
+    {
+        anyhow::bail!(tr(
+            "wrong-username",
+            &[("site", &prompt.site), ("have", have), ("want", want)]
+        ));
+    }
+    if prompt.want == askpass::Want::Username {
+        let username = site.username.as_ref().or(prompt.username.as_ref());
+        return print_secret(username.context(tr("no-username", &[]))?);
+    }
+    let words = words::List::open(config.words_paths(), config.wordlist, config.cache_dir())?;
+    let words = words::Filters::new(&words);
+    let (_, password) = site_password(config, &words, &prompt.site, &Master::new(&key))?;
+    print_secret(&password)
+}
+
+fn git_credential_command(config: &Config, operation: GitOperation, key: KeyArgs) -> Result<()> {
+    // Passwords are derived, so there is nothing to store or erase.
+    if operation != GitOperation::Get {
+        return Ok(());
+    }
+    // Printing nothing lets git fall back to its other helpers or to prompting.
+    let request = GitRequest::parse(&io::read_to_string(stdin())?);
+    let Some(name) = request.site() else {
+        return Ok(());
+    };
+    let Some((_, site)) = config.find_site(&name)? else {
+        return Ok(());
+    };
+    if let (Some(want), Some(have)) = (request.get("username"), &site.username)
+        && want != have
+    {
+        return Ok(());
+    }
+    let words = words::List::open(config.words_paths(), config.wordlist, config.cache_dir())?;
+    let words = words::Filters::new(&words);
+    let (salt, password) = site_password(config, &words, &name, &Master::new(&key))?;
+    let mut stdout = stdout().lock();
+    if let Some(username) = salt.username.as_deref().or(request.get("username")) {
+        writeln!(stdout, "username={username}")?;
+    }
+    writeln!(stdout, "password={}", password.as_str())?;
+    Ok(())
+}
+
+fn docker_credential_command(
+    config: &Config,
+    operation: DockerOperation,
+    key: KeyArgs,
+) -> Result<()> {
+    let input = match operation {
+        DockerOperation::List => String::new(),
+        _ => io::read_to_string(stdin())?,
+    };
+    match operation {
+        DockerOperation::Get => {
+            let name = credential::docker_site(&input)?;
+            let Some((_, site)) = config.find_site(&name)? else {
+                println!("{}", credential::DOCKER_NOT_FOUND);
+                return Err(Failure::UnknownSite(name).into());
+            };
+            let username = site
+                .username
+                .clone()
+                .with_context(|| tr("no-username-for", &[("site", &name)]))?;
+            let words =
+                words::List::open(config.words_paths(), config.wordlist, config.cache_dir())?;
+            let words = words::Filters::new(&words);
+            let (_, password) = site_password(config, &words, &name, &Master::new(&key))?;
+            let credentials = DockerCredentials {
+                server_url: input.trim().to_owned(),
+                username,
+                secret: &password,
+            };
+            let credentials = Zeroizing::new(serde_json::to_string(&credentials)?);
+            println!("{}", credentials.as_str());
+        }
+        DockerOperation::List => {
+            let registries: BTreeMap<_, _> = config
+                .sites
+                .iter()
+                .filter_map(|(url, site)| Some((url, site.username.as_ref()?)))
+                .collect();
+            println!("{}", serde_json::to_string(&registries)?);
+        }
+        // Passwords are derived, so there is nothing to store or erase.
+        DockerOperation::Store | DockerOperation::Erase => {}
+    }
+    Ok(())
+}
+
+fn kube_credential_command(config: &Config, cluster: Option<String>, key: KeyArgs) -> Result<()> {
+    let info = std::env::var("KUBERNETES_EXEC_INFO")
+        .ok()
+        .map(|info| KubeExecInfo::parse(&info))
+        .transpose()?;
+    let name = cluster
+        .as_deref()
+        .or_else(|| info.as_ref()?.server())
+        .context(tr("no-cluster", &[]))?;
+    let words = words::List::open(config.words_paths(), config.wordlist, config.cache_dir())?;
+    let words = words::Filters::new(&words);
+    let (_, password) = site_password(config, &words, name, &Master::new(&key))?;
+    println!(
+        "{}",
+        credential::kube_exec_credential(info.as_ref(), &password)?
+    );
+    Ok(())
+}
+
+/// Returns the name `onepass ls` lists the site `url` under, e.g. `example.com`.
+fn pass_name(url: &str) -> &str {
+    let name = url.strip_prefix("https://").unwrap_or(url);
+    name.strip_suffix('/').unwrap_or(name)
+}
+
+fn audit_command(config: &Config, args: AuditArgs, porcelain: bool) -> Result<()> {
+    let words = words::List::open(config.words_paths(), config.wordlist, config.cache_dir())?;
+    let words = words::Filters::new(&words);
+    let min_bits = args
+        .min_bits
+        .or(config.min_bits)
+        .unwrap_or(config_audit::MIN_BITS);
+    let findings = config_audit::audit(config, &words, min_bits, |url, site| {
+        let site = (url.to_owned(), site);
+        Ok(SiteArgs::default().salt(config, url, Some(&site))?.salt)
+    })?;
+    if porcelain {
+        let mut out = Porcelain::new();
+        for finding in &findings {
+            out.line("check", &finding.check.to_string())?
+                .maybe("site", finding.site.as_deref())?
+                .line("message", &finding.message)?;
+        }
+    } else {
+        for finding in &findings {
+            println!(
+                "{}: {}",
+                finding.site.as_deref().unwrap_or("config"),
+                finding.message
+            );
+        }
+    }
+    anyhow::ensure!(
+        findings.is_empty(),
+        tr("weaknesses", &[("count", &findings.len())])
+    );
+    if !porcelain {
+        println!("no weaknesses found in {} sites", config.sites.len());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "history")]
+fn history_command(config: &Config, args: HistoryArgs, porcelain: bool) -> Result<()> {
+    let path = config.config_dir()?.join(history::FILE);
+    let identity = args.key.derive_key(config, "history", "", 0)?;
+    if args.init {
+        history::init(&path, &identity)?;
+        eprintln!("{}", tr("recording-history", &[("path", &path.display())]));
+        return Ok(());
+    }
+    let site = match &args.site {
+        Some(name) => Some(match config.find_site(name)? {
+            Some((url, _)) => history::without_username(&url),
+            None => config
+                .canonicalize(name, None, None)
+                .unwrap_or_else(|_| name.clone()),
+        }),
+        None => None,
+    };
+    let events = history::read(&path, &identity)?;
+    let events = events.iter().filter(|event| {
+        site.as_ref().is_none_or(|site| event.site() == *site)
+            && args
+                .since
+                .is_none_or(|since| Date::from_unix(event.time) >= since)
+    });
+    let mut out = Porcelain::new();
+    for event in events {
+        if porcelain {
+            out.line("time", &event.time.to_string())?
+                .line("url", &event.url)?
+                .line("increment", &event.increment.to_string())?
+                .maybe("output", event.output.as_deref())?;
+            continue;
+        }
+        let mut line = format!("{}  {}", event.time(), event.url);
+        if event.increment != 0 {
+            line.push_str(&format!(" increment {}", event.increment));
+        }
+        if let Some(output) = &event.output {
+            line.push_str(&format!(" {output}"));
+        }
+        println!("{line}");
+    }
+    Ok(())
+}
+
+/// Records the derivation of `salt`'s site's password, or of its output labeled `output` (e.g.
+/// `field:pin`), in the history, if one is kept. A failure only warns, as the secret was derived.
+#[cfg(feature = "history")]
+fn record_history(config: &Config, salt: &Salt, output: Option<&str>) {
+    let record = || -> Result<()> {
+        let path = config.config_dir()?.join(history::FILE);
+        history::record(
+            &path,
+            &history::Event::now(&salt.url, salt.increment, output),
+        )
+    };
+    if let Err(e) = record() {
+        let error = format!("{e:#}");
+        eprintln!("{}", tr("failed-recording-history", &[("error", &error)]));
+    }
+}
+
+#[cfg(not(feature = "history"))]
+fn record_history(_config: &Config, _salt: &Salt, _output: Option<&str>) {}
+
+fn pwned_command(config: &Config, args: PwnedArgs, porcelain: bool) -> Result<()> {
+    let words = words::List::open(config.words_paths(), config.wordlist, config.cache_dir())?;
+    let words = words::Filters::new(&words);
+    let (salt, password) = site_password(config, &words, &args.site, &Master::new(&args.key))?;
+    let (found, source) = match &args.list {
+        Some(path) => (pwned::lookup(path, &password)?, path.display().to_string()),
+        None => (
+            pwned::Found::Count(pwned::count(&password)?),
+            "Have I Been Pwned".into(),
+        ),
+    };
+    if porcelain {
+        let mut out = Porcelain::new();
+        out.line("url", &salt.url)?;
+        match found {
+            pwned::Found::Count(count) => out.line("count", &count.to_string())?,
+            pwned::Found::Probably(found) => out.line("found", &found.to_string())?,
+        };
+    }
+    match found {
+        pwned::Found::Count(0) | pwned::Found::Probably(false) => {}
+        pwned::Found::Count(count) => anyhow::bail!(tr(
+            "pwned",
+            &[("site", &salt.url), ("count", &count), ("source", &source)]
+        )),
+        pwned::Found::Probably(true) => anyhow::bail!(tr(
+            "probably-pwned",
+            &[("site", &salt.url), ("source", &source)]
+        )),
+    }
+    if !porcelain {
+        println!("{}'s password is not in {source}", salt.url);
+    }
+    Ok(())
+}
+
+fn build_breach_filter_command(args: BuildBreachFilterArgs, porcelain: bool) -> Result<()> {
+    let stats = pwned::build_filter(&args.input, &args.output, args.false_positive_rate)?;
+    if porcelain {
+        Porcelain::new()
+            .line("hashes", &stats.hashes.to_string())?
+            .line("bits", &stats.bits.to_string())?
+            .line("hash-functions", &stats.functions.to_string())?;
+    } else {
         eprintln!(
-            "schema has about {0} bits of entropy (0x{1} possible passwords)",
-            &size.bits(),
-            &size.to_string().trim_start_matches('0')
+            "{}",
+            tr(
+                "breach-filter-written",
+                &[
+                    ("hashes", &stats.hashes),
+                    ("path", &args.output.display()),
+                    ("bytes", &stats.bits.div_ceil(8)),
+                    ("functions", &stats.functions),
+                ]
+            )
         );
-        eprintln!("salt: {salt:?}");
     }
+    Ok(())
+}
+
+fn show_command(
+    config: &Config,
+    args: ShowArgs,
+    clipboard: Option<ClipboardBackend>,
+    porcelain: bool,
+) -> Result<()> {
+    let words = words::List::open(config.words_paths(), config.wordlist, config.cache_dir())?;
+    let words = words::Filters::new(&words);
+    let mut name = args.name.as_str();
+    if config.find_site(name)?.is_none() {
+        name = name.rsplit('/').next().unwrap_or(name);
+        if config.find_site(name)?.is_none() {
+            anyhow::bail!(tr("not-in-store", &[("name", &args.name)]));
+        }
+    }
+    let (salt, password) = site_password(config, &words, name, &Master::new(&args.key))?;
+    if args.clip {
+        let clipboard = clipboard::choose(config, clipboard);
+        clipboard.copy(&password)?;
+        if porcelain {
+            Porcelain::new()
+                .line("url", &salt.url)?
+                .maybe("username", salt.username.as_deref())?
+                .line("clipboard", &clipboard.to_string())?
+                .line("clear-after", &clipboard::CLEAR_SECS.to_string())?;
+            return Ok(());
+        }
+        println!(
+            "Copied {} to clipboard. Will clear in {} seconds.",
+            args.name,
+            clipboard::CLEAR_SECS
+        );
+        return Ok(());
+    }
+    if porcelain {
+        Porcelain::new()
+            .line("url", &salt.url)?
+            .maybe("username", salt.username.as_deref())?
+            .line("password", &password)?;
+        return Ok(());
+    }
+    println!("{}", password.as_str());
+    if let Some(username) = &salt.username {
+        println!("login: {username}");
+    }
+    println!("url: {}", salt.url);
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", feature = "secret-service"))]
+fn secret_service_command(config: &Config, key: KeyArgs) -> Result<()> {
+    let words = words::List::open(config.words_paths(), config.wordlist, config.cache_dir())?;
+    let words = words::Filters::new(&words);
+    let mut sites: Vec<_> = config.sites.keys().collect();
+    sites.sort();
+    let items = sites
+        .iter()
+        .map(|url| secret_service::Item::new(url, config.sites[*url].username.as_deref()))
+        .collect::<Result<Vec<_>>>()?;
+    // Applications ask for secrets with no terminal to prompt on, so read the password up front.
+    let master = Master::read(&key, config)?;
+    eprintln!("{}", tr("serving", &[("count", &items.len())]));
+    secret_service::serve(&items, |i| {
+        let (_, password) = site_password(config, &words, sites[i], &master)?;
+        Ok(password)
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn secret_service_command(_config: &Config, _key: KeyArgs) -> Result<()> {
+    anyhow::bail!(tr("secret-service-unsupported", &[]));
+}
+
+#[cfg(all(target_os = "linux", not(feature = "secret-service")))]
+fn secret_service_command(_config: &Config, _key: KeyArgs) -> Result<()> {
+    anyhow::bail!(tr("built-without-secret-service", &[]));
+}
+
+fn native_host_command(config: &Config, args: NativeHostArgs) -> Result<()> {
+    let words = words::List::open(config.words_paths(), config.wordlist, config.cache_dir())?;
+    let words = words::Filters::new(&words);
+    let mut origins = Origins::load(config.config_dir()?)?;
+    let master = Master::new(&args.key);
+    native_host::serve(|request| match request {
+        Request::Get { url } => {
+            let origin = native_host::origin(&url)?;
+            if !origins.authorize(&origin)? {
+                anyhow::bail!(tr("not-authorized", &[("origin", &origin)]));
+            }
+            // Sites with path prefixes are found by the page's URL, the rest by its origin.
+            let name = match config.find_site(&url)? {
+                Some(_) => &url,
+                None => &origin,
+            };
+            let (salt, password) = site_password(config, &words, name, &master)?;
+            Ok(Response::Password {
+                url: origin,
+                username: salt.username,
+                password: password.to_string(),
+            })
+        }
+        Request::List => {
+            let mut sites: Vec<_> = config
+                .sites
+                .iter()
+                .map(|(url, site)| native_host::Site {
+                    url: url.clone(),
+                    username: site.username.clone(),
+                })
+                .collect();
+            sites.sort_by(|a, b| a.url.cmp(&b.url));
+            Ok(Response::Sites { sites })
+        }
+    })
+}
+
+fn merge_command(config: &Config, path: &Path, args: MergeArgs) -> Result<()> {
+    let read = |path: &Path| -> Result<serde_yaml::Value> {
+        let text = read_to_string(path)
+            .with_context(|| tr("failed-reading", &[("path", &path.display())]))?;
+        serde_yaml::from_str(&text)
+            .with_context(|| tr("invalid-config", &[("path", &path.display())]))
+    };
+    let sites = |doc: &serde_yaml::Value| {
+        let sites = doc.get("sites").and_then(|sites| sites.as_mapping());
+        sites.cloned().unwrap_or_default()
+    };
+    let ours = read(path)?;
+    // Sites are matched up by canonical URL, so that `github.com` in one and
+    // `https://github.com/` in the other are one site, spelled as in this config.
+    let mut spellings = BTreeMap::new();
+    for key in sites(&ours).keys() {
+        if let Some(key) = key.as_str()
+            && let Some(url) = config.site_key(key)?
+        {
+            spellings.insert(url, key.to_owned());
+        }
+    }
+    let respell = |mut doc: serde_yaml::Value| -> Result<serde_yaml::Value> {
+        if let Some(sites) = doc.get_mut("sites").and_then(|s| s.as_mapping_mut()) {
+            let mut respelled = serde_yaml::Mapping::new();
+            for (key, site) in std::mem::take(sites) {
+                let spelling = match key.as_str() {
+                    Some(name) => config.site_key(name)?.and_then(|url| spellings.get(&url)),
+                    None => None,
+                };
+                respelled.insert(spelling.map_or(key, |s| s.as_str().into()), site);
+            }
+            *sites = respelled;
+        }
+        Ok(doc)
+    };
+    let other = respell(read(&args.other)?)?;
+    let base = match &args.base {
+        Some(base) => respell(read(base)?)?,
+        None => serde_yaml::Value::Null,
+    };
+    let merged = match merge::merge(&base, &other, &ours, args.prefer) {
+        Ok(merged) => merged,
+        Err(conflicts) => {
+            let conflicts: Vec<_> = conflicts.iter().map(|c| format!("  {c}")).collect();
+            anyhow::bail!(tr(
+                "merge-conflicts",
+                &[
+                    ("path", &args.other.display()),
+                    ("conflicts", &conflicts.join("\n"))
+                ]
+            ));
+        }
+    };
+    if args.print {
+        stdout().write_all(serde_yaml::to_string(&merged)?.as_bytes())?;
+        return Ok(());
+    }
+    let after = serde_yaml::Value::from(merged.clone());
+    if after == ours {
+        eprintln!("{}", tr("up-to-date", &[("path", &args.other.display())]));
+        return Ok(());
+    }
+    let (before, after) = (sites(&ours), sites(&after));
+    let added = after
+        .keys()
+        .filter(|key| !before.contains_key(*key))
+        .count();
+    let removed = before
+        .keys()
+        .filter(|key| !after.contains_key(*key))
+        .count();
+    let changed = after
+        .iter()
+        .filter(|(key, site)| before.get(*key).is_some_and(|was| was != *site))
+        .count();
+    exit::writable()?;
+    config.update(|doc| {
+        *doc = merged;
+        Ok(())
+    })?;
+    eprintln!(
+        "{}",
+        tr(
+            "merged",
+            &[
+                ("path", &args.other.display()),
+                ("added", &added),
+                ("changed", &changed),
+                ("removed", &removed),
+            ]
+        )
+    );
+    Ok(())
+}
+
+fn import_command(config: &Config, args: ImportArgs) -> Result<()> {
+    let mut import = args.from.parse(&read_to_string(&args.path)?)?;
+    // A different full name would derive different Spectre passwords.
+    if let (Some(have), Some(want)) = (&config.full_name, &import.full_name) {
+        if have != want {
+            anyhow::bail!(tr(
+                "full-name-mismatch",
+                &[
+                    ("have", &format!("{have:?}")),
+                    ("want", &format!("{want:?}"))
+                ]
+            ));
+        }
+        import.full_name = None;
+    }
+    for (name, _) in import.sites.extract_if(.., |name, _| {
+        config.site_key(name).is_ok_and(|key| key.is_some())
+    }) {
+        import.skipped.push((name, "already in the config".into()));
+    }
+    for (name, reason) in &import.skipped {
+        eprintln!(
+            "{}",
+            tr("import-skipped", &[("name", name), ("reason", reason)])
+        );
+    }
+
+    let mut doc = serde_yaml::Mapping::new();
+    if let Some(full_name) = import.full_name {
+        doc.insert("full_name".into(), full_name.into());
+    }
+    let sites: serde_yaml::Mapping = import
+        .sites
+        .iter()
+        .map(|(name, site)| Ok((name.as_str().into(), serde_yaml::to_value(site)?)))
+        .collect::<Result<_>>()?;
+    let count = sites.len();
+    doc.insert("sites".into(), sites.into());
+    if args.print {
+        stdout().write_all(serde_yaml::to_string(&doc)?.as_bytes())?;
+        return Ok(());
+    }
+    exit::writable()?;
+    config.update(|config| {
+        for (key, value) in doc {
+            match (config.get_mut(&key), value) {
+                (Some(serde_yaml::Value::Mapping(sites)), serde_yaml::Value::Mapping(imported)) => {
+                    sites.extend(imported)
+                }
+                (_, value) => {
+                    config.insert(key, value);
+                }
+            }
+        }
+        Ok(())
+    })?;
+    eprintln!("{}", tr("imported", &[("count", &count)]));
+    Ok(())
+}
+
+fn duress_fingerprint_command(porcelain: bool) -> Result<()> {
+    let password = prompt_master_password("duress-password-prompt", true)?;
+    let fingerprint = duress::fingerprint(&password)?;
+    if porcelain {
+        Porcelain::new().line("fingerprint", &fingerprint)?;
+    } else {
+        println!("duress:\n  fingerprint: {fingerprint}");
+        eprintln!("{}", tr("duress-instructions", &[]));
+    }
+    Ok(())
+}
+
+fn rekey_command(config: &Config, args: RekeyArgs, porcelain: bool) -> Result<()> {
+    let words = words::List::open(config.words_paths(), config.wordlist, config.cache_dir())?;
+    let words = words::Filters::new(&words);
+    let old = prompt_master_password("old-master-password-prompt", false)?;
+    let new = prompt_master_password("new-master-password-prompt", true)?;
 
-    if args.reset_keyring {
-        get_onepass_entry()?.delete_credential()?;
+    let mut sites: Vec<_> = config.sites.keys().collect();
+    sites.sort();
+    let mut found = Vec::with_capacity(sites.len());
+    for name in sites {
+        if let Some(site) = config.find_site(name)? {
+            found.push((name, site));
+        }
+    }
+    // Each site's old and new passwords are derived separately, so both can run at once.
+    let jobs = found
+        .iter()
+        .flat_map(|(name, site)| [(*name, site.1, &old), (*name, site.1, &new)])
+        .collect();
+    let secrets = batch::map(
+        args.key.batch_threads(config)?,
+        jobs,
+        |(name, site, password)| site_secrets(config, &words, &args.key, password, (name, site)),
+    )?;
+    let mut reports = Vec::with_capacity(found.len());
+    for ((_, site), pair) in found.into_iter().zip(secrets.chunks_exact(2)) {
+        let [old, new] = pair else { unreachable!() };
+        let fields = old
+            .fields
+            .iter()
+            .zip(&new.fields)
+            .map(|((field, old), (_, new))| {
+                let field_report = RekeyField {
+                    old: old.to_string(),
+                    new: new.to_string(),
+                };
+                (field.clone(), field_report)
+            })
+            .collect();
+        reports.push(RekeyReport {
+            old: old.password.to_string(),
+            new: new.password.to_string(),
+            url: site.0,
+            fields,
+        });
     }
 
-    let use_keyring = args.keyring.or(config.use_keyring).unwrap_or(false);
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+        return Ok(());
+    }
+    if porcelain {
+        let mut out = Porcelain::new();
+        for report in &reports {
+            out.line("url", &report.url)?
+                .line("old", &report.old)?
+                .line("new", &report.new)?;
+            for (name, field) in &report.fields {
+                out.line("field", name)?
+                    .line("old", &field.old)?
+                    .line("new", &field.new)?;
+            }
+        }
+        return Ok(());
+    }
+    for report in &reports {
+        println!("[ ] {}", report.url);
+        println!("      old: {}", report.old);
+        println!("      new: {}", report.new);
+        for (name, field) in &report.fields {
+            println!("      {name}: {} -> {}", field.old, field.new);
+        }
+    }
+    if config.use_keyring == Some(true) {
+        eprintln!("{}", tr("rekey-keyring", &[]));
+    }
+    Ok(())
+}
 
-    let password = read_password(use_keyring, args.confirm)?;
-    let mut rng = Rng::from_password_salt(password, salt)?;
-    let index = U256::random_mod(&mut rng, &NonZero::new(size).unwrap());
-    let res = words.gen_at(&expr, index)?;
+#[cfg(feature = "keys")]
+fn seed_command(config: &Config, args: SeedArgs, porcelain: bool) -> Result<()> {
+    let seed = args
+        .key
+        .derive_key(config, "bip39", &args.label, args.increment)?;
+    let mnemonic = keys::bip39_mnemonic(&seed, args.words)?;
+    if porcelain {
+        Porcelain::new().line("mnemonic", &mnemonic)?;
+        return Ok(());
+    }
+    print_secret(&mnemonic)
+}
+
+#[cfg(feature = "keys")]
+fn age_key_command(config: &Config, args: KeyPairArgs, porcelain: bool) -> Result<()> {
+    let seed = args
+        .key
+        .derive_key(config, "age-key", &args.label, args.increment)?;
+    let key = AgeKey::from_seed(&seed);
+    if porcelain {
+        let mut out = Porcelain::new();
+        out.line("public-key", &key.recipient())?;
+        if !args.public {
+            out.line("private-key", &key.identity())?;
+        }
+    } else if args.public {
+        println!("{}", key.recipient());
+    } else {
+        println!("# public key: {}", key.recipient());
+        stdout().write_all(key.identity().as_bytes())?;
+        println!();
+    }
+    Ok(())
+}
+
+#[cfg(feature = "keys")]
+fn wg_key_command(config: &Config, args: KeyPairArgs, porcelain: bool) -> Result<()> {
+    let seed = args
+        .key
+        .derive_key(config, "wg-key", &args.label, args.increment)?;
+    let key = WgKey::from_seed(&seed);
+    if porcelain {
+        let mut out = Porcelain::new();
+        out.line("public-key", &key.public_base64())?;
+        if !args.public {
+            out.line("private-key", &key.private_base64())?;
+        }
+    } else if args.public {
+        println!("{}", key.public_base64());
+    } else {
+        print_secret(&key.private_base64())?;
+    }
+    Ok(())
+}
+
+/// Writes `secret` to stdout, with a trailing newline only if stdout is a terminal.
+fn print_secret(secret: &str) -> Result<()> {
     let mut stdout = stdout();
-    stdout.write_all(res.as_bytes())?;
+    stdout.write_all(secret.as_bytes())?;
     if stdout.is_terminal() {
         writeln!(stdout)?;
     }
     Ok(())
 }
+
+/// Returns the compatibility settings to derive a site's password with: those of `algorithm` if
+/// given, taking the site's options if it has some for it, and otherwise the site's unless the
+/// password is not being derived (`other`, e.g. with `--field`).
+fn site_compat(
+    site: Option<&SiteConfig>,
+    algorithm: Option<compat::Algorithm>,
+    other: bool,
+) -> Option<Compat> {
+    let configured = site.and_then(|site| site.compat.as_ref());
+    match algorithm {
+        Some(algorithm) => Some(
+            configured
+                .filter(|compat| compat.algorithm() == algorithm)
+                .cloned()
+                .unwrap_or_else(|| Compat::new(algorithm)),
+        ),
+        None if other => None,
+        None => configured.cloned(),
+    }
+}
+
+fn generate(args: Args, config: &Config) -> Result<()> {
+    let porcelain = args.porcelain().is_some();
+    // A built-in list given on the command line overrides the config's words file too.
+    let words_path = match args.wordlist {
+        Some(_) => args.words_path,
+        None if args.words_path.is_empty() => config.words_paths(),
+        None => args.words_path,
+    };
+    let list = words::List::open(
+        words_path,
+        args.wordlist.unwrap_or(config.wordlist),
+        config.cache_dir(),
+    )?;
+
+    let site = match args.site.as_deref() {
+        Some(name) => args.site_args.find_site(config, name)?,
+        None => None,
+    };
+    // A script's typo would otherwise silently derive the password of another site.
+    if let Some(name) = &args.site
+        && site.is_none()
+        && exit::non_interactive()
+    {
+        return Err(Failure::UnknownSite(name.clone()).into());
+    }
+    let mut filter = site
+        .as_ref()
+        .map(|(_, site)| site.word_filter.clone())
+        .unwrap_or_default();
+    filter.max_len = args.max_word_len.or(filter.max_len);
+    filter.chars = args.word_chars.clone().or(filter.chars);
+    let words = filter.apply(&list)?;
+    let words = Words(&words);
+    let schema = schema(
+        config,
+        args.site.as_deref().filter(|_| !args.site_args.raw),
+        site.as_ref().map(|(_, site)| *site),
+        args.schema.as_deref(),
+        args.field.as_deref(),
+    );
+    let expr = Expr::parse(schema).context(tr("invalid-schema", &[]))?;
+    let size = words.size(&expr);
+    let compat = site_compat(
+        site.as_ref().map(|(_, site)| *site),
+        args.compat,
+        args.random || args.encoding.is_some() || args.field.is_some(),
+    );
+    if let (Some(compat), Some(_)) = (&compat, &args.schema) {
+        anyhow::bail!(tr("compat-schema", &[("algorithm", &compat.algorithm())]));
+    }
+    if compat.is_none() && args.encoding.is_none() {
+        // Fail before asking for the master password.
+        nonzero_size(&words, &expr)?;
+    }
+    if args.verbose && compat.is_none() {
+        match args.encoding {
+            Some(encoding) => {
+                let bits = encoding.bits(args.length.unwrap_or(raw::DEFAULT_LENGTH));
+                eprintln!("{}", tr("output-bits", &[("bits", &bits)]));
+            }
+            None => {
+                let count = size.to_string();
+                let count = count.trim_start_matches('0');
+                let bits = size.bits();
+                eprintln!(
+                    "{}",
+                    tr("schema-bits", &[("bits", &bits), ("count", &count)])
+                );
+            }
+        }
+    }
+    let output = |rng: &mut dyn RngCore| -> Result<Zeroizing<String>> {
+        match args.encoding {
+            Some(encoding) => encoding.encode(rng, args.length),
+            None => {
+                let index = random_below(rng, &nonzero_size(&words, &expr)?);
+                words.gen_at(&expr, index)
+            }
+        }
+    };
+
+    let clipboard = if args.copy_osc52 {
+        Some(ClipboardBackend::Osc52)
+    } else if args.copy_tmux {
+        Some(ClipboardBackend::Tmux)
+    } else {
+        args.copy.then(|| clipboard::choose(config, args.clipboard))
+    };
+    let emit = |secret: &str| {
+        if args.a11y {
+            eprint!("{}", Zeroizing::new(a11y::spell(secret)).as_str());
+        }
+        if args.hold {
+            return hold::show(secret, &|secret| {
+                let clipboard = clipboard::choose(config, args.clipboard);
+                clipboard.copy(secret)?;
+                let destination = clipboard.destination();
+                let seconds = clipboard::CLEAR_SECS;
+                Ok(tr(
+                    "copied",
+                    &[("destination", &destination), ("seconds", &seconds)],
+                ))
+            });
+        }
+        let Some(clipboard) = clipboard else {
+            if porcelain {
+                Porcelain::new().line("secret", secret)?;
+                return Ok(());
+            }
+            return print_secret(secret);
+        };
+        clipboard.copy(secret)?;
+        if porcelain {
+            Porcelain::new()
+                .line("clipboard", &clipboard.to_string())?
+                .line("clear-after", &clipboard::CLEAR_SECS.to_string())?;
+            return Ok(());
+        }
+        let destination = clipboard.destination();
+        let seconds = clipboard::CLEAR_SECS;
+        eprintln!(
+            "{}",
+            tr(
+                "copied",
+                &[("destination", &destination), ("seconds", &seconds)]
+            )
+        );
+        Ok(())
+    };
+
+    if args.random {
+        return emit(&output(&mut OsRng.unwrap_err())?);
+    }
+
+    if let Some(label) = &args.hidden {
+        let label = match label.as_str() {
+            "" => Zeroizing::new(exit::prompt_password(
+                "hidden-label",
+                "hidden-label-prompt",
+            )?),
+            label => Zeroizing::new(label.to_owned()),
+        };
+        let increment = args.site_args.increment.unwrap_or(0);
+        let salt = key_salt("hidden", &label, increment);
+        // Read up front, so that the key material is derived afresh rather than cached.
+        let key = Master::read(&args.key, config)?.key_material(config, &salt)?;
+        let mut rng = match (&args.encoding, &args.field) {
+            (Some(_), _) => key.labeled_rng("raw"),
+            (None, Some(field)) => key.labeled_rng(&format!("field:{field}")),
+            // Pinned, as hidden accounts have no config to keep an older version in.
+            (None, None) => key.password_rng(DerivationVersion::V2),
+        };
+        return emit(&output(&mut rng)?);
+    }
+
+    let name = args.site.as_deref().context(tr("no-site", &[]))?;
+    let salt = args.site_args.salt(config, name, site.as_ref())?;
+    if site.is_none()
+        && !args.site_args.raw
+        && let Some(like) = config.lookalike_site(name)?
+    {
+        eprintln!(
+            "{}",
+            tr("lookalike-site", &[("site", &name), ("like", &like)])
+        );
+    }
+    let raw = args.site_args.raw || site.as_ref().is_some_and(|(_, site)| site.raw);
+    let site_rules = match site
+        .as_ref()
+        .and_then(|(_, site)| site.passwordrules.as_deref())
+    {
+        Some(rules) => Some(Cow::Owned(rules.parse::<rules::PasswordRules>()?)),
+        // A schema the user chose is theirs to keep; only the default is checked.
+        None if raw || schema != config.default_schema => None,
+        None => config
+            .known_rules(name, rules::LATEST)
+            .map(|known| Cow::Borrowed(&known.rules)),
+    };
+    if compat.is_none()
+        && args.encoding.is_none()
+        && args.field.is_none()
+        && let Some(site_rules) = site_rules
+    {
+        let violations = site_rules.check(&words, &expr);
+        for violation in &violations {
+            eprintln!(
+                "{}",
+                tr(
+                    "rule-violation",
+                    &[("site", &name), ("violation", violation)]
+                )
+            );
+        }
+        let suggested = site_rules.schema();
+        if !violations.is_empty() && suggested != schema {
+            eprintln!("{}", tr("rule-schema", &[("schema", &suggested)]));
+        }
+    }
+    let secret = match &compat {
+        Some(compat) => {
+            if args.verbose {
+                eprintln!("{}", tr("derivation", &[("derivation", compat)]));
+            }
+            let password = args.key.read_password(config)?;
+            salt.compat_password(config, compat, &password)?
+        }
+        None => {
+            if args.verbose {
+                eprintln!("{}", tr("derivation", &[("derivation", &salt.version)]));
+                let quoted = format!("{:?}", String::from_utf8_lossy(&salt.salt));
+                eprintln!("{}", tr("salt", &[("salt", &quoted)]));
+            }
+            let key = args.key.key_material(config, &salt.salt, args.verbose)?;
+            let mut rng = match (&args.encoding, &args.field) {
+                (Some(_), _) => key.labeled_rng("raw"),
+                (None, Some(field)) => key.labeled_rng(&format!("field:{field}")),
+                (None, None) => key.password_rng(salt.version),
+            };
+            output(&mut rng)?
+        }
+    };
+    let output = match (&args.encoding, &args.field) {
+        (Some(_), _) => Some("raw".into()),
+        (None, field) => field.as_ref().map(|field| format!("field:{field}")),
+    };
+    record_history(config, &salt, output.as_deref());
+    let Salt { url, username, .. } = salt;
+    if porcelain {
+        Porcelain::new()
+            .line("url", &url)?
+            .maybe("username", username.as_deref())?
+            .maybe("field", args.field.as_deref())?;
+    }
+    match args.format {
+        Some(format) => {
+            let config = Zeroizing::new(format.render(&url, username.as_deref(), &secret)?);
+            stdout().write_all(config.as_bytes())?;
+            Ok(())
+        }
+        None => emit(&secret),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hidden_conflicts() {
+        // --hold's c key would copy the password.
+        for flag in ["--copy", "--copy-osc52", "--copy-tmux", "--hold"] {
+            assert!(Args::try_parse_from(["onepass", "example.com", flag]).is_ok());
+            let args = Args::try_parse_from(["onepass", "--hidden=label", flag]);
+            assert!(args.is_err(), "{flag}");
+        }
+        assert!(Args::try_parse_from(["onepass", "--hidden=label"]).is_ok());
+    }
+
+    #[test]
+    fn porcelain_versions() {
+        let porcelain = |args: &[&str]| Args::try_parse_from(args).map(|args| args.porcelain());
+        assert_eq!(porcelain(&["onepass", "a.com"]).unwrap(), None);
+        let latest = Some(porcelain::LATEST);
+        assert_eq!(
+            porcelain(&["onepass", "--porcelain", "a.com"]).unwrap(),
+            latest
+        );
+        assert_eq!(
+            porcelain(&["onepass", "--porcelain=1", "a.com"]).unwrap(),
+            Some(1)
+        );
+        assert!(porcelain(&["onepass", "--porcelain=0", "a.com"]).is_err());
+        let next = format!("--porcelain={}", porcelain::LATEST + 1);
+        assert!(porcelain(&["onepass", &next, "a.com"]).is_err());
+    }
+}
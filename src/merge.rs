@@ -0,0 +1,337 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Three-way merges of configs, as YAML, for `onepass merge` and `onepass sync`, where two
+//! machines changed one config independently.
+//!
+//! Settings are merged one at a time, and sites one field at a time: each is taken from whichever
+//! side changed it since their common ancestor, so that two machines that changed different sites
+//! merge cleanly. Increments and HOTP counters only ever go up, as going back would give an old
+//! password or code, so they take the larger value of the two, and `rotated` the later date,
+//! without ever conflicting.
+
+use std::fmt;
+
+use clap::ValueEnum;
+use serde_yaml::{Mapping, Value};
+
+/// The fields of sites merged by taking the larger value.
+const MONOTONIC: &[&str] = &["increment", "hotp_counter", "rotated"];
+
+/// Which side wins where both changed a site or setting differently.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub(crate) enum Prefer {
+    /// This config's change
+    Local,
+    /// The other config's change, the remote's for sync
+    #[value(alias = "other")]
+    Remote,
+}
+
+/// A site or setting changed differently on both sides, or `None` where deleted.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Conflict {
+    key: String,
+    local: Option<Value>,
+    remote: Option<Value>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |value: &Option<Value>| match value {
+            Some(value) => serde_json::to_string(value).unwrap_or_else(|_| "?".into()),
+            None => "deleted".into(),
+        };
+        write!(
+            f,
+            "{}: {} here, {} there",
+            self.key,
+            show(&self.local),
+            show(&self.remote)
+        )
+    }
+}
+
+/// Merges the changes to the config `base` in `remote` and `local`, or returns the conflicts that
+/// `prefer` doesn't settle. With no common ancestor, `base` is null, and only sites and settings
+/// that are the same on both sides, or on only one, merge.
+pub(crate) fn merge(
+    base: &Value,
+    remote: &Value,
+    local: &Value,
+    prefer: Option<Prefer>,
+) -> Result<Mapping, Vec<Conflict>> {
+    let mapping = |value: &Value| value.as_mapping().cloned().unwrap_or_default();
+    let mut conflicts = Vec::new();
+    let mut merged = merge_mappings(
+        "",
+        &mapping(base),
+        &mapping(remote),
+        &mapping(local),
+        prefer,
+        &mut conflicts,
+    );
+    let sites = |value: &Value| value.get("sites").and_then(Value::as_mapping).cloned();
+    if let (Some(remote_sites), Some(local_sites)) = (sites(remote), sites(local)) {
+        let base_sites = sites(base).unwrap_or_default();
+        // Sites are merged one by one instead.
+        conflicts.retain(|conflict| conflict.key != "sites");
+        let mut sites = Mapping::new();
+        for key in keys(&base_sites, &remote_sites, &local_sites) {
+            let name = key.as_str().unwrap_or("?");
+            let (was, theirs, ours) = (
+                base_sites.get(key),
+                remote_sites.get(key),
+                local_sites.get(key),
+            );
+            let site = match (theirs, ours) {
+                // Changed on both sides, so merged field by field.
+                (Some(theirs), Some(ours))
+                    if theirs != ours && Some(theirs) != was && Some(ours) != was =>
+                {
+                    let prefix = format!("site {name} ");
+                    let fields = |site: Option<&Value>| match site {
+                        Some(Value::String(schema)) => {
+                            Mapping::from_iter([("schema".into(), schema.as_str().into())])
+                        }
+                        site => site.map(mapping).unwrap_or_default(),
+                    };
+                    let mut site = merge_mappings(
+                        &prefix,
+                        &fields(was),
+                        &fields(Some(theirs)),
+                        &fields(Some(ours)),
+                        prefer,
+                        &mut conflicts,
+                    );
+                    for field in MONOTONIC {
+                        let larger = [theirs, ours]
+                            .into_iter()
+                            .filter_map(|site| site.get(field))
+                            .max_by(|a, b| compare(a, b));
+                        if let Some(larger) = larger {
+                            site.insert((*field).into(), larger.clone());
+                        }
+                    }
+                    // Bumps made on either side all belong to the site's trail.
+                    let mut bumps: Vec<&Value> = [theirs, ours]
+                        .into_iter()
+                        .filter_map(|site| site.get("bumps")?.as_sequence())
+                        .flatten()
+                        .collect();
+                    bumps.sort_by(|a, b| {
+                        compare(&a["date"], &b["date"])
+                            .then_with(|| compare(&a["increment"], &b["increment"]))
+                    });
+                    bumps.dedup();
+                    if !bumps.is_empty() {
+                        let bumps = bumps.into_iter().cloned().collect();
+                        site.insert("bumps".into(), Value::Sequence(bumps));
+                    }
+                    conflicts.retain(|conflict| {
+                        !MONOTONIC
+                            .iter()
+                            .chain(&["bumps"])
+                            .any(|field| conflict.key == format!("{prefix}{field}"))
+                    });
+                    Some(Value::Mapping(site))
+                }
+                _ => pick(
+                    &format!("site {name}"),
+                    was,
+                    theirs,
+                    ours,
+                    prefer,
+                    &mut conflicts,
+                )
+                .cloned(),
+            };
+            if let Some(site) = site {
+                sites.insert(key.clone(), site);
+            }
+        }
+        merged.insert("sites".into(), Value::Mapping(sites));
+    }
+    if conflicts.is_empty() {
+        Ok(merged)
+    } else {
+        Err(conflicts)
+    }
+}
+
+/// Returns the keys of the three sides, in local order first, each once.
+fn keys<'a>(base: &'a Mapping, remote: &'a Mapping, local: &'a Mapping) -> Vec<&'a Value> {
+    let mut keys: Vec<&Value> = Vec::new();
+    for key in local.keys().chain(remote.keys()).chain(base.keys()) {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+/// Orders numbers by value and anything else, like dates, by its text.
+fn compare(a: &Value, b: &Value) -> std::cmp::Ordering {
+    match (a.as_u64(), b.as_u64()) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        _ => a.as_str().cmp(&b.as_str()),
+    }
+}
+
+/// Returns the side's value that changed since `was`, adding a conflict named `key` if both did
+/// differently and `prefer` doesn't pick one.
+fn pick<'a>(
+    key: &str,
+    was: Option<&'a Value>,
+    theirs: Option<&'a Value>,
+    ours: Option<&'a Value>,
+    prefer: Option<Prefer>,
+    conflicts: &mut Vec<Conflict>,
+) -> Option<&'a Value> {
+    if theirs == ours || ours == was {
+        theirs
+    } else if theirs == was {
+        ours
+    } else {
+        match prefer {
+            Some(Prefer::Local) => ours,
+            Some(Prefer::Remote) => theirs,
+            None => {
+                conflicts.push(Conflict {
+                    key: key.to_owned(),
+                    local: ours.cloned(),
+                    remote: theirs.cloned(),
+                });
+                ours
+            }
+        }
+    }
+}
+
+/// Merges two sides' changes to `base` key by key, adding conflicts named with `prefix`.
+fn merge_mappings(
+    prefix: &str,
+    base: &Mapping,
+    remote: &Mapping,
+    local: &Mapping,
+    prefer: Option<Prefer>,
+    conflicts: &mut Vec<Conflict>,
+) -> Mapping {
+    let mut merged = Mapping::new();
+    for key in keys(base, remote, local) {
+        let name = format!("{prefix}{}", key.as_str().unwrap_or("?"));
+        let value = pick(
+            &name,
+            base.get(key),
+            remote.get(key),
+            local.get(key),
+            prefer,
+            conflicts,
+        );
+        if let Some(value) = value {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges() -> anyhow::Result<()> {
+        let yaml = |s: &str| serde_yaml::from_str::<Value>(s);
+        let base = yaml("kdf: {memory: 1}\nsites: {a.com: {increment: 1}, b.com: B, c.com: C}")?;
+        let remote = yaml("kdf: {memory: 1}\nsites: {a.com: {increment: 2}, b.com: B}")?;
+        let local = yaml("kdf: {memory: 2}\nsites: {a.com: {increment: 1}, b.com: X, d.com: D}")?;
+        let merged = merge(&base, &remote, &local, None).unwrap();
+        assert_eq!(
+            yaml("kdf: {memory: 2}\nsites: {a.com: {increment: 2}, b.com: X, d.com: D}")?,
+            Value::Mapping(merged)
+        );
+
+        // Bumping a site on both sides takes the larger increment.
+        let local = yaml("sites: {a.com: {increment: 3, rotated: 2025-01-02}, c.com: C}")?;
+        let remote = yaml("sites: {a.com: {increment: 2, rotated: 2025-03-01, username: u}}")?;
+        let base = yaml("sites: {a.com: {increment: 1}, c.com: C}")?;
+        assert_eq!(
+            yaml("sites: {a.com: {increment: 3, rotated: 2025-03-01, username: u}}")?,
+            Value::Mapping(merge(&base, &remote, &local, None).unwrap())
+        );
+
+        // And keeps the bumps from both.
+        let bump = |n, date| format!("{{increment: {n}, date: {date}}}");
+        let (first, theirs, ours) = (
+            bump(0, "2025-01-01"),
+            bump(1, "2025-03-01"),
+            bump(1, "2025-01-02"),
+        );
+        let local = yaml(&format!(
+            "sites: {{a.com: {{increment: 2, bumps: [{first}, {ours}]}}}}"
+        ))?;
+        let remote = yaml(&format!(
+            "sites: {{a.com: {{increment: 2, bumps: [{first}, {theirs}]}}}}"
+        ))?;
+        let base = yaml(&format!(
+            "sites: {{a.com: {{increment: 1, bumps: [{first}]}}}}"
+        ))?;
+        assert_eq!(
+            yaml(&format!(
+                "sites: {{a.com: {{increment: 2, bumps: [{first}, {ours}, {theirs}]}}}}"
+            ))?,
+            Value::Mapping(merge(&base, &remote, &local, None).unwrap())
+        );
+
+        let base = yaml("sites: {a.com: {increment: 1}, c.com: C}")?;
+        let local = yaml("sites: {a.com: {schema: X, increment: 3}, c.com: D}")?;
+        let remote = yaml("sites: {a.com: {schema: Y, increment: 2}}")?;
+        let conflicts = merge(&base, &remote, &local, None).unwrap_err();
+        assert_eq!(
+            [
+                "site a.com schema: \"X\" here, \"Y\" there",
+                "site c.com: \"D\" here, deleted there",
+            ],
+            conflicts
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()[..]
+        );
+        let merged = merge(&base, &remote, &local, Some(Prefer::Remote)).unwrap();
+        assert_eq!(
+            yaml("sites: {a.com: {schema: Y, increment: 3}}")?,
+            Value::Mapping(merged)
+        );
+
+        // With nothing in common, sites and fields on one side only are added.
+        let remote = yaml("kdf: {memory: 1}\nsites: {a.com: {increment: 2}, b.com: B}")?;
+        let local = yaml("sites: {a.com: {username: u, increment: 1}, d.com: D}")?;
+        assert_eq!(
+            yaml(
+                "kdf: {memory: 1}\nsites: {a.com: {increment: 2, username: u}, b.com: B, d.com: D}"
+            )?,
+            Value::Mapping(merge(&Value::Null, &remote, &local, None).unwrap())
+        );
+        let local = yaml("sites: {b.com: C}")?;
+        assert_eq!(
+            ["site b.com schema: \"C\" here, \"B\" there"],
+            merge(&Value::Null, &remote, &local, None)
+                .unwrap_err()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()[..]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,245 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The browser native messaging protocol, for `onepass native-host`: JSON messages on stdio, each
+//! preceded by its length as a native-endian u32.
+
+use std::{
+    collections::BTreeSet,
+    fs::{OpenOptions, read_to_string},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::i18n::tr;
+
+/// The largest message browsers accept from a native host.
+const MAX_MESSAGE: usize = 1024 * 1024;
+
+/// The file next to the config listing the origins the user has allowed passwords to be filled
+/// for.
+const ORIGINS_FILE: &str = "native-host-origins";
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub(crate) enum Request {
+    /// The password for the page at `url`
+    Get { url: String },
+    /// The configured sites
+    List,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub(crate) enum Response {
+    Password {
+        url: String,
+        username: Option<String>,
+        password: String,
+    },
+    Sites {
+        sites: Vec<Site>,
+    },
+    Error {
+        error: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Site {
+    pub url: String,
+    pub username: Option<String>,
+}
+
+/// Answers requests from the browser on stdio until it closes the connection.
+pub(crate) fn serve(mut handle: impl FnMut(Request) -> Result<Response>) -> Result<()> {
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+    while let Some(message) = read_message(&mut stdin)? {
+        let response = serde_json::from_slice(&message)
+            .context(tr("invalid-request", &[]))
+            .and_then(&mut handle)
+            .unwrap_or_else(|e| Response::Error {
+                error: format!("{e:#}"),
+            });
+        write_message(&mut stdout, &response)?;
+    }
+    Ok(())
+}
+
+/// Reads one message, or returns `None` at the end of the input.
+fn read_message(r: &mut impl Read) -> Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match r.read_exact(&mut len) {
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        r => r.context(tr("failed-reading-message", &[]))?,
+    }
+    let len = u32::from_ne_bytes(len) as usize;
+    if len > MAX_MESSAGE {
+        bail!(tr("message-too-long", &[("bytes", &len)]));
+    }
+    let mut message = vec![0u8; len];
+    r.read_exact(&mut message)
+        .context(tr("failed-reading-message", &[]))?;
+    Ok(Some(message))
+}
+
+fn write_message(w: &mut impl Write, message: &impl Serialize) -> Result<()> {
+    let message = serde_json::to_vec(message)?;
+    if message.len() > MAX_MESSAGE {
+        bail!(tr("message-too-long", &[("bytes", &message.len())]));
+    }
+    w.write_all(&(message.len() as u32).to_ne_bytes())?;
+    w.write_all(&message)?;
+    w.flush()?;
+    Ok(())
+}
+
+/// Returns the origin of the page at `url` as a site URL, e.g. `https://example.com/`.
+pub(crate) fn origin(url: &str) -> Result<String> {
+    let origin = Url::parse(url).context(tr("invalid-url", &[]))?.origin();
+    if !origin.is_tuple() {
+        bail!(tr("no-origin", &[("url", &url)]));
+    }
+    Ok(format!("{}/", origin.ascii_serialization()))
+}
+
+/// The origins the user has allowed passwords to be filled for.
+pub(crate) struct Origins {
+    path: PathBuf,
+    allowed: BTreeSet<String>,
+}
+
+impl Origins {
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(ORIGINS_FILE);
+        let allowed = match read_to_string(&path) {
+            Ok(s) => s.lines().map(str::to_owned).collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeSet::new(),
+            Err(e) => return Err(e).context(tr("failed-reading-origins", &[])),
+        };
+        Ok(Origins { path, allowed })
+    }
+
+    /// Returns whether `origin` is allowed, asking the user and remembering their answer if it
+    /// has not been allowed before.
+    pub fn authorize(&mut self, origin: &str) -> Result<bool> {
+        if self.allowed.contains(origin) {
+            return Ok(true);
+        }
+        if !confirm(&tr("allow-origin", &[("origin", &origin)]))? {
+            return Ok(false);
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .context(tr("failed-opening-origins", &[]))?;
+        writeln!(file, "{origin}")?;
+        self.allowed.insert(origin.to_owned());
+        Ok(true)
+    }
+}
+
+/// Asks the user `question` in a dialog, since the browser owns stdio.
+fn confirm(question: &str) -> Result<bool> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display dialog {question:?} with title \"onepass\" buttons {{\"Deny\", \"Allow\"}} \
+             default button \"Deny\" cancel button \"Deny\""
+        ));
+        command
+    } else {
+        let mut command = Command::new("zenity");
+        command.args([
+            "--question",
+            "--no-markup",
+            "--title",
+            "onepass",
+            "--text",
+            question,
+        ]);
+        command
+    };
+    let status = command.status().with_context(|| {
+        tr(
+            "failed-running",
+            &[("program", &command.get_program().display())],
+        )
+    })?;
+    match status.code() {
+        Some(0) => Ok(true),
+        Some(1) => Ok(false),
+        _ => bail!(tr("prompt-failed", &[("status", &status)])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn framing() -> Result<()> {
+        let mut buf = Vec::new();
+        write_message(&mut buf, &Response::Sites { sites: Vec::new() })?;
+        write_message(&mut buf, &Response::Error { error: "x".into() })?;
+        let mut r = buf.as_slice();
+        assert_eq!(
+            read_message(&mut r)?.as_deref(),
+            Some(br#"{"type":"sites","sites":[]}"#.as_slice())
+        );
+        assert_eq!(
+            read_message(&mut r)?.as_deref(),
+            Some(br#"{"type":"error","error":"x"}"#.as_slice())
+        );
+        assert_eq!(read_message(&mut r)?, None);
+
+        let mut r = [0xffu8; 4].as_slice();
+        assert!(read_message(&mut r).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn requests() -> Result<()> {
+        assert_eq!(
+            serde_json::from_str::<Request>(r#"{"type":"get","url":"https://a.com/x"}"#)?,
+            Request::Get {
+                url: "https://a.com/x".into()
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<Request>(r#"{"type":"list"}"#)?,
+            Request::List
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn origins() -> Result<()> {
+        assert_eq!(origin("https://a.com/login?next=/")?, "https://a.com/");
+        assert_eq!(
+            origin("http://user@localhost:8080/x")?,
+            "http://localhost:8080/"
+        );
+        assert!(origin("data:text/plain,x").is_err());
+        assert!(origin("a.com").is_err());
+        Ok(())
+    }
+}
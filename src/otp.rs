@@ -0,0 +1,162 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! One-time passwords (RFC 4226 and RFC 6238), and their secrets.
+
+use anyhow::{Context, Result};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+#[cfg(feature = "qr")]
+use qrcode::{QrCode, render::unicode::Dense1x2};
+use sha1::Sha1;
+use url::Url;
+use zeroize::Zeroizing;
+
+use crate::i18n::tr;
+
+/// The length of a derived OTP secret, as recommended by RFC 4226.
+pub(crate) const SECRET_LEN: usize = 20;
+
+/// The TOTP time step in seconds, as used by nearly every site.
+pub(crate) const TOTP_PERIOD: u64 = 30;
+
+/// Returns the `digits`-digit HOTP code for `secret` at `counter`.
+pub(crate) fn hotp(secret: &[u8], counter: u64, digits: u32) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC takes keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = (digest[19] & 0xf) as usize;
+    let code = u32::from_be_bytes(digest[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+    format!(
+        "{:0width$}",
+        code % 10u32.pow(digits),
+        width = digits as usize
+    )
+}
+
+/// Returns the 6-digit TOTP code for `secret` at `unix_time`.
+pub(crate) fn totp(secret: &[u8], unix_time: u64) -> String {
+    hotp(secret, unix_time / TOTP_PERIOD, 6)
+}
+
+/// Decodes a base32 secret as sites display it, ignoring case, spaces, and padding.
+pub(crate) fn decode_secret(secret: &str) -> Result<Zeroizing<Vec<u8>>> {
+    let secret = Zeroizing::new(
+        secret
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '=')
+            .map(|c| c.to_ascii_uppercase())
+            .collect::<String>(),
+    );
+    Ok(Zeroizing::new(
+        BASE32_NOPAD
+            .decode(secret.as_bytes())
+            .context(tr("invalid-base32", &[]))?,
+    ))
+}
+
+/// Returns the `otpauth://` URI for enrolling `secret` for the site `url` with an authenticator
+/// app, as a HOTP secret starting at `counter` if given and a TOTP secret otherwise. The site's
+/// host is used as the issuer.
+pub(crate) fn otpauth_uri(
+    secret: &[u8],
+    url: &str,
+    account: Option<&str>,
+    counter: Option<u64>,
+) -> Result<Zeroizing<String>> {
+    let url = Url::parse(url)?;
+    let issuer = url.host_str().unwrap_or(url.as_str());
+    let mut uri = Url::parse(match counter {
+        Some(_) => "otpauth://hotp/",
+        None => "otpauth://totp/",
+    })?;
+    uri.set_path(&match account {
+        Some(account) => format!("{issuer}:{account}"),
+        None => issuer.into(),
+    });
+    uri.query_pairs_mut()
+        .append_pair("secret", &Zeroizing::new(BASE32_NOPAD.encode(secret)))
+        .append_pair("issuer", issuer);
+    if let Some(counter) = counter {
+        uri.query_pairs_mut()
+            .append_pair("counter", &counter.to_string());
+    }
+    Ok(Zeroizing::new(uri.into()))
+}
+
+/// Renders `data` as a QR code for the terminal.
+#[cfg(feature = "qr")]
+pub(crate) fn qr_code(data: &str) -> Result<Zeroizing<String>> {
+    let code = QrCode::new(data)?;
+    Ok(Zeroizing::new(
+        code.render::<Dense1x2>()
+            .dark_color(Dense1x2::Light)
+            .light_color(Dense1x2::Dark)
+            .build(),
+    ))
+}
+
+#[cfg(not(feature = "qr"))]
+pub(crate) fn qr_code(_data: &str) -> Result<Zeroizing<String>> {
+    anyhow::bail!(tr("built-without-qr", &[]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc_vectors() {
+        let secret = b"12345678901234567890";
+        let hotp: Vec<_> = (0..4).map(|counter| hotp(secret, counter, 6)).collect();
+        assert_eq!(["755224", "287082", "359152", "969429"], hotp.as_slice());
+        assert_eq!("94287082", super::hotp(secret, 59 / TOTP_PERIOD, 8));
+        assert_eq!("69279037", super::hotp(secret, 2000000000 / TOTP_PERIOD, 8));
+        assert_eq!("279037", totp(secret, 2000000000));
+    }
+
+    #[test]
+    fn decode() -> Result<()> {
+        assert_eq!(
+            b"12345678901234567890".as_slice(),
+            decode_secret("gezd gnbv gy3t qojq gezd gnbv gy3t qojq")?.as_slice()
+        );
+        assert_eq!(b"\0\0".as_slice(), decode_secret("AAAA====")?.as_slice());
+        assert!(decode_secret("1234").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn uri() -> Result<()> {
+        assert_eq!(
+            "otpauth://totp/example.com:me%20too?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=example.com",
+            otpauth_uri(
+                b"12345678901234567890",
+                "https://example.com/",
+                Some("me too"),
+                None
+            )?
+            .as_str()
+        );
+        assert_eq!(
+            "otpauth://totp/example.com?secret=AAAA&issuer=example.com",
+            otpauth_uri(&[0, 0], "https://example.com/", None, None)?.as_str()
+        );
+        assert_eq!(
+            "otpauth://hotp/example.com?secret=AAAA&issuer=example.com&counter=7",
+            otpauth_uri(&[0, 0], "https://example.com/", None, Some(7))?.as_str()
+        );
+        Ok(())
+    }
+}
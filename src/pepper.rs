@@ -0,0 +1,90 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `onepass tpm` and `onepass keyring`: storing a pepper, the second factor kept outside the
+//! config, in the TPM or the system keyring, and exporting it to back it up.
+
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use onepass::config::Config;
+use rand_core::{OsRng, TryRngCore};
+use zeroize::Zeroizing;
+
+use crate::{
+    crypto::{read_pepper_keyring, store_pepper_keyring},
+    exit,
+    factor::{self, PepperSource},
+    i18n::tr,
+    porcelain::Porcelain,
+    print_secret, tpm,
+};
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum PepperCommand {
+    /// Store a new random pepper
+    Init {
+        /// Store an existing pepper, as printed by `export`, instead of a new one
+        #[arg(long)]
+        import: bool,
+    },
+
+    /// Print the stored pepper in hex, e.g. to back it up or to store it on another machine
+    Export,
+}
+
+/// Runs `command` on the pepper kept in `source`.
+pub(crate) fn command(
+    config: &Config,
+    source: PepperSource,
+    command: PepperCommand,
+    porcelain: bool,
+) -> Result<()> {
+    let dir = config.config_dir()?;
+    match command {
+        PepperCommand::Init { import } => {
+            let exists = match source {
+                PepperSource::Tpm => tpm::is_sealed(dir),
+                PepperSource::Keyring => read_pepper_keyring()?.is_some(),
+            };
+            if exists {
+                anyhow::bail!(tr("pepper-exists", &[("source", &source)]));
+            }
+            let pepper = if import {
+                let input = Zeroizing::new(exit::prompt_password("pepper", "pepper-prompt")?);
+                Zeroizing::new(hex::decode(input.trim()).context(tr("invalid-pepper", &[]))?)
+            } else {
+                let mut pepper = Zeroizing::new(vec![0u8; 32]);
+                OsRng.try_fill_bytes(&mut pepper)?;
+                pepper
+            };
+            match source {
+                PepperSource::Tpm => tpm::seal(dir, &pepper)?,
+                PepperSource::Keyring => store_pepper_keyring(&pepper)?,
+            }
+            if config.pepper != Some(source) {
+                eprintln!("{}", tr("pepper-stored", &[("source", &source)]));
+            }
+        }
+        PepperCommand::Export => {
+            let pepper = factor::read_pepper(source, dir)?;
+            let pepper = Zeroizing::new(hex::encode(&pepper));
+            if porcelain {
+                Porcelain::new().line("pepper", &pepper)?;
+            } else {
+                print_secret(&pepper)?;
+            }
+        }
+    }
+    Ok(())
+}
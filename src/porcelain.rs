@@ -0,0 +1,84 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The `--porcelain` output format: one `key=value` line per item, for scripts. The keys each
+//! command prints are documented in HACKING.md, and only change with the format's version.
+
+use std::io::{self, StdoutLock, Write, stdout};
+
+/// The latest version of the format.
+pub(crate) const LATEST: u32 = 1;
+
+/// Writes porcelain lines to stdout.
+pub(crate) struct Porcelain {
+    stdout: StdoutLock<'static>,
+}
+
+impl Porcelain {
+    pub fn new() -> Self {
+        Porcelain {
+            stdout: stdout().lock(),
+        }
+    }
+
+    /// Writes the line `key=value`.
+    pub fn line(&mut self, key: &str, value: &str) -> io::Result<&mut Self> {
+        write_line(&mut self.stdout, key, value)?;
+        Ok(self)
+    }
+
+    /// Writes the line `key=value` if there is a value.
+    pub fn maybe(&mut self, key: &str, value: Option<&str>) -> io::Result<&mut Self> {
+        match value {
+            Some(value) => self.line(key, value),
+            None => Ok(self),
+        }
+    }
+}
+
+/// Writes `key=value` and a newline to `w`, escaping backslashes, newlines, and carriage returns
+/// in `value` as `\\`, `\n`, and `\r` so that every value fits on its line.
+fn write_line(w: &mut impl Write, key: &str, value: &str) -> io::Result<()> {
+    write!(w, "{key}=")?;
+    let mut rest = value;
+    while let Some(i) = rest.find(['\\', '\n', '\r']) {
+        w.write_all(&rest.as_bytes()[..i])?;
+        w.write_all(match rest.as_bytes()[i] {
+            b'\\' => b"\\\\",
+            b'\n' => b"\\n",
+            _ => b"\\r",
+        })?;
+        rest = &rest[i + 1..];
+    }
+    w.write_all(rest.as_bytes())?;
+    writeln!(w)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines() -> io::Result<()> {
+        let mut out = Vec::new();
+        write_line(&mut out, "password", "a=b c")?;
+        write_line(&mut out, "private-key", "-----BEGIN\r\nx\\y\n")?;
+        write_line(&mut out, "username", "")?;
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "password=a=b c\nprivate-key=-----BEGIN\\r\\nx\\\\y\\n\nusername=\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,310 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Breach checks against Have I Been Pwned's Pwned Passwords, by k-anonymity: only the first five
+//! hex digits of a password's SHA-1 hash are sent, and the matching suffixes are compared here.
+//!
+//! Offline, the same checks run against a local breach list: either Pwned Passwords' downloadable
+//! file of SHA-1 hashes ordered by hash, as `HASH:COUNT` lines, which is binary searched in
+//! place, or a much smaller Bloom filter built from such a file by [`build_filter`].
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+    process::Command,
+};
+
+use anyhow::{Context, Result};
+use sha1::{Digest, Sha1};
+
+use crate::i18n::tr;
+
+/// The Pwned Passwords range API, to which a hash prefix is appended.
+const RANGE_URL: &str = "https://api.pwnedpasswords.com/range/";
+
+/// The number of hex digits of the hash sent to the API.
+const PREFIX_LEN: usize = 5;
+
+/// The start of a Bloom filter file, followed by its number of bits and of hash functions as
+/// little-endian u64 and u32, then the bits.
+const FILTER_MAGIC: &[u8; 16] = b"onepass-bloom-v1";
+
+/// The length of a Bloom filter file's header.
+const FILTER_HEADER_LEN: u64 = 16 + 8 + 4;
+
+/// A span of a sorted hash file short enough to scan instead of bisecting further.
+const SCAN_LEN: u64 = 4096;
+
+/// What a local breach list says of a password.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Found {
+    /// A sorted hash file's count of the password, 0 if it isn't there
+    Count(u64),
+    /// Whether a Bloom filter probably has the password: false positives are possible, but not
+    /// false negatives
+    Probably(bool),
+}
+
+/// The size of a Bloom filter built by [`build_filter`].
+pub(crate) struct FilterStats {
+    pub(crate) hashes: u64,
+    pub(crate) bits: u64,
+    pub(crate) functions: u32,
+}
+
+/// Returns how many times `password` appears in the breaches Have I Been Pwned knows of, asking
+/// its API with curl.
+pub(crate) fn count(password: &str) -> Result<u64> {
+    let hash = hex::encode_upper(Sha1::digest(password.as_bytes()));
+    let (prefix, suffix) = hash.split_at(PREFIX_LEN);
+    // Padding hides the number of suffixes in the response, which would otherwise hint at the
+    // prefix to anyone watching its size.
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error"])
+        .args(["--proto", "=https", "--proto-redir", "=https"])
+        .args(["--header", "Add-Padding: true", "--user-agent", "onepass"])
+        .arg(format!("{RANGE_URL}{prefix}"))
+        .output()
+        .context(tr("failed-running", &[("program", &"curl")]))?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!(tr(
+            "program-failed-with",
+            &[("program", &"curl"), ("error", &error.trim())]
+        ));
+    }
+    let body = std::str::from_utf8(&output.stdout).context(tr("invalid-range-response", &[]))?;
+    range_count(body, suffix)
+}
+
+/// Looks `password` up in the local breach list at `path`, a Bloom filter from [`build_filter`]
+/// or a file of hashes ordered by hash.
+pub(crate) fn lookup(path: &Path, password: &str) -> Result<Found> {
+    let mut file =
+        File::open(path).with_context(|| tr("failed-opening", &[("path", &path.display())]))?;
+    let mut magic = [0; FILTER_MAGIC.len()];
+    let is_filter = file.read_exact(&mut magic).is_ok() && &magic == FILTER_MAGIC;
+    let digest = Sha1::digest(password.as_bytes());
+    if is_filter {
+        return Ok(Found::Probably(filter_has(&mut file, &digest)?));
+    }
+    Ok(Found::Count(search(&mut file, &hex::encode_upper(digest))?))
+}
+
+/// Builds a Bloom filter at `output` of the hashes in `input`, lines of hex SHA-1 hashes that
+/// may each be followed by a colon and a count, with about `rate` of other passwords testing
+/// positive.
+pub(crate) fn build_filter(input: &Path, output: &Path, rate: f64) -> Result<FilterStats> {
+    anyhow::ensure!(rate > 0.0 && rate < 1.0, tr("false-positive-rate", &[]));
+    let open = || -> Result<_> {
+        let file = File::open(input)
+            .with_context(|| tr("failed-opening", &[("path", &input.display())]))?;
+        Ok(BufReader::new(file).lines())
+    };
+    let mut hashes = 0u64;
+    for line in open()? {
+        if !line?.trim().is_empty() {
+            hashes += 1;
+        }
+    }
+    anyhow::ensure!(hashes > 0, tr("no-hashes", &[("path", &input.display())]));
+    let ln2 = std::f64::consts::LN_2;
+    let bits = ((-(hashes as f64) * rate.ln() / (ln2 * ln2)).ceil() as u64).max(8);
+    let functions = ((bits as f64 / hashes as f64 * ln2).round() as u32).clamp(1, 32);
+    let mut filter = vec![0u8; bits.div_ceil(8).try_into()?];
+    for (i, line) in open()?.enumerate() {
+        let line = line?;
+        let hash = line.split(':').next().unwrap_or_default().trim();
+        if hash.is_empty() {
+            continue;
+        }
+        let digest = hex::decode(hash)
+            .ok()
+            .filter(|digest| digest.len() == 20)
+            .with_context(|| {
+                tr(
+                    "not-sha1",
+                    &[("path", &input.display()), ("line", &(i + 1))],
+                )
+            })?;
+        for index in filter_indexes(&digest, bits, functions) {
+            filter[(index / 8) as usize] |= 1 << (index % 8);
+        }
+    }
+    let file = File::create(output)
+        .with_context(|| tr("failed-creating", &[("path", &output.display())]))?;
+    let mut out = BufWriter::new(file);
+    out.write_all(FILTER_MAGIC)?;
+    out.write_all(&bits.to_le_bytes())?;
+    out.write_all(&functions.to_le_bytes())?;
+    out.write_all(&filter)?;
+    out.flush()?;
+    Ok(FilterStats {
+        hashes,
+        bits,
+        functions,
+    })
+}
+
+/// Returns the bits of a filter of `bits` bits and `functions` hash functions that `digest` sets,
+/// by double hashing: the SHA-1 is already uniform, so its first two words serve as the hashes.
+fn filter_indexes(digest: &[u8], bits: u64, functions: u32) -> impl Iterator<Item = u64> {
+    let h1 = u64::from_le_bytes(digest[..8].try_into().unwrap());
+    let h2 = u64::from_le_bytes(digest[8..16].try_into().unwrap()) | 1;
+    (0..u64::from(functions)).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % bits)
+}
+
+/// Returns whether the filter in `file`, read past its magic, probably has `digest`.
+fn filter_has(file: &mut File, digest: &[u8]) -> Result<bool> {
+    let mut header = [0; 12];
+    file.read_exact(&mut header)
+        .context(tr("invalid-filter", &[]))?;
+    let bits = u64::from_le_bytes(header[..8].try_into().unwrap());
+    let functions = u32::from_le_bytes(header[8..].try_into().unwrap());
+    let len = file.metadata()?.len();
+    anyhow::ensure!(
+        bits > 0 && len == FILTER_HEADER_LEN + bits.div_ceil(8),
+        tr("invalid-filter", &[])
+    );
+    for index in filter_indexes(digest, bits, functions) {
+        let mut byte = [0];
+        file.seek(SeekFrom::Start(FILTER_HEADER_LEN + index / 8))?;
+        file.read_exact(&mut byte)?;
+        if byte[0] & (1 << (index % 8)) == 0 {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Returns the count of `hash`, in uppercase hex, in `file`, of `HASH:COUNT` lines ordered by
+/// hash, by bisecting the file down to a span short enough to scan.
+fn search(file: &mut File, hash: &str) -> Result<u64> {
+    // Lines starting before `lo` are before `hash`, and those starting at `hi` or later are not.
+    let (mut lo, mut hi) = (0, file.metadata()?.len());
+    while hi - lo > SCAN_LEN {
+        let mid = lo + (hi - lo) / 2;
+        file.seek(SeekFrom::Start(mid - 1))?;
+        let mut reader = BufReader::new(&mut *file);
+        let mut skipped = Vec::new();
+        let start = mid - 1 + reader.read_until(b'\n', &mut skipped)? as u64;
+        let mut line = String::new();
+        let read = reader.read_line(&mut line)? as u64;
+        if start >= hi || read == 0 {
+            break;
+        }
+        if line_hash(&line).as_str() < hash {
+            lo = start + read;
+        } else {
+            hi = start;
+        }
+    }
+    file.seek(SeekFrom::Start(lo))?;
+    for line in BufReader::new(&mut *file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_hash = line_hash(&line);
+        if line_hash == hash {
+            let (_, count) = line.trim().split_once(':').unwrap_or_default();
+            return count
+                .parse()
+                .with_context(|| tr("invalid-hash-line", &[("line", &format!("{line:?}"))]));
+        }
+        if line_hash.as_str() > hash {
+            break;
+        }
+    }
+    Ok(0)
+}
+
+/// Returns the hash of a line of a hash file, in uppercase.
+fn line_hash(line: &str) -> String {
+    let hash = line.split(':').next().unwrap_or_default().trim();
+    hash.to_ascii_uppercase()
+}
+
+/// Returns the count of the hash suffix `suffix` in the range response `body`, of lines like
+/// `SUFFIX:COUNT`; padding lines have a count of 0.
+fn range_count(body: &str, suffix: &str) -> Result<u64> {
+    for line in body.lines().filter(|line| !line.trim().is_empty()) {
+        let (line_suffix, count) = line
+            .trim()
+            .split_once(':')
+            .with_context(|| tr("invalid-range-line", &[("line", &format!("{line:?}"))]))?;
+        if line_suffix.eq_ignore_ascii_case(suffix) {
+            return count
+                .parse()
+                .with_context(|| tr("invalid-range-line", &[("line", &format!("{line:?}"))]));
+        }
+    }
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range() -> Result<()> {
+        // The SHA-1 of "password" is 5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8.
+        let body = "003D68EB55068C33ACE09247EE4C639306B:3\r\n\
+                    1E4C9B93F3F0682250B6CF8331B7EE68FD8:10434004\r\n\
+                    1E4C9B93F3F0682250B6CF8331B7EE68FD9:0\r\n";
+        assert_eq!(
+            10434004,
+            range_count(body, "1E4C9B93F3F0682250B6CF8331B7EE68FD8")?
+        );
+        assert_eq!(0, range_count(body, "1E4C9B93F3F0682250B6CF8331B7EE68FD9")?);
+        assert_eq!(0, range_count(body, "0000000000000000000000000000000000A")?);
+        assert!(range_count("nonsense\n", "1E4C9B93F3F0682250B6CF8331B7EE68FD8").is_err());
+        let hash = hex::encode_upper(Sha1::digest(b"password"));
+        assert_eq!("5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8", hash);
+        Ok(())
+    }
+
+    #[test]
+    fn offline() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("onepass-pwned-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let mut lines: Vec<_> = (0..2000)
+            .map(|i| {
+                let hash = hex::encode_upper(Sha1::digest(format!("breached{i}")));
+                format!("{hash}:{}\r\n", i + 1)
+            })
+            .collect();
+        lines.sort();
+        let hashes = dir.join("hashes.txt");
+        std::fs::write(&hashes, lines.concat())?;
+        for i in [0, 1, 998, 1999] {
+            let found = lookup(&hashes, &format!("breached{i}"))?;
+            assert_eq!(Found::Count(i + 1), found);
+        }
+        assert_eq!(Found::Count(0), lookup(&hashes, "not breached")?);
+
+        let filter = dir.join("filter.bin");
+        let stats = build_filter(&hashes, &filter, 0.001)?;
+        assert_eq!((2000, 10), (stats.hashes, stats.functions));
+        for i in 0..2000 {
+            let found = lookup(&filter, &format!("breached{i}"))?;
+            assert_eq!(Found::Probably(true), found);
+        }
+        assert_eq!(Found::Probably(false), lookup(&filter, "not breached")?);
+        assert!(build_filter(&filter, &dir.join("bad.bin"), 0.001).is_err());
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Raw encodings of random bytes, for tokens that don't need a schema.
+
+use anyhow::Result;
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use clap::ValueEnum;
+use rand_core::RngCore;
+use zeroize::Zeroizing;
+
+use crate::i18n::tr;
+
+/// The number of bytes encoded when no length is given.
+pub(crate) const DEFAULT_LENGTH: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Encoding {
+    /// Lowercase hexadecimal
+    Hex,
+    /// Unpadded URL-safe base64
+    Base64url,
+    /// A random (version 4) UUID
+    Uuid,
+}
+
+impl Encoding {
+    /// The number of bits of entropy in an encoding of `length` bytes.
+    pub fn bits(self, length: usize) -> usize {
+        match self {
+            Encoding::Hex | Encoding::Base64url => length * 8,
+            Encoding::Uuid => 122,
+        }
+    }
+
+    /// Encodes `length` bytes from `rng`, or for UUIDs, 16 bytes.
+    pub fn encode<R: RngCore + ?Sized>(
+        self,
+        rng: &mut R,
+        length: Option<usize>,
+    ) -> Result<Zeroizing<String>> {
+        let length = match (self, length) {
+            (Encoding::Uuid, Some(_)) => anyhow::bail!(tr("uuid-length", &[])),
+            (Encoding::Uuid, None) => 16,
+            (_, length) => length.unwrap_or(DEFAULT_LENGTH),
+        };
+        let mut bytes = Zeroizing::new(vec![0u8; length]);
+        rng.fill_bytes(&mut bytes);
+        Ok(Zeroizing::new(match self {
+            Encoding::Hex => hex::encode(&*bytes),
+            Encoding::Base64url => BASE64_URL_SAFE_NO_PAD.encode(&*bytes),
+            Encoding::Uuid => {
+                bytes[6] = bytes[6] & 0x0f | 0x40;
+                bytes[8] = bytes[8] & 0x3f | 0x80;
+                let hex = Zeroizing::new(hex::encode(&*bytes));
+                format!(
+                    "{}-{}-{}-{}-{}",
+                    &hex[..8],
+                    &hex[8..12],
+                    &hex[12..16],
+                    &hex[16..20],
+                    &hex[20..]
+                )
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn encodings() -> Result<()> {
+        let mut rng = ChaCha20Rng::from_seed([0u8; 32]);
+        assert_eq!(64, Encoding::Hex.encode(&mut rng, None)?.len());
+        assert_eq!(6, Encoding::Base64url.encode(&mut rng, Some(4))?.len());
+        let uuid = Encoding::Uuid.encode(&mut rng, None)?;
+        assert_eq!(36, uuid.len());
+        assert_eq!(Some('4'), uuid.chars().nth(14));
+        assert!("89ab".contains(uuid.chars().nth(19).unwrap()));
+        assert!(Encoding::Uuid.encode(&mut rng, Some(16)).is_err());
+        Ok(())
+    }
+}
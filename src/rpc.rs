@@ -0,0 +1,264 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! JSON-RPC 2.0 on stdio, for `onepass serve --stdio`: each line of input is a request or a batch
+//! of requests, and each response or batch of responses is written as a line of output.
+
+use std::io::{self, BufRead, Write};
+
+use anyhow::Result;
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The code of errors from the methods themselves, e.g. an unconfigured site.
+const SERVER_ERROR: i64 = -32000;
+
+/// A method call, with its parameters.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Call {
+    /// The password, or a field, of a site
+    Generate(GenerateParams),
+    /// The configured sites
+    ListSites,
+    /// The entropy of a site's schema, or of a given or the default schema
+    Entropy(EntropyParams),
+    /// Rotate a site's password by incrementing its increment in the config
+    Bump(BumpParams),
+    /// The current TOTP code of a site
+    Totp(TotpParams),
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct GenerateParams {
+    pub site: String,
+    pub schema: Option<String>,
+    pub field: Option<String>,
+    pub increment: Option<u32>,
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct EntropyParams {
+    pub site: Option<String>,
+    pub schema: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct BumpParams {
+    pub site: String,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct TotpParams {
+    pub site: String,
+    pub increment: Option<u32>,
+    pub username: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Error {
+    code: i64,
+    pub message: String,
+}
+
+impl Call {
+    /// Parses a call of the method `method`, with by-name parameters `params`.
+    pub fn parse(method: &str, params: Value) -> Result<Self, Error> {
+        Ok(match method {
+            "generate" => Call::Generate(parse_params(params)?),
+            "listSites" => Call::ListSites,
+            "entropy" => Call::Entropy(parse_params(params)?),
+            "bump" => Call::Bump(parse_params(params)?),
+            "totp" => Call::Totp(parse_params(params)?),
+            _ => {
+                return Err(Error {
+                    code: METHOD_NOT_FOUND,
+                    message: format!("no method {method}"),
+                });
+            }
+        })
+    }
+}
+
+/// Parses by-name parameters, treating missing ones as empty.
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, Error> {
+    let params = match params {
+        Value::Null => json!({}),
+        params => params,
+    };
+    serde_json::from_value(params).map_err(|e| Error {
+        code: INVALID_PARAMS,
+        message: e.to_string(),
+    })
+}
+
+/// Answers requests on stdio until the input ends.
+pub(crate) fn serve(mut handle: impl FnMut(Call) -> Result<Value>) -> Result<()> {
+    let stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+    for line in stdin.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = answer(&line, &mut handle) {
+            writeln!(stdout, "{response}")?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the response to the line `line`, or `None` if it held only notifications.
+fn answer(line: &str, handle: &mut impl FnMut(Call) -> Result<Value>) -> Option<Value> {
+    match serde_json::from_str(line) {
+        Ok(Value::Array(batch)) if !batch.is_empty() => {
+            let responses: Vec<_> = batch
+                .into_iter()
+                .filter_map(|request| answer_one(request, handle))
+                .collect();
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        Ok(request) => answer_one(request, handle),
+        Err(e) => Some(response(
+            Value::Null,
+            Err(Error {
+                code: PARSE_ERROR,
+                message: e.to_string(),
+            }),
+        )),
+    }
+}
+
+fn answer_one(request: Value, handle: &mut impl FnMut(Call) -> Result<Value>) -> Option<Value> {
+    #[derive(Deserialize)]
+    struct Request {
+        jsonrpc: String,
+        method: String,
+        #[serde(default)]
+        params: Value,
+    }
+    // Requests without an id are notifications, which get no response.
+    let id = request.get("id").cloned();
+    let result = serde_json::from_value::<Request>(request)
+        .ok()
+        .filter(|request| request.jsonrpc == "2.0")
+        .ok_or_else(|| Error {
+            code: INVALID_REQUEST,
+            message: "invalid request".into(),
+        })
+        .and_then(|request| Call::parse(&request.method, request.params))
+        .and_then(|call| {
+            handle(call).map_err(|e| Error {
+                code: SERVER_ERROR,
+                message: format!("{e:#}"),
+            })
+        });
+    match (id, &result) {
+        (Some(id), _) => Some(response(id, result)),
+        (None, Err(e)) if e.code == INVALID_REQUEST => Some(response(Value::Null, result)),
+        (None, _) => None,
+    }
+}
+
+fn response(id: Value, result: Result<Value, Error>) -> Value {
+    match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "result": result, "id": id}),
+        Err(Error { code, message }) => json!({
+            "jsonrpc": "2.0",
+            "error": {"code": code, "message": message},
+            "id": id,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calls() {
+        assert_eq!(
+            Call::parse("generate", json!({"site": "a.com", "increment": 2})),
+            Ok(Call::Generate(GenerateParams {
+                site: "a.com".into(),
+                increment: Some(2),
+                ..Default::default()
+            }))
+        );
+        assert_eq!(Call::parse("listSites", Value::Null), Ok(Call::ListSites));
+        assert_eq!(
+            Call::parse("entropy", Value::Null),
+            Ok(Call::Entropy(EntropyParams::default()))
+        );
+        assert_eq!(
+            Call::parse("totp", json!({"site": "a.com"})),
+            Ok(Call::Totp(TotpParams {
+                site: "a.com".into(),
+                ..Default::default()
+            }))
+        );
+        assert_eq!(
+            Call::parse("bump", json!({})).map_err(|e| e.code),
+            Err(INVALID_PARAMS)
+        );
+        assert_eq!(
+            Call::parse("generate", json!({"site": "a.com", "x": 1})).map_err(|e| e.code),
+            Err(INVALID_PARAMS)
+        );
+        assert_eq!(
+            Call::parse("delete", Value::Null).map_err(|e| e.code),
+            Err(METHOD_NOT_FOUND)
+        );
+    }
+
+    #[test]
+    fn answers() {
+        let mut handle = |call| match call {
+            Call::ListSites => Ok(json!(["a.com"])),
+            _ => anyhow::bail!("nope"),
+        };
+        let mut answer = |line| answer(line, &mut handle).map(|v| v.to_string());
+        assert_eq!(
+            answer(r#"{"jsonrpc":"2.0","method":"listSites","id":1}"#).as_deref(),
+            Some(r#"{"id":1,"jsonrpc":"2.0","result":["a.com"]}"#)
+        );
+        assert_eq!(
+            answer(r#"{"jsonrpc":"2.0","method":"bump","params":{"site":"a"},"id":"x"}"#)
+                .as_deref(),
+            Some(r#"{"error":{"code":-32000,"message":"nope"},"id":"x","jsonrpc":"2.0"}"#)
+        );
+        assert_eq!(answer(r#"{"jsonrpc":"2.0","method":"listSites"}"#), None);
+        assert_eq!(
+            answer(r#"[{"jsonrpc":"2.0","method":"listSites","id":1},{"jsonrpc":"2.0","method":"listSites"}]"#)
+                .as_deref(),
+            Some(r#"[{"id":1,"jsonrpc":"2.0","result":["a.com"]}]"#)
+        );
+        assert_eq!(
+            answer(r#"{"method":"listSites","id":2}"#).as_deref(),
+            Some(r#"{"error":{"code":-32600,"message":"invalid request"},"id":2,"jsonrpc":"2.0"}"#)
+        );
+        assert!(answer("{").is_some_and(|r| r.contains("-32700")));
+        assert!(answer("[]").is_some_and(|r| r.contains("-32600")));
+    }
+}
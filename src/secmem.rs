@@ -0,0 +1,340 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Best-effort protection of secrets in memory: locking pages holding them into RAM so they are
+//! never swapped, excluding them from core dumps, and disabling core dumps entirely.
+//!
+//! All of this is best-effort; failures (e.g. from a low `RLIMIT_MEMLOCK`) are ignored, since
+//! refusing to generate a password would not make anyone safer. The exception is [`harden`], for
+//! `--harden`, which is asked for explicitly and so fails rather than run unprotected.
+
+#[cfg(any(unix, windows))]
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use std::{
+    mem,
+    ops::{Deref, DerefMut},
+};
+
+use anyhow::Result;
+use rand_chacha::ChaCha20Rng;
+use zeroize::Zeroize;
+
+use crate::i18n::tr;
+
+/// A value holding secrets in memory that can be locked and wiped.
+pub(crate) trait Secret {
+    /// The bytes holding the secret parts of this value. These must not move while it is locked.
+    fn region(&self) -> (*const u8, usize);
+
+    /// Overwrites the secret parts of this value with zeroes.
+    fn wipe(&mut self);
+}
+
+impl Secret for String {
+    fn region(&self) -> (*const u8, usize) {
+        (self.as_ptr(), self.capacity())
+    }
+
+    fn wipe(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<const N: usize> Secret for [u8; N] {
+    fn region(&self) -> (*const u8, usize) {
+        (self.as_ptr(), N)
+    }
+
+    fn wipe(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl Secret for ChaCha20Rng {
+    fn region(&self) -> (*const u8, usize) {
+        (self as *const Self as *const u8, mem::size_of::<Self>())
+    }
+
+    fn wipe(&mut self) {
+        unsafe {
+            let ptr = self as *mut Self as *mut u8;
+            std::slice::from_raw_parts_mut(ptr, mem::size_of::<Self>()).zeroize();
+        }
+    }
+}
+
+/// A boxed secret whose memory is locked for its lifetime, and wiped on drop.
+///
+/// The value must not be grown or otherwise reallocated while locked.
+pub(crate) struct Locked<T: Secret>(Box<T>);
+
+impl<T: Secret> Locked<T> {
+    pub fn new(value: T) -> Self {
+        let value = Box::new(value);
+        let (ptr, len) = value.region();
+        lock(ptr, len);
+        Locked(value)
+    }
+}
+
+impl<T: Secret> Deref for Locked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Secret> DerefMut for Locked<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Secret> Drop for Locked<T> {
+    fn drop(&mut self) {
+        let (ptr, len) = self.0.region();
+        self.0.wipe();
+        unlock(ptr, len);
+    }
+}
+
+impl From<String> for Locked<String> {
+    fn from(value: String) -> Self {
+        Locked::new(value)
+    }
+}
+
+/// Disables core dumps for this process, so a crash can't write secrets to disk.
+#[cfg(unix)]
+pub(crate) fn disable_core_dumps() {
+    let limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    unsafe {
+        libc::setrlimit(libc::RLIMIT_CORE, &limit);
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn disable_core_dumps() {}
+
+/// The least `RLIMIT_MEMLOCK` that [`harden`] accepts, as all memory is locked from then on and
+/// allocations past the limit would fail: enough for Argon2 with up to about 1 GiB of memory.
+#[cfg(target_os = "linux")]
+const MIN_MEMLOCK: u64 = 1 << 30;
+
+/// Whether [`harden`] has locked all memory, which unlocking any of it would undo.
+#[cfg(any(unix, windows))]
+static HARDENED: AtomicBool = AtomicBool::new(false);
+
+/// Makes the process as hard to read secrets out of as it can be without privileges: refuses to
+/// run under a debugger, makes the process undumpable, so that other processes of the user can't
+/// attach to it or read its memory, and locks all of its memory, present and future, into RAM.
+#[cfg(target_os = "linux")]
+pub(crate) fn harden() -> Result<()> {
+    use anyhow::Context;
+
+    let status = std::fs::read_to_string("/proc/self/status")
+        .context(tr("failed-checking-debugger", &[]))?;
+    match tracer_pid(&status) {
+        Some(0) => {}
+        Some(pid) => anyhow::bail!(tr("debugger", &[("pid", &pid)])),
+        None => anyhow::bail!(tr("no-tracer-pid", &[])),
+    }
+    if unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) } != 0 {
+        return Err(std::io::Error::last_os_error()).context(tr("failed-undumpable", &[]));
+    }
+    // Raise the limit as far as allowed: unlimited with CAP_SYS_RESOURCE, or else the hard limit.
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    unsafe {
+        let unlimited = libc::rlimit {
+            rlim_cur: libc::RLIM_INFINITY,
+            rlim_max: libc::RLIM_INFINITY,
+        };
+        if libc::setrlimit(libc::RLIMIT_MEMLOCK, &unlimited) != 0 {
+            libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut limit);
+            limit.rlim_cur = limit.rlim_max;
+            libc::setrlimit(libc::RLIMIT_MEMLOCK, &limit);
+        }
+        libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut limit);
+    }
+    if limit.rlim_cur != libc::RLIM_INFINITY && limit.rlim_cur < MIN_MEMLOCK {
+        anyhow::bail!(tr(
+            "memlock-limit",
+            &[
+                ("min", &(MIN_MEMLOCK >> 20)),
+                ("limit", &(limit.rlim_cur >> 10))
+            ]
+        ));
+    }
+    if unsafe { libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE) } != 0 {
+        return Err(std::io::Error::last_os_error()).context(tr("failed-locking-memory", &[]));
+    }
+    HARDENED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn harden() -> Result<()> {
+    anyhow::bail!(tr("harden-unsupported", &[]))
+}
+
+/// Returns the pid of the process tracing this one from the contents of `/proc/self/status`, or
+/// 0 if there is none.
+#[cfg(any(target_os = "linux", test))]
+fn tracer_pid(status: &str) -> Option<u32> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("TracerPid:"))
+        .and_then(|pid| pid.trim().parse().ok())
+}
+
+/// How many [`Locked`] values hold each locked page, by address. Locks aren't counted by the OS,
+/// so unlocking a page for one value would unlock it for every other value on it.
+#[cfg(any(unix, windows))]
+static PAGES: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+#[cfg(unix)]
+fn page_size() -> usize {
+    // SAFETY: takes no pointers.
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+/// Windows' pages are 4 KiB on every architecture it runs on.
+#[cfg(windows)]
+fn page_size() -> usize {
+    4096
+}
+
+/// Returns the addresses of the pages that the `len` bytes at `ptr` are on.
+#[cfg(any(unix, windows))]
+fn pages(ptr: *const u8, len: usize) -> impl Iterator<Item = usize> {
+    let page = page_size();
+    let start = ptr as usize & !(page - 1);
+    let end = (ptr as usize + len).next_multiple_of(page);
+    (start..end).step_by(page)
+}
+
+#[cfg(any(unix, windows))]
+fn lock(ptr: *const u8, len: usize) {
+    if len == 0 {
+        return;
+    }
+    let mut locked = PAGES.lock().unwrap_or_else(PoisonError::into_inner);
+    for page in pages(ptr, len) {
+        *locked.entry(page).or_default() += 1;
+        lock_page(page as *mut _, page_size());
+    }
+}
+
+#[cfg(any(unix, windows))]
+fn unlock(ptr: *const u8, len: usize) {
+    if len == 0 {
+        return;
+    }
+    let mut locked = PAGES.lock().unwrap_or_else(PoisonError::into_inner);
+    for page in pages(ptr, len) {
+        match locked.get_mut(&page) {
+            Some(count) if *count > 1 => *count -= 1,
+            _ => {
+                locked.remove(&page);
+                if !HARDENED.load(Ordering::Relaxed) {
+                    unlock_page(page as *mut _, page_size());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn lock_page(page: *mut libc::c_void, len: usize) {
+    // SAFETY: locking and advising on pages of this process's memory doesn't change it.
+    unsafe {
+        libc::mlock(page, len);
+        #[cfg(target_os = "linux")]
+        libc::madvise(page, len, libc::MADV_DONTDUMP);
+    }
+}
+
+#[cfg(unix)]
+fn unlock_page(page: *mut libc::c_void, len: usize) {
+    // SAFETY: unlocking pages of this process's memory doesn't change it.
+    unsafe {
+        libc::munlock(page, len);
+    }
+}
+
+#[cfg(windows)]
+fn lock_page(page: *mut std::ffi::c_void, len: usize) {
+    // SAFETY: locking pages of this process's memory doesn't change it.
+    unsafe {
+        windows_sys::Win32::System::Memory::VirtualLock(page, len);
+    }
+}
+
+#[cfg(windows)]
+fn unlock_page(page: *mut std::ffi::c_void, len: usize) {
+    // SAFETY: unlocking pages of this process's memory doesn't change it.
+    unsafe {
+        windows_sys::Win32::System::Memory::VirtualUnlock(page, len);
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn lock(_ptr: *const u8, _len: usize) {}
+
+#[cfg(not(any(unix, windows)))]
+fn unlock(_ptr: *const u8, _len: usize) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracers() {
+        let status = "Name:\tonepass\nState:\tR (running)\nTracerPid:\t0\nUid:\t1000\n";
+        assert_eq!(Some(0), tracer_pid(status));
+        assert_eq!(Some(4242), tracer_pid("TracerPid:\t4242\n"));
+        assert_eq!(None, tracer_pid("Name:\tonepass\n"));
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn shared_pages() {
+        let buffer = [0u8; 2];
+        let (first, second) = (buffer.as_ptr(), buffer[1..].as_ptr());
+        let page = || pages(second, 1).next().unwrap();
+        let held = || {
+            let locked = PAGES.lock().unwrap();
+            locked.get(&page()).copied()
+        };
+        lock(first, 1);
+        lock(second, 1);
+        unlock(first, 1);
+        assert_eq!(Some(1), held());
+        unlock(second, 1);
+        assert_eq!(None, held());
+    }
+}
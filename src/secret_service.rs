@@ -0,0 +1,392 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A read-only provider of the freedesktop.org Secret Service API on the session bus, so that
+//! applications using libsecret can look up the passwords of configured sites.
+//!
+//! There is one collection, which is also the `default` alias, holding one always-unlocked item
+//! per site. Only the `plain` session algorithm is supported; libsecret falls back to it, and the
+//! secrets never leave the session bus.
+
+use std::{collections::HashMap, ffi::CString};
+
+use anyhow::{Context, Result, anyhow, bail};
+use dbus::{
+    Message, MessageType, Path,
+    arg::{RefArg, Variant},
+    blocking::Connection,
+    strings::ErrorName,
+};
+use url::Url;
+use zeroize::Zeroizing;
+
+use crate::i18n::tr;
+
+const BUS_NAME: &str = "org.freedesktop.secrets";
+const SERVICE_PATH: &str = "/org/freedesktop/secrets";
+const COLLECTION_PATH: &str = "/org/freedesktop/secrets/collection/onepass";
+const ALIAS_PATH: &str = "/org/freedesktop/secrets/aliases/default";
+const SESSION_PREFIX: &str = "/org/freedesktop/secrets/session/";
+
+const SERVICE: &str = "org.freedesktop.Secret.Service";
+const COLLECTION: &str = "org.freedesktop.Secret.Collection";
+const ITEM: &str = "org.freedesktop.Secret.Item";
+const SESSION: &str = "org.freedesktop.Secret.Session";
+const PROPERTIES: &str = "org.freedesktop.DBus.Properties";
+
+/// An item in the collection, i.e. a configured site.
+pub(crate) struct Item {
+    label: String,
+    attributes: HashMap<String, String>,
+}
+
+impl Item {
+    /// Describes the site `url`, with `username` if it has one, using the attributes of
+    /// libsecret's network password schema so that lookups by server and user find it.
+    pub fn new(url: &str, username: Option<&str>) -> Result<Self> {
+        let parsed = Url::parse(url).context(tr("invalid-url", &[]))?;
+        let mut attributes = HashMap::from([
+            ("url".to_owned(), url.to_owned()),
+            ("protocol".to_owned(), parsed.scheme().to_owned()),
+        ]);
+        if let Some(host) = parsed.host_str() {
+            attributes.insert("server".to_owned(), host.to_owned());
+        }
+        if let Some(port) = parsed.port() {
+            attributes.insert("port".to_owned(), port.to_string());
+        }
+        if let Some(username) = username {
+            attributes.insert("user".to_owned(), username.to_owned());
+        }
+        let label = match (username, parsed.host_str()) {
+            (Some(username), Some(host)) => format!("{username}@{host}"),
+            (None, Some(host)) => host.to_owned(),
+            _ => url.to_owned(),
+        };
+        Ok(Item { label, attributes })
+    }
+
+    /// Returns whether the item has all of `attributes`. `xdg:schema` is ignored, since libsecret
+    /// adds it to lookups and these items belong to no schema in particular.
+    fn matches(&self, attributes: &HashMap<String, String>) -> bool {
+        attributes
+            .iter()
+            .filter(|(name, _)| *name != "xdg:schema")
+            .all(|(name, value)| self.attributes.get(name) == Some(value))
+    }
+}
+
+/// A D-Bus error reply.
+struct Failure(&'static str, String);
+
+impl Failure {
+    fn not_supported(what: &str) -> Self {
+        Failure(
+            "org.freedesktop.DBus.Error.NotSupported",
+            format!("{what} is not supported by onepass"),
+        )
+    }
+}
+
+impl From<anyhow::Error> for Failure {
+    fn from(e: anyhow::Error) -> Self {
+        Failure("org.freedesktop.DBus.Error.Failed", format!("{e:#}"))
+    }
+}
+
+impl From<dbus::arg::TypeMismatchError> for Failure {
+    fn from(e: dbus::arg::TypeMismatchError) -> Self {
+        Failure("org.freedesktop.DBus.Error.InvalidArgs", e.to_string())
+    }
+}
+
+type Secret = (Path<'static>, Vec<u8>, Vec<u8>, &'static str);
+
+struct Service<'a, F> {
+    items: &'a [Item],
+    secret: F,
+    next_session: u64,
+    sessions: Vec<u64>,
+}
+
+/// Serves `items` until the connection to the session bus is lost, deriving their secrets with
+/// `secret` from their indices as they are requested.
+pub(crate) fn serve(
+    items: &[Item],
+    secret: impl FnMut(usize) -> Result<Zeroizing<String>>,
+) -> Result<()> {
+    let conn = Connection::new_session().context(tr("failed-connecting-bus", &[]))?;
+    let reply = conn
+        .request_name(BUS_NAME, false, false, true)
+        .context(tr("failed-requesting-name", &[]))?;
+    if reply != dbus::blocking::stdintf::org_freedesktop_dbus::RequestNameReply::PrimaryOwner {
+        bail!(tr("secret-service-running", &[]));
+    }
+    let mut service = Service {
+        items,
+        secret,
+        next_session: 0,
+        sessions: Vec::new(),
+    };
+    loop {
+        conn.channel()
+            .read_write(None)
+            .map_err(|()| anyhow!(tr("lost-bus", &[])))?;
+        while let Some(msg) = conn.channel().pop_message() {
+            if msg.msg_type() != MessageType::MethodCall || msg.get_no_reply() {
+                continue;
+            }
+            let reply = service
+                .handle(&msg)
+                .unwrap_or_else(|Failure(name, message)| {
+                    let message = CString::new(message).unwrap_or_default();
+                    msg.error(&ErrorName::from(name), &message)
+                });
+            conn.channel()
+                .send(reply)
+                .map_err(|()| anyhow!(tr("failed-sending-reply", &[])))?;
+        }
+    }
+}
+
+impl<F: FnMut(usize) -> Result<Zeroizing<String>>> Service<'_, F> {
+    fn handle(&mut self, msg: &Message) -> Result<Message, Failure> {
+        let path = msg.path().map(|path| path.to_string()).unwrap_or_default();
+        let interface = msg.interface().map(|i| i.to_string()).unwrap_or_default();
+        let member = msg.member().map(|m| m.to_string()).unwrap_or_default();
+        let reply = msg.method_return();
+        if interface == PROPERTIES {
+            return self.handle_properties(msg, &path, &member);
+        }
+        let reply = match (self.object(&path), interface.as_str(), member.as_str()) {
+            (Object::Service, SERVICE, "OpenSession") => {
+                let (algorithm, _): (&str, Variant<Box<dyn RefArg>>) = msg.read2()?;
+                if algorithm != "plain" {
+                    return Err(Failure::not_supported(&format!("algorithm {algorithm}")));
+                }
+                let id = self.next_session;
+                self.next_session += 1;
+                self.sessions.push(id);
+                let output: Box<dyn RefArg> = Box::new(String::new());
+                reply.append2(Variant(output), session_path(id))
+            }
+            (Object::Service, SERVICE, "SearchItems") => {
+                let unlocked = self.search(&msg.read1()?);
+                reply.append2(unlocked, Vec::<Path>::new())
+            }
+            (Object::Service, SERVICE, "Unlock") => {
+                let objects: Vec<Path> = msg.read1()?;
+                reply.append2(objects, Path::from("/"))
+            }
+            (Object::Service, SERVICE, "Lock") => {
+                reply.append2(Vec::<Path>::new(), Path::from("/"))
+            }
+            (Object::Service, SERVICE, "GetSecrets") => {
+                let (paths, session): (Vec<Path>, Path) = msg.read2()?;
+                let session = self.session(&session)?;
+                let mut secrets = HashMap::new();
+                for path in paths {
+                    if let Object::Item(i) = self.object(&path) {
+                        secrets.insert(item_path(i), self.secret(i, &session)?);
+                    }
+                }
+                reply.append1(secrets)
+            }
+            (Object::Service, SERVICE, "ReadAlias") => {
+                let name: &str = msg.read1()?;
+                reply.append1(Path::from(if name == "default" {
+                    COLLECTION_PATH
+                } else {
+                    "/"
+                }))
+            }
+            (Object::Collection, COLLECTION, "SearchItems") => {
+                reply.append1(self.search(&msg.read1()?))
+            }
+            (Object::Item(i), ITEM, "GetSecret") => {
+                let session = self.session(&msg.read1()?)?;
+                reply.append1(self.secret(i, &session)?)
+            }
+            (Object::Session(id), SESSION, "Close") => {
+                self.sessions.retain(|&session| session != id);
+                reply
+            }
+            (Object::None, ..) => {
+                return Err(Failure(
+                    "org.freedesktop.DBus.Error.UnknownObject",
+                    format!("no object at {path}"),
+                ));
+            }
+            _ => return Err(Failure::not_supported(&format!("{interface}.{member}"))),
+        };
+        Ok(reply)
+    }
+
+    fn handle_properties(
+        &self,
+        msg: &Message,
+        path: &str,
+        member: &str,
+    ) -> Result<Message, Failure> {
+        let reply = msg.method_return();
+        match member {
+            "Get" => {
+                let (interface, name): (&str, &str) = msg.read2()?;
+                let value = self
+                    .properties(path, interface)
+                    .remove(name)
+                    .ok_or_else(|| {
+                        Failure(
+                            "org.freedesktop.DBus.Error.UnknownProperty",
+                            format!("no property {interface}.{name}"),
+                        )
+                    })?;
+                Ok(reply.append1(value))
+            }
+            "GetAll" => {
+                let interface: &str = msg.read1()?;
+                Ok(reply.append1(self.properties(path, interface)))
+            }
+            _ => Err(Failure::not_supported(&format!("{PROPERTIES}.{member}"))),
+        }
+    }
+
+    fn properties(
+        &self,
+        path: &str,
+        interface: &str,
+    ) -> HashMap<&'static str, Variant<Box<dyn RefArg>>> {
+        let mut properties: Vec<(&'static str, Box<dyn RefArg>)> = Vec::new();
+        match (self.object(path), interface) {
+            (Object::Service, SERVICE) => {
+                properties.push(("Collections", Box::new(vec![Path::from(COLLECTION_PATH)])));
+            }
+            (Object::Collection, COLLECTION) => {
+                let items: Vec<_> = (0..self.items.len()).map(item_path).collect();
+                properties.push(("Items", Box::new(items)));
+                properties.push(("Label", Box::new("onepass".to_owned())));
+            }
+            (Object::Item(i), ITEM) => {
+                let item = &self.items[i];
+                properties.push(("Attributes", Box::new(item.attributes.clone())));
+                properties.push(("Label", Box::new(item.label.clone())));
+            }
+            _ => return HashMap::new(),
+        }
+        if interface != SERVICE {
+            properties.push(("Locked", Box::new(false)));
+            properties.push(("Created", Box::new(0u64)));
+            properties.push(("Modified", Box::new(0u64)));
+        }
+        properties
+            .into_iter()
+            .map(|(name, value)| (name, Variant(value)))
+            .collect()
+    }
+
+    fn object(&self, path: &str) -> Object {
+        if path == SERVICE_PATH {
+            return Object::Service;
+        }
+        if path == COLLECTION_PATH || path == ALIAS_PATH {
+            return Object::Collection;
+        }
+        if let Some(i) = path
+            .strip_prefix(COLLECTION_PATH)
+            .and_then(|i| i.strip_prefix('/'))
+            .and_then(|i| i.parse().ok())
+            && i < self.items.len()
+        {
+            return Object::Item(i);
+        }
+        if let Some(id) = path
+            .strip_prefix(SESSION_PREFIX)
+            .and_then(|id| id.parse().ok())
+            && self.sessions.contains(&id)
+        {
+            return Object::Session(id);
+        }
+        Object::None
+    }
+
+    fn search(&self, attributes: &HashMap<String, String>) -> Vec<Path<'static>> {
+        (0..self.items.len())
+            .filter(|&i| self.items[i].matches(attributes))
+            .map(item_path)
+            .collect()
+    }
+
+    fn session(&self, path: &Path) -> Result<Path<'static>, Failure> {
+        match self.object(path) {
+            Object::Session(id) => Ok(session_path(id)),
+            _ => Err(Failure(
+                "org.freedesktop.Secret.Error.NoSession",
+                format!("no session {path}"),
+            )),
+        }
+    }
+
+    fn secret(&mut self, i: usize, session: &Path<'static>) -> Result<Secret, Failure> {
+        let secret = (self.secret)(i)?;
+        Ok((
+            session.clone(),
+            Vec::new(),
+            secret.as_bytes().to_vec(),
+            "text/plain; charset=utf8",
+        ))
+    }
+}
+
+enum Object {
+    Service,
+    Collection,
+    Item(usize),
+    Session(u64),
+    None,
+}
+
+fn item_path(i: usize) -> Path<'static> {
+    Path::from(format!("{COLLECTION_PATH}/{i}"))
+}
+
+fn session_path(id: u64) -> Path<'static> {
+    Path::from(format!("{SESSION_PREFIX}{id}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attributes() -> Result<()> {
+        let item = Item::new("https://bob@example.com:8443/", Some("bob"))?;
+        assert_eq!(item.label, "bob@example.com");
+        let query = |attributes: &[(&str, &str)]| {
+            attributes
+                .iter()
+                .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+                .collect::<HashMap<_, _>>()
+        };
+        assert!(item.matches(&query(&[])));
+        assert!(item.matches(&query(&[("server", "example.com"), ("user", "bob")])));
+        assert!(item.matches(&query(&[
+            ("xdg:schema", "org.gnome.keyring.NetworkPassword"),
+            ("port", "8443"),
+            ("protocol", "https"),
+        ])));
+        assert!(!item.matches(&query(&[("user", "alice")])));
+        assert!(!item.matches(&query(&[("domain", "example.com")])));
+        Ok(())
+    }
+}
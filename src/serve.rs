@@ -0,0 +1,210 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `onepass serve`: answering other programs' requests for sites' passwords under one master
+//! password, as JSON-RPC over stdio (see [`rpc`]) or a REST API on a loopback address (see
+//! [`http`]).
+
+use std::{
+    net::SocketAddr,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use onepass::{
+    config::Config,
+    randexp::{Expr, Quantifiable, Words},
+};
+use rand_core::{OsRng, TryRngCore};
+use zeroize::Zeroizing;
+
+use crate::{
+    KeyArgs, Master, SiteArgs, bump_site, derive_otp_secret, http, i18n::tr, otp, pull_config,
+    push_config, raw::Encoding, record_history, rpc, schema, site_output, words,
+};
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct ServeArgs {
+    /// Answer JSON-RPC 2.0 requests on stdin, one per line, with the methods `generate`,
+    /// `listSites`, `entropy`, `bump`, and `totp`
+    #[arg(long, required_unless_present = "http", conflicts_with = "http")]
+    stdio: bool,
+
+    /// Serve a REST API on this loopback address, e.g. 127.0.0.1:8420, with the endpoints `GET
+    /// /sites`, `POST /generate`, and `POST /totp`
+    #[arg(long, value_name = "ADDR")]
+    http: Option<SocketAddr>,
+
+    /// The bearer token HTTP clients must send (default: a random one, printed on stderr)
+    #[arg(
+        long,
+        env = "ONEPASS_HTTP_TOKEN",
+        hide_env_values = true,
+        requires = "http",
+        value_parser = http::parse_token
+    )]
+    token: Option<String>,
+
+    #[command(flatten)]
+    key: KeyArgs,
+}
+
+/// Answers requests on stdin, or on `args.http` if given, from the sites of `config`.
+pub(crate) fn command(
+    path: &Path,
+    mut config: Config,
+    args: ServeArgs,
+    no_cache: bool,
+) -> Result<()> {
+    let words = words::List::open(config.words_paths(), config.wordlist, config.cache_dir())?;
+    let words = words::Filters::new(&words);
+    let server = args.http.map(http::bind).transpose()?;
+    // Over HTTP, requests come in with no one at the terminal, so read the password up front.
+    let master = match server {
+        Some(_) => Master::read(&args.key, &config)?,
+        None => Master::new(&args.key),
+    };
+    let handle = |call| match call {
+        rpc::Call::Generate(params) => {
+            let site = config.find_site(&params.site)?;
+            let schema = schema(
+                &config,
+                Some(&params.site),
+                site.as_ref().map(|(_, site)| *site),
+                params.schema.as_deref(),
+                params.field.as_deref(),
+            );
+            let site_args = SiteArgs {
+                increment: params.increment,
+                username: params.username,
+                ..Default::default()
+            };
+            let salt = site_args.salt(&config, &params.site, site.as_ref())?;
+            let compat = site
+                .as_ref()
+                .and_then(|(_, site)| site.compat.as_ref())
+                .filter(|_| params.field.is_none());
+            let password = match compat {
+                Some(compat) => {
+                    if params.schema.is_some() {
+                        anyhow::bail!(tr(
+                            "site-compat",
+                            &[("site", &params.site), ("algorithm", &compat.algorithm())]
+                        ));
+                    }
+                    salt.compat_password(&config, compat, master.password(&config)?)?
+                }
+                None => {
+                    let key = master.key_material(&config, &salt.salt)?;
+                    let site = site.as_ref().map(|(_, site)| *site);
+                    let field = params.field.as_deref();
+                    site_output(&words, site, &key, salt.version, schema, field)?
+                }
+            };
+            let output = params.field.as_ref().map(|field| format!("field:{field}"));
+            record_history(&config, &salt, output.as_deref());
+            Ok(serde_json::json!({
+                "url": salt.url,
+                "username": salt.username,
+                "password": password.as_str(),
+            }))
+        }
+        rpc::Call::ListSites => {
+            let mut sites: Vec<_> = config.sites.iter().collect();
+            sites.sort_by_key(|(url, _)| *url);
+            let sites: Vec<_> = sites
+                .into_iter()
+                .map(|(url, site)| {
+                    serde_json::json!({
+                        "url": url,
+                        "username": site.username,
+                        "schema": site.schema,
+                        "increment": site.increment,
+                    })
+                })
+                .collect();
+            Ok(sites.into())
+        }
+        rpc::Call::Entropy(params) => {
+            let site = match &params.site {
+                Some(name) => config.find_site(name)?,
+                None => None,
+            };
+            let schema = schema(
+                &config,
+                params.site.as_deref(),
+                site.as_ref().map(|(_, site)| *site),
+                params.schema.as_deref(),
+                None,
+            );
+            let expr = Expr::parse(schema).context(tr("invalid-schema", &[]))?;
+            let filter = site
+                .map(|(_, site)| site.word_filter.clone())
+                .unwrap_or_default();
+            let bits = Words(&*words.get(&filter)?).size(&expr).bits();
+            Ok(serde_json::json!({"schema": schema, "bits": bits}))
+        }
+        rpc::Call::Bump(params) => {
+            // Pulled and pushed as `onepass bump` is, so that a long-running server bumps the
+            // latest config and other machines see the bump.
+            pull_config(path, &mut config, no_cache)?;
+            let (url, bump) = bump_site(&config, &params.site, params.reason)?;
+            let increment = bump.increment + 1;
+            // Later calls see the new increment without rereading the config.
+            if let Some(key) = config.site_key(&params.site)?
+                && let Some(site) = config.sites.get_mut(&key)
+            {
+                site.increment = increment;
+                site.rotated = Some(bump.date);
+                site.bumps.push(bump);
+            }
+            push_config(path, &config, &format!("Bump the increment of {url}"))?;
+            Ok(serde_json::json!({"url": url, "increment": increment}))
+        }
+        rpc::Call::Totp(params) => {
+            let stored = config
+                .find_site(&params.site)?
+                .and_then(|(_, site)| site.totp_secret.as_deref());
+            let secret = match stored {
+                Some(secret) => {
+                    otp::decode_secret(secret).context(tr("invalid-totp-secret", &[]))?
+                }
+                None => {
+                    let site_args = SiteArgs {
+                        increment: params.increment,
+                        username: params.username,
+                        ..Default::default()
+                    };
+                    derive_otp_secret(&config, &params.site, &site_args, &master, "totp")?.0
+                }
+            };
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+            let expires = now - now % otp::TOTP_PERIOD + otp::TOTP_PERIOD;
+            Ok(serde_json::json!({"code": otp::totp(&secret, now), "expires": expires}))
+        }
+    };
+    let Some(server) = server else {
+        return rpc::serve(handle);
+    };
+    let token = match args.token {
+        Some(token) => Zeroizing::new(token),
+        None => {
+            let token = Encoding::Base64url.encode(&mut OsRng.unwrap_err(), None)?;
+            eprintln!("{}", tr("token", &[("token", &*token)]));
+            token
+        }
+    };
+    http::serve(server, &token, handle)
+}
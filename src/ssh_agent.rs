@@ -0,0 +1,174 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A minimal client for the ssh-agent protocol (draft-miller-ssh-agent).
+
+#[cfg(all(unix, feature = "agent"))]
+use std::{
+    env,
+    io::{Read, Write},
+};
+
+use anyhow::{Context, Result};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use zeroize::Zeroizing;
+
+use crate::i18n::tr;
+#[cfg(feature = "keys")]
+use crate::keys::SshKey;
+
+const SSH_AGENT_FAILURE: u8 = 5;
+#[cfg(feature = "keys")]
+const SSH_AGENT_SUCCESS: u8 = 6;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+#[cfg(feature = "keys")]
+const SSH_AGENTC_ADD_IDENTITY: u8 = 17;
+const SSH_AGENT_RSA_SHA2_256: u32 = 2;
+
+pub(crate) struct Agent {
+    #[cfg(all(unix, feature = "agent"))]
+    stream: std::os::unix::net::UnixStream,
+}
+
+impl Agent {
+    #[cfg(all(unix, feature = "agent"))]
+    pub fn connect() -> Result<Self> {
+        let path = env::var_os("SSH_AUTH_SOCK").context(tr("no-auth-sock", &[]))?;
+        let stream = std::os::unix::net::UnixStream::connect(path)
+            .context(tr("failed-connecting-agent", &[]))?;
+        Ok(Agent { stream })
+    }
+
+    #[cfg(not(unix))]
+    pub fn connect() -> Result<Self> {
+        anyhow::bail!(tr("agent-unsupported", &[]));
+    }
+
+    #[cfg(all(unix, not(feature = "agent")))]
+    pub fn connect() -> Result<Self> {
+        anyhow::bail!(tr("built-without-agent", &[]));
+    }
+
+    /// Signs `data` with the key whose public key blob is `key_blob`, returning the signature blob.
+    pub fn sign(&mut self, key_blob: &[u8], data: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        let flags = if key_type(key_blob)? == "ssh-rsa" {
+            SSH_AGENT_RSA_SHA2_256
+        } else {
+            0
+        };
+        let mut msg = vec![SSH_AGENTC_SIGN_REQUEST];
+        put_string(&mut msg, key_blob);
+        put_string(&mut msg, data);
+        msg.extend_from_slice(&flags.to_be_bytes());
+        let res = self.request(&msg)?;
+        match res.split_first() {
+            Some((&SSH_AGENT_SIGN_RESPONSE, mut rest)) => {
+                Ok(Zeroizing::new(get_string(&mut rest)?.to_vec()))
+            }
+            Some((&SSH_AGENT_FAILURE, _)) => anyhow::bail!(tr("agent-refused-sign", &[])),
+            _ => anyhow::bail!(tr("unexpected-agent-response", &[])),
+        }
+    }
+
+    /// Adds `key` to the agent.
+    #[cfg(feature = "keys")]
+    pub fn add(&mut self, key: &SshKey) -> Result<()> {
+        let public = key.public_blob();
+        let mut public = public.as_slice();
+        let mut msg = Zeroizing::new(vec![SSH_AGENTC_ADD_IDENTITY]);
+        put_string(&mut msg, get_string(&mut public)?);
+        put_string(&mut msg, get_string(&mut public)?);
+        put_string(&mut msg, &key.private_bytes());
+        put_string(&mut msg, key.comment().as_bytes());
+        match self.request(&msg)?.first() {
+            Some(&SSH_AGENT_SUCCESS) => Ok(()),
+            Some(&SSH_AGENT_FAILURE) => anyhow::bail!(tr("agent-refused-key", &[])),
+            _ => anyhow::bail!(tr("unexpected-agent-response", &[])),
+        }
+    }
+
+    #[cfg(all(unix, feature = "agent"))]
+    fn request(&mut self, msg: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        self.stream.write_all(&(msg.len() as u32).to_be_bytes())?;
+        self.stream.write_all(msg)?;
+        let mut len = [0u8; 4];
+        self.stream.read_exact(&mut len)?;
+        let mut res = Zeroizing::new(vec![0u8; u32::from_be_bytes(len) as usize]);
+        self.stream.read_exact(&mut res)?;
+        Ok(res)
+    }
+
+    #[cfg(not(all(unix, feature = "agent")))]
+    fn request(&mut self, _msg: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        unreachable!()
+    }
+}
+
+/// Parses an OpenSSH public key line (`ssh-ed25519 AAAA... comment`) into its key blob.
+pub(crate) fn parse_public_key(line: &str) -> Result<Vec<u8>> {
+    let mut parts = line.split_whitespace();
+    let (Some(algorithm), Some(blob)) = (parts.next(), parts.next()) else {
+        anyhow::bail!(tr("invalid-public-key", &[]));
+    };
+    let blob = BASE64_STANDARD
+        .decode(blob)
+        .context(tr("invalid-public-key", &[]))?;
+    if key_type(&blob)? != algorithm {
+        anyhow::bail!(tr("key-type-mismatch", &[]));
+    }
+    Ok(blob)
+}
+
+pub(crate) fn key_type(mut blob: &[u8]) -> Result<&str> {
+    std::str::from_utf8(get_string(&mut blob)?).context(tr("invalid-key-type", &[]))
+}
+
+pub(crate) fn put_string(buf: &mut Vec<u8>, s: &[u8]) {
+    buf.extend_from_slice(&(s.len() as u32).to_be_bytes());
+    buf.extend_from_slice(s);
+}
+
+pub(crate) fn get_string<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let (len, rest) = buf
+        .split_at_checked(4)
+        .context(tr("truncated-string", &[]))?;
+    let len = u32::from_be_bytes(len.try_into().unwrap()) as usize;
+    let (s, rest) = rest
+        .split_at_checked(len)
+        .context(tr("truncated-string", &[]))?;
+    *buf = rest;
+    Ok(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_key() -> Result<()> {
+        let blob = parse_public_key(
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDq3Xb8C2lT1YcE0n+0f8U1ZpNqf3XH0+Vq6p6n0tW4b me@host",
+        )?;
+        assert_eq!("ssh-ed25519", key_type(&blob)?);
+        assert_eq!(51, blob.len());
+        assert!(
+            parse_public_key(
+                "ssh-rsa AAAAC3NzaC1lZDI1NTE5AAAAIDq3Xb8C2lT1YcE0n+0f8U1ZpNqf3XH0+Vq6p6n0tW4b"
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+}
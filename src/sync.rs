@@ -0,0 +1,267 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `onepass sync`: keeping the config file in step across machines through a git repository in
+//! its directory, so that every machine derives from the same increments and schemas.
+//!
+//! Local commits are rebased onto the remote branch. Where both sides changed the config, it is
+//! merged as YAML by [`merge`] rather than as lines, so that conflicts are only reported for
+//! sites (or settings) changed differently on both sides.
+//!
+//! The repository must be the config directory's own, not one it happens to be inside, like a
+//! dotfiles repository, whose whole history would otherwise be pushed to the sync remote.
+
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Output},
+};
+
+use anyhow::{Context, Result};
+use onepass::{
+    backup,
+    config::{self, SyncConfig},
+};
+use serde_yaml::Value;
+
+use crate::{
+    i18n::tr,
+    merge::{self, Prefer},
+};
+
+/// The git repository holding the config file at `path`.
+struct Repo<'a> {
+    dir: &'a Path,
+    /// The config file's name in the repository
+    file: &'a str,
+}
+
+impl<'a> Repo<'a> {
+    fn new(path: &'a Path) -> Result<Self> {
+        let dir = path.parent().context(tr("invalid-config-path", &[]))?;
+        let file = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .context(tr("invalid-config-path", &[]))?;
+        Ok(Repo { dir, file })
+    }
+
+    /// Runs git in the repository, whether or not it succeeds.
+    fn run(&self, args: &[&str]) -> Result<Output> {
+        let mut command = Command::new("git");
+        command
+            .arg("-C")
+            .arg(self.dir)
+            .args(args)
+            .env("GIT_EDITOR", "true");
+        // The config files git writes while rebasing are readable only by the user until
+        // `rebase` gives the config back its permissions.
+        #[cfg(unix)]
+        // SAFETY: umask is async-signal-safe and takes no pointers.
+        unsafe {
+            command.pre_exec(|| {
+                libc::umask(0o077);
+                Ok(())
+            });
+        }
+        command
+            .output()
+            .context(tr("failed-running", &[("program", &"git")]))
+    }
+
+    /// Runs git in the repository, returning its output, or failing with its errors.
+    fn git(&self, args: &[&str]) -> Result<String> {
+        let output = self.run(args)?;
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!(tr(
+                "git-failed",
+                &[("command", &args[0]), ("error", &error.trim())]
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Makes the config directory a repository if it isn't one, failing if it is inside another
+    /// repository.
+    fn init(&self, branch: &str) -> Result<()> {
+        let toplevel = self.run(&["rev-parse", "--show-toplevel"])?;
+        if !toplevel.status.success() {
+            self.git(&["init", "--initial-branch", branch])?;
+            eprintln!("{}", tr("made-repository", &[("dir", &self.dir.display())]));
+            return Ok(());
+        }
+        let toplevel =
+            Path::new(String::from_utf8_lossy(&toplevel.stdout).trim()).canonicalize()?;
+        anyhow::ensure!(
+            toplevel == self.dir.canonicalize()?,
+            tr(
+                "enclosing-repository",
+                &[
+                    ("dir", &self.dir.display()),
+                    ("repository", &toplevel.display())
+                ]
+            )
+        );
+        Ok(())
+    }
+
+    /// Commits the config file if it has changed, returning whether it had.
+    fn commit(&self, message: &str) -> Result<bool> {
+        self.git(&["add", "--", self.file])?;
+        let staged = self.run(&["diff", "--cached", "--quiet", "--", self.file])?;
+        if staged.status.success() {
+            return Ok(false);
+        }
+        self.git(&["commit", "--quiet", "--message", message, "--", self.file])?;
+        Ok(true)
+    }
+
+    /// Fetches the remote branch, returning whether it exists yet.
+    fn fetch(&self, sync: &SyncConfig) -> Result<bool> {
+        let branch = format!("refs/heads/{}", sync.branch);
+        // The remote follows `--` everywhere, so that one like `--upload-pack=...` isn't taken
+        // for an option.
+        let remote = self.run(&["ls-remote", "--exit-code", "--", &sync.remote, &branch])?;
+        // ls-remote exits with 2 for a reachable remote without the branch.
+        match remote.status.code() {
+            Some(0) => {}
+            Some(2) => return Ok(false),
+            _ => {
+                let error = String::from_utf8_lossy(&remote.stderr);
+                anyhow::bail!(tr(
+                    "failed-reaching",
+                    &[("remote", &sync.remote), ("error", &error.trim())]
+                ))
+            }
+        }
+        self.git(&["fetch", "--quiet", "--", &sync.remote, &branch])?;
+        Ok(true)
+    }
+
+    /// Rebases local commits onto the fetched branch, merging the config file wherever the
+    /// rebase stops on it. On conflicts that `prefer` doesn't settle, the rebase is undone. The
+    /// config is snapshotted first, as [`config::rewrite`] does, and keeps its permissions.
+    fn rebase(&self, prefer: Option<Prefer>) -> Result<()> {
+        let path = self.dir.join(self.file);
+        let permissions = fs::metadata(&path)?.permissions();
+        backup::snapshot(&path)?;
+        let rebased = self.rebase_onto(prefer);
+        fs::set_permissions(&path, permissions)?;
+        rebased
+    }
+
+    fn rebase_onto(&self, prefer: Option<Prefer>) -> Result<()> {
+        let path = self.dir.join(self.file);
+        let head = self.run(&["rev-parse", "--verify", "--quiet", "HEAD"])?;
+        if !head.status.success() {
+            // Nothing committed here yet, so take the remote's history as it is.
+            let remote = self.git(&["show", &format!("FETCH_HEAD:./{}", self.file)])?;
+            self.git(&["reset", "--quiet", "FETCH_HEAD"])?;
+            return config::rewrite(&path, &remote);
+        }
+        let mut rebased = self.run(&["rebase", "--quiet", "FETCH_HEAD"])?;
+        while !rebased.status.success() {
+            let unmerged = self.git(&["diff", "--name-only", "--diff-filter=U"])?;
+            if unmerged.lines().collect::<Vec<_>>() != [self.file] {
+                self.run(&["rebase", "--abort"])?;
+                let error = String::from_utf8_lossy(&rebased.stderr);
+                anyhow::bail!(tr("rebase-failed", &[("error", &error.trim())]));
+            }
+            // While rebasing, stage 2 ("ours") is the remote's and stage 3 the local commit's.
+            let stage = |n: u8| -> Result<Value> {
+                let output = self.run(&["show", &format!(":{n}:./{}", self.file)])?;
+                if !output.status.success() {
+                    return Ok(Value::Null);
+                }
+                serde_yaml::from_slice(&output.stdout)
+                    .with_context(|| tr("invalid-stage", &[("stage", &n)]))
+            };
+            let (base, remote, local) = (stage(1)?, stage(2)?, stage(3)?);
+            let merged = match merge::merge(&base, &remote, &local, prefer) {
+                Ok(merged) => merged,
+                Err(conflicts) => {
+                    self.run(&["rebase", "--abort"])?;
+                    let conflicts: Vec<_> = conflicts.iter().map(|c| format!("  {c}")).collect();
+                    anyhow::bail!(tr(
+                        "sync-conflicts",
+                        &[("conflicts", &conflicts.join("\n"))]
+                    ));
+                }
+            };
+            if Value::Mapping(merged.clone()) == remote {
+                rebased = self.run(&["rebase", "--skip"])?;
+                continue;
+            }
+            config::rewrite(&path, &serde_yaml::to_string(&merged)?)?;
+            self.git(&["add", "--", self.file])?;
+            rebased = self.run(&["rebase", "--continue"])?;
+        }
+        Ok(())
+    }
+
+    fn push(&self, sync: &SyncConfig) -> Result<()> {
+        let branch = format!("HEAD:refs/heads/{}", sync.branch);
+        self.git(&["push", "--quiet", "--", &sync.remote, &branch])?;
+        Ok(())
+    }
+}
+
+/// Commits changes to the config file at `path`, rebases them onto the remote's, and pushes the
+/// result.
+pub(crate) fn sync(path: &Path, sync: &SyncConfig, prefer: Option<Prefer>) -> Result<()> {
+    let repo = Repo::new(path)?;
+    repo.init(&sync.branch)?;
+    if repo.commit("Edit config")? {
+        eprintln!("{}", tr("committed", &[("file", &repo.file)]));
+    }
+    if repo.fetch(sync)? {
+        repo.rebase(prefer)?;
+    }
+    repo.push(sync)?;
+    eprintln!("{}", tr("synced", &[("remote", &sync.remote)]));
+    Ok(())
+}
+
+/// Brings the config file at `path` up to date with the remote before a command changes it. Only
+/// conflicts are errors: without the remote, e.g. offline, this warns and leaves the config be.
+pub(crate) fn pull(path: &Path, sync: &SyncConfig) -> Result<()> {
+    let repo = Repo::new(path)?;
+    repo.init(&sync.branch)?;
+    repo.commit("Edit config")?;
+    match repo.fetch(sync) {
+        Ok(true) => repo.rebase(None),
+        Ok(false) => Ok(()),
+        Err(e) => {
+            let error = format!("{e:#}");
+            eprintln!("{}", tr("not-synced", &[("error", &error)]));
+            Ok(())
+        }
+    }
+}
+
+/// Commits a change to the config file at `path` made by a command, described by `message`, and
+/// pushes it, only warning if the remote can't be reached.
+pub(crate) fn push(path: &Path, sync: &SyncConfig, message: &str) -> Result<()> {
+    let repo = Repo::new(path)?;
+    if repo.commit(message)?
+        && let Err(e) = repo.push(sync)
+    {
+        let error = format!("{e:#}");
+        eprintln!("{}", tr("not-pushed", &[("error", &error)]));
+    }
+    Ok(())
+}
@@ -0,0 +1,140 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sealing and unsealing a pepper in the TPM, by running the `tpm2_*` commands of tpm2-tools,
+//! which have to be installed for `pepper: tpm`.
+//!
+//! The pepper is sealed under the owner hierarchy's default primary key, which the TPM can
+//! regenerate on demand, so only the sealed object's public and private blobs need to be stored.
+//! Those blobs are useless on any other TPM.
+
+use std::{
+    env,
+    fs::{DirBuilder, remove_dir_all},
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result};
+use rand_core::{OsRng, TryRngCore};
+use zeroize::Zeroizing;
+
+use crate::i18n::tr;
+
+const PUBLIC_BLOB: &str = "tpm_pepper.pub";
+const PRIVATE_BLOB: &str = "tpm_pepper.priv";
+
+/// A directory for the TPM's context files, under a random name and readable only by the user.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new() -> Result<Self> {
+        let mut name = [0; 16];
+        OsRng.try_fill_bytes(&mut name)?;
+        let path = env::temp_dir().join(format!("onepass-tpm-{}", hex::encode(name)));
+        let mut builder = DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder
+            .create(&path)
+            .with_context(|| tr("failed-creating", &[("path", &path.display())]))?;
+        Ok(TempDir(path))
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = remove_dir_all(&self.0);
+    }
+}
+
+pub(crate) fn is_sealed(dir: &Path) -> bool {
+    dir.join(PUBLIC_BLOB).exists() && dir.join(PRIVATE_BLOB).exists()
+}
+
+/// Seals `pepper` in the TPM, storing the resulting blobs in `dir`.
+pub(crate) fn seal(dir: &Path, pepper: &[u8]) -> Result<()> {
+    let tmp = TempDir::new()?;
+    let primary = tmp.0.join("primary.ctx");
+    create_primary(&primary)?;
+    let mut child = Command::new("tpm2_create")
+        .args(["-Q", "-C"])
+        .arg(&primary)
+        .arg("-u")
+        .arg(dir.join(PUBLIC_BLOB))
+        .arg("-r")
+        .arg(dir.join(PRIVATE_BLOB))
+        .args(["-i", "-"])
+        .stdin(Stdio::piped())
+        .spawn()
+        .context(tr("failed-running-tpm2", &[("program", &"tpm2_create")]))?;
+    child
+        .stdin
+        .take()
+        .context(tr("no-stdin", &[]))?
+        .write_all(pepper)?;
+    if !child.wait()?.success() {
+        anyhow::bail!(tr("program-failed", &[("program", &"tpm2_create")]));
+    }
+    Ok(())
+}
+
+/// Unseals the pepper whose blobs are stored in `dir`.
+pub(crate) fn unseal(dir: &Path) -> Result<Zeroizing<Vec<u8>>> {
+    if !is_sealed(dir) {
+        anyhow::bail!(tr("no-sealed-pepper", &[("dir", &dir.display())]));
+    }
+    let tmp = TempDir::new()?;
+    let primary = tmp.0.join("primary.ctx");
+    let sealed = tmp.0.join("sealed.ctx");
+    create_primary(&primary)?;
+    run(Command::new("tpm2_load")
+        .args(["-Q", "-C"])
+        .arg(&primary)
+        .arg("-u")
+        .arg(dir.join(PUBLIC_BLOB))
+        .arg("-r")
+        .arg(dir.join(PRIVATE_BLOB))
+        .arg("-c")
+        .arg(&sealed))?;
+    let output = Command::new("tpm2_unseal")
+        .arg("-c")
+        .arg(&sealed)
+        .stderr(Stdio::inherit())
+        .output()
+        .context(tr("failed-running-tpm2", &[("program", &"tpm2_unseal")]))?;
+    let pepper = Zeroizing::new(output.stdout);
+    if !output.status.success() {
+        anyhow::bail!(tr("program-failed", &[("program", &"tpm2_unseal")]));
+    }
+    Ok(pepper)
+}
+
+fn create_primary(path: &Path) -> Result<()> {
+    run(Command::new("tpm2_createprimary")
+        .args(["-Q", "-C", "o", "-c"])
+        .arg(path))
+}
+
+fn run(command: &mut Command) -> Result<()> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let status = command
+        .status()
+        .with_context(|| tr("failed-running-tpm2", &[("program", &program)]))?;
+    if !status.success() {
+        anyhow::bail!(tr("program-failed", &[("program", &program)]));
+    }
+    Ok(())
+}
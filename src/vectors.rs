@@ -0,0 +1,236 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test vectors for third-party reimplementations of the derivation pipeline.
+
+use anyhow::Result;
+use crypto_bigint::NonZero;
+use onepass::{
+    derive::random_below,
+    randexp::{Enumerable, Expr, Quantifiable, Words},
+};
+use serde::Serialize;
+
+use crate::{
+    crypto::{
+        Argon2Config, BalloonConfig, DerivationVersion, KdfAlgorithm, KdfConfig, KeyMaterial,
+        SaltInputs, ScryptConfig,
+    },
+    factor::Factors,
+};
+
+/// Bumped whenever the format of the output changes.
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct Vectors {
+    format_version: u32,
+    master_password: String,
+    /// The BLAKE3 hash of the word list, one word per line with trailing newlines
+    wordlist_blake3: String,
+    vectors: Vec<Vector>,
+}
+
+#[derive(Serialize)]
+struct Vector {
+    url: &'static str,
+    username: Option<&'static str>,
+    increment: u32,
+    derivation_version: DerivationVersion,
+    salt_template: Option<&'static str>,
+    kdf: KdfConfig,
+    schema: &'static str,
+    field: Option<&'static str>,
+    salt_hex: String,
+    key_material_hex: String,
+    index_hex: String,
+    password: String,
+}
+
+struct Case {
+    url: &'static str,
+    username: Option<&'static str>,
+    increment: u32,
+    version: DerivationVersion,
+    salt_template: Option<&'static str>,
+    algorithm: KdfAlgorithm,
+    schema: &'static str,
+    field: Option<&'static str>,
+}
+
+const BASE: Case = Case {
+    url: "https://google.com/",
+    username: None,
+    increment: 0,
+    version: DerivationVersion::V1,
+    salt_template: None,
+    algorithm: KdfAlgorithm::Argon2id,
+    schema: "[!-~]{12}",
+    field: None,
+};
+
+const CASES: &[Case] = &[
+    BASE,
+    Case {
+        url: "https://iphone.local/",
+        increment: 1,
+        schema: "[0-9]{8}",
+        ..BASE
+    },
+    Case {
+        url: "https://test%40gmail.com@google.com/",
+        username: Some("test@gmail.com"),
+        schema: "[A-Za-z0-9]{18}",
+        ..BASE
+    },
+    Case {
+        url: "https://github.com/",
+        version: DerivationVersion::V2,
+        schema: "[:word:](-[:word:]){4}",
+        ..BASE
+    },
+    Case {
+        url: "https://apple.com/",
+        increment: 2,
+        version: DerivationVersion::V2,
+        schema: "[:Word:](-[:word:]){3}[0-9!-/]",
+        ..BASE
+    },
+    Case {
+        url: "https://me@example.com/",
+        username: Some("me"),
+        salt_template: Some("{url}#{increment}#{username}"),
+        ..BASE
+    },
+    Case {
+        url: "https://me@example.com/",
+        username: Some("me"),
+        version: DerivationVersion::V2,
+        salt_template: Some("{url}#{increment}#{username}"),
+        ..BASE
+    },
+    Case {
+        url: "https://bank.com/",
+        version: DerivationVersion::V2,
+        schema: "[0-9]{6}",
+        field: Some("pin"),
+        ..BASE
+    },
+    Case {
+        algorithm: KdfAlgorithm::Argon2i,
+        ..BASE
+    },
+    Case {
+        algorithm: KdfAlgorithm::Argon2d,
+        ..BASE
+    },
+    Case {
+        algorithm: KdfAlgorithm::Scrypt,
+        ..BASE
+    },
+    Case {
+        algorithm: KdfAlgorithm::Balloon,
+        ..BASE
+    },
+];
+
+/// Writes test vectors for `master_password` as JSON to stdout.
+pub(crate) fn print_vectors(master_password: &str, words: &[&str]) -> Result<()> {
+    let mut hasher = blake3::Hasher::new();
+    for word in words {
+        hasher.update(word.as_bytes());
+        hasher.update(b"\n");
+    }
+    let wl = Words(words);
+    let mut vectors = Vec::with_capacity(CASES.len());
+    for case in CASES {
+        let kdf = explicit_kdf(case.algorithm);
+        let salt = case.version.salt(
+            case.salt_template,
+            &SaltInputs {
+                increment: case.increment,
+                url: case.url,
+                username: case.username,
+            },
+        )?;
+        let key = KeyMaterial::derive(master_password, &salt, &kdf, &Factors::default())?;
+        let mut rng = match case.field {
+            Some(field) => key.labeled_rng(&format!("field:{field}")),
+            None => key.password_rng(case.version),
+        };
+        let expr = Expr::parse(case.schema)?;
+        let index = random_below(&mut rng, &NonZero::new(wl.size(&expr)).unwrap());
+        let password = wl.gen_at(&expr, index)?;
+        vectors.push(Vector {
+            url: case.url,
+            username: case.username,
+            increment: case.increment,
+            derivation_version: case.version,
+            salt_template: case.salt_template,
+            kdf,
+            schema: case.schema,
+            field: case.field,
+            salt_hex: hex::encode(&salt),
+            key_material_hex: hex::encode(key.expose()),
+            index_hex: index.to_string().to_lowercase(),
+            password: password.to_string(),
+        });
+    }
+    let vectors = Vectors {
+        format_version: FORMAT_VERSION,
+        master_password: master_password.into(),
+        wordlist_blake3: hasher.finalize().to_hex().to_string(),
+        vectors,
+    };
+    println!("{}", serde_json::to_string_pretty(&vectors)?);
+    Ok(())
+}
+
+/// Returns the default config for `algorithm`, with its parameters spelled out.
+fn explicit_kdf(algorithm: KdfAlgorithm) -> KdfConfig {
+    let mut kdf = KdfConfig {
+        algorithm,
+        ..Default::default()
+    };
+    match algorithm {
+        KdfAlgorithm::Argon2id | KdfAlgorithm::Argon2i | KdfAlgorithm::Argon2d => {
+            kdf.argon2 = Some(Argon2Config::default())
+        }
+        KdfAlgorithm::Scrypt => kdf.scrypt = Some(ScryptConfig::default()),
+        KdfAlgorithm::Balloon => kdf.balloon = Some(BalloonConfig::default()),
+    }
+    kdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cases_are_valid() {
+        for case in CASES {
+            case.version
+                .salt(
+                    case.salt_template,
+                    &SaltInputs {
+                        increment: case.increment,
+                        url: case.url,
+                        username: case.username,
+                    },
+                )
+                .unwrap();
+            Expr::parse(case.schema).unwrap();
+        }
+    }
+}
@@ -0,0 +1,831 @@
+// Copyright 2025 Steven Dee
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Word list files: reading words files, memory-mapped and indexed only once a schema draws from
+//! them, and the `wordlist` subcommands, which download well-known ones, pinned to the checksums
+//! of their published files, and check ones made by hand.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+    fs::{self, File, create_dir_all, rename, write},
+    io::Read,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Arc, Mutex, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use onepass::{
+    config,
+    randexp::WordList,
+    wordlist::{
+        DICEWARE_SHA256, EFF_LARGE_SHA256, EFF_SHORT_2_SHA256, EFF_SHORT_SHA256, Filtered,
+        WordFilter, Wordlist,
+    },
+};
+use sha2::{Digest, Sha256};
+
+use crate::i18n::tr;
+
+/// The words that `[:word:]` draws from: a built-in list, or words files.
+pub(crate) enum List {
+    Builtin(&'static [&'static str]),
+    Files(WordsFiles),
+}
+
+impl List {
+    /// Opens the words files at `paths` if there are any, or else returns the built-in
+    /// `wordlist`. With a `cache_dir`, the files' index is cached there.
+    pub(crate) fn open(
+        paths: Vec<Box<Path>>,
+        wordlist: Wordlist,
+        cache_dir: Option<&Path>,
+    ) -> Result<Self> {
+        if paths.is_empty() {
+            return Ok(List::Builtin(wordlist.words()));
+        }
+        Ok(List::Files(
+            WordsFiles::open(&paths, cache_dir).context(tr("failed-reading-words", &[]))?,
+        ))
+    }
+
+    /// Returns the built-in list called `name` if there is one, or else opens the words file at
+    /// `name`; `./bip39` names a file called `bip39`.
+    pub(crate) fn named(name: &str) -> Result<Self> {
+        match Wordlist::from_str(name, false) {
+            Ok(wordlist) => Ok(List::Builtin(wordlist.words())),
+            Err(_) if !Path::new(name).exists() => {
+                anyhow::bail!(tr("unknown-word-list", &[("name", &name)]))
+            }
+            Err(_) => Self::open(vec![Path::new(name).into()], Wordlist::default(), None),
+        }
+    }
+}
+
+impl WordList for List {
+    fn len(&self) -> usize {
+        match self {
+            List::Builtin(words) => words.len(),
+            List::Files(files) => files.len(),
+        }
+    }
+
+    fn word(&self, index: usize) -> &str {
+        match self {
+            List::Builtin(words) => words[index],
+            List::Files(files) => files.word(index),
+        }
+    }
+}
+
+/// A list of words with the filtered lists of the word filters passwords have drawn from it, so
+/// that a server filters each site's list once rather than for every password.
+pub(crate) struct Filters<'a> {
+    list: &'a List,
+    filtered: Mutex<HashMap<WordFilter, Arc<Filtered<'a, List>>>>,
+}
+
+impl<'a> Filters<'a> {
+    pub(crate) fn new(list: &'a List) -> Self {
+        Filters {
+            list,
+            filtered: Mutex::default(),
+        }
+    }
+
+    /// Returns the words of the list that pass `filter`.
+    pub(crate) fn get(&self, filter: &WordFilter) -> Result<Arc<Filtered<'a, List>>> {
+        let mut filtered = self.filtered.lock().unwrap();
+        if let Some(words) = filtered.get(filter) {
+            return Ok(words.clone());
+        }
+        let words = Arc::new(filter.apply(self.list)?);
+        filtered.insert(filter.clone(), words.clone());
+        Ok(words)
+    }
+}
+
+/// Words files, with one word per line, combined into one list. Blank lines and surrounding
+/// whitespace are ignored, and the words of all the files are drawn from in sorted order, each
+/// once, so that a base list can be extended with words of one's own.
+///
+/// The files are memory-mapped where possible, and only indexed, by the offsets of their words,
+/// the first time a schema draws from them, so that large lists cost little until they are used
+/// and nothing for schemas without words. The index can be cached, for the files as they are
+/// now, so that later runs only hash them.
+pub(crate) struct WordsFiles {
+    /// The files' contents, each with its offset in the files as if they were concatenated
+    texts: Vec<(usize, Text)>,
+    index: OnceLock<Box<[(u32, u32)]>>,
+    cache: Option<IndexCache>,
+}
+
+/// Where the index of words files is cached, with the files' modification times.
+struct IndexCache {
+    path: Box<Path>,
+    modified: Vec<Option<SystemTime>>,
+}
+
+impl WordsFiles {
+    /// Opens the words files at `paths`, failing if any is not UTF-8. With a `cache_dir`, their
+    /// index is cached there.
+    pub(crate) fn open(paths: &[Box<Path>], cache_dir: Option<&Path>) -> Result<Self> {
+        let mut texts = Vec::new();
+        let mut modified = Vec::new();
+        let mut offset = 0;
+        for path in paths {
+            let text = Text::open(path).with_context(|| format!("{}", path.display()))?;
+            let len = text.bytes().len();
+            texts.push((offset, text));
+            modified.push(fs::metadata(path).and_then(|m| m.modified()).ok());
+            offset += len;
+        }
+        if offset > u32::MAX as usize {
+            anyhow::bail!(tr("words-too-large", &[]));
+        }
+        let cache = cache_dir.map(|dir| {
+            let mut hasher = blake3::Hasher::new();
+            for path in paths {
+                hasher.update(path.as_os_str().as_encoded_bytes());
+                hasher.update(b"\0");
+            }
+            let name = format!("words-{}.idx", &hasher.finalize().to_hex()[..16]);
+            IndexCache {
+                path: dir.join(name).into(),
+                modified,
+            }
+        });
+        Ok(WordsFiles {
+            texts,
+            index: OnceLock::new(),
+            cache,
+        })
+    }
+
+    /// Returns the word between the offsets `start` and `end` of the concatenated files.
+    fn at(&self, offsets: (u32, u32)) -> &str {
+        self.get(offsets).expect("word offsets out of bounds")
+    }
+
+    /// Returns the word between the offsets `start` and `end` of the concatenated files, if they
+    /// are the bounds of a UTF-8 string within one file.
+    fn get(&self, (start, end): (u32, u32)) -> Option<&str> {
+        let (start, end) = (start as usize, end as usize);
+        let i = self.texts.partition_point(|&(offset, _)| offset <= start) - 1;
+        let (offset, text) = &self.texts[i];
+        let word = text
+            .bytes()
+            .get(start - offset..end.checked_sub(*offset)?)?;
+        std::str::from_utf8(word).ok()
+    }
+
+    /// Returns the offsets of the files' distinct words, in sorted order, indexing them or
+    /// reading the cached index if needed.
+    fn index(&self) -> &[(u32, u32)] {
+        self.index.get_or_init(|| {
+            let Some(cache) = &self.cache else {
+                return self.build_index();
+            };
+            let key = self.cache_key(&cache.modified);
+            if let Some(index) = self.read_index(&cache.path, &key) {
+                return index;
+            }
+            let index = self.build_index();
+            write_index(&cache.path, &key, &index);
+            index
+        })
+    }
+
+    /// Indexes the files' distinct words.
+    fn build_index(&self) -> Box<[(u32, u32)]> {
+        let mut index = Vec::new();
+        for (offset, text) in &self.texts {
+            // Checked in `Text::open`; a file that has changed since has no words.
+            let text = std::str::from_utf8(text.bytes()).unwrap_or_default();
+            let words = text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(|line| {
+                    let start = offset + (line.as_ptr() as usize - text.as_ptr() as usize);
+                    (start as u32, (start + line.len()) as u32)
+                });
+            index.extend(words);
+        }
+        // Published lists are mostly sorted already, with nothing to remove.
+        if !index.is_sorted_by(|&a, &b| self.at(a) < self.at(b)) {
+            index.sort_unstable_by(|&a, &b| self.at(a).cmp(self.at(b)));
+            index.dedup_by(|&mut a, &mut b| self.at(a) == self.at(b));
+        }
+        index.into()
+    }
+
+    /// Returns the key of the files' cached index: a hash of the files as they are now, with
+    /// their modification times `modified`, under this version of onepass.
+    fn cache_key(&self, modified: &[Option<SystemTime>]) -> blake3::Hash {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+        for ((_, text), modified) in self.texts.iter().zip(modified) {
+            let modified = modified
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |time| time.as_nanos());
+            hasher.update(&modified.to_le_bytes());
+            hasher.update(&(text.bytes().len() as u64).to_le_bytes());
+            hasher.update(text.bytes());
+        }
+        hasher.finalize()
+    }
+
+    /// Reads the index cached at `path`, if it has the key `key` and is valid for the files.
+    fn read_index(&self, path: &Path, key: &blake3::Hash) -> Option<Box<[(u32, u32)]>> {
+        let bytes = fs::read(path).ok()?;
+        let (header, offsets) = bytes.split_at_checked(blake3::OUT_LEN)?;
+        if header != key.as_bytes() || offsets.len() % 8 != 0 {
+            return None;
+        }
+        let index: Box<[(u32, u32)]> = offsets
+            .chunks_exact(8)
+            .map(|pair| {
+                let (start, end) = pair.split_at(4);
+                let word = |bytes: &[u8]| u32::from_le_bytes(bytes.try_into().unwrap());
+                (word(start), word(end))
+            })
+            .collect();
+        // A corrupted cache must not make `at` panic.
+        index
+            .iter()
+            .all(|&offsets| self.get(offsets).is_some())
+            .then_some(index)
+    }
+}
+
+/// Writes the index `index` of words files, with its key `key`, to the cache file `path`.
+fn write_index(path: &Path, key: &blake3::Hash, index: &[(u32, u32)]) {
+    let mut bytes = Vec::with_capacity(blake3::OUT_LEN + 8 * index.len());
+    bytes.extend(key.as_bytes());
+    for (start, end) in index {
+        bytes.extend(start.to_le_bytes());
+        bytes.extend(end.to_le_bytes());
+    }
+    if let Some(dir) = path.parent() {
+        config::write_cache(dir, path, &bytes);
+    }
+}
+
+impl WordList for WordsFiles {
+    fn len(&self) -> usize {
+        self.index().len()
+    }
+
+    fn word(&self, index: usize) -> &str {
+        self.at(self.index()[index])
+    }
+}
+
+/// The contents of a words file, as bytes: nothing assumes they are still UTF-8 after
+/// [`Text::open`] checks them, since a mapped file can change underneath.
+enum Text {
+    Mapped(Mapping),
+    Read(Vec<u8>),
+}
+
+impl Text {
+    /// Maps or reads the words file at `path`, failing if it is not UTF-8.
+    fn open(path: &Path) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let metadata = file.metadata()?;
+        let text = match Mapping::new(&file, &metadata) {
+            Some(mapping) => Text::Mapped(mapping),
+            None => {
+                let mut text = Vec::new();
+                file.read_to_end(&mut text)?;
+                Text::Read(text)
+            }
+        };
+        std::str::from_utf8(text.bytes()).context(tr("invalid-utf8", &[]))?;
+        Ok(text)
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match self {
+            Text::Mapped(mapping) => mapping.bytes(),
+            Text::Read(bytes) => bytes,
+        }
+    }
+}
+
+/// A read-only, private memory mapping of a whole file.
+///
+/// Like any mapping of a file, it shows changes other processes make to the file, and reading a
+/// part of it that has been truncated away raises SIGBUS. onepass itself never rewrites a words
+/// file in place: downloads are written beside it and renamed over it, which leaves the mapped
+/// file as it was. Words are checked to be UTF-8 each time one is read, so a file changed by hand
+/// while mapped can only give wrong words, not invalid strings.
+#[cfg(unix)]
+struct Mapping {
+    ptr: std::ptr::NonNull<libc::c_void>,
+    len: usize,
+}
+
+#[cfg(unix)]
+impl Mapping {
+    /// Maps `file`, or returns `None` if it can't be, e.g. because it is empty or a pipe.
+    fn new(file: &File, metadata: &fs::Metadata) -> Option<Self> {
+        use std::os::fd::AsRawFd;
+
+        let len = usize::try_from(metadata.len()).ok()?;
+        if !metadata.is_file() || len == 0 {
+            return None;
+        }
+        // SAFETY: maps a new region chosen by the kernel, so no existing memory is affected; the
+        // file descriptor is open for reading for the duration of the call.
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return None;
+        }
+        Some(Mapping {
+            ptr: std::ptr::NonNull::new(ptr)?,
+            len,
+        })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        // SAFETY: the region is `len` readable bytes, mapped until `self` is dropped, and never
+        // written through by this process. See the type's docs for changes made by others.
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr().cast::<u8>(), self.len) }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        // SAFETY: unmaps exactly the region `new` mapped, which no slice from `bytes` outlives.
+        unsafe { libc::munmap(self.ptr.as_ptr(), self.len) };
+    }
+}
+
+// SAFETY: the mapping is read-only and owned by `Mapping`, so it can be read from any thread.
+#[cfg(unix)]
+unsafe impl Send for Mapping {}
+// SAFETY: as for `Send`; `bytes` only ever reads.
+#[cfg(unix)]
+unsafe impl Sync for Mapping {}
+
+/// Elsewhere, words files are read into memory instead.
+#[cfg(not(unix))]
+enum Mapping {}
+
+#[cfg(not(unix))]
+impl Mapping {
+    fn new(_file: &File, _metadata: &fs::Metadata) -> Option<Self> {
+        None
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match *self {}
+    }
+}
+
+/// Well-known word lists that can be downloaded.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub(crate) enum Download {
+    /// The EFF's large list, 7776 words for five dice
+    EffLarge,
+    /// The EFF's first short list, 1296 words for four dice
+    EffShort,
+    /// The EFF's second short list, 1296 words unique in their first three letters
+    #[value(name = "eff-short-2")]
+    EffShort2,
+    /// Reinhold's original Diceware list, 7776 words for five dice
+    Diceware,
+    /// The BIP39 English list
+    Bip39,
+    /// The BIP39 Czech list
+    Bip39Czech,
+    /// The BIP39 French list
+    Bip39French,
+    /// The BIP39 Italian list
+    Bip39Italian,
+    /// The BIP39 Portuguese list
+    Bip39Portuguese,
+    /// The BIP39 Spanish list
+    Bip39Spanish,
+}
+
+const BIP39: &str = "https://raw.githubusercontent.com/bitcoin/bips/master/bip-0039";
+
+impl Download {
+    /// Returns the URL the list is published at.
+    fn url(self) -> String {
+        let bip39 = |language| format!("{BIP39}/{language}.txt");
+        match self {
+            Download::EffLarge => {
+                "https://www.eff.org/files/2016/07/18/eff_large_wordlist.txt".into()
+            }
+            Download::EffShort => {
+                "https://www.eff.org/files/2016/09/08/eff_short_wordlist_1.txt".into()
+            }
+            Download::EffShort2 => {
+                "https://www.eff.org/files/2016/09/08/eff_short_wordlist_2_0.txt".into()
+            }
+            Download::Diceware => "https://theworld.com/~reinhold/diceware.wordlist.asc".into(),
+            Download::Bip39 => bip39("english"),
+            Download::Bip39Czech => bip39("czech"),
+            Download::Bip39French => bip39("french"),
+            Download::Bip39Italian => bip39("italian"),
+            Download::Bip39Portuguese => bip39("portuguese"),
+            Download::Bip39Spanish => bip39("spanish"),
+        }
+    }
+
+    /// Returns the SHA-256 checksum of the published file, in hex.
+    fn sha256(self) -> &'static str {
+        match self {
+            Download::EffLarge => EFF_LARGE_SHA256,
+            Download::EffShort => EFF_SHORT_SHA256,
+            Download::EffShort2 => EFF_SHORT_2_SHA256,
+            Download::Diceware => DICEWARE_SHA256,
+            Download::Bip39 => "2f5eed53a4727b4bf8880d8f3f199efc90e58503646d9ff8eff3a2ed3b24dbda",
+            Download::Bip39Czech => {
+                "7e80e161c3e93d9554c2efb78d4e3cebf8fc727e9c52e03b83b94406bdcc95fc"
+            }
+            Download::Bip39French => {
+                "ebc3959ab7801a1df6bac4fa7d970652f1df76b683cd2f4003c941c63d517e59"
+            }
+            Download::Bip39Italian => {
+                "d392c49fdb700a24cd1fceb237c1f65dcc128f6b34a8aacb58b59384b5c648c2"
+            }
+            Download::Bip39Portuguese => {
+                "2685e9c194c82ae67e10ba59d9ea5345a23dc093e92276fc5361f6667d79cd3f"
+            }
+            Download::Bip39Spanish => {
+                "46846a5a0139d1e3cb77293e521c2865f7bcdb82c44e8d0a06a2cd0ecba48c0b"
+            }
+        }
+    }
+
+    /// Returns the words of the published file `contents`, one per line; the EFF's and
+    /// Reinhold's lines start with their dice rolls, and Reinhold's are wrapped in a PGP signature.
+    fn words(self, contents: &str) -> Result<String> {
+        let mut words = String::new();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let word = match self {
+                Download::EffLarge | Download::EffShort | Download::EffShort2 => line
+                    .split('\t')
+                    .nth(1)
+                    .context(tr("invalid-word-list", &[]))?,
+                Download::Diceware => match line.split_once('\t') {
+                    Some((_, word)) => word,
+                    None => continue,
+                },
+                _ => line,
+            };
+            words.push_str(word.trim());
+            words.push('\n');
+        }
+        Ok(words)
+    }
+}
+
+/// Downloads `list` with curl, checks it against its pinned checksum, and writes its words to a
+/// file named after it in `dir`'s `wordlists` directory, returning the file's path.
+pub(crate) fn fetch(list: Download, dir: &Path) -> Result<PathBuf> {
+    let url = list.url();
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location"])
+        .args(["--proto", "=https", "--proto-redir", "=https"])
+        .arg(&url)
+        .output()
+        .context(tr("failed-running", &[("program", &"curl")]))?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!(tr(
+            "program-failed-with",
+            &[("program", &"curl"), ("error", &error.trim())]
+        ));
+    }
+    let sha256 = hex::encode(Sha256::digest(&output.stdout));
+    if sha256 != list.sha256() {
+        anyhow::bail!(tr(
+            "checksum-mismatch",
+            &[
+                ("url", &url),
+                ("sha256", &sha256),
+                ("pinned", &list.sha256())
+            ]
+        ));
+    }
+    let contents = std::str::from_utf8(&output.stdout).context(tr("invalid-word-list", &[]))?;
+    let dir = dir.join("wordlists");
+    create_dir_all(&dir)?;
+    let path = dir.join(format!("{list}.txt"));
+    // Renamed into place, as a running onepass may have the old file mapped.
+    let tmp = path.with_extension("txt.tmp");
+    write(&tmp, list.words(contents)?)
+        .and_then(|()| rename(&tmp, &path))
+        .with_context(|| tr("failed-writing", &[("path", &path.display())]))?;
+    Ok(path)
+}
+
+/// The numbers of words in the passphrases that `wordlist stats` describes.
+pub(crate) const PASSPHRASE_WORDS: RangeInclusive<usize> = 4..=8;
+
+/// Statistics of a word list, for choosing one.
+pub(crate) struct Stats {
+    pub(crate) words: usize,
+    /// The average length of its words, in characters
+    pub(crate) average_length: f64,
+    /// The length of its longest word, in characters
+    pub(crate) max_length: usize,
+}
+
+impl Stats {
+    pub(crate) fn new(list: &(impl WordList + ?Sized)) -> Result<Self> {
+        if list.is_empty() {
+            anyhow::bail!(tr("empty-word-list", &[]));
+        }
+        let lengths = (0..list.len()).map(|i| list.word(i).chars().count());
+        let (total, max_length) =
+            lengths.fold((0, 0), |(total, max), len| (total + len, max.max(len)));
+        Ok(Stats {
+            words: list.len(),
+            average_length: total as f64 / list.len() as f64,
+            max_length,
+        })
+    }
+
+    /// Returns the bits of entropy of one word drawn from the list.
+    pub(crate) fn bits_per_word(&self) -> f64 {
+        (self.words as f64).log2()
+    }
+
+    /// Returns the expected length, in characters, of a passphrase of `count` words with one
+    /// character between each, as from `[:word:](-[:word:]){3}` for four.
+    pub(crate) fn passphrase_length(&self, count: usize) -> f64 {
+        count as f64 * self.average_length + count.saturating_sub(1) as f64
+    }
+}
+
+/// A problem with one line of a words file.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum Issue<'a> {
+    /// The line is empty, and skipped
+    Blank,
+    /// The word is on an earlier line too, so counts once
+    Duplicate(&'a str),
+    /// The word is not ASCII, so may be hard to type, or spelled differently elsewhere
+    NonAscii(&'a str),
+    /// The word contains whitespace, so reads as several words in a passphrase
+    Whitespace(&'a str),
+}
+
+/// What `wordlist check` found in a words file.
+pub(crate) struct Check<'a> {
+    /// The distinct words, as passwords are derived from them
+    pub(crate) words: Box<[&'a str]>,
+    /// The problems found, with their line numbers
+    pub(crate) issues: Vec<(usize, Issue<'a>)>,
+}
+
+impl<'a> Check<'a> {
+    /// Checks the words file `contents`.
+    pub(crate) fn new(contents: &'a str) -> Self {
+        let mut words = BTreeSet::new();
+        let mut issues = Vec::new();
+        for (line, word) in contents.lines().map(str::trim).enumerate() {
+            let line = line + 1;
+            if word.is_empty() {
+                issues.push((line, Issue::Blank));
+                continue;
+            }
+            if !words.insert(word) {
+                issues.push((line, Issue::Duplicate(word)));
+                continue;
+            }
+            if !word.is_ascii() {
+                issues.push((line, Issue::NonAscii(word)));
+            }
+            if word.contains(char::is_whitespace) {
+                issues.push((line, Issue::Whitespace(word)));
+            }
+        }
+        let words = words.into_iter().collect();
+        Check { words, issues }
+    }
+
+    /// Returns the bits of entropy of one word drawn from the list.
+    pub(crate) fn bits_per_word(&self) -> f64 {
+        (self.words.len() as f64).log2()
+    }
+}
+
+impl fmt::Display for Issue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::Blank => f.write_str("blank line"),
+            Issue::Duplicate(word) => write!(f, "duplicate word `{word}`"),
+            Issue::NonAscii(word) => write!(f, "non-ASCII word `{word}`"),
+            Issue::Whitespace(word) => write!(f, "word `{word}` contains whitespace"),
+        }
+    }
+}
+
+impl fmt::Display for Download {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().ok_or(fmt::Error)?;
+        f.write_str(value.get_name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums() {
+        // The published files, as the copies built in.
+        let eff = include_str!("../core/eff_large_wordlist.txt");
+        let short = include_str!("../core/eff_short_wordlist_1.txt");
+        let short_2 = include_str!("../core/eff_short_wordlist_2_0.txt");
+        let diceware = include_str!("../core/diceware.wordlist.asc");
+        let bip39 = |language: bip39::Language| language.word_list().join("\n") + "\n";
+        let lists = [
+            (Download::EffLarge, eff.to_owned()),
+            (Download::EffShort, short.to_owned()),
+            (Download::EffShort2, short_2.to_owned()),
+            (Download::Diceware, diceware.to_owned()),
+            (Download::Bip39, bip39(bip39::Language::English)),
+            #[cfg(any(feature = "czech", feature = "all-languages"))]
+            (Download::Bip39Czech, bip39(bip39::Language::Czech)),
+            #[cfg(any(feature = "french", feature = "all-languages"))]
+            (Download::Bip39French, bip39(bip39::Language::French)),
+            #[cfg(any(feature = "italian", feature = "all-languages"))]
+            (Download::Bip39Italian, bip39(bip39::Language::Italian)),
+            #[cfg(any(feature = "portuguese", feature = "all-languages"))]
+            (
+                Download::Bip39Portuguese,
+                bip39(bip39::Language::Portuguese),
+            ),
+            #[cfg(any(feature = "spanish", feature = "all-languages"))]
+            (Download::Bip39Spanish, bip39(bip39::Language::Spanish)),
+        ];
+        for (list, contents) in lists {
+            let sha256 = hex::encode(Sha256::digest(&contents));
+            assert_eq!(sha256, list.sha256(), "{list}");
+        }
+        let words = Download::EffLarge.words(eff).unwrap();
+        assert!(words.starts_with("abacus\nabdomen\n"));
+        assert_eq!(words.lines().count(), 7776);
+        for (list, contents, builtin) in [
+            (Download::EffShort, short, Wordlist::EffShort),
+            (Download::EffShort2, short_2, Wordlist::EffShort2),
+            (Download::Diceware, diceware, Wordlist::Diceware),
+        ] {
+            let words = list.words(contents).unwrap();
+            assert!(words.lines().eq(builtin.words().iter().copied()), "{list}");
+        }
+    }
+
+    #[test]
+    fn words_files() {
+        let dir = std::env::temp_dir();
+        let path = |name: &str| -> Box<Path> {
+            dir.join(format!("onepass-words-{}-{name}.txt", std::process::id()))
+                .into()
+        };
+        let files = |contents: &[&str]| {
+            let paths: Vec<_> = (0..contents.len()).map(|i| path(&i.to_string())).collect();
+            for (path, contents) in paths.iter().zip(contents) {
+                write(path, contents).unwrap();
+            }
+            WordsFiles::open(&paths, None).unwrap()
+        };
+        let words = |files: &WordsFiles| {
+            (0..files.len())
+                .map(|i| files.word(i).to_owned())
+                .collect::<Vec<_>>()
+        };
+        let sorted = files(&["apple\nbanana\ncherry\n"]);
+        assert_eq!(words(&sorted), ["apple", "banana", "cherry"]);
+        let unsorted = files(&["  cherry\n\napple\r\nbanana\napple\n"]);
+        assert_eq!(words(&unsorted), ["apple", "banana", "cherry"]);
+        let combined = files(&["banana\ncherry\n", "", "apple\ncherry\ndate"]);
+        assert_eq!(words(&combined), ["apple", "banana", "cherry", "date"]);
+        assert!(files(&[""]).is_empty());
+        write(path("0"), b"\xff\n").unwrap();
+        assert!(WordsFiles::open(&[path("0")], None).is_err());
+        for i in 0..3 {
+            std::fs::remove_file(path(&i.to_string())).unwrap();
+        }
+    }
+
+    #[test]
+    fn cached_index() {
+        let dir = std::env::temp_dir().join(format!("onepass-index-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let path: Box<Path> = dir.join("words.txt").into();
+        let open = || WordsFiles::open(std::slice::from_ref(&path), Some(&dir)).unwrap();
+        let words = |files: &WordsFiles| {
+            (0..files.len())
+                .map(|i| files.word(i).to_owned())
+                .collect::<Vec<_>>()
+        };
+        write(&path, "é\ncherry\napple\n").unwrap();
+        assert_eq!(words(&open()), ["apple", "cherry", "é"]);
+        let files = open();
+        #[cfg(unix)]
+        assert!(matches!(files.texts[0].1, Text::Mapped(_)));
+        let cache = files.cache.as_ref().unwrap();
+        let key = files.cache_key(&cache.modified);
+        assert!(files.read_index(&cache.path, &key).is_some());
+        assert_eq!(words(&files), ["apple", "cherry", "é"]);
+
+        // Offsets outside the files, or within a character, are not trusted.
+        for bad in [(0, 100), (0, 1)] {
+            write_index(&cache.path, &key, &[bad]);
+            assert!(files.read_index(&cache.path, &key).is_none(), "{bad:?}");
+        }
+        // Unmapped before the file is rewritten in place, which onepass itself never does.
+        drop(files);
+        write(&path, "date\nfig\n").unwrap();
+        assert_eq!(words(&open()), ["date", "fig"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn filters() -> Result<()> {
+        let list = List::Builtin(Wordlist::Bip39.words());
+        let filters = Filters::new(&list);
+        let short = WordFilter {
+            max_len: Some(3),
+            ..Default::default()
+        };
+        let words = filters.get(&short)?;
+        assert!(Arc::ptr_eq(&words, &filters.get(&short)?));
+        assert!((0..words.len()).all(|i| words.word(i).len() <= 3));
+        assert_eq!(2048, filters.get(&WordFilter::default())?.len());
+        Ok(())
+    }
+
+    #[test]
+    fn stats() {
+        let stats = Stats::new(&["a", "bb", "cccccc", "dé"][..]).unwrap();
+        assert_eq!(stats.words, 4);
+        assert_eq!(stats.bits_per_word(), 2.0);
+        assert_eq!(stats.average_length, 2.75);
+        assert_eq!(stats.max_length, 6);
+        assert_eq!(stats.passphrase_length(4), 14.0);
+        assert!(Stats::new(&[][..]).is_err());
+
+        let stats = Stats::new(&List::named("eff-large").unwrap()).unwrap();
+        assert_eq!(stats.words, 7776);
+        assert_eq!(stats.max_length, 9);
+    }
+
+    #[test]
+    fn check() {
+        let check = Check::new("apple\n\nbanana\n  apple \ncafé\nice cream\nbanana\n");
+        assert_eq!(*check.words, ["apple", "banana", "café", "ice cream"]);
+        assert_eq!(check.bits_per_word(), 2.0);
+        assert_eq!(
+            check.issues,
+            [
+                (2, Issue::Blank),
+                (4, Issue::Duplicate("apple")),
+                (5, Issue::NonAscii("café")),
+                (6, Issue::Whitespace("ice cream")),
+                (7, Issue::Duplicate("banana")),
+            ]
+        );
+        let eff = Download::EffLarge
+            .words(include_str!("../core/eff_large_wordlist.txt"))
+            .unwrap();
+        let check = Check::new(&eff);
+        assert!(check.issues.is_empty());
+        assert_eq!(check.words.len(), 7776);
+    }
+}